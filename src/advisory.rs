@@ -0,0 +1,92 @@
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::catalog::{SoftwareSpec, SourceSpec};
+use crate::error::Error;
+use crate::resolver::{send, ResolvedAsset};
+
+const OSV_QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// A published vulnerability affecting a resolved version.
+#[derive(Debug, Clone)]
+pub struct Advisory {
+    pub id: String,
+    pub summary: String,
+    /// As rated by the source database, e.g. "HIGH"; not every entry has one.
+    pub severity: Option<String>,
+}
+
+/// Outcome of [`lookup`]: the advisories found, possibly none.
+pub type Advisories = Result<Vec<Advisory>, String>;
+
+#[derive(Deserialize)]
+struct OsvResponse {
+    #[serde(default)]
+    vulns: Vec<OsvVuln>,
+}
+
+#[derive(Deserialize)]
+struct OsvVuln {
+    id: String,
+    summary: Option<String>,
+    details: Option<String>,
+    database_specific: Option<serde_json::Value>,
+}
+
+/// Asks the OSV database (osv.dev) about the resolved version. Entries name
+/// their package with `osv = { ecosystem, name }`; GitHub sources are looked
+/// up by repository and release tag without one.
+pub fn lookup(client: &Client, spec: &SoftwareSpec, resolved: &ResolvedAsset) -> Advisories {
+    let version = &resolved.version;
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(format!("'{version}' is not a release version, nothing to look up"));
+    }
+    let queries = match (&spec.osv, &spec.source) {
+        (Some(package), _) => vec![json!({
+            "package": { "ecosystem": package.ecosystem, "name": package.name },
+            "version": version,
+        })],
+        // The resolver drops the tag's `v` prefix, so both spellings are tried.
+        (None, SourceSpec::Github { repo: Some(repo), .. }) => [version.clone(), format!("v{version}")]
+            .into_iter()
+            .map(|tag| {
+                json!({
+                    "package": { "ecosystem": "GIT", "name": format!("https://github.com/{repo}") },
+                    "version": tag,
+                })
+            })
+            .collect(),
+        (None, _) => {
+            return Err("no OSV package known, add `osv = { ecosystem = \"...\", name = \"...\" }` to the entry".to_string());
+        }
+    };
+
+    let mut advisories: Vec<Advisory> = Vec::new();
+    for query in queries {
+        let response: OsvResponse = crate::runtime::block_on(async {
+            send(client.post(OSV_QUERY_URL).json(&query), "failed to query OSV")
+                .await?
+                .json()
+                .await
+                .map_err(|e| Error::Resolve(format!("failed to decode OSV response: {e}")))
+        })?;
+        for vuln in response.vulns {
+            if advisories.iter().any(|a| a.id == vuln.id) {
+                continue;
+            }
+            let summary = vuln
+                .summary
+                .or_else(|| vuln.details.and_then(|d| d.lines().next().map(str::to_string)))
+                .unwrap_or_default();
+            let severity = vuln
+                .database_specific
+                .as_ref()
+                .and_then(|d| d.get("severity"))
+                .and_then(|s| s.as_str())
+                .map(str::to_uppercase);
+            advisories.push(Advisory { id: vuln.id, summary, severity });
+        }
+    }
+    Ok(advisories)
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+/// Responses to the prompts of a run, from `--answers answers.toml`, so
+/// flows that would stop to ask can run unattended. A prompt the file does
+/// not answer fails the run instead of waiting for input that never comes.
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Answers {
+    /// Confirmations by name: `apply` and `uninstall`.
+    #[serde(default)]
+    pub confirm: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub conflicts: Conflicts,
+    /// Secrets of `secret` setup steps by name, used when the keyring has
+    /// none stored.
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretRef>,
+    /// Password for sudo when a step needs root; without it only a cached
+    /// or passwordless sudo works.
+    pub sudo: Option<SecretRef>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Conflicts {
+    /// What a manifest's dotfiles do to a different file already at the
+    /// target.
+    #[serde(default)]
+    pub dotfiles: ConflictPolicy,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Overwrite,
+    /// Keeps the old file next to the new one as `<name>.rusty_rebase.bak`.
+    Backup,
+    Skip,
+}
+
+/// Where a secret comes from; the answers file never holds the value itself.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum SecretRef {
+    /// Stored in the keyring under this name, e.g. with `secret-tool` or an
+    /// earlier interactive run.
+    Keyring(String),
+    /// Read from this environment variable.
+    Env(String),
+}
+
+impl SecretRef {
+    fn read(&self) -> Result<String, String> {
+        match self {
+            SecretRef::Keyring(name) => crate::secrets::lookup(name).ok_or_else(|| format!("no secret '{name}' in the keyring")),
+            SecretRef::Env(var) => std::env::var(var).map_err(|_| format!("${var} is not set")),
+        }
+    }
+}
+
+static ACTIVE: OnceLock<Answers> = OnceLock::new();
+
+/// Reads the answers file and uses it for the rest of the run.
+pub fn load(path: &Path) -> Result<(), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let answers: Answers = toml::from_str(&content).map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+    let _ = ACTIVE.set(answers);
+    Ok(())
+}
+
+/// Whether the run is unattended.
+pub fn active() -> bool {
+    ACTIVE.get().is_some()
+}
+
+/// Asks `question` on the terminal, or answers it from the file by `name`.
+pub fn confirm(name: &str, question: &str) -> Result<bool, String> {
+    if let Some(answers) = ACTIVE.get() {
+        let answer = *answers.confirm.get(name).ok_or_else(|| format!("the answers file has no `confirm.{name}` for \"{question}\""))?;
+        println!("{question} [y/N] {} (from the answers file)", if answer { "y" } else { "n" });
+        return Ok(answer);
+    }
+    print!("{question} [y/N] ");
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).map_err(|e| e.to_string())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// The secret `name` from the answers file; `None` when no file is in use,
+/// so the caller prompts as usual.
+pub fn secret(name: &str) -> Option<Result<String, String>> {
+    let answers = ACTIVE.get()?;
+    Some(match answers.secrets.get(name) {
+        Some(reference) => reference.read().map_err(|e| format!("secret {name}: {e}")),
+        None => Err(format!("the answers file has no `secrets.{name}`")),
+    })
+}
+
+pub fn dotfile_conflicts() -> ConflictPolicy {
+    ACTIVE.get().map(|answers| answers.conflicts.dotfiles).unwrap_or_default()
+}
+
+/// Validates sudo credentials without a terminal: with the password from
+/// the file, or a cached or passwordless sudo otherwise.
+pub fn elevate() -> bool {
+    let password = match ACTIVE.get().and_then(|answers| answers.sudo.as_ref()) {
+        Some(reference) => match reference.read() {
+            Ok(password) => Some(password),
+            Err(e) => {
+                println!("    [error] sudo password: {e}");
+                return false;
+            }
+        },
+        None => None,
+    };
+    match password {
+        Some(password) => match crate::sudo::authenticate(&password) {
+            Ok(()) => true,
+            Err(e) => {
+                println!("    [error] sudo: {e}");
+                false
+            }
+        },
+        None => std::process::Command::new("sudo").args(["-n", "-v"]).status().is_ok_and(|s| s.success()),
+    }
+}
@@ -1,40 +1,538 @@
-use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
-use crate::app::{App, InstallMsg, ViewState};
-use crate::installer::install_software;
+use std::time::{Duration, Instant};
+use crossterm::event::KeyCode;
+use tokio_util::sync::CancellationToken;
+use crate::app::{App, CatalogRow, InstallMsg, ResolveStatus, ViewState};
+use crate::app::state::{MaintenanceRow, OnboardingStep, PlannedInstall, RestorePart};
+use crate::app::screen::Modal;
+use crate::catalog::SetupStep;
+use crate::error::Error;
+use crate::installer::{install_software, verify_install};
+use crate::report::{RecordKind, RecordStatus, RunRecord};
+use crate::state::HealthEvent;
 
-pub fn start_resolution(app: &mut App) {
+/// Resolves every entry; with `force`, cached resolutions are ignored.
+pub fn start_resolution(app: &mut App, force: bool) {
     if app.is_resolving { return; }
     
-    app.logs.push("[resolve] Spawning background resolution thread...".to_string());
-    let (tx, rx) = mpsc::channel();
+    app.log("[resolve] Spawning background resolution thread...".to_string());
+    let keys: Vec<String> = app.tools.iter().map(|t| t.key.clone()).collect();
+    resolve_keys(app, keys, force);
+}
+
+/// Delay before a resolution that failed `failures` times in a row is
+/// retried: 5 seconds, doubling up to 5 minutes.
+pub fn retry_backoff(failures: u32) -> Duration {
+    Duration::from_secs((5u64 << failures.saturating_sub(1).min(6)).min(300))
+}
+
+/// Resolves again only the entries whose last resolution failed and whose
+/// backoff has run out, so one flaky request doesn't mean re-resolving the
+/// whole catalog.
+pub fn retry_failed_resolutions(app: &mut App) {
+    if app.is_resolving { return; }
+
+    let now = Instant::now();
+    let (ready, waiting): (Vec<&crate::app::ToolItem>, Vec<&crate::app::ToolItem>) = app
+        .tools
+        .iter()
+        .filter(|t| matches!(t.status, ResolveStatus::Failed(_)))
+        .partition(|t| t.retry_at.is_none_or(|at| at <= now));
+    let keys: Vec<String> = ready.iter().map(|t| t.key.clone()).collect();
+    let next = waiting.iter().filter_map(|t| t.retry_at).min().map(|at| at.duration_since(now).as_secs() + 1);
+    let waiting = waiting.len();
+
+    match (keys.is_empty(), next) {
+        (true, None) => app.log("No failed resolutions to retry".to_string()),
+        (true, Some(secs)) => app.log(format!("[warn] Nothing to retry yet: {waiting} backing off, next in {secs}s")),
+        (false, _) => {
+            let note = if waiting > 0 { format!(" ({waiting} still backing off)") } else { String::new() };
+            app.log(format!("[resolve] Retrying {}{note}...", keys.join(", ")));
+            resolve_keys(app, keys, false);
+        }
+    }
+}
+
+/// Resolves only the highlighted entry from the network and opens a popup
+/// with what it found, for debugging catalog entries.
+pub fn probe_highlighted(app: &mut App) {
+    let Some(key) = app.current_tool().map(|t| t.key.clone()) else { return };
+    probe(app, key);
+}
+
+pub fn probe(app: &mut App, key: String) {
+    if app.offline {
+        app.log("[warn] Test resolution needs the network; turn offline mode off with o".to_string());
+        return;
+    }
+    let Some(spec) = app.catalog.software.get(&key).cloned() else { return };
+    app.push_modal(Modal::Probe(crate::app::ProbePopup { key: key.clone(), probe: None, show_snippets: true }));
+    let (tx, rx) = app.events.waker().channel();
+    app.probe_rx = Some(rx);
+    let client = app.client.clone();
+    let distro = app.distro.clone();
+    crate::runtime::spawn(async move {
+        let _ = tx.send((key, crate::resolver::probe(&client, &spec, &distro).await));
+    });
+}
+
+/// Shows a test resolution in its popup and, when it worked, takes it as
+/// the entry's resolution.
+pub fn finish_probe(app: &mut App, key: String, probe: crate::resolver::Probe) {
+    if let Ok(asset) = &probe.result {
+        app.log(format!("[done] Test-resolved {key} to {}", asset.version));
+        if let Err(e) = crate::state::record_health([(key.clone(), HealthEvent::Resolved)]) {
+            app.log(format!("[warn] state database: {e}"));
+        }
+        if let Some(tool) = app.tools.iter_mut().find(|t| t.key == key) {
+            tool.cached_at = None;
+            tool.resolved = Some(asset.clone());
+            tool.status = ResolveStatus::Resolved;
+            tool.last_error = None;
+            tool.resolve_failures = 0;
+            tool.retry_at = None;
+        }
+    }
+    if let Some(popup) = app.probe_mut()
+        && popup.key == key
+    {
+        popup.probe = Some(probe);
+    }
+    if let ViewState::Maintenance { ref mut rows, ref mut cursor } = app.state {
+        *rows = maintenance_rows(&app.catalog);
+        *cursor = rows.iter().position(|row| row.key == key).unwrap_or_default();
+    }
+}
+
+/// Hands the typed password to sudo on a background thread; the verdict
+/// arrives through `sudo_rx`.
+pub fn submit_sudo(app: &mut App) {
+    let Some(Modal::Sudo(prompt)) = app.modals.last_mut() else { return };
+    // A fresh buffer with the same room, so typing again never reallocates.
+    let password = std::mem::replace(&mut prompt.input, super::state::secret_input());
+    prompt.checking = true;
+    prompt.error = None;
+    let (tx, rx) = app.events.waker().channel();
+    app.sudo_rx = Some(rx);
+    thread::spawn(move || {
+        let _ = tx.send(crate::sudo::authenticate(&password));
+    });
+}
+
+/// Answers the privileged step once sudo accepted the password, or after
+/// three wrong ones.
+pub fn finish_sudo(app: &mut App, result: Result<(), String>) {
+    let Some(Modal::Sudo(prompt)) = app.modals.last_mut() else { return };
+    prompt.checking = false;
+    match result {
+        Ok(()) => {
+            if let Some(Modal::Sudo(prompt)) = app.modals.pop() {
+                let _ = prompt.reply.send(true);
+            }
+            app.elevated = true;
+            crate::sudo::keep_alive();
+            app.log("[sudo] Authenticated; the credential is kept fresh until the run ends".to_string());
+        }
+        Err(e) => {
+            prompt.attempts += 1;
+            if prompt.attempts < 3 {
+                prompt.error = Some(e);
+                return;
+            }
+            if let Some(Modal::Sudo(prompt)) = app.modals.pop() {
+                let _ = prompt.reply.send(false);
+            }
+            app.log(format!("[error] sudo: {e}; 3 incorrect password attempts"));
+        }
+    }
+}
+
+pub fn decline_sudo(app: &mut App) {
+    if let Some(Modal::Sudo(prompt)) = app.modals.pop() {
+        let _ = prompt.reply.send(false);
+    }
+    app.log("[warn] sudo declined; the privileged steps of this tool will fail".to_string());
+}
+
+/// Resolves `keys` on a background thread; results arrive through
+/// `resolution_rx`. Entries resolved within the cache TTL are filled in
+/// straight away unless `force` is set.
+pub fn resolve_keys(app: &mut App, keys: Vec<String>, force: bool) {
+    // Offline, downloads are looked for in the download folder instead.
+    let offline_dir = if app.offline {
+        match crate::installer::download_dir() {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                app.log(format!("[error] {e}"));
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    let ttl = crate::resolve_cache::ttl_secs();
+    let keys = if force || app.offline || ttl == 0 { keys } else { take_cached(app, keys, ttl) };
+    if keys.is_empty() {
+        return;
+    }
+    let (tx, rx) = app.events.waker().channel();
     app.resolution_rx = Some(rx);
     app.is_resolving = true;
     app.progress.done = 0;
-    app.progress.total = app.tools.len();
+    app.progress.total = keys.len();
     app.progress.current = "Resolving...".to_string();
+    for tool in app.tools.iter_mut().filter(|t| keys.contains(&t.key)) {
+        tool.status = ResolveStatus::Pending;
+    }
 
-    let catalog = app.catalog.clone();
+    // Results of an earlier batch would be dropped with its receiver.
+    let cancel = CancellationToken::new();
+    if let Some(previous) = app.resolution_cancel.replace(cancel.clone()) {
+        previous.cancel();
+    }
+
+    let catalog = Arc::new(app.catalog.clone());
     let distro = app.distro.clone();
     let client = app.client.clone();
-    let tools_keys: Vec<String> = app.tools.iter().map(|t| t.key.clone()).collect();
+    let parallel = crate::preflight::concurrency();
 
-    thread::spawn(move || {
-        for key in tools_keys {
-            let res = if let Some(spec) = catalog.software.get(&key) {
-                crate::resolver::resolve_asset(&client, spec, &distro)
-                    .map_err(|e| e.to_string())
-            } else {
-                Err("Missing spec".to_string())
-            };
-            let _ = tx.send((key, res));
+    crate::runtime::spawn(async move {
+        let packages: Vec<String> = keys
+            .iter()
+            .filter_map(|k| catalog.software.get(k))
+            .filter(|spec| matches!(spec.source, crate::catalog::SourceSpec::PackageManager))
+            .filter_map(|spec| crate::resolver::primary_package(spec).cloned())
+            .collect();
+        let prefetching = distro.clone();
+        let _ = tokio::task::spawn_blocking(move || prefetching.prefetch_versions(&packages)).await;
+
+        crate::runtime::bounded(keys, parallel, &cancel, |key| {
+            let (catalog, distro, client, tx, offline_dir) = (catalog.clone(), distro.clone(), client.clone(), tx.clone(), offline_dir.clone());
+            async move {
+                let res = match catalog.software.get(&key) {
+                    Some(spec) => match (crate::bundle::resolve(&key, spec, &distro), &offline_dir) {
+                        (Some(bundled), _) => bundled,
+                        (None, Some(dir)) => crate::resolver::resolve_offline(spec, &distro, dir),
+                        (None, None) => match crate::resolver::resolve_asset(&client, spec, &distro).await {
+                            // Release listings carry sizes; other downloads are asked.
+                            Ok(mut asset) if asset.size.is_none() && spec.source.has_artifact() && asset.url.starts_with("http") => {
+                                asset.size = crate::preflight::remote_size(&client, &asset.url).await;
+                                Ok(asset)
+                            }
+                            other => other,
+                        },
+                    },
+                    None => Err(Error::Catalog("Missing spec".to_string())),
+                };
+                tx.send((key, res)).is_ok()
+            }
+        })
+        .await;
+    });
+}
+
+/// Fills in the entries with a fresh cached resolution and returns the
+/// ones still to resolve.
+fn take_cached(app: &mut App, keys: Vec<String>, ttl: u64) -> Vec<String> {
+    let cache = crate::resolve_cache::ResolveCache::load();
+    let mut cached = 0;
+    let mut missing = Vec::new();
+    for key in keys {
+        let hit = app
+            .catalog
+            .software
+            .get(&key)
+            .and_then(|spec| cache.lookup(&key, spec, &app.distro, ttl));
+        match (hit, app.tools.iter_mut().find(|t| t.key == key)) {
+            (Some((asset, resolved_at)), Some(tool)) => {
+                tool.resolved = Some(asset);
+                tool.status = ResolveStatus::Resolved;
+                tool.cached_at = Some(resolved_at);
+                tool.last_error = None;
+                tool.resolve_failures = 0;
+                tool.retry_at = None;
+                cached += 1;
+            }
+            _ => missing.push(key),
+        }
+    }
+    if cached > 0 {
+        app.log(format!("[resolve] {cached} entr{} taken from the cache; press R to resolve everything again", if cached == 1 { "y" } else { "ies" }));
+    }
+    missing
+}
+
+pub fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Re-reads the catalog from disk, keeping selection and resolution state
+/// for entries that still exist. A broken file leaves the current catalog
+/// in place.
+pub fn reload_catalog(app: &mut App) {
+    if app.is_resolving {
+        app.log("[warn] Cannot reload the catalog while resolving".to_string());
+        return;
+    }
+    let Some(path) = app.catalog_path.clone().or_else(crate::config::find_catalog) else {
+        app.log("[warn] No catalog file to reload".to_string());
+        return;
+    };
+    app.catalog_mtime = modified_time(&path);
+
+    let catalog = match crate::catalog::load_catalog(&path) {
+        Ok(c) => c,
+        Err(e) => {
+            app.log(format!("[error] Catalog reload failed, keeping the previous one: {e}"));
+            return;
         }
+    };
+
+    // Keep the cursor on the same entry (or category header) when it still exists.
+    let (cursor_key, cursor_category) = match app.catalog_rows().get(app.cursor) {
+        Some(CatalogRow::Tool(idx)) => (Some(app.tools[*idx].key.clone()), None),
+        Some(CatalogRow::Category(category)) => (None, Some(category.clone())),
+        None => (None, None),
+    };
+    let mut previous: std::collections::HashMap<String, crate::app::ToolItem> =
+        app.tools.drain(..).map(|t| (t.key.clone(), t)).collect();
+    app.tools = crate::app::tools_from_catalog(&catalog)
+        .into_iter()
+        .map(|fresh| previous.remove(&fresh.key).unwrap_or(fresh))
+        .collect();
+    let removed = previous.len();
+    app.catalog = catalog;
+    let rows = app.catalog_rows();
+    app.cursor = rows
+        .iter()
+        .position(|row| match row {
+            CatalogRow::Tool(idx) => cursor_key.as_ref() == Some(&app.tools[*idx].key),
+            CatalogRow::Category(category) => cursor_category.as_ref() == Some(category),
+        })
+        .unwrap_or(app.cursor)
+        .min(rows.len().saturating_sub(1));
+    app.catalog_path = Some(path.clone());
+    app.log(format!(
+        "[done] Reloaded {} ({} entries, {} removed)",
+        path.display(),
+        app.tools.len(),
+        removed
+    ));
+}
+
+/// Whether a shell step edits the package sources rather than just running a
+/// program.
+fn touches_repositories(command: &str) -> bool {
+    ["add-apt-repository", "sources.list", "config-manager", "rpm --import", "apt-key", "gpg --dearmor", "/etc/yum.repos.d", "pacman-key"]
+        .iter()
+        .any(|marker| command.contains(marker))
+}
+
+/// Opens the details popup for the highlighted entry and measures its
+/// resolved download in the background.
+pub fn open_detail(app: &mut App) {
+    let Some(tool) = app.current_tool() else { return };
+    let key = tool.key.clone();
+    let has_artifact = app.catalog.software.get(&key).is_some_and(|spec| spec.source.has_artifact());
+    let url = tool.resolved.as_ref().map(|r| r.url.clone()).filter(|u| u.starts_with("http") && has_artifact);
+    let size = tool.resolved.as_ref().and_then(|r| r.size);
+
+    let mut dirs: Vec<std::path::PathBuf> = std::env::current_dir().into_iter().collect();
+    if let Some(dir) = app.catalog_path.as_deref().and_then(|p| p.parent()) {
+        dirs.push(dir.to_path_buf());
+    }
+    let recorded = app.installed.tools.get(&key).map(|t| t.installed_at);
+    let installed_at = crate::manifest::last_installed(&key, &dirs).max(recorded);
+
+    if let Some(url) = url.clone() {
+        let (tx, rx) = app.events.waker().channel();
+        app.size_rx = Some(rx);
+        let client = app.client.clone();
+        let key = key.clone();
+        crate::runtime::spawn(async move {
+            let _ = tx.send((key, crate::preflight::check_link(&client, &url).await));
+        });
+    }
+    app.push_modal(Modal::Detail(crate::app::DetailPopup {
+        key,
+        size,
+        sizing: url.is_some(),
+        installed_at,
+        scroll: 0,
+        contents: None,
+        previewing: false,
+        advisories: None,
+        checking: false,
+    }));
+    if crate::config::current().check_advisories == Some(true) {
+        check_advisories(app);
+    }
+}
+
+/// Looks the open popup's resolved version up in the OSV vulnerability
+/// database in the background.
+pub fn check_advisories(app: &mut App) {
+    let Some(key) = app.detail().filter(|d| !d.checking).map(|d| d.key.clone()) else { return };
+    let resolved = app.tools.iter().find(|t| t.key == key).and_then(|t| t.resolved.clone());
+    let spec = app.catalog.software.get(&key).cloned();
+    let Some(detail) = app.detail_mut() else { return };
+    let (Some(spec), Some(resolved)) = (spec, resolved) else {
+        detail.advisories = Some(Err("resolve the entry first, press [r] in the catalog".to_string()));
+        return;
+    };
+    detail.checking = true;
+    detail.advisories = None;
+
+    let (tx, rx) = app.events.waker().channel();
+    app.advisory_rx = Some(rx);
+    let client = app.client.clone();
+    thread::spawn(move || {
+        let _ = tx.send((key, crate::advisory::lookup(&client, &spec, &resolved)));
     });
 }
 
+/// Lists the top level of the open popup's archive without extracting it.
+/// A copy already in the download folder is used as-is; otherwise the
+/// resolved artifact is downloaded there first.
+pub fn preview_archive(app: &mut App) {
+    let Some(key) = app.detail().filter(|d| !d.previewing).map(|d| d.key.clone()) else { return };
+    let resolved = app
+        .tools
+        .iter()
+        .find(|t| t.key == key)
+        .and_then(|t| t.resolved.clone())
+        .filter(|r| r.url.starts_with("http"));
+    let has_artifact = app.catalog.software.get(&key).is_some_and(|spec| spec.source.has_artifact());
+    let Some(detail) = app.detail_mut() else { return };
+    let Some(resolved) = resolved.filter(|_| has_artifact) else {
+        detail.contents = Some(Err(Error::Resolve("resolve the entry first, press [r] in the catalog".to_string())));
+        return;
+    };
+    detail.previewing = true;
+    detail.contents = None;
+
+    let (tx, rx) = app.events.waker().channel();
+    app.preview_rx = Some(rx);
+    let client = app.client.clone();
+    thread::spawn(move || {
+        let listing = crate::installer::fetch_and_list(&client, &key, &resolved);
+        let _ = tx.send((key, listing));
+    });
+}
+
+/// Opens the pre-install summary for the selected tools. Every download is
+/// checked with parallel HEAD requests in the background, filling in sizes
+/// and dead links while the modal is shown.
+pub fn open_install_confirmation(app: &mut App) {
+    drop_offline_blocked(app);
+    let selected: Vec<&crate::app::ToolItem> = app.tools.iter().filter(|it| it.selected).collect();
+    if selected.is_empty() {
+        app.log("[warn] No tools selected for installation".to_string());
+        return;
+    }
+
+    let platform = crate::platform::Platform::current();
+    let mut items = Vec::new();
+    let mut changes = Vec::new();
+    let mut urls = Vec::new();
+    for tool in &selected {
+        let Some(spec) = app.catalog.software.get(&tool.key) else { continue };
+        let name = spec.display_name.clone();
+        let url = tool.resolved.as_ref().map(|r| r.url.clone()).filter(|u| u.starts_with("http") && spec.source.has_artifact() && !app.offline);
+        if let Some(file) = tool.resolved.as_ref().map(|r| r.file_name.as_str())
+            && crate::installer::is_package_artifact(file)
+        {
+            changes.push(format!("{name}: installs {file} system-wide"));
+        }
+        if spec.requires_kvm {
+            changes.extend(crate::virt::kvm_problems().into_iter().map(|problem| format!("{name}: warning: {problem}")));
+        }
+        for step in &spec.setup_steps {
+            match step {
+                SetupStep::Package { .. } => {
+                    let packages = step.packages_for(&app.distro.id, app.distro.pkg_manager.name());
+                    if !packages.is_empty() {
+                        changes.push(format!("{name}: installs packages via {}: {}", app.distro.pkg_manager, packages.join(" ")));
+                    }
+                }
+                SetupStep::Shell { command, .. } if touches_repositories(command) => {
+                    changes.push(format!("{name}: changes package sources: {command}"))
+                }
+                SetupStep::Shell { command, .. } => changes.push(format!("{name}: runs {command}")),
+                SetupStep::PathHint { .. } => {
+                    let profile = dirs::home_dir().map(|home| platform.profile_path(&home).display().to_string()).unwrap_or_default();
+                    changes.push(format!("{name}: appends a PATH entry to {profile}"))
+                }
+                SetupStep::GitConfig { key, value, scope } => {
+                    changes.push(format!("{name}: sets git config {} {key} = {value}", scope.flag()))
+                }
+                SetupStep::Template { dest, .. } | SetupStep::CopyFile { to: dest, .. } => changes.push(format!("{name}: writes {dest}")),
+                SetupStep::Swap { method, .. } => changes.push(format!("{name}: sets up {} swap", method.label())),
+                SetupStep::Sysctl { .. } => changes.push(format!("{name}: writes {}", crate::tuning::SYSCTL_FILE)),
+                SetupStep::Cargo { .. } | SetupStep::Npm { .. } | SetupStep::Pipx { .. } => {
+                    let Some((toolchain, packages, _)) = crate::toolchain::step_toolchain(step) else { continue };
+                    let provider = crate::toolchain::provider(&app.catalog, toolchain);
+                    let provided = provider.as_ref().is_some_and(|key| selected.iter().any(|t| &t.key == key));
+                    if toolchain.locate().is_none() && !provided {
+                        let hint = match provider {
+                            Some(key) => format!(", select '{key}' too"),
+                            None => String::new(),
+                        };
+                        changes.push(format!("{name}: needs {}, which is not installed{hint}", toolchain.program()));
+                    }
+                    changes.push(format!("{name}: installs {} packages: {}", toolchain.program(), packages.join(" ")))
+                }
+                SetupStep::VersionManager { manager, versions, .. } => {
+                    if !manager.installed() {
+                        changes.push(format!("{name}: installs {} from its official installer script", manager.label()));
+                    }
+                    changes.push(format!("{name}: installs {} versions: {}", manager.label(), versions.join(" ")))
+                }
+                SetupStep::Note { .. } | SetupStep::Secret { .. } | SetupStep::EnsureDirs { .. } => {}
+            }
+        }
+        items.push(PlannedInstall {
+            key: tool.key.clone(),
+            name,
+            version: tool.resolved.as_ref().map(|r| r.version.clone()),
+            size: tool.resolved.as_ref().and_then(|r| r.size),
+            sizing: url.is_some(),
+            problem: None,
+        });
+        if let Some(url) = url {
+            urls.push((tool.key.clone(), url));
+        }
+    }
+
+    let (tx, rx) = app.events.waker().channel();
+    app.size_rx = Some(rx);
+    let client = app.client.clone();
+    let parallel = crate::preflight::concurrency();
+    crate::runtime::spawn(crate::preflight::check_links(client, urls, parallel, tx));
+
+    app.state = ViewState::ConfirmInstall { items, changes, on_battery: crate::power::on_battery() };
+}
+
+/// Deselects what offline mode cannot install, saying why.
+fn drop_offline_blocked(app: &mut App) {
+    let blocked: Vec<(usize, String)> = app
+        .tools
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.selected)
+        .filter_map(|(i, t)| Some((i, app.offline_blocker(&t.key)?)))
+        .collect();
+    for (index, reason) in blocked {
+        app.tools[index].selected = false;
+        let key = app.tools[index].key.clone();
+        app.log(format!("[warn] Skipping {key}: {reason}"));
+    }
+}
+
 pub fn install_selected(app: &mut App) {
     if app.state == ViewState::Installing { return; }
+    drop_offline_blocked(app);
     
     let selected_items: Vec<(String, Option<crate::resolver::ResolvedAsset>)> = app.tools.iter()
         .filter(|it| it.selected)
@@ -42,37 +540,87 @@ pub fn install_selected(app: &mut App) {
         .collect();
 
     if selected_items.is_empty() {
-        app.logs.push("[warn] No tools selected for installation".to_string());
+        app.log("[warn] No tools selected for installation".to_string());
         return;
     }
 
     app.state = ViewState::Installing;
     app.install_start = Some(Instant::now());
-    let (tx, rx) = mpsc::channel();
+    app.meter = Some(crate::resources::Meter::start());
+    app.run_resources = None;
+    app.records.clear();
+    app.last_report = None;
+    app.log_filter = None;
+    let (tx, rx) = app.events.waker().channel();
     app.installation_rx = Some(rx);
     
     app.progress.total = selected_items.len();
+    crate::ipc::publish(crate::ipc::ProgressEvent::Started { source: "tui".to_string(), total: selected_items.len() });
     app.progress.done = 0;
     app.progress.succeeded = 0;
     app.progress.failed = 0;
     app.progress.skipped = 0;
+    app.progress.skipped_items.clear();
+    app.progress.banner = None;
 
-    let (cancel_tx, cancel_rx) = mpsc::channel();
-    app.cancel_tx = Some(cancel_tx);
+    let cancel = CancellationToken::new();
+    app.cancel = Some(cancel.clone());
+    let current_tool = app.current_tool.clone();
 
     let catalog = app.catalog.clone();
     let distro = app.distro.clone();
     let client = app.client.clone();
     let dry_run = app.dry_run;
+    let recorder = (!dry_run).then(|| crate::state::RunRecorder::start("tui"));
+    // Failures offline say nothing about the entry.
+    let track_health = !dry_run && !app.offline;
+
+    // Later artifacts download while the first tools install.
+    let parallel = crate::preflight::parallel_downloads();
+    if !dry_run && !app.offline && parallel > 0 {
+        let ahead: Vec<crate::resolver::ResolvedAsset> = selected_items
+            .iter()
+            .skip(1)
+            .filter_map(|(key, resolved)| Some((catalog.software.get(key)?, resolved.as_ref()?)))
+            .filter(|(spec, resolved)| spec.source.has_artifact() && resolved.url.starts_with("http"))
+            .map(|(_, resolved)| resolved.clone())
+            .collect();
+        let (client, cancel) = (client.clone(), cancel.clone());
+        crate::runtime::spawn(async move {
+            crate::runtime::bounded(ahead, parallel, &cancel.clone(), |resolved| {
+                let (client, cancel) = (client.clone(), cancel.clone());
+                async move {
+                    let _ = crate::installer::fetch_ahead(&client, &resolved, &cancel).await;
+                    true
+                }
+            })
+            .await;
+        });
+    }
 
     thread::spawn(move || {
+        let record = |key: &str, version: &str, result: Result<&[std::path::PathBuf], &str>| {
+            let Some(recorder) = &recorder else { return };
+            if let Err(e) = recorder.record(key, version, result) {
+                let _ = tx.send(InstallMsg::Log(crate::app::LogLevel::Warn, format!("[warn] state database: {e}")));
+            }
+        };
+        let health = |key: &str, event: HealthEvent| {
+            if !track_health {
+                return;
+            }
+            if let Err(e) = crate::state::record_health([(key.to_string(), event)]) {
+                let _ = tx.send(InstallMsg::Log(crate::app::LogLevel::Warn, format!("[warn] state database: {e}")));
+            }
+        };
         for (key, resolved_opt) in selected_items {
+            let started = Instant::now();
             let _ = tx.send(InstallMsg::Progress(key.clone(), "Preparing".to_string(), None));
             
             let spec = match catalog.software.get(&key) {
                 Some(s) => s,
                 None => {
-                    let _ = tx.send(InstallMsg::Done(key, Err("Missing spec".to_string())));
+                    let _ = tx.send(InstallMsg::Done(key, Err(Error::Catalog("Missing spec".to_string()))));
                     continue;
                 }
             };
@@ -81,24 +629,78 @@ pub fn install_selected(app: &mut App) {
                 Some(r) => r,
                 None => {
                     let _ = tx.send(InstallMsg::Progress(key.clone(), "Resolving".to_string(), None));
-                    match crate::resolver::resolve_asset(&client, spec, &distro) {
+                    let resolved = crate::bundle::resolve(&key, spec, &distro)
+                        .unwrap_or_else(|| crate::runtime::block_on(crate::resolver::resolve_asset(&client, spec, &distro)));
+                    match resolved {
                         Ok(asset) => asset,
                         Err(e) => {
-                            let _ = tx.send(InstallMsg::Done(key, Err(format!("Resolve failed: {}", e))));
+                            let error = e.map_message(|message| format!("Resolve failed: {message}"));
+                            record(&key, "", Err(&error.to_string()));
+                            health(&key, HealthEvent::ResolveFailed(error.to_string()));
+                            let _ = tx.send(InstallMsg::Done(key, Err(error)));
                             continue;
                         }
                     }
                 }
             };
 
-            let _ = tx.send(InstallMsg::Progress(key.clone(), "Installing".to_string(), Some("BUSY".to_string())));
-            let result = install_software(&client, &key, spec, &resolved, &distro, dry_run, &tx, &cancel_rx)
-                .map(|outcome| outcome.logs);
-            
-            let is_cancelled = match &result {
-                Err(e) if e.contains("cancelled") => true,
-                _ => false,
-            };
+            let _ = tx.send(InstallMsg::Progress(key.clone(), "Installing".to_string(), None));
+            // Skipping cancels this tool's token; cancelling the run cancels it too.
+            let tool_cancel = cancel.child_token();
+            *current_tool.lock().unwrap_or_else(|e| e.into_inner()) = Some(tool_cancel.clone());
+            let outcome = install_software(&client, &key, spec, &resolved, &distro, dry_run, &tx, &tool_cancel);
+            let downloaded = outcome.as_ref().ok().and_then(|o| o.downloaded);
+            match &outcome {
+                Ok(o) => {
+                    record(&key, &resolved.version, Ok(&[o.created_dirs.as_slice(), &o.files].concat()));
+                    health(&key, HealthEvent::Installed);
+                }
+                Err(e) => {
+                    record(&key, &resolved.version, Err(&e.to_string()));
+                    if !e.is_cancelled() {
+                        health(&key, HealthEvent::InstallFailed(e.to_string()));
+                    }
+                }
+            }
+            let result = outcome.map(|o| o.logs);
+
+            let is_cancelled = matches!(&result, Err(e) if e.is_cancelled());
+            if is_cancelled && !cancel.is_cancelled() {
+                let _ = tx.send(InstallMsg::Record(RunRecord {
+                    kind: RecordKind::Install,
+                    key: key.clone(),
+                    name: spec.display_name.clone(),
+                    version: resolved.version.clone(),
+                    source: resolved.url.clone(),
+                    duration: started.elapsed(),
+                    bytes: None,
+                    status: RecordStatus::Skipped,
+                    verification: "n/a".to_string(),
+                }));
+                let _ = tx.send(InstallMsg::Skipped(key));
+                continue;
+            }
+
+            let _ = tx.send(InstallMsg::Record(RunRecord {
+                kind: RecordKind::Install,
+                key: key.clone(),
+                name: spec.display_name.clone(),
+                version: resolved.version.clone(),
+                source: resolved.url.clone(),
+                duration: started.elapsed(),
+                bytes: downloaded,
+                status: match &result {
+                    Ok(_) => RecordStatus::Succeeded,
+                    Err(e) => RecordStatus::Failed(e.to_string()),
+                },
+                verification: if dry_run {
+                    "skipped (dry-run)".to_string()
+                } else if result.is_ok() {
+                    verify_install(spec, &distro)
+                } else {
+                    "n/a".to_string()
+                },
+            }));
 
             let _ = tx.send(InstallMsg::Done(key, result));
             
@@ -106,6 +708,7 @@ pub fn install_selected(app: &mut App) {
                 break;
             }
         }
+        *current_tool.lock().unwrap_or_else(|e| e.into_inner()) = None;
         let _ = tx.send(InstallMsg::Finished);
     });
 }
@@ -124,7 +727,7 @@ pub fn update_file_picker(app: &mut App, dir: std::path::PathBuf) {
             let path = entry.path();
             if path.is_dir() {
                 dirs.push(path);
-            } else if path.extension().map_or(false, |e| e == "json") {
+            } else if path.extension().is_some_and(|e| e == "json") {
                 files.push(path);
             }
         }
@@ -136,44 +739,265 @@ pub fn update_file_picker(app: &mut App, dir: std::path::PathBuf) {
     app.state = ViewState::FilePicker { current_dir: dir, entries, cursor: 0 };
 }
 
-pub fn start_restore_from_file(app: &mut App, json_file: std::path::PathBuf) {
+/// Opens the restore picker for backups with groups; restores the others
+/// straight away, as they only have their files.
+pub fn pick_restore_parts(app: &mut App, json_file: std::path::PathBuf) {
+    let info = match json_file.parent().map(crate::restorer::load_backup_info) {
+        Some(Ok(info)) => info,
+        Some(Err(e)) => {
+            app.log(format!("[error] {e}"));
+            return;
+        }
+        None => return,
+    };
+    if info.groups.is_empty() {
+        start_restore_from_file(app, json_file, None);
+        return;
+    }
+    let files = (!info.zip_files.is_empty()).then(|| RestorePart {
+        group: None,
+        label: format!("Files of {} ({} archives)", info.source_path, info.zip_files.len()),
+        selected: true,
+    });
+    let groups = info.groups.iter().map(|group| RestorePart { group: Some(group.name.clone()), label: group.label.clone(), selected: true });
+    let parts = files.into_iter().chain(groups).collect();
+    app.state = ViewState::RestorePicker { json_file, parts, cursor: 0 };
+}
+
+/// Restores the backup `json_file` describes: the parts in `selection`, or
+/// all of it.
+pub fn start_restore_from_file(app: &mut App, json_file: std::path::PathBuf, selection: Option<crate::restorer::RestoreSelection>) {
     app.state = ViewState::Restoring;
     app.install_start = Some(Instant::now());
-    let (tx, rx) = mpsc::channel();
+    app.meter = Some(crate::resources::Meter::start());
+    app.run_resources = None;
+    app.records.clear();
+    app.last_report = None;
+    app.log_filter = None;
+    let (tx, rx) = app.events.waker().channel();
     app.installation_rx = Some(rx);
     
     app.progress.operation = "Restore".to_string();
     app.progress.current = "User Files".to_string();
     app.progress.total = 1;
+    crate::ipc::publish(crate::ipc::ProgressEvent::Started { source: format!("restore {}", json_file.display()), total: 1 });
     app.progress.done = 0;
     app.progress.succeeded = 0;
     app.progress.failed = 0;
     app.progress.skipped = 0;
 
-    let (cancel_tx, _cancel_rx) = mpsc::channel();
-    app.cancel_tx = Some(cancel_tx);
+    // Restores run to the end; the token only marks the run as cancellable.
+    app.cancel = Some(CancellationToken::new());
 
-    app.logs.push(format!("[restore] Starting restore using metadata: {}", json_file.display()));
+    app.log(format!("[restore] Starting restore using metadata: {}", json_file.display()));
 
     thread::spawn(move || {
         let backup_dir = match json_file.parent() {
             Some(p) => p,
             None => {
-                let _ = tx.send(InstallMsg::Done("Restore".to_string(), Err("Invalid JSON path".to_string())));
+                let _ = tx.send(InstallMsg::Done("Restore".to_string(), Err(Error::Other("Invalid JSON path".to_string()))));
                 let _ = tx.send(InstallMsg::Finished);
                 return;
             }
         };
 
-        let _ = tx.send(InstallMsg::Progress("Restore".to_string(), "Restoring Files".to_string(), Some("BUSY".to_string())));
+        let _ = tx.send(InstallMsg::Progress("Restore".to_string(), "Restoring Files".to_string(), None));
         
-        let result = crate::restorer::restore_backup(backup_dir, Some(&tx));
-        let logs_vec = match result {
-            Ok(l) => Ok(l),
-            Err(e) => Err(e),
+        let started = Instant::now();
+        let result = match &selection {
+            Some(selection) => crate::restorer::restore_selected(backup_dir, selection, Some(&tx)),
+            None => crate::restorer::restore_backup(backup_dir, Some(&tx)),
         };
-
-        let _ = tx.send(InstallMsg::Done("Restore".to_string(), logs_vec));
+        let _ = tx.send(InstallMsg::Record(RunRecord {
+            kind: RecordKind::Restore,
+            key: "Restore".to_string(),
+            name: "User Files".to_string(),
+            version: "-".to_string(),
+            source: backup_dir.display().to_string(),
+            duration: started.elapsed(),
+            bytes: None,
+            status: match &result {
+                Ok(_) => RecordStatus::Succeeded,
+                Err(e) => RecordStatus::Failed(e.to_string()),
+            },
+            verification: match &result {
+                Ok(logs) => {
+                    let warnings = logs.iter().filter(|l| l.contains("Integrity check FAILED")).count();
+                    if warnings == 0 { "integrity ok".to_string() } else { format!("{warnings} integrity warning(s)") }
+                }
+                Err(_) => "n/a".to_string(),
+            },
+        }));
+        let _ = tx.send(InstallMsg::Done("Restore".to_string(), result));
         let _ = tx.send(InstallMsg::Finished);
     });
 }
+
+/// Measures the install directories and download cache on a worker, since
+/// walking a large home takes a while; the view opens once it reports back.
+pub fn open_disk_usage(app: &mut App) {
+    app.log("[usage] Measuring install directories and download cache...".to_string());
+    let (tx, rx) = app.events.waker().channel();
+    app.installation_rx = Some(rx);
+    let catalog = app.catalog.clone();
+    thread::spawn(move || {
+        let entries = crate::usage::collect_usage(&catalog);
+        let total: u64 = entries.iter().map(|e| e.bytes).sum();
+        let line = format!("[usage] {} entries using {}", entries.len(), crate::units::format_size(total));
+        let _ = tx.send(InstallMsg::Log(crate::events::LogLevel::Info, line));
+        let _ = tx.send(InstallMsg::DiskUsage(entries));
+    });
+}
+
+/// Every catalog entry with its recorded health, the ones failing for the
+/// most sessions first.
+fn maintenance_rows(catalog: &crate::catalog::CatalogFile) -> Vec<MaintenanceRow> {
+    let mut health = crate::state::StateDb::load().health;
+    let mut rows: Vec<MaintenanceRow> = catalog
+        .software
+        .iter()
+        .map(|(key, spec)| MaintenanceRow {
+            key: key.clone(),
+            name: spec.display_name.clone(),
+            health: health.remove(key).unwrap_or_default(),
+        })
+        .collect();
+    rows.sort_by_key(|row| std::cmp::Reverse(row.health.failing_sessions));
+    rows
+}
+
+pub fn open_maintenance(app: &mut App) {
+    let rows = maintenance_rows(&app.catalog);
+    let threshold = crate::state::attention_after_sessions();
+    let attention = rows.iter().filter(|r| r.health.needs_attention(threshold)).count();
+    app.log(format!("[maintenance] {attention} of {} entries failed in {threshold}+ sessions in a row", rows.len()));
+    app.state = ViewState::Maintenance { rows, cursor: 0 };
+}
+
+/// Points at entries that keep failing, once at startup, so a shared
+/// catalog does not rot unnoticed.
+pub fn warn_needing_attention(app: &mut App) {
+    let threshold = crate::state::attention_after_sessions();
+    let keys: Vec<&str> = app
+        .installed
+        .health
+        .iter()
+        .filter(|(key, health)| health.needs_attention(threshold) && app.catalog.software.contains_key(*key))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if keys.is_empty() {
+        return;
+    }
+    let message = format!("[warn] Failing for {threshold}+ sessions in a row: {}. Press 'w' for the maintenance view.", keys.join(", "));
+    app.log(message);
+}
+
+pub fn open_quarantine(app: &mut App) {
+    let entries = crate::quarantine::list();
+    if let Some(dir) = crate::quarantine::quarantine_dir() {
+        app.log(format!("[quarantine] {} download(s) in {}", entries.len(), dir.display()));
+    }
+    app.state = ViewState::Quarantine { entries, cursor: 0 };
+}
+
+/// Deletes the highlighted quarantined download, or all of them.
+pub fn purge_quarantined(app: &mut App, all: bool) {
+    let ViewState::Quarantine { ref entries, cursor } = app.state else { return };
+    let targets: Vec<_> = if all { entries.clone() } else { entries.get(cursor).cloned().into_iter().collect() };
+    let mut purged = 0;
+    let mut freed = 0;
+    for entry in &targets {
+        match crate::quarantine::purge(&entry.dir) {
+            Ok(()) => {
+                purged += 1;
+                freed += entry.bytes;
+            }
+            Err(e) => app.log(format!("[error] {e}")),
+        }
+    }
+    if purged > 0 {
+        app.log(format!("[quarantine] Purged {purged} download(s), freeing {}", crate::units::format_size(freed)));
+    }
+    let entries = crate::quarantine::list();
+    let cursor = cursor.min(entries.len().saturating_sub(1));
+    app.state = ViewState::Quarantine { entries, cursor };
+}
+
+pub fn onboarding_state() -> ViewState {
+    let download_dir = crate::installer::default_download_dir()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    ViewState::Onboarding {
+        step: OnboardingStep::Profile,
+        profile: 0,
+        install_root: "~/".to_string(),
+        download_dir,
+    }
+}
+
+/// Drives the first-run wizard. Returns false when the user quits.
+pub fn handle_onboarding_key(app: &mut App, code: KeyCode) -> bool {
+    let ViewState::Onboarding { ref mut step, ref mut profile, ref mut install_root, ref mut download_dir } = app.state else {
+        return true;
+    };
+    let profiles = crate::catalog::STARTER_PROFILES.len();
+    match (*step, code) {
+        (OnboardingStep::Profile, KeyCode::Esc) => return false,
+        (OnboardingStep::Profile, KeyCode::Up) => *profile = profile.saturating_sub(1),
+        (OnboardingStep::Profile, KeyCode::Down) => *profile = (*profile + 1).min(profiles - 1),
+        (OnboardingStep::Profile, KeyCode::Enter) => *step = OnboardingStep::InstallRoot,
+        (OnboardingStep::InstallRoot, KeyCode::Esc) => *step = OnboardingStep::Profile,
+        (OnboardingStep::InstallRoot, KeyCode::Enter) => *step = OnboardingStep::DownloadDir,
+        (OnboardingStep::InstallRoot, KeyCode::Backspace) => { install_root.pop(); }
+        (OnboardingStep::InstallRoot, KeyCode::Char(c)) => install_root.push(c),
+        (OnboardingStep::DownloadDir, KeyCode::Esc) => *step = OnboardingStep::InstallRoot,
+        (OnboardingStep::DownloadDir, KeyCode::Enter) => *step = OnboardingStep::Confirm,
+        (OnboardingStep::DownloadDir, KeyCode::Backspace) => { download_dir.pop(); }
+        (OnboardingStep::DownloadDir, KeyCode::Char(c)) => download_dir.push(c),
+        (OnboardingStep::Confirm, KeyCode::Esc) => *step = OnboardingStep::DownloadDir,
+        (OnboardingStep::Confirm, KeyCode::Enter) => finish_onboarding(app),
+        _ => {}
+    }
+    true
+}
+
+fn finish_onboarding(app: &mut App) {
+    let ViewState::Onboarding { profile, ref install_root, ref download_dir, .. } = app.state else {
+        return;
+    };
+    let starter = &crate::catalog::STARTER_PROFILES[profile];
+    let non_empty = |s: &String| if s.trim().is_empty() { None } else { Some(s.trim().to_string()) };
+
+    let result = (|| -> Result<(std::path::PathBuf, std::path::PathBuf), String> {
+        let catalog_path = crate::config::config_dir()?.join(crate::config::CATALOG_FILE_NAME);
+        let content = crate::catalog::starter_catalog(starter)?;
+        std::fs::create_dir_all(crate::config::config_dir()?).map_err(|e| e.to_string())?;
+        std::fs::write(&catalog_path, content)
+            .map_err(|e| format!("failed to write {}: {e}", catalog_path.display()))?;
+        let config = crate::config::Config {
+            catalog_path: Some(catalog_path.display().to_string()),
+            install_root: non_empty(install_root),
+            download_dir: non_empty(download_dir),
+            ..Default::default()
+        };
+        let config_path = crate::config::save_config(&config)?;
+        Ok((config_path, catalog_path))
+    })();
+
+    match result.and_then(|(config_path, catalog_path)| {
+        let catalog = crate::catalog::load_catalog(&catalog_path)?;
+        Ok((config_path, catalog_path, catalog))
+    }) {
+        Ok((config_path, catalog_path, catalog)) => {
+            app.tools = crate::app::tools_from_catalog(&catalog);
+            app.catalog = catalog;
+            app.catalog_mtime = modified_time(&catalog_path);
+            app.catalog_path = Some(catalog_path.clone());
+            app.cursor = 0;
+            app.state = ViewState::Browsing;
+            app.log(format!("[done] Wrote config to {}", config_path.display()));
+            app.log(format!("[done] Wrote '{}' catalog to {}", starter.name, catalog_path.display()));
+        }
+        Err(e) => app.log(format!("[error] Onboarding failed: {e}")),
+    }
+}
@@ -1,84 +1,239 @@
 pub mod state;
 pub mod ui;
 pub mod actions;
+pub mod new_entry;
+pub mod palette;
+pub mod screen;
+pub mod settings;
+pub mod startup;
+pub mod wake;
 
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::Event;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{execute, ExecutableCommand};
-use reqwest::blocking::Client;
+use reqwest::Client;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use sysinfo::System;
+use tokio_util::sync::CancellationToken;
 
-use crate::catalog::{load_catalog, CatalogFile};
+use crate::catalog::CatalogFile;
 use crate::distro::{detect_distro, DistroInfo};
+use crate::error::Error;
 use crate::resolver::ResolvedAsset;
+use crate::state::HealthEvent;
 
-pub use state::{ProgressInfo, ToolItem, ViewState, InstallMsg};
+pub use crate::events::{ArchivePreview, InstallMsg, LogLevel};
+pub use state::{CatalogRow, DetailPopup, ProbePopup, ProgressInfo, ResolveStatus, ToolItem, ViewState, LogEntry, SecretPrompt, SudoPrompt};
 
 pub struct App {
     pub(crate) catalog: CatalogFile,
+    pub(crate) catalog_path: Option<PathBuf>,
+    pub(crate) catalog_mtime: Option<std::time::SystemTime>,
+    pub(crate) watch_catalog: bool,
     pub(crate) distro: DistroInfo,
     pub(crate) client: Client,
     pub(crate) tools: Vec<ToolItem>,
+    /// Index into [`App::catalog_rows`].
     pub(crate) cursor: usize,
-    pub(crate) logs: Vec<String>,
+    /// Categories whose entries are hidden in the catalog list.
+    pub(crate) collapsed: std::collections::BTreeSet<String>,
+    /// Keys from `hidden` in the config, left out of the list unless
+    /// `show_hidden` is on.
+    pub(crate) hidden: std::collections::BTreeSet<String>,
+    pub(crate) show_hidden: bool,
+    /// Width of the catalog list in the browsing view, in percent.
+    pub(crate) catalog_split: u16,
+    pub(crate) logs: Vec<LogEntry>,
+    pub(crate) active_tool: Option<String>,
+    pub(crate) log_filter: Option<String>,
+    pub(crate) level_filter: Option<LogLevel>,
     pub(crate) dry_run: bool,
+    /// `download_warn_mb` from the config, read once at startup.
+    pub(crate) download_warn_mb: u64,
+    pub(crate) theme: crate::config::Theme,
+    /// Result of the startup connectivity probe; `None` while it runs or
+    /// when it is turned off.
+    pub(crate) connectivity: Option<crate::connectivity::Connectivity>,
+    pub(crate) connectivity_rx: Option<mpsc::Receiver<crate::connectivity::Connectivity>>,
+    /// Only package-manager entries and downloads already in the download
+    /// folder can be resolved and installed.
+    pub(crate) offline: bool,
     pub(crate) progress: ProgressInfo,
     pub(crate) state: ViewState,
     pub(crate) sys: System,
-    pub(crate) resolution_rx: Option<mpsc::Receiver<(String, Result<ResolvedAsset, String>)>>,
+    pub(crate) load_monitor: crate::throttle::LoadMonitor,
+    pub(crate) resolution_rx: Option<mpsc::Receiver<(String, Result<ResolvedAsset, Error>)>>,
     pub(crate) installation_rx: Option<mpsc::Receiver<InstallMsg>>,
-    pub(crate) cancel_tx: Option<mpsc::Sender<()>>,
+    pub(crate) install_log: crate::install_log::InstallLog,
+    pub(crate) size_rx: Option<mpsc::Receiver<(String, crate::preflight::LinkCheck)>>,
+    pub(crate) preview_rx: Option<mpsc::Receiver<(String, Result<ArchivePreview, Error>)>>,
+    pub(crate) advisory_rx: Option<mpsc::Receiver<(String, crate::advisory::Advisories)>>,
+    pub(crate) probe_rx: Option<mpsc::Receiver<(String, crate::resolver::Probe)>>,
+    /// Test resolution of the entry in the new-entry form.
+    pub(crate) entry_test_rx: Option<mpsc::Receiver<Result<ResolvedAsset, Error>>>,
+    /// sudo's verdict on the password typed into the prompt.
+    pub(crate) sudo_rx: Option<mpsc::Receiver<Result<(), String>>>,
+    /// Set once a privileged step authenticated during the current run, so the
+    /// sudo credential can be dropped when it ends.
+    pub(crate) elevated: bool,
+    /// Popups over the current view, topmost last.
+    pub(crate) modals: Vec<screen::Modal>,
+    /// Cancels the install or restore in progress.
+    pub(crate) cancel: Option<CancellationToken>,
+    /// Token of the tool being installed; skipping cancels it, so the worker
+    /// abandons that tool only and carries on.
+    pub(crate) current_tool: Arc<Mutex<Option<CancellationToken>>>,
+    /// Stops the resolution batch in flight when a new one replaces it.
+    pub(crate) resolution_cancel: Option<CancellationToken>,
     pub(crate) install_start: Option<Instant>,
     pub(crate) is_resolving: bool,
+    pub(crate) records: Vec<crate::report::RunRecord>,
+    /// Accounts for the run in progress.
+    pub(crate) meter: Option<crate::resources::Meter>,
+    /// What the last run cost, once it finished.
+    pub(crate) run_resources: Option<crate::resources::RunResources>,
+    pub(crate) last_report: Option<PathBuf>,
+    pub(crate) report_html: bool,
+    /// Tools recorded as installed, reloaded after every run.
+    pub(crate) installed: crate::state::StateDb,
+    /// Key presses and wake-ups from background work.
+    pub(crate) events: wake::Events,
+}
+
+/// How often CPU and memory usage are sampled while nothing else happens.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Heading for entries without a `category`; listed last.
+pub(crate) const UNCATEGORIZED: &str = "Other";
+
+pub(crate) fn category_of(catalog: &CatalogFile, key: &str) -> String {
+    catalog
+        .software
+        .get(key)
+        .and_then(|spec| spec.category.clone())
+        .unwrap_or_else(|| UNCATEGORIZED.to_string())
+}
+
+/// Catalog entries grouped by category (alphabetically, uncategorized last),
+/// then by key.
+pub(crate) fn tools_from_catalog(catalog: &CatalogFile) -> Vec<ToolItem> {
+    let hidden = crate::config::current().hidden;
+    let mut tools: Vec<ToolItem> = catalog
+        .software
+        .iter()
+        // Driver entries only show up on machines with the hardware.
+        .filter(|(_, spec)| spec.only_if_hardware.is_none_or(crate::hardware::present))
+        .map(|(key, spec)| ToolItem {
+            key: key.clone(),
+            selected: spec.enabled_by_default && !hidden.contains(key) && crate::distro::Environment::current().incompatibility(spec).is_none(),
+            resolved: None,
+            status: ResolveStatus::Idle,
+            last_error: None,
+            resolve_failures: 0,
+            retry_at: None,
+            cached_at: None,
+        })
+        .collect();
+    tools.sort_by_cached_key(|t| {
+        let category = catalog.software.get(&t.key).and_then(|spec| spec.category.clone());
+        (category.is_none(), category, t.key.clone())
+    });
+    tools
 }
 
 impl App {
     pub fn new() -> Result<Self, String> {
-        let root = std::env::current_dir().map_err(|e| e.to_string())?;
-        let catalog_path: PathBuf = root.join("software_catalog.toml");
-        let catalog = load_catalog(&catalog_path).map_err(|e| e.to_string())?;
+        let (catalog_path, catalog, load_error) = match startup::load() {
+            Ok(Some((path, catalog))) => (Some(path), catalog, None),
+            Ok(None) => (None, CatalogFile::default(), None),
+            Err(error) => (None, CatalogFile::default(), Some(error)),
+        };
         let distro = detect_distro().map_err(|e| e.to_string())?;
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("rusty_rebase/0.1")
-            .build()
-            .map_err(|e| e.to_string())?;
-
-        let tools = catalog
-            .software
-            .iter()
-            .map(|(key, spec)| ToolItem {
-                key: key.clone(),
-                selected: spec.enabled_by_default,
-                resolved: None,
-            })
-            .collect();
+        let client = crate::resolver::build_client()?;
+        let config = crate::config::current();
+        let events = wake::Events::new();
+        let connectivity_rx = crate::connectivity::probe_url().map(|url| {
+            let (tx, rx) = events.waker().channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(crate::connectivity::probe(&url));
+            });
+            rx
+        });
+        let tools = tools_from_catalog(&catalog);
+        let state = if let Some(error) = load_error {
+            ViewState::LoadError(Box::new(error))
+        } else if catalog_path.is_some() {
+            ViewState::Browsing
+        } else {
+            actions::onboarding_state()
+        };
 
         let mut sys = System::new_all();
         sys.refresh_all();
 
-        Ok(Self {
+        let catalog_mtime = catalog_path.as_deref().and_then(actions::modified_time);
+
+        let mut app = Self {
             catalog,
+            catalog_path,
+            catalog_mtime,
+            watch_catalog: false,
             distro,
             client,
             tools,
             cursor: 0,
-            logs: vec!["Ready. Press 'r' to resolve versions or 'i' to install selected tools.".to_string()],
-            dry_run: true,
+            collapsed: Default::default(),
+            hidden: config.hidden.iter().cloned().collect(),
+            show_hidden: false,
+            catalog_split: ui::DEFAULT_CATALOG_SPLIT,
+            logs: vec![LogEntry::new("Ready. Press 'r' to resolve versions or 'i' to install selected tools.")],
+            active_tool: None,
+            log_filter: None,
+            level_filter: None,
+            dry_run: config.dry_run != Some(false),
+            download_warn_mb: config.download_warn_mb.unwrap_or(crate::preflight::DEFAULT_DOWNLOAD_WARN_MB),
+            theme: config.theme.unwrap_or_default(),
+            connectivity: None,
+            connectivity_rx,
+            offline: false,
             progress: ProgressInfo::default(),
-            state: ViewState::Browsing,
+            state,
             sys,
+            load_monitor: crate::throttle::LoadMonitor::new(
+                config
+                    .throttle_cpu_percent
+                    .unwrap_or(crate::throttle::DEFAULT_CPU_THRESHOLD),
+            ),
             resolution_rx: None,
             installation_rx: None,
-            cancel_tx: None,
+            install_log: crate::install_log::InstallLog::spawn(),
+            size_rx: None,
+            preview_rx: None,
+            advisory_rx: None,
+            probe_rx: None,
+            entry_test_rx: None,
+            sudo_rx: None,
+            elevated: false,
+            modals: Vec::new(),
+            cancel: None,
+            current_tool: Arc::new(Mutex::new(None)),
+            resolution_cancel: None,
             install_start: None,
             is_resolving: false,
-        })
+            records: Vec::new(),
+            meter: None,
+            run_resources: None,
+            last_report: None,
+            report_html: false,
+            installed: crate::state::StateDb::load(),
+            events,
+        };
+        actions::warn_needing_attention(&mut app);
+        Ok(app)
     }
 
     pub fn run(&mut self) -> Result<(), String> {
@@ -86,7 +241,8 @@ impl App {
             return Err(format!("failed to enable raw mode: {e}"));
         }
         let mut stdout = std::io::stdout();
-        if let Err(e) = execute!(stdout, EnterAlternateScreen) {
+        let alternate_screen = crate::run_context::current().alternate_screen();
+        if alternate_screen && let Err(e) = execute!(stdout, EnterAlternateScreen) {
             return Err(format!("failed to enter alternate screen: {e}"));
         }
 
@@ -96,36 +252,349 @@ impl App {
             Err(e) => return Err(format!("failed to create terminal: {e}")),
         };
 
+        if !alternate_screen {
+            terminal.clear().ok();
+        }
+        self.events.listen();
         let result = self.event_loop(&mut terminal);
+        self.events.stop();
+        self.install_log.flush();
 
         disable_raw_mode().ok();
-        terminal.backend_mut().execute(LeaveAlternateScreen).ok();
+        if alternate_screen {
+            terminal.backend_mut().execute(LeaveAlternateScreen).ok();
+        } else {
+            terminal.clear().ok();
+        }
         terminal.show_cursor().ok();
 
         result
     }
 
+    /// Rows of the catalog list: the header of every category with listed
+    /// entries, followed by them unless the category is collapsed.
+    pub(crate) fn catalog_rows(&self) -> Vec<CatalogRow> {
+        let mut rows = Vec::new();
+        let mut current: Option<String> = None;
+        for (idx, tool) in self.tools.iter().enumerate().filter(|(_, t)| self.is_listed(&t.key)) {
+            let category = category_of(&self.catalog, &tool.key);
+            if current.as_ref() != Some(&category) {
+                rows.push(CatalogRow::Category(category.clone()));
+                current = Some(category.clone());
+            }
+            if !self.collapsed.contains(&category) {
+                rows.push(CatalogRow::Tool(idx));
+            }
+        }
+        rows
+    }
+
+    /// Whether the entry shows up in the list: it isn't hidden, or hidden
+    /// entries are being shown.
+    pub(crate) fn is_listed(&self, key: &str) -> bool {
+        self.show_hidden || !self.hidden.contains(key)
+    }
+
+    /// The entry under the cursor; `None` on a category header.
+    pub(crate) fn current_tool(&self) -> Option<&ToolItem> {
+        match self.catalog_rows().get(self.cursor) {
+            Some(CatalogRow::Tool(idx)) => self.tools.get(*idx),
+            _ => None,
+        }
+    }
+
+    /// Space on an entry toggles it; on a category header it selects the
+    /// whole category, or clears it when everything is already selected.
+    fn toggle_selection(&mut self) {
+        match self.catalog_rows().get(self.cursor) {
+            Some(CatalogRow::Tool(idx)) => {
+                let key = self.tools[*idx].key.clone();
+                if let Some(reason) = self.unavailable(&key) {
+                    self.log(format!("[warn] {key} cannot be selected: {reason}"));
+                } else if let Some(item) = self.tools.get_mut(*idx) {
+                    item.selected = !item.selected;
+                }
+            }
+            Some(CatalogRow::Category(category)) => {
+                let available: Vec<bool> = self
+                    .tools
+                    .iter()
+                    .map(|t| {
+                        &category_of(&self.catalog, &t.key) == category && !self.hidden.contains(&t.key) && self.unavailable(&t.key).is_none()
+                    })
+                    .collect();
+                let members: Vec<&mut ToolItem> = self.tools.iter_mut().zip(available).filter(|(_, ok)| *ok).map(|(t, _)| t).collect();
+                let select = !members.iter().all(|t| t.selected);
+                for item in members {
+                    item.selected = select;
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Why the entry doesn't suit this machine (WSL, container, no
+    /// desktop) or can't be installed in offline mode, if it doesn't.
+    pub(crate) fn unavailable(&self, key: &str) -> Option<String> {
+        let spec = self.catalog.software.get(key)?;
+        self.distro.environment.incompatibility(spec).or_else(|| self.offline_blocker(key))
+    }
+
+    /// Why offline mode rules the entry out: it builds from a checkout or
+    /// its download is not in the download folder.
+    pub(crate) fn offline_blocker(&self, key: &str) -> Option<String> {
+        if !self.offline {
+            return None;
+        }
+        let spec = self.catalog.software.get(key)?;
+        if matches!(spec.source, crate::catalog::SourceSpec::BuildFromSource { .. }) {
+            return Some("offline mode: builds from a git checkout".to_string());
+        }
+        if !spec.source.has_artifact() {
+            return None;
+        }
+        match self.tools.iter().find(|t| t.key == key).and_then(|t| t.resolved.as_ref()) {
+            Some(resolved) if crate::installer::cached_artifact(resolved) => None,
+            Some(_) => Some("offline mode: download is not cached".to_string()),
+            None => Some("offline mode: no cached download found yet (press r to look)".to_string()),
+        }
+    }
+
+    /// Collapses or expands the category of the row under the cursor and
+    /// leaves the cursor on its header.
+    fn set_collapsed(&mut self, collapse: Option<bool>) {
+        let rows = self.catalog_rows();
+        let category = match rows.get(self.cursor) {
+            Some(CatalogRow::Category(category)) => category.clone(),
+            Some(CatalogRow::Tool(idx)) => category_of(&self.catalog, &self.tools[*idx].key),
+            None => return,
+        };
+        let collapse = collapse.unwrap_or(!self.collapsed.contains(&category));
+        if collapse {
+            self.collapsed.insert(category.clone());
+        } else {
+            self.collapsed.remove(&category);
+        }
+        self.cursor = self
+            .catalog_rows()
+            .iter()
+            .position(|row| row == &CatalogRow::Category(category.clone()))
+            .unwrap_or(0);
+    }
+
+    /// Hides the entry under the cursor, or unhides it when it is already
+    /// hidden, and saves the set to the config. Hiding also deselects it.
+    fn toggle_hidden(&mut self) {
+        let Some(key) = self.current_tool().map(|t| t.key.clone()) else { return };
+        let mut config = crate::config::current();
+        let hide = !self.hidden.contains(&key);
+        if hide {
+            config.hidden.push(key.clone());
+        } else {
+            config.hidden.retain(|k| *k != key);
+        }
+        if let Err(e) = crate::config::save_config(&config) {
+            self.log(format!("[error] {e}"));
+            return;
+        }
+        self.hidden = config.hidden.into_iter().collect();
+        if hide {
+            if let Some(item) = self.tools.iter_mut().find(|t| t.key == key) {
+                item.selected = false;
+            }
+            let hint = if self.show_hidden { "" } else { " (H shows hidden entries)" };
+            self.log(format!("Hid {key}{hint}"));
+        } else {
+            self.log(format!("Unhid {key}"));
+        }
+        self.cursor = self.cursor.min(self.catalog_rows().len().saturating_sub(1));
+    }
+
+    /// Shows or leaves out hidden entries, keeping the cursor on the same
+    /// row when it is still listed.
+    fn toggle_show_hidden(&mut self) {
+        let current = self.catalog_rows().get(self.cursor).cloned();
+        self.show_hidden = !self.show_hidden;
+        let rows = self.catalog_rows();
+        self.cursor = current
+            .and_then(|row| rows.iter().position(|r| *r == row))
+            .unwrap_or(self.cursor)
+            .min(rows.len().saturating_sub(1));
+        let shown = self.hidden.len();
+        self.log(if self.show_hidden { format!("Showing {shown} hidden entries") } else { "Hidden entries left out".to_string() });
+    }
+
+    pub(crate) fn log(&mut self, text: impl Into<String>) {
+        self.logs.push(LogEntry::new(text));
+    }
+
+    /// Cycles the Terminal Output focus through the queued tools and back to
+    /// showing everything.
+    fn cycle_log_filter(&mut self) {
+        let keys: Vec<String> = self.tools.iter().filter(|t| t.selected).map(|t| t.key.clone()).collect();
+        self.log_filter = match &self.log_filter {
+            None => keys.first().cloned(),
+            Some(current) => keys
+                .iter()
+                .position(|k| k == current)
+                .and_then(|idx| keys.get(idx + 1).cloned()),
+        };
+    }
+
+    fn cycle_level_filter(&mut self) {
+        self.level_filter = match self.level_filter {
+            None => Some(LogLevel::Error),
+            Some(LogLevel::Error) => Some(LogLevel::Warn),
+            Some(LogLevel::Warn) => Some(LogLevel::Command),
+            Some(_) => None,
+        };
+    }
+
+    /// Focuses the Terminal Output on the highlighted tool, or clears the focus
+    /// when it is already on it.
+    fn toggle_tool_filter(&mut self) {
+        let key = self.current_tool().map(|t| t.key.clone());
+        self.log_filter = if self.log_filter == key { None } else { key };
+    }
+
+    /// Moves the divider between the catalog list and the details pane.
+    fn resize_catalog(&mut self, grow: bool) {
+        self.catalog_split = if grow {
+            (self.catalog_split + 5).min(ui::MAX_CATALOG_SPLIT)
+        } else {
+            self.catalog_split.saturating_sub(5).max(ui::MIN_CATALOG_SPLIT)
+        };
+    }
+
+    /// Switches to the next package manager for this platform, for when
+    /// detection guessed wrong. Resolutions are dropped: package versions
+    /// and the preferred download format depend on the manager.
+    fn cycle_package_manager(&mut self) {
+        if self.is_resolving {
+            self.log("[warn] Cannot switch the package manager while resolving".to_string());
+            return;
+        }
+        let names = crate::distro::selectable_managers();
+        let current = self.distro.pkg_manager.name();
+        let next = names.iter().position(|n| *n == current).map_or(0, |i| (i + 1) % names.len());
+        let pkg_manager = match crate::distro::package_manager_by_name(names[next]) {
+            Ok(pm) => pm,
+            Err(e) => {
+                self.log(format!("[error] {e}"));
+                return;
+            }
+        };
+        self.distro = self.distro.with_package_manager(pkg_manager);
+        for tool in &mut self.tools {
+            tool.resolved = None;
+            tool.status = ResolveStatus::Idle;
+        }
+        self.log(format!("Package manager set to {}. Press 'r' to resolve again.", names[next]));
+    }
+
+    /// Writes the install report for this session's records into the current
+    /// directory and remembers its path.
+    fn write_report(&mut self) {
+        if self.records.is_empty() {
+            return;
+        }
+        let dir = std::env::current_dir().unwrap_or_default();
+        match crate::report::write_report(&dir, &self.records, self.run_resources.as_ref(), self.dry_run, self.report_html) {
+            Ok(path) => {
+                self.log(format!("[done] Report written to {}", path.display()));
+                self.last_report = Some(path);
+            }
+            Err(e) => self.log(format!("[error] {e}")),
+        }
+    }
+
+    /// CPU and memory for the header, the load throttle and the run's
+    /// resource meter, and the catalog file when it is watched.
+    fn sample(&mut self) {
+        self.sys.refresh_cpu_all();
+        self.sys.refresh_memory();
+        self.load_monitor.sample(self.sys.global_cpu_usage());
+        if let Some(meter) = self.meter.as_mut() {
+            meter.sample(&mut self.sys);
+        }
+
+        if self.watch_catalog
+            && self.state == ViewState::Browsing
+            && let Some(path) = &self.catalog_path
+            && actions::modified_time(path) != self.catalog_mtime
+        {
+            actions::reload_catalog(self);
+        }
+    }
+
+    /// Sleeps until a key is pressed, background work reports, or the next
+    /// sample is due; nothing is redrawn in between.
     fn event_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<(), String> {
+        let mut sample_due = Instant::now();
         loop {
-            self.sys.refresh_cpu_all();
-            self.sys.refresh_memory();
+            if Instant::now() >= sample_due {
+                sample_due = Instant::now() + SAMPLE_INTERVAL;
+                self.sample();
+            }
+
+            if let Some(result) = self.connectivity_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                self.connectivity_rx = None;
+                if let Some(banner) = result.banner()
+                    && !self.offline
+                {
+                    let detail = result.detail().map(|d| format!(" ({d})")).unwrap_or_default();
+                    self.log(format!("[warn] {banner}{detail}. Press 'o' for offline mode: package-manager and cached installs only."));
+                }
+                self.connectivity = Some(result);
+            }
 
             if let Some(ref rx) = self.resolution_rx {
+                // Failures while offline or behind a captive portal say
+                // nothing about the entry.
+                let network_down = self.offline || self.connectivity.as_ref().is_some_and(|c| c.banner().is_some());
+                let mut health = Vec::new();
                 while let Ok((key, result)) = rx.try_recv() {
                     match result {
                         Ok(asset) => {
-                            self.logs.push(format!("[done] Resolved {} to {}", key, asset.version));
+                            if !self.offline {
+                                health.push((key.clone(), HealthEvent::Resolved));
+                            }
+                            self.logs.push(LogEntry::tagged(Some(key.clone()), format!("[done] Resolved {} to {}", key, asset.version)));
+                            // Offline and bundle resolutions point at local files.
+                            if !self.offline
+                                && let Some(spec) = self.catalog.software.get(&key)
+                                && let Err(e) = crate::resolve_cache::store(&key, spec, &self.distro, &asset)
+                            {
+                                self.logs.push(LogEntry::new(format!("[warn] {e}")));
+                            }
                             if let Some(tool) = self.tools.iter_mut().find(|t| t.key == key) {
+                                tool.cached_at = None;
                                 tool.resolved = Some(asset);
+                                tool.status = ResolveStatus::Resolved;
+                                tool.last_error = None;
+                                tool.resolve_failures = 0;
+                                tool.retry_at = None;
                             }
                         }
                         Err(err) => {
-                            self.logs.push(format!("[error] Failed to resolve {}: {}", key, err));
+                            self.logs.push(LogEntry::tagged(Some(key.clone()), format!("[error] Failed to resolve {}: {}", key, err)));
+                            if !network_down && !err.is_cancelled() {
+                                health.push((key.clone(), HealthEvent::ResolveFailed(err.to_string())));
+                            }
+                            if let Some(tool) = self.tools.iter_mut().find(|t| t.key == key) {
+                                tool.last_error = Some(format!("resolve: {err}"));
+                                tool.status = ResolveStatus::Failed(err.to_string());
+                                tool.resolve_failures += 1;
+                                tool.retry_at = Some(Instant::now() + actions::retry_backoff(tool.resolve_failures));
+                            }
                         }
                     }
                     self.progress.done += 1;
                 }
-                
+                if let Err(e) = crate::state::record_health(health) {
+                    self.logs.push(LogEntry::new(format!("[warn] state database: {e}")));
+                }
+
                 if self.progress.done >= self.progress.total {
                     self.resolution_rx = None;
                     self.is_resolving = false;
@@ -133,11 +602,64 @@ impl App {
                 }
             }
 
+            let checks: Vec<_> = self.size_rx.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default();
+            for (key, check) in checks {
+                if let Some(detail) = self.detail_mut()
+                    && detail.key == key
+                {
+                    detail.size = check.clone().ok().flatten().or(detail.size);
+                    detail.sizing = false;
+                }
+                if let ViewState::ConfirmInstall { ref mut items, .. } = self.state
+                    && let Some(item) = items.iter_mut().find(|i| i.key == key)
+                {
+                    item.size = check.as_ref().ok().copied().flatten().or(item.size);
+                    item.problem = check.err();
+                    item.sizing = false;
+                }
+            }
+
+            if let Some((key, contents)) = self.preview_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                if let Some(detail) = self.detail_mut()
+                    && detail.key == key
+                {
+                    detail.contents = Some(contents);
+                    detail.previewing = false;
+                }
+                self.preview_rx = None;
+            }
+
+            if let Some((key, advisories)) = self.advisory_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                if let Some(detail) = self.detail_mut()
+                    && detail.key == key
+                {
+                    detail.advisories = Some(advisories);
+                    detail.checking = false;
+                }
+                self.advisory_rx = None;
+            }
+
+            if let Some((key, probe)) = self.probe_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                self.probe_rx = None;
+                actions::finish_probe(self, key, probe);
+            }
+
+            if let Some(result) = self.entry_test_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                self.entry_test_rx = None;
+                new_entry::finish_test(self, result);
+            }
+
             let mut finished = false;
+            let mut measured = false;
+            let mut elevation = None;
             if let Some(ref rx) = self.installation_rx {
                 while let Ok(msg) = rx.try_recv() {
+                    crate::ipc::publish_msg(&msg);
                     match msg {
                         InstallMsg::Progress(key, op, speed) => {
+                            if !key.is_empty() {
+                                self.active_tool = Some(key.clone());
+                            }
                             self.progress.current = key;
                             self.progress.operation = op;
                             self.progress.speed = speed;
@@ -145,34 +667,44 @@ impl App {
                         InstallMsg::SubProgress(ratio) => {
                             self.progress.sub_ratio = ratio;
                         }
-                        InstallMsg::Log(log) => {
-                            self.logs.push(log.clone());
-                            if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("rusty_rebase_install.log") {
-                                use std::io::Write;
-                                let _ = writeln!(file, "{}", log);
-                            }
+                        InstallMsg::Banner(text) => {
+                            self.progress.banner = (!text.is_empty()).then_some(text);
+                        }
+                        InstallMsg::Secret(prompt, reply) => {
+                            self.modals.push(screen::Modal::Secret(SecretPrompt { prompt, input: state::secret_input(), reply }));
+                        }
+                        InstallMsg::Elevate(reply) => {
+                            elevation = Some(reply);
+                            break;
+                        }
+                        InstallMsg::Log(level, log) => {
+                            self.install_log.write(log.clone());
+                            self.logs.push(LogEntry { tool: self.active_tool.clone(), level, text: log });
                         }
                         InstallMsg::Done(key, result) => {
                             self.progress.done_items.push(key.clone());
                             match result {
                                 Ok(logs) => {
                                     for log in &logs {
-                                        if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("rusty_rebase_install.log") {
-                                            use std::io::Write;
-                                            let _ = writeln!(file, "{}", log);
-                                        }
+                                        self.install_log.write(log.clone());
                                     }
-                                    self.logs.extend(logs);
+                                    self.logs.extend(logs.into_iter().map(|l| LogEntry::tagged(Some(key.clone()), l)));
                                     self.progress.succeeded += 1;
+                                    if let Some(tool) = self.tools.iter_mut().find(|t| t.key == key) {
+                                        tool.last_error = None;
+                                    }
                                 }
                                 Err(err) => {
-                                    let msg = format!("[error] {} failed: {}", key, err);
-                                    self.logs.push(msg.clone());
-                                    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("rusty_rebase_install.log") {
-                                        use std::io::Write;
-                                        let _ = writeln!(file, "{}", msg);
+                                    if !err.is_cancelled() {
+                                        crate::notify::tool_failed(&key, &err.to_string());
                                     }
+                                    let msg = format!("[error] {} failed: {}", key, err);
+                                    self.install_log.write(msg.clone());
+                                    self.logs.push(LogEntry::tagged(Some(key.clone()), msg));
                                     self.progress.failed += 1;
+                                    if let Some(tool) = self.tools.iter_mut().find(|t| t.key == key) {
+                                        tool.last_error = Some(format!("install: {err}"));
+                                    }
                                 }
                             }
                             self.progress.done += 1;
@@ -186,163 +718,135 @@ impl App {
                                     let eta_secs = (remaining_items as f64 * time_per_item) as u64;
                                     
                                     if eta_secs > 0 {
-                                        let mins = eta_secs / 60;
-                                        let secs = eta_secs % 60;
-                                        self.progress.eta = Some(if mins > 0 {
-                                            format!("~{}m {}s", mins, secs)
-                                        } else {
-                                            format!("~{}s", secs)
-                                        });
+                                        self.progress.eta = Some(format!("~{}", crate::units::format_eta(eta_secs)));
                                     } else {
                                         self.progress.eta = Some("finishing...".to_string());
                                     }
                                 }
                             }
                         }
+                        InstallMsg::Skipped(key) => {
+                            let msg = format!("[warn] {key} skipped, moving on");
+                            self.logs.push(LogEntry::tagged(Some(key.clone()), msg));
+                            self.progress.done_items.push(key.clone());
+                            self.progress.skipped_items.push(key);
+                            self.progress.skipped += 1;
+                            self.progress.done += 1;
+                            self.progress.sub_ratio = 0.0;
+                        }
+                        InstallMsg::Record(record) => {
+                            self.records.push(record);
+                        }
+                        InstallMsg::DiskUsage(entries) => {
+                            // Not when the user moved on while it measured.
+                            if self.state == ViewState::Browsing {
+                                self.state = ViewState::DiskUsage { entries, cursor: 0, by_size: true };
+                            }
+                            measured = true;
+                        }
                         InstallMsg::Finished => {
+                            crate::ipc::publish(crate::ipc::ProgressEvent::Finished {
+                                succeeded: self.progress.succeeded,
+                                failed: self.progress.failed,
+                                skipped: self.progress.skipped,
+                            });
+                            if !self.dry_run {
+                                crate::notify::run_finished(self.progress.succeeded, self.progress.failed, self.progress.skipped);
+                            }
                             self.state = ViewState::Completed;
+                            self.run_resources = self.meter.take().map(|meter| meter.finish());
                             finished = true;
                             self.progress.eta = None;
                         }
                     }
                 }
             }
+            if let Some(reply) = elevation {
+                self.log("[sudo] A privileged step needs root; enter your password".to_string());
+                self.push_modal(screen::Modal::Sudo(SudoPrompt {
+                    input: state::secret_input(),
+                    reply,
+                    error: None,
+                    attempts: 0,
+                    checking: false,
+                }));
+            }
+            if let Some(result) = self.sudo_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                self.sudo_rx = None;
+                actions::finish_sudo(self, result);
+            }
+            if measured {
+                self.installation_rx = None;
+            }
             if finished {
                 self.installation_rx = None;
-                self.cancel_tx = None;
+                self.cancel = None;
+                if self.elevated {
+                    crate::sudo::forget();
+                    self.elevated = false;
+                }
+                self.install_log.flush();
+                self.write_report();
+                self.installed = crate::state::StateDb::load();
             }
 
             if let Err(e) = terminal.draw(|f| ui::render(self, f)) {
                 return Err(format!("failed to draw frame: {e}"));
             }
+            let redraw_due = Instant::now() + crate::run_context::current().refresh_interval();
 
-            match event::poll(Duration::from_millis(200)) {
-                Ok(true) => {
-                    let key_event = match event::read() {
-                        Ok(Event::Key(k)) => k,
-                        Ok(_) => continue,
-                        Err(e) => return Err(format!("failed to read event: {e}")),
-                    };
-
-                    match key_event.code {
-                        KeyCode::Char('q') => {
-                            if self.state == ViewState::Installing || self.state == ViewState::Restoring {
-                                if let Some(ref tx) = self.cancel_tx {
-                                    let _ = tx.send(());
-                                    self.logs.push("[User] Process cancelled. Waiting to abort...".to_string());
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                            if let Some(ref tx) = self.cancel_tx {
-                                let _ = tx.send(());
-                            }
-                            break;
-                        }
-                        KeyCode::Esc => {
-                            if self.state == ViewState::Completed {
-                                self.state = ViewState::Browsing;
-                                self.progress = ProgressInfo::default();
-                                self.logs.push("Returned to browsing. Select more tools or resolve again.".to_string());
-                            } else if let ViewState::FilePicker { .. } = self.state {
-                                self.state = ViewState::Browsing;
-                                self.logs.push("File picker cancelled.".to_string());
-                            }
-                        }
-                        KeyCode::Enter => {
-                            if self.state == ViewState::Completed {
-                                self.state = ViewState::Browsing;
-                                self.progress = ProgressInfo::default();
-                                self.logs.push("Returned to browsing. Select more tools or resolve again.".to_string());
-                            } else if let ViewState::FilePicker { ref mut current_dir, ref mut entries, ref mut cursor } = self.state.clone() {
-                                if let Some(path) = entries.get(*cursor) {
-                                    if path.file_name().unwrap_or_default().is_empty() {
-                                        if let Some(parent) = current_dir.parent() {
-                                            actions::update_file_picker(self, parent.to_path_buf());
-                                        }
-                                    } else if path.is_dir() {
-                                        actions::update_file_picker(self, path.clone());
-                                    } else if path.is_file() && path.extension().map_or(false, |e| e == "json") {
-                                        actions::start_restore_from_file(self, path.clone());
-                                    } else {
-                                        self.logs.push("Please select a JSON metadata file or a folder.".to_string());
-                                    }
-                                }
-                            }
-                        }
-                        KeyCode::Down => {
-                            if let ViewState::FilePicker { ref mut cursor, ref entries, .. } = self.state {
-                                if *cursor + 1 < entries.len() { *cursor += 1; }
-                            } else if self.state == ViewState::Browsing && self.cursor + 1 < self.tools.len() {
-                                self.cursor += 1;
-                            }
-                        }
-                        KeyCode::Up => {
-                            if let ViewState::FilePicker { ref mut cursor, .. } = self.state {
-                                if *cursor > 0 { *cursor -= 1; }
-                            } else if self.state == ViewState::Browsing && self.cursor > 0 {
-                                self.cursor -= 1;
-                            }
-                        }
-                        KeyCode::Char(' ') => {
-                            if let Some(item) = self.tools.get_mut(self.cursor) {
-                                item.selected = !item.selected;
-                            }
-                        }
-                        KeyCode::Char('a') => {
-                            for item in &mut self.tools {
-                                item.selected = true;
-                            }
-                        }
-                        KeyCode::Char('n') => {
-                            for item in &mut self.tools {
-                                item.selected = false;
-                            }
-                        }
-                        KeyCode::Char('d') => {
-                            self.dry_run = !self.dry_run;
-                            self.logs.push(format!("dry-run = {}", self.dry_run));
-                        }
-                        KeyCode::Char('r') => {
-                            actions::start_resolution(self);
-                        }
-                        KeyCode::Char('u') => {
-                            if self.state == ViewState::Browsing {
-                                actions::update_file_picker(self, std::env::current_dir().unwrap_or_default());
-                            }
-                        }
-                        KeyCode::Char('i') => {
-                            if !self.dry_run {
-                                disable_raw_mode().ok();
-                                std::io::stdout().execute(LeaveAlternateScreen).ok();
-                                println!("\n[Sudo] Authenticating for system installation...");
-                                let _ = std::process::Command::new("sudo").arg("-v").status();
-                                std::io::stdout().execute(EnterAlternateScreen).ok();
-                                enable_raw_mode().ok();
-                                terminal.clear().ok();
-                                terminal.hide_cursor().ok();
-                            }
-                            actions::install_selected(self)
+            let mut events = self.events.wait(sample_due.saturating_duration_since(Instant::now()));
+            // Progress from background work comes in bursts; redraw for it at
+            // most once per refresh interval, but answer keys straight away.
+            while !events.is_empty() && events.iter().all(|e| matches!(e, wake::Event::Wake)) {
+                let left = redraw_due.min(sample_due).saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    break;
+                }
+                let more = self.events.wait(left);
+                if more.is_empty() {
+                    break;
+                }
+                events.extend(more);
+            }
+
+            for event in events {
+                let wake::Event::Input(Event::Key(key_event)) = event else { continue };
+                match screen::handle_key(self, key_event) {
+                    screen::Flow::Continue => {}
+                    screen::Flow::Quit => return Ok(()),
+                    screen::Flow::Edit { path, line } => {
+                        if let Err(e) = outside_tui(terminal, &self.events, || startup::edit(&path, line)) {
+                            self.log(format!("[error] {e}"));
                         }
-                        KeyCode::Char('c') => {
-                            if self.state == ViewState::Installing {
-                                if let Some(ref tx) = self.cancel_tx {
-                                    let _ = tx.send(());
-                                    self.logs.push("[User] Cancellation signal sent...".to_string());
-                                }
-                            } else {
-                                self.logs.clear();
-                            }
+                        if matches!(self.state, ViewState::LoadError(_)) {
+                            startup::retry(self);
+                        } else {
+                            actions::reload_catalog(self);
                         }
-                        _ => {}
                     }
                 }
-                Ok(false) => {}
-                Err(e) => return Err(format!("event poll failed: {e}")),
             }
         }
-        Ok(())
     }
 }
+
+/// Runs `f` on the normal screen with raw mode off, for programs that talk
+/// to the terminal themselves (sudo, an editor), then takes the screen back.
+fn outside_tui<T>(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, events: &wake::Events, f: impl FnOnce() -> T) -> T {
+    let alternate_screen = crate::run_context::current().alternate_screen();
+    disable_raw_mode().ok();
+    if alternate_screen {
+        std::io::stdout().execute(LeaveAlternateScreen).ok();
+    } else {
+        terminal.clear().ok();
+    }
+    let result = events.paused(f);
+    if alternate_screen {
+        std::io::stdout().execute(EnterAlternateScreen).ok();
+    }
+    enable_raw_mode().ok();
+    terminal.clear().ok();
+    terminal.hide_cursor().ok();
+    result
+}
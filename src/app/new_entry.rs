@@ -0,0 +1,381 @@
+use regex::Regex;
+
+use crate::catalog::SoftwareSpec;
+use crate::error::Error;
+use crate::resolver::ResolvedAsset;
+
+use super::{actions, App, CatalogRow, ViewState};
+
+/// Source kinds the form can fill in. JSON APIs and source builds have too
+/// many fields for it and are written by hand.
+pub const KINDS: [&str; 5] = ["github", "templated", "official_source", "package_manager", "none"];
+
+/// A text field of the new-entry form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Key,
+    DisplayName,
+    Category,
+    Description,
+    Repo,
+    AssetPattern,
+    VersionUrl,
+    VersionRegex,
+    UrlTemplate,
+    PageUrl,
+    DownloadRegex,
+    Packages,
+}
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Key => "Key",
+            Field::DisplayName => "Display name",
+            Field::Category => "Category",
+            Field::Description => "Description",
+            Field::Repo => "Repository",
+            Field::AssetPattern => "Asset pattern",
+            Field::VersionUrl => "Version URL",
+            Field::VersionRegex => "Version regex",
+            Field::UrlTemplate => "URL template",
+            Field::PageUrl => "Download page",
+            Field::DownloadRegex => "Download URL regex",
+            Field::Packages => "Packages",
+        }
+    }
+
+    pub fn help(self) -> &'static str {
+        match self {
+            Field::Key => "Table name under [software], e.g. ripgrep. Letters, digits, - and _.",
+            Field::DisplayName => "Name shown in the catalog list.",
+            Field::Category => "Group the entry is listed under. Empty for Other.",
+            Field::Description => "One line shown in the details. Optional.",
+            Field::Repo => "GitHub owner/name, e.g. BurntSushi/ripgrep.",
+            Field::AssetPattern => "Regex matched against the release asset names, e.g. linux.*\\.tar\\.gz$.",
+            Field::VersionUrl => "Page or file the latest version is read from.",
+            Field::VersionRegex => "Captures the version; without a group the whole match is used.",
+            Field::UrlTemplate => "Download URL with {version} and {arch} placeholders.",
+            Field::PageUrl => "Page listing the downloads.",
+            Field::DownloadRegex => "Captures the download URL from the page.",
+            Field::Packages => "Package names for the package manager, separated by spaces.",
+        }
+    }
+
+    fn required(self) -> bool {
+        !matches!(self, Field::Category | Field::Description)
+    }
+}
+
+/// One row of the form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    Field(Field),
+    Kind,
+    Step(usize),
+    AddStep,
+}
+
+/// State of the new-entry view.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EntryForm {
+    pub values: Vec<(Field, String)>,
+    /// Index into [`KINDS`].
+    pub kind: usize,
+    /// Shell commands run after the install, in order.
+    pub steps: Vec<String>,
+    pub cursor: usize,
+    /// Text typed for the row under the cursor while editing it.
+    pub editing: Option<String>,
+    /// Why the last save was refused.
+    pub error: Option<String>,
+    /// Outcome of the last test resolution.
+    pub test: Option<Result<ResolvedAsset, Error>>,
+    pub testing: bool,
+    /// The entry as TOML once it validated, waiting for its test resolution
+    /// before it is appended.
+    pending: Option<String>,
+    /// The TOML whose test resolution failed; saving it again appends it
+    /// anyway.
+    failed: Option<String>,
+}
+
+impl EntryForm {
+    pub fn value(&self, field: Field) -> &str {
+        self.values.iter().find(|(f, _)| *f == field).map(|(_, v)| v.trim()).unwrap_or_default()
+    }
+
+    fn set(&mut self, field: Field, text: String) {
+        match self.values.iter_mut().find(|(f, _)| *f == field) {
+            Some((_, value)) => *value = text,
+            None => self.values.push((field, text)),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        KINDS[self.kind]
+    }
+
+    fn source_fields(&self) -> &'static [Field] {
+        match self.kind() {
+            "github" => &[Field::Repo, Field::AssetPattern],
+            "templated" => &[Field::VersionUrl, Field::VersionRegex, Field::UrlTemplate],
+            "official_source" => &[Field::PageUrl, Field::VersionRegex, Field::DownloadRegex],
+            "package_manager" => &[Field::Packages],
+            _ => &[],
+        }
+    }
+
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows: Vec<Row> = [Field::Key, Field::DisplayName, Field::Category, Field::Description].into_iter().map(Row::Field).collect();
+        rows.push(Row::Kind);
+        rows.extend(self.source_fields().iter().map(|f| Row::Field(*f)));
+        rows.extend((0..self.steps.len()).map(Row::Step));
+        rows.push(Row::AddStep);
+        rows
+    }
+
+    pub fn text(&self, row: Row) -> String {
+        match row {
+            Row::Field(field) => self.value(field).to_string(),
+            Row::Kind => self.kind().to_string(),
+            Row::Step(index) => self.steps.get(index).cloned().unwrap_or_default(),
+            Row::AddStep => String::new(),
+        }
+    }
+
+    /// Checks the fields and renders the entry as a `[software.<key>]`
+    /// table, also returning the spec it parses into.
+    fn build(&self, app: &App) -> Result<(String, SoftwareSpec), String> {
+        let key = self.value(Field::Key);
+        for field in [Field::Key, Field::DisplayName].iter().chain(self.source_fields()) {
+            if field.required() && self.value(*field).is_empty() {
+                return Err(format!("{} is required", field.label()));
+            }
+        }
+        if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("key '{key}' may only use letters, digits, - and _"));
+        }
+        if app.catalog.software.contains_key(key) {
+            return Err(format!("the catalog already has an entry called {key}"));
+        }
+        for field in [Field::AssetPattern, Field::VersionRegex, Field::DownloadRegex] {
+            if self.source_fields().contains(&field) {
+                Regex::new(self.value(field)).map_err(|e| format!("{} is not a valid regex: {e}", field.label()))?;
+            }
+        }
+        for field in [Field::VersionUrl, Field::UrlTemplate, Field::PageUrl] {
+            let value = self.value(field);
+            if self.source_fields().contains(&field) && !(value.starts_with("http://") || value.starts_with("https://")) {
+                return Err(format!("{} must be an http(s) URL", field.label()));
+            }
+        }
+        if self.kind() == "templated" && !self.value(Field::UrlTemplate).contains("{version}") {
+            return Err("URL template needs a {version} placeholder".to_string());
+        }
+
+        let mut entry = toml::Table::new();
+        let text = |field: Field| toml::Value::String(self.value(field).to_string());
+        entry.insert("display_name".into(), text(Field::DisplayName));
+        for (name, field) in [("description", Field::Description), ("category", Field::Category)] {
+            if !self.value(field).is_empty() {
+                entry.insert(name.into(), text(field));
+            }
+        }
+        entry.insert("enabled_by_default".into(), toml::Value::Boolean(false));
+        let mut source = toml::Table::new();
+        source.insert("kind".into(), toml::Value::String(self.kind().to_string()));
+        match self.kind() {
+            "github" => {
+                source.insert("repo".into(), text(Field::Repo));
+                source.insert("asset_pattern".into(), text(Field::AssetPattern));
+            }
+            "templated" => {
+                source.insert("version_url".into(), text(Field::VersionUrl));
+                source.insert("version_regex".into(), text(Field::VersionRegex));
+                source.insert("url_template".into(), text(Field::UrlTemplate));
+            }
+            "official_source" => {
+                source.insert("url".into(), text(Field::PageUrl));
+                source.insert("version_regex".into(), text(Field::VersionRegex));
+                source.insert("download_url_regex".into(), text(Field::DownloadRegex));
+            }
+            _ => {}
+        }
+        entry.insert("source".into(), toml::Value::Table(source));
+
+        let mut steps = Vec::new();
+        if self.kind() == "package_manager" {
+            let packages = self.value(Field::Packages).split_whitespace().map(|p| toml::Value::String(p.to_string())).collect();
+            steps.push(toml::Value::Table(toml::Table::from_iter([
+                ("kind".to_string(), toml::Value::String("package".into())),
+                ("packages".to_string(), toml::Value::Array(packages)),
+            ])));
+        }
+        for command in self.steps.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            steps.push(toml::Value::Table(toml::Table::from_iter([
+                ("kind".to_string(), toml::Value::String("shell".into())),
+                ("command".to_string(), toml::Value::String(command.to_string())),
+            ])));
+        }
+        if !steps.is_empty() {
+            entry.insert("setup_steps".into(), toml::Value::Array(steps));
+        }
+
+        let spec: SoftwareSpec = toml::Value::Table(entry.clone()).try_into().map_err(|e| format!("the entry does not parse: {e}"))?;
+        let document = toml::Table::from_iter([("software".to_string(), toml::Value::Table(toml::Table::from_iter([(key.to_string(), toml::Value::Table(entry))])))]);
+        let rendered = toml::to_string_pretty(&document).map_err(|e| format!("failed to render the entry: {e}"))?;
+        Ok((rendered, spec))
+    }
+}
+
+pub fn open(app: &mut App) {
+    if app.catalog_path.is_none() {
+        app.log("[warn] No catalog file is loaded to add the entry to".to_string());
+        return;
+    }
+    app.state = ViewState::NewEntry(Box::default());
+}
+
+fn form_mut(app: &mut App) -> Option<&mut EntryForm> {
+    match app.state {
+        ViewState::NewEntry(ref mut form) => Some(form),
+        _ => None,
+    }
+}
+
+/// Cycles the source kind, or starts editing the row under the cursor.
+pub fn activate(app: &mut App) {
+    let Some(form) = form_mut(app) else { return };
+    match form.rows().get(form.cursor) {
+        Some(Row::Kind) => cycle_kind(app, true),
+        Some(row) => form.editing = Some(form.text(*row)),
+        None => {}
+    }
+}
+
+pub fn cycle_kind(app: &mut App, forward: bool) {
+    let Some(form) = form_mut(app) else { return };
+    if form.rows().get(form.cursor) != Some(&Row::Kind) {
+        return;
+    }
+    form.kind = if forward { (form.kind + 1) % KINDS.len() } else { (form.kind + KINDS.len() - 1) % KINDS.len() };
+    form.test = None;
+}
+
+/// Stores the text typed for the row under the cursor. An emptied step is
+/// removed.
+pub fn commit_edit(app: &mut App) {
+    let Some(form) = form_mut(app) else { return };
+    let Some(text) = form.editing.take() else { return };
+    match form.rows().get(form.cursor) {
+        Some(Row::Field(field)) => form.set(*field, text),
+        Some(Row::Step(index)) if text.trim().is_empty() => {
+            form.steps.remove(*index);
+        }
+        Some(Row::Step(index)) => form.steps[*index] = text,
+        Some(Row::AddStep) if !text.trim().is_empty() => {
+            form.steps.push(text);
+            form.cursor += 1;
+        }
+        _ => {}
+    }
+    form.test = None;
+}
+
+/// Validates the form and resolves the entry in the background. With
+/// `save`, it is appended to the catalog once the resolution succeeds;
+/// saving an entry whose resolution failed a second time appends it anyway.
+pub fn test(app: &mut App, save: bool) {
+    let ViewState::NewEntry(ref form) = app.state else { return };
+    let built = form.build(app);
+    let Some(form) = form_mut(app) else { return };
+    let (rendered, spec) = match built {
+        Ok(built) => built,
+        Err(e) => {
+            form.error = Some(e);
+            return;
+        }
+    };
+    form.error = None;
+    if save && form.failed.as_deref() == Some(rendered.as_str()) {
+        append(app, &rendered);
+        return;
+    }
+    form.pending = save.then(|| rendered.clone());
+    form.failed = None;
+    form.testing = true;
+    form.test = None;
+
+    let (tx, rx) = app.events.waker().channel();
+    app.entry_test_rx = Some(rx);
+    let client = app.client.clone();
+    let distro = app.distro.clone();
+    crate::runtime::spawn(async move {
+        let _ = tx.send(crate::resolver::resolve_asset(&client, &spec, &distro).await);
+    });
+}
+
+/// Takes the test resolution in, appending the entry when it was saved and
+/// resolved.
+pub fn finish_test(app: &mut App, result: Result<ResolvedAsset, Error>) {
+    let Some(form) = form_mut(app) else { return };
+    form.testing = false;
+    let pending = form.pending.take();
+    match (result, pending) {
+        (Ok(asset), Some(rendered)) => {
+            let key = form.value(Field::Key).to_string();
+            if !append(app, &rendered) {
+                return;
+            }
+            if let Some(item) = app.tools.iter_mut().find(|t| t.key == key) {
+                item.resolved = Some(asset);
+                item.status = super::ResolveStatus::Resolved;
+            }
+        }
+        (result @ Err(_), Some(rendered)) => {
+            form.failed = Some(rendered);
+            form.test = Some(result);
+        }
+        (result, None) => form.test = Some(result),
+    }
+}
+
+/// Appends the rendered entry to the catalog file, reloads it and puts the
+/// cursor on the new entry. Returns whether it was added.
+fn append(app: &mut App, rendered: &str) -> bool {
+    let Some(path) = app.catalog_path.clone() else { return false };
+    let key = form_mut(app).map(|f| f.value(Field::Key).to_string()).unwrap_or_default();
+    let mut content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            app.log(format!("[error] failed to read {}: {e}", path.display()));
+            return false;
+        }
+    };
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(rendered);
+    if let Err(e) = crate::catalog::parse_catalog(&content) {
+        if let Some(form) = form_mut(app) {
+            form.error = Some(format!("the catalog would no longer parse: {e}"));
+        }
+        return false;
+    }
+    if let Err(e) = std::fs::write(&path, content) {
+        app.log(format!("[error] failed to write {}: {e}", path.display()));
+        return false;
+    }
+    app.state = ViewState::Browsing;
+    actions::reload_catalog(app);
+    if let Some(index) = app.tools.iter().position(|t| t.key == key) {
+        app.collapsed.remove(&super::category_of(&app.catalog, &key));
+        if let Some(row) = app.catalog_rows().iter().position(|r| *r == CatalogRow::Tool(index)) {
+            app.cursor = row;
+        }
+    }
+    app.log(format!("[done] Added {key} to {}", path.display()));
+    true
+}
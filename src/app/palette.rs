@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::screen::Modal;
+use super::{actions, App, CatalogRow, ViewState};
+
+/// Recent actions kept for the palette.
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteAction {
+    Install,
+    Resolve,
+    Details,
+}
+
+impl PaletteAction {
+    /// In the order matches are preferred on equal scores, so typing a tool
+    /// name puts its install first.
+    const ALL: [PaletteAction; 3] = [PaletteAction::Install, PaletteAction::Resolve, PaletteAction::Details];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PaletteAction::Install => "install",
+            PaletteAction::Resolve => "resolve",
+            PaletteAction::Details => "details",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaletteCommand {
+    pub action: PaletteAction,
+    pub key: String,
+}
+
+/// One row of the palette.
+pub struct PaletteMatch {
+    pub command: PaletteCommand,
+    pub name: String,
+    pub recent: bool,
+    pub favorite: bool,
+}
+
+/// Ctrl-P overlay for running an action on any catalog entry by typing part
+/// of its name.
+pub struct CommandPalette {
+    pub query: String,
+    pub cursor: usize,
+    /// From `favorites` in `config.toml`.
+    pub favorites: Vec<String>,
+    /// Most recent first.
+    pub recent: Vec<PaletteCommand>,
+}
+
+impl CommandPalette {
+    pub fn open() -> Self {
+        CommandPalette { query: String::new(), cursor: 0, favorites: crate::config::current().favorites, recent: load_recent() }
+    }
+
+    /// Without a query: recent actions, then installing each favorite. With
+    /// one: every action on every entry that fuzzily matches, best first,
+    /// with recent and favorite entries winning ties.
+    pub fn matches(&self, app: &App) -> Vec<PaletteMatch> {
+        let entry = |command: PaletteCommand| {
+            let name = app.catalog.software.get(&command.key)?.display_name.clone();
+            Some(PaletteMatch {
+                recent: self.recent.contains(&command),
+                favorite: self.favorites.contains(&command.key),
+                command,
+                name,
+            })
+        };
+        if self.query.trim().is_empty() {
+            let favorites = self.favorites.iter().map(|key| PaletteCommand { action: PaletteAction::Install, key: key.clone() });
+            let mut commands = self.recent.clone();
+            for command in favorites {
+                if !commands.contains(&command) {
+                    commands.push(command);
+                }
+            }
+            return commands.into_iter().filter_map(entry).collect();
+        }
+        let mut scored: Vec<(i32, usize, PaletteMatch)> = app
+            .tools
+            .iter()
+            .flat_map(|tool| PaletteAction::ALL.into_iter().enumerate().map(move |(order, action)| (order, PaletteCommand { action, key: tool.key.clone() })))
+            .filter_map(|(order, command)| {
+                let found = entry(command)?;
+                let text = format!("{} {} {}", found.command.action.label(), found.command.key, found.name);
+                let score = fuzzy_score(&self.query, &text)? + if found.recent { 4 } else { 0 } + if found.favorite { 2 } else { 0 };
+                Some((score, order, found))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then_with(|| a.2.command.key.cmp(&b.2.command.key)));
+        scored.into_iter().map(|(_, _, found)| found).collect()
+    }
+}
+
+/// Scores `text` against `query` typed as a subsequence (spaces ignored,
+/// case-insensitive): consecutive letters and letters starting a word count
+/// extra, gaps count against. `None` when some letter is missing.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = pos + text[pos..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        } else if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if previous.is_some() {
+            score -= (found - pos).min(5) as i32;
+        }
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// `~/.local/share/rusty_rebase/palette.json` on Linux.
+fn recent_file() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("rusty_rebase").join("palette.json"))
+}
+
+fn load_recent() -> Vec<PaletteCommand> {
+    recent_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_recent(recent: &[PaletteCommand]) -> Result<(), String> {
+    let path = recent_file().ok_or("no data directory for recent actions")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(recent).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+/// Pins or unpins the highlighted entry in `favorites` in `config.toml`.
+pub fn toggle_favorite(app: &mut App) {
+    let Some(palette) = app.palette() else { return };
+    let Some(key) = palette.matches(app).into_iter().nth(palette.cursor).map(|m| m.command.key) else { return };
+    let mut config = crate::config::current();
+    let pinned = if let Some(index) = config.favorites.iter().position(|k| *k == key) {
+        config.favorites.remove(index);
+        false
+    } else {
+        config.favorites.push(key.clone());
+        true
+    };
+    match crate::config::save_config(&config) {
+        Ok(_) => {
+            if let Some(palette) = app.palette_mut() {
+                palette.favorites = config.favorites;
+            }
+            app.log(format!("{} {key} {} favorites", if pinned { "Added" } else { "Removed" }, if pinned { "to" } else { "from" }));
+        }
+        Err(e) => app.log(format!("[error] {e}")),
+    }
+}
+
+/// Closes the palette and runs the highlighted command, remembering it as
+/// the most recent action.
+pub fn run_selected(app: &mut App) {
+    let Some(Modal::Palette(palette)) = app.close_modal() else { return };
+    let Some(found) = palette.matches(app).into_iter().nth(palette.cursor) else { return };
+    let command = found.command;
+
+    let mut recent = palette.recent;
+    recent.retain(|c| *c != command);
+    recent.insert(0, command.clone());
+    recent.truncate(MAX_RECENT);
+    if let Err(e) = save_recent(&recent) {
+        app.log(format!("[warn] {e}"));
+    }
+
+    match command.action {
+        PaletteAction::Install => {
+            if let Some(reason) = app.unavailable(&command.key) {
+                app.log(format!("[warn] {} cannot be installed here: {reason}", command.key));
+                return;
+            }
+            for tool in &mut app.tools {
+                tool.selected = tool.key == command.key;
+            }
+            app.log(format!("Selected only {} for installation.", command.key));
+            if app.dry_run {
+                actions::install_selected(app)
+            } else {
+                actions::open_install_confirmation(app)
+            }
+        }
+        PaletteAction::Resolve => actions::resolve_keys(app, vec![command.key], false),
+        PaletteAction::Details => {
+            if let Some(index) = app.tools.iter().position(|t| t.key == command.key) {
+                app.collapsed.remove(&super::category_of(&app.catalog, &command.key));
+                app.show_hidden |= app.hidden.contains(&command.key);
+                app.state = ViewState::Browsing;
+                if let Some(row) = app.catalog_rows().iter().position(|r| *r == CatalogRow::Tool(index)) {
+                    app.cursor = row;
+                }
+                actions::open_detail(app);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_scores_prefer_runs_and_word_starts() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("rgx", "ripgrep"), None);
+        assert_eq!(fuzzy_score("per", "ripgrep"), None);
+        assert!(fuzzy_score("Rip Grep", "ripgrep").is_some());
+        assert!(fuzzy_score("rip", "ripgrep") > fuzzy_score("rip", "r-i-p"));
+        assert!(fuzzy_score("in", "install") > fuzzy_score("in", "main"));
+    }
+}
@@ -0,0 +1,626 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::Frame;
+
+use super::new_entry;
+use super::palette::{self, CommandPalette};
+use super::settings::{self, Setting};
+use super::startup;
+use super::state::push_secret;
+use super::{actions, ui, App, CatalogRow, DetailPopup, ProbePopup, ProgressInfo, SecretPrompt, SudoPrompt, ViewState};
+
+/// What the event loop does after a key press.
+#[derive(PartialEq)]
+pub enum Flow {
+    Continue,
+    Quit,
+    /// Suspend the TUI to edit the file, at `line` when given, then load it
+    /// again.
+    Edit { path: PathBuf, line: Option<usize> },
+}
+
+/// A popup drawn over the current view. Modals stack, and the top one takes
+/// all input until it closes.
+pub enum Modal {
+    Detail(DetailPopup),
+    Palette(CommandPalette),
+    Probe(ProbePopup),
+    Secret(SecretPrompt),
+    Sudo(SudoPrompt),
+}
+
+impl Modal {
+    fn render(&self, app: &App, frame: &mut Frame, area: Rect) {
+        match self {
+            Modal::Detail(detail) => ui::render_detail_popup(app, frame, area, detail),
+            Modal::Palette(palette) => ui::render_palette(app, frame, area, palette),
+            Modal::Probe(probe) => ui::render_probe_popup(frame, area, probe),
+            Modal::Secret(prompt) => ui::render_secret_prompt(frame, area, &prompt.prompt, prompt.input.chars().count()),
+            Modal::Sudo(prompt) => ui::render_sudo_prompt(frame, area, prompt),
+        }
+    }
+}
+
+impl App {
+    pub(crate) fn push_modal(&mut self, modal: Modal) {
+        self.modals.push(modal);
+    }
+
+    /// Closes the top modal. Closing the details popup also drops the
+    /// lookups still running for it.
+    pub(crate) fn close_modal(&mut self) -> Option<Modal> {
+        let modal = self.modals.pop();
+        match modal {
+            Some(Modal::Detail(_)) => {
+                self.size_rx = None;
+                self.preview_rx = None;
+                self.advisory_rx = None;
+            }
+            Some(Modal::Probe(_)) => self.probe_rx = None,
+            _ => {}
+        }
+        modal
+    }
+
+    pub(crate) fn detail(&self) -> Option<&DetailPopup> {
+        self.modals.iter().find_map(|m| match m {
+            Modal::Detail(detail) => Some(detail),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn detail_mut(&mut self) -> Option<&mut DetailPopup> {
+        self.modals.iter_mut().find_map(|m| match m {
+            Modal::Detail(detail) => Some(detail),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn probe_mut(&mut self) -> Option<&mut ProbePopup> {
+        self.modals.iter_mut().find_map(|m| match m {
+            Modal::Probe(probe) => Some(probe),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn palette(&self) -> Option<&CommandPalette> {
+        self.modals.iter().find_map(|m| match m {
+            Modal::Palette(palette) => Some(palette),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn palette_mut(&mut self) -> Option<&mut CommandPalette> {
+        self.modals.iter_mut().find_map(|m| match m {
+            Modal::Palette(palette) => Some(palette),
+            _ => None,
+        })
+    }
+}
+
+/// Draws the current view into the body area.
+pub fn render_view(app: &App, frame: &mut Frame, body: Rect) {
+    match app.state {
+        ViewState::Browsing => ui::render_browsing(app, frame, body),
+        ViewState::Installing | ViewState::Completed | ViewState::Restoring => ui::render_progress(app, frame, body),
+        ViewState::FilePicker { ref current_dir, ref entries, cursor } => ui::render_file_picker(app, frame, body, current_dir, entries, cursor),
+        ViewState::Onboarding { step, profile, ref install_root, ref download_dir } => {
+            ui::render_onboarding(app, frame, body, step, profile, install_root, download_dir)
+        }
+        ViewState::DiskUsage { ref entries, cursor, by_size } => ui::render_disk_usage(frame, body, entries, cursor, by_size),
+        ViewState::ConfirmInstall { ref items, ref changes, on_battery } => ui::render_confirm_install(app, frame, body, items, changes, on_battery),
+        ViewState::Settings { cursor, ref editing } => ui::render_settings(app, frame, body, cursor, editing.as_deref()),
+        ViewState::Quarantine { ref entries, cursor } => ui::render_quarantine(app, frame, body, entries, cursor),
+        ViewState::Maintenance { ref rows, cursor } => ui::render_maintenance(app, frame, body, rows, cursor),
+        ViewState::RestorePicker { ref json_file, ref parts, cursor } => ui::render_restore_picker(frame, body, json_file, parts, cursor),
+        ViewState::NewEntry(ref form) => ui::render_new_entry(app, frame, body, form),
+        ViewState::LoadError(ref error) => ui::render_load_error(app, frame, body, error),
+    }
+}
+
+/// Draws the open modals over the whole frame, bottom of the stack first.
+pub fn render_modals(app: &App, frame: &mut Frame, area: Rect) {
+    for modal in &app.modals {
+        modal.render(app, frame, area);
+    }
+}
+
+/// Routes a key press to the top modal, or to the current view when no
+/// modal is open.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> Flow {
+    match app.modals.last() {
+        Some(Modal::Secret(_)) => return secret_key(app, key),
+        Some(Modal::Sudo(_)) => return sudo_key(app, key),
+        Some(Modal::Detail(_)) => return detail_key(app, key.code),
+        Some(Modal::Palette(_)) => return palette_key(app, key),
+        Some(Modal::Probe(_)) => return probe_key(app, key.code),
+        None => {}
+    }
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        if let Some(cancel) = &app.cancel {
+            cancel.cancel();
+        }
+        return Flow::Quit;
+    }
+    match app.state {
+        ViewState::Onboarding { .. } => {
+            if actions::handle_onboarding_key(app, key.code) {
+                Flow::Continue
+            } else {
+                Flow::Quit
+            }
+        }
+        ViewState::Browsing => browsing_key(app, key),
+        ViewState::ConfirmInstall { .. } => confirm_key(app, key.code),
+        ViewState::Installing | ViewState::Restoring => running_key(app, key.code),
+        ViewState::Completed => completed_key(app, key.code),
+        ViewState::FilePicker { .. } => file_picker_key(app, key.code),
+        ViewState::DiskUsage { .. } => disk_usage_key(app, key.code),
+        ViewState::Settings { .. } => settings_key(app, key),
+        ViewState::Quarantine { .. } => quarantine_key(app, key.code),
+        ViewState::Maintenance { .. } => maintenance_key(app, key.code),
+        ViewState::RestorePicker { .. } => restore_picker_key(app, key.code),
+        ViewState::NewEntry(_) => new_entry_key(app, key),
+        ViewState::LoadError(ref error) => match key.code {
+            KeyCode::Char('e') => Flow::Edit { path: error.path.clone(), line: error.position.map(|(line, _)| line) },
+            KeyCode::Char('d') => {
+                startup::load_defaults(app);
+                Flow::Continue
+            }
+            KeyCode::Char('r') => {
+                startup::retry(app);
+                Flow::Continue
+            }
+            KeyCode::Char('q') | KeyCode::Esc => Flow::Quit,
+            _ => Flow::Continue,
+        },
+    }
+}
+
+/// Masked input: any other key dismisses the prompt without a value.
+fn secret_key(app: &mut App, key: KeyEvent) -> Flow {
+    let Some(Modal::Secret(prompt)) = app.modals.last_mut() else { return Flow::Continue };
+    match key.code {
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => push_secret(&mut prompt.input, c),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Enter | KeyCode::Esc | KeyCode::Char(_) => {
+            if let Some(Modal::Secret(mut prompt)) = app.close_modal() {
+                // Moves the buffer out whole, leaving no copy behind.
+                let value = (key.code == KeyCode::Enter).then(|| std::mem::take(&mut *prompt.input));
+                let _ = prompt.reply.send(value);
+            }
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+/// Typing is never shown. Esc and Ctrl-C decline, failing the privileged
+/// steps of the tool being installed.
+fn sudo_key(app: &mut App, key: KeyEvent) -> Flow {
+    let Some(Modal::Sudo(prompt)) = app.modals.last_mut() else { return Flow::Continue };
+    if prompt.checking {
+        return Flow::Continue;
+    }
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => actions::decline_sudo(app),
+        KeyCode::Char('c') if ctrl => actions::decline_sudo(app),
+        KeyCode::Char('u') if ctrl => prompt.input.clear(),
+        KeyCode::Char(c) if !ctrl => push_secret(&mut prompt.input, c),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Enter => actions::submit_sudo(app),
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn detail_key(app: &mut App, code: KeyCode) -> Flow {
+    match code {
+        KeyCode::Down => {
+            if let Some(detail) = app.detail_mut() {
+                detail.scroll = detail.scroll.saturating_add(1);
+            }
+        }
+        KeyCode::Up => {
+            if let Some(detail) = app.detail_mut() {
+                detail.scroll = detail.scroll.saturating_sub(1);
+            }
+        }
+        KeyCode::Char('p') => actions::preview_archive(app),
+        KeyCode::Char('v') => actions::check_advisories(app),
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('?') | KeyCode::Char('q') => {
+            app.close_modal();
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn probe_key(app: &mut App, code: KeyCode) -> Flow {
+    match code {
+        KeyCode::Char('h') => {
+            if let Some(probe) = app.probe_mut() {
+                probe.show_snippets = !probe.show_snippets;
+            }
+        }
+        KeyCode::Char('r') => {
+            app.close_modal();
+            actions::probe_highlighted(app);
+        }
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+            app.close_modal();
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn palette_key(app: &mut App, key: KeyEvent) -> Flow {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Char('f') if ctrl => palette::toggle_favorite(app),
+        KeyCode::Char('p') | KeyCode::Char('c') if ctrl => {
+            app.close_modal();
+        }
+        KeyCode::Esc => {
+            app.close_modal();
+        }
+        KeyCode::Enter => palette::run_selected(app),
+        code => {
+            let Some(palette) = app.palette_mut() else { return Flow::Continue };
+            match code {
+                KeyCode::Char(c) if !ctrl => {
+                    palette.query.push(c);
+                    palette.cursor = 0;
+                }
+                KeyCode::Backspace => {
+                    palette.query.pop();
+                    palette.cursor = 0;
+                }
+                KeyCode::Down => palette.cursor += 1,
+                KeyCode::Up => palette.cursor = palette.cursor.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+    let matches = app.palette().map(|palette| palette.matches(app).len()).unwrap_or_default();
+    if let Some(palette) = app.palette_mut() {
+        palette.cursor = palette.cursor.min(matches.saturating_sub(1));
+    }
+    Flow::Continue
+}
+
+fn browsing_key(app: &mut App, key: KeyEvent) -> Flow {
+    match key.code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.push_modal(Modal::Palette(CommandPalette::open())),
+        KeyCode::Enter => match app.catalog_rows().get(app.cursor) {
+            Some(CatalogRow::Category(_)) => app.set_collapsed(None),
+            Some(CatalogRow::Tool(_)) => actions::open_detail(app),
+            None => {}
+        },
+        KeyCode::Down if app.cursor + 1 < app.catalog_rows().len() => app.cursor += 1,
+        KeyCode::Up => app.cursor = app.cursor.saturating_sub(1),
+        KeyCode::Char(' ') => app.toggle_selection(),
+        KeyCode::Left => app.set_collapsed(Some(true)),
+        KeyCode::Right => app.set_collapsed(Some(false)),
+        KeyCode::Char('a') => {
+            let available: Vec<bool> = app.tools.iter().map(|t| !app.hidden.contains(&t.key) && app.unavailable(&t.key).is_none()).collect();
+            for (item, available) in app.tools.iter_mut().zip(available) {
+                item.selected = available;
+            }
+        }
+        KeyCode::Char('h') => app.toggle_hidden(),
+        KeyCode::Char('H') => app.toggle_show_hidden(),
+        KeyCode::Char('n') => {
+            for item in &mut app.tools {
+                item.selected = false;
+            }
+        }
+        KeyCode::Char('d') => toggle_dry_run(app),
+        KeyCode::Char('o') => toggle_offline(app),
+        KeyCode::Char('r') => actions::start_resolution(app, false),
+        KeyCode::Char('R') => actions::start_resolution(app, true),
+        KeyCode::Char('t') => actions::retry_failed_resolutions(app),
+        KeyCode::Char('T') => actions::probe_highlighted(app),
+        KeyCode::Char('l') => app.cycle_level_filter(),
+        KeyCode::Char('f') => app.toggle_tool_filter(),
+        KeyCode::F(5) => actions::reload_catalog(app),
+        KeyCode::Char('?') => actions::open_detail(app),
+        KeyCode::Char('m') => app.cycle_package_manager(),
+        KeyCode::Char('<') => app.resize_catalog(false),
+        KeyCode::Char('>') => app.resize_catalog(true),
+        KeyCode::Char('z') => actions::open_disk_usage(app),
+        KeyCode::Char('s') => settings::open(app),
+        KeyCode::Char('x') => actions::open_quarantine(app),
+        KeyCode::Char('w') => actions::open_maintenance(app),
+        KeyCode::Char('u') => actions::update_file_picker(app, std::env::current_dir().unwrap_or_default()),
+        KeyCode::Char('i') => start_install(app),
+        KeyCode::Char('c') => app.logs.clear(),
+        KeyCode::Char('e') => return edit_catalog(app, app.current_tool().map(|t| t.key.clone())),
+        KeyCode::Char('+') => new_entry::open(app),
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn confirm_key(app: &mut App, code: KeyCode) -> Flow {
+    match code {
+        KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+            app.size_rx = None;
+            app.state = ViewState::Browsing;
+            actions::install_selected(app);
+        }
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Char('q') | KeyCode::Esc => {
+            app.size_rx = None;
+            app.state = ViewState::Browsing;
+            app.log("Installation cancelled.".to_string());
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+/// An install or restore in progress: keys cancel or skip work rather than
+/// leave the view. 'd' and 'r' still toggle dry-run and resolve, as in the
+/// other views.
+fn running_key(app: &mut App, code: KeyCode) -> Flow {
+    match code {
+        KeyCode::Char('q') | KeyCode::Char('c') => {
+            if let Some(cancel) = &app.cancel {
+                cancel.cancel();
+                app.log("[User] Process cancelled. Waiting to abort...".to_string());
+            }
+        }
+        KeyCode::Char('s') if app.state == ViewState::Installing => {
+            let current = app.current_tool.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            if let Some(tool) = current {
+                tool.cancel();
+                app.log("[User] Skipping the current tool...".to_string());
+            }
+        }
+        KeyCode::Tab if app.state == ViewState::Installing => app.cycle_log_filter(),
+        KeyCode::Char('l') => app.cycle_level_filter(),
+        KeyCode::Char('d') => toggle_dry_run(app),
+        KeyCode::Char('r') => actions::start_resolution(app, false),
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn completed_key(app: &mut App, code: KeyCode) -> Flow {
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Esc | KeyCode::Enter => {
+            app.state = ViewState::Browsing;
+            app.progress = ProgressInfo::default();
+            app.log("Returned to browsing. Select more tools or resolve again.".to_string());
+        }
+        KeyCode::Tab => app.cycle_log_filter(),
+        KeyCode::Char('l') => app.cycle_level_filter(),
+        KeyCode::Char('d') => toggle_dry_run(app),
+        KeyCode::Char('r') => actions::start_resolution(app, false),
+        KeyCode::Char('i') => start_install(app),
+        KeyCode::Char('c') => app.logs.clear(),
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn file_picker_key(app: &mut App, code: KeyCode) -> Flow {
+    let ViewState::FilePicker { ref current_dir, ref entries, ref mut cursor } = app.state else { return Flow::Continue };
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < entries.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Esc => {
+            app.state = ViewState::Browsing;
+            app.log("File picker cancelled.".to_string());
+        }
+        KeyCode::Char('d') => toggle_dry_run(app),
+        KeyCode::Char('r') => actions::start_resolution(app, false),
+        KeyCode::Enter => {
+            let Some(path) = entries.get(*cursor).cloned() else { return Flow::Continue };
+            if path.file_name().unwrap_or_default().is_empty() {
+                if let Some(parent) = current_dir.parent().map(|p| p.to_path_buf()) {
+                    actions::update_file_picker(app, parent);
+                }
+            } else if path.is_dir() {
+                actions::update_file_picker(app, path);
+            } else if path.is_file() && path.extension().is_some_and(|e| e == "json") {
+                actions::pick_restore_parts(app, path);
+            } else {
+                app.log("Please select a JSON metadata file or a folder.".to_string());
+            }
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn disk_usage_key(app: &mut App, code: KeyCode) -> Flow {
+    let ViewState::DiskUsage { ref mut entries, ref mut cursor, ref mut by_size } = app.state else { return Flow::Continue };
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < entries.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Char('s') => {
+            *by_size = !*by_size;
+            *cursor = 0;
+            crate::usage::sort_usage(entries, *by_size);
+        }
+        KeyCode::Char('d') => toggle_dry_run(app),
+        KeyCode::Char('r') => actions::start_resolution(app, false),
+        KeyCode::Esc => app.state = ViewState::Browsing,
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn quarantine_key(app: &mut App, code: KeyCode) -> Flow {
+    let ViewState::Quarantine { ref entries, ref mut cursor } = app.state else { return Flow::Continue };
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < entries.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Char('d') | KeyCode::Delete => actions::purge_quarantined(app, false),
+        KeyCode::Char('D') => actions::purge_quarantined(app, true),
+        KeyCode::Esc => app.state = ViewState::Browsing,
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn maintenance_key(app: &mut App, code: KeyCode) -> Flow {
+    let ViewState::Maintenance { ref rows, ref mut cursor } = app.state else { return Flow::Continue };
+    let key = rows.get(*cursor).map(|row| row.key.clone());
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < rows.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Char('t') | KeyCode::Enter => {
+            if let Some(key) = key {
+                actions::probe(app, key);
+            }
+        }
+        KeyCode::Char('e') => return edit_catalog(app, key),
+        KeyCode::Esc => app.state = ViewState::Browsing,
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn restore_picker_key(app: &mut App, code: KeyCode) -> Flow {
+    let ViewState::RestorePicker { ref json_file, ref mut parts, ref mut cursor } = app.state else { return Flow::Continue };
+    match code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < parts.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Char(' ') => {
+            if let Some(part) = parts.get_mut(*cursor) {
+                part.selected = !part.selected;
+            }
+        }
+        KeyCode::Char('a') => parts.iter_mut().for_each(|part| part.selected = true),
+        KeyCode::Char('n') => parts.iter_mut().for_each(|part| part.selected = false),
+        KeyCode::Enter => {
+            if !parts.iter().any(|part| part.selected) {
+                app.log("Select at least one part to restore.".to_string());
+                return Flow::Continue;
+            }
+            let selection = crate::restorer::RestoreSelection {
+                files: parts.iter().any(|part| part.selected && part.group.is_none()),
+                groups: parts.iter().filter(|part| part.selected).filter_map(|part| part.group.clone()).collect(),
+                keep_owners: false,
+            };
+            let json_file = json_file.clone();
+            actions::start_restore_from_file(app, json_file, Some(selection));
+        }
+        KeyCode::Esc => {
+            let dir = json_file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+            actions::update_file_picker(app, dir);
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn new_entry_key(app: &mut App, key: KeyEvent) -> Flow {
+    let ViewState::NewEntry(ref mut form) = app.state else { return Flow::Continue };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    if let Some(text) = form.editing.as_mut() {
+        match key.code {
+            KeyCode::Enter => new_entry::commit_edit(app),
+            KeyCode::Esc => form.editing = None,
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) if !ctrl => text.push(c),
+            _ => {}
+        }
+        return Flow::Continue;
+    }
+    match key.code {
+        KeyCode::Char('s') if ctrl => new_entry::test(app, true),
+        KeyCode::Char('t') if ctrl => new_entry::test(app, false),
+        KeyCode::Down if form.cursor + 1 < form.rows().len() => form.cursor += 1,
+        KeyCode::Up => form.cursor = form.cursor.saturating_sub(1),
+        KeyCode::Left => new_entry::cycle_kind(app, false),
+        KeyCode::Right => new_entry::cycle_kind(app, true),
+        KeyCode::Enter | KeyCode::Char(' ') => new_entry::activate(app),
+        KeyCode::Esc => {
+            app.state = ViewState::Browsing;
+            app.entry_test_rx = None;
+            app.log("New entry discarded.".to_string());
+        }
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn settings_key(app: &mut App, key: KeyEvent) -> Flow {
+    let ViewState::Settings { ref mut cursor, ref mut editing } = app.state else { return Flow::Continue };
+    if let Some(text) = editing {
+        match key.code {
+            KeyCode::Enter => settings::commit_edit(app),
+            KeyCode::Esc => *editing = None,
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => text.push(c),
+            _ => {}
+        }
+        return Flow::Continue;
+    }
+    match key.code {
+        KeyCode::Char('q') => return Flow::Quit,
+        KeyCode::Down if *cursor + 1 < Setting::ALL.len() => *cursor += 1,
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Enter | KeyCode::Char(' ') => settings::activate(app),
+        KeyCode::Esc => app.state = ViewState::Browsing,
+        _ => {}
+    }
+    Flow::Continue
+}
+
+fn toggle_dry_run(app: &mut App) {
+    app.dry_run = !app.dry_run;
+    app.log(format!("dry-run = {}", app.dry_run));
+}
+
+fn toggle_offline(app: &mut App) {
+    app.offline = !app.offline;
+    if app.offline {
+        app.log("Offline mode: only package-manager entries and cached downloads can be installed. Press 'r' to resolve from the download folder.".to_string());
+    } else {
+        app.log("Offline mode off.".to_string());
+    }
+}
+
+/// Opens the catalog file in the editor, at `key`'s entry when given.
+fn edit_catalog(app: &mut App, key: Option<String>) -> Flow {
+    let Some(path) = app.catalog_path.clone() else {
+        app.log("[warn] The bundled catalog is in use; there is no file to edit".to_string());
+        return Flow::Continue;
+    };
+    let line = key
+        .zip(std::fs::read_to_string(&path).ok())
+        .and_then(|(key, content)| crate::catalog::entry_line(&content, &key));
+    Flow::Edit { path, line }
+}
+
+/// Dry runs start straight away; real ones go through the summary first.
+fn start_install(app: &mut App) {
+    if app.dry_run {
+        actions::install_selected(app)
+    } else {
+        actions::open_install_confirmation(app)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::config::{Config, Theme};
+use crate::units::SizeUnits;
+
+use super::{App, ViewState};
+
+/// One row of the settings view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    DryRun,
+    Concurrency,
+    DownloadDir,
+    Theme,
+    SizeUnits,
+    Proxy,
+    GithubToken,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 7] = [
+        Setting::DryRun,
+        Setting::Concurrency,
+        Setting::DownloadDir,
+        Setting::Theme,
+        Setting::SizeUnits,
+        Setting::Proxy,
+        Setting::GithubToken,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::DryRun => "Dry-run at startup",
+            Setting::Concurrency => "Concurrency",
+            Setting::DownloadDir => "Download directory",
+            Setting::Theme => "Theme",
+            Setting::SizeUnits => "Size units",
+            Setting::Proxy => "Proxy",
+            Setting::GithubToken => "GitHub token",
+        }
+    }
+
+    pub fn help(self) -> &'static str {
+        match self {
+            Setting::DryRun => "Whether new sessions start in dry-run mode. [d] still toggles it for this one.",
+            Setting::Concurrency => "Resolutions and download checks run at once.",
+            Setting::DownloadDir => "Where archives are downloaded before extraction. Empty for the default.",
+            Setting::Theme => "default draws in colour, monochrome with the terminal's own colours only.",
+            Setting::SizeUnits => "binary counts in KiB and MiB, decimal in kB and MB as disk vendors do.",
+            Setting::Proxy => "Proxy URL for every request, e.g. http://proxy:3128. Empty to use HTTPS_PROXY.",
+            Setting::GithubToken => "Read from GITHUB_TOKEN or GH_TOKEN and never stored in the config.",
+        }
+    }
+
+    pub fn value(self, config: &Config) -> String {
+        match self {
+            Setting::DryRun => if config.dry_run == Some(false) { "off" } else { "on" }.to_string(),
+            Setting::Concurrency => concurrency(config).to_string(),
+            Setting::DownloadDir => match &config.download_dir {
+                Some(dir) => dir.clone(),
+                None => crate::installer::default_download_dir().map(|p| format!("{} (default)", p.display())).unwrap_or_default(),
+            },
+            Setting::Theme => config.theme.unwrap_or_default().label().to_string(),
+            Setting::SizeUnits => config.size_units.unwrap_or_default().label().to_string(),
+            Setting::Proxy => config.proxy.clone().unwrap_or_else(|| "none".to_string()),
+            Setting::GithubToken => match crate::resolver::github_token() {
+                Some((var, _)) => format!("set ({var})"),
+                None => "not set".to_string(),
+            },
+        }
+    }
+
+    /// The starting text when editing, or `None` for settings that toggle.
+    fn edit_text(self, config: &Config) -> Option<String> {
+        match self {
+            Setting::Concurrency => Some(concurrency(config).to_string()),
+            Setting::DownloadDir => Some(config.download_dir.clone().unwrap_or_default()),
+            Setting::Proxy => Some(config.proxy.clone().unwrap_or_default()),
+            Setting::DryRun | Setting::Theme | Setting::SizeUnits | Setting::GithubToken => None,
+        }
+    }
+}
+
+fn concurrency(config: &Config) -> usize {
+    config.concurrency.unwrap_or(crate::preflight::DEFAULT_CONCURRENCY).max(1)
+}
+
+pub fn open(app: &mut App) {
+    app.state = ViewState::Settings { cursor: 0, editing: None };
+}
+
+/// Toggles the setting under the cursor or starts editing it.
+pub fn activate(app: &mut App) {
+    let ViewState::Settings { cursor, ref mut editing } = app.state else { return };
+    let setting = Setting::ALL[cursor];
+    let mut config = crate::config::current();
+    if let Some(text) = setting.edit_text(&config) {
+        *editing = Some(text);
+        return;
+    }
+    match setting {
+        Setting::DryRun => config.dry_run = Some(config.dry_run == Some(false)),
+        Setting::Theme => {
+            config.theme = Some(match config.theme.unwrap_or_default() {
+                Theme::Default => Theme::Monochrome,
+                Theme::Monochrome => Theme::Default,
+            })
+        }
+        Setting::SizeUnits => {
+            config.size_units = Some(match config.size_units.unwrap_or_default() {
+                SizeUnits::Binary => SizeUnits::Decimal,
+                SizeUnits::Decimal => SizeUnits::Binary,
+            })
+        }
+        Setting::GithubToken => {
+            app.log("[warn] Set GITHUB_TOKEN or GH_TOKEN in the environment to change the GitHub token".to_string());
+            return;
+        }
+        _ => return,
+    }
+    save(app, config, setting);
+}
+
+/// Stores the text typed for the setting under the cursor.
+pub fn commit_edit(app: &mut App) {
+    let ViewState::Settings { cursor, ref mut editing } = app.state else { return };
+    let Some(text) = editing.take() else { return };
+    let setting = Setting::ALL[cursor];
+    let text = text.trim();
+    let non_empty = (!text.is_empty()).then(|| text.to_string());
+    let mut config = crate::config::current();
+    match setting {
+        Setting::Concurrency => match text.parse::<usize>() {
+            Ok(n) if n > 0 => config.concurrency = Some(n),
+            _ => {
+                app.log(format!("[warn] Concurrency must be a positive number, not '{text}'"));
+                return;
+            }
+        },
+        Setting::DownloadDir => config.download_dir = non_empty,
+        Setting::Proxy => {
+            if let Some(Err(e)) = non_empty.as_deref().map(crate::resolver::parse_proxy) {
+                app.log(format!("[warn] {e}"));
+                return;
+            }
+            config.proxy = non_empty;
+        }
+        Setting::DryRun | Setting::Theme | Setting::SizeUnits | Setting::GithubToken => return,
+    }
+    save(app, config, setting);
+}
+
+/// Writes `config` and applies what the running session can pick up.
+fn save(app: &mut App, config: Config, setting: Setting) {
+    if let Err(e) = crate::config::save_config(&config) {
+        app.log(format!("[error] {e}"));
+        return;
+    }
+    match setting {
+        Setting::Theme => app.theme = config.theme.unwrap_or_default(),
+        Setting::SizeUnits => crate::units::reload(),
+        Setting::Proxy => match crate::resolver::build_client() {
+            Ok(client) => app.client = client,
+            Err(e) => app.log(format!("[error] {e}")),
+        },
+        _ => {}
+    }
+    app.log(format!("{} = {}", setting.label(), setting.value(&config)));
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::catalog::CatalogFile;
+use crate::config::Config;
+use crate::platform::Platform;
+
+use super::{actions, App, ViewState};
+
+/// Lines shown on either side of the offending one.
+const EXCERPT_CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrokenFile {
+    Config,
+    Catalog,
+}
+
+impl BrokenFile {
+    pub fn label(self) -> &'static str {
+        match self {
+            BrokenFile::Config => "config",
+            BrokenFile::Catalog => "catalog",
+        }
+    }
+}
+
+/// A config or catalog that failed to load at startup, shown in the TUI
+/// instead of exiting before it appears.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError {
+    pub file: BrokenFile,
+    pub path: PathBuf,
+    pub message: String,
+    /// Line and column of the error, from 1, when the parser knows them.
+    pub position: Option<(usize, usize)>,
+    /// Numbered lines around the error.
+    pub excerpt: Vec<(usize, String)>,
+}
+
+impl LoadError {
+    /// Parses the file again to find where it breaks; read errors keep
+    /// `message` as it is.
+    fn new(file: BrokenFile, path: PathBuf, message: String) -> Self {
+        let content = fs::read_to_string(&path).ok();
+        let parse_error = content.as_deref().and_then(|content| match file {
+            BrokenFile::Config => toml::from_str::<Config>(content).err(),
+            BrokenFile::Catalog => crate::catalog::parse_catalog(content).err(),
+        });
+        let mut error = LoadError { file, path, message, position: None, excerpt: Vec::new() };
+        let Some(parse_error) = parse_error else { return error };
+        error.message = parse_error.message().trim().replace('\n', "; ");
+        if let (Some(content), Some(span)) = (content, parse_error.span())
+            && let Some(before) = content.get(..span.start)
+        {
+            let line = before.matches('\n').count() + 1;
+            let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+            error.position = Some((line, column));
+            error.excerpt = content
+                .lines()
+                .enumerate()
+                .map(|(index, text)| (index + 1, text.to_string()))
+                .skip(line.saturating_sub(EXCERPT_CONTEXT + 1))
+                .take(EXCERPT_CONTEXT * 2 + 1)
+                .collect();
+        }
+        error
+    }
+}
+
+/// Loads the config and then the catalog it leads to; `Ok(None)` when
+/// there is no catalog yet.
+pub fn load() -> Result<Option<(PathBuf, CatalogFile)>, LoadError> {
+    if let Err(message) = crate::config::load_config() {
+        let path = crate::config::config_path().unwrap_or_default();
+        return Err(LoadError::new(BrokenFile::Config, path, message));
+    }
+    let Some(path) = crate::config::find_catalog() else { return Ok(None) };
+    match crate::catalog::load_catalog(&path) {
+        Ok(catalog) => Ok(Some((path, catalog))),
+        Err(message) => Err(LoadError::new(BrokenFile::Catalog, path, message)),
+    }
+}
+
+/// Loads both files again, after they were edited, and carries on where
+/// startup would have.
+pub fn retry(app: &mut App) {
+    match load() {
+        Ok(loaded) => {
+            apply_config(app);
+            match loaded {
+                Some((path, catalog)) => use_catalog(app, Some(path), catalog),
+                None => app.state = actions::onboarding_state(),
+            }
+        }
+        Err(error) => app.state = ViewState::LoadError(Box::new(error)),
+    }
+}
+
+/// Moves a broken config aside and starts from the defaults, or uses the
+/// bundled catalog for this session in place of a broken one.
+pub fn load_defaults(app: &mut App) {
+    let ViewState::LoadError(ref error) = app.state else { return };
+    let error = error.clone();
+    match error.file {
+        BrokenFile::Config => {
+            let backup = error.path.with_extension("toml.broken");
+            if let Err(e) = fs::rename(&error.path, &backup) {
+                app.log(format!("[error] failed to move {} aside: {e}", error.path.display()));
+                return;
+            }
+            retry(app);
+            app.log(format!("[warn] Using the default config; the broken one was moved to {}", backup.display()));
+        }
+        BrokenFile::Catalog => {
+            let starter = crate::catalog::starter_catalog(&crate::catalog::STARTER_PROFILES[0])
+                .and_then(|content| crate::catalog::parse_catalog(&content).map_err(|e| format!("failed to parse bundled catalog: {e}")));
+            match starter {
+                Ok(catalog) => {
+                    apply_config(app);
+                    use_catalog(app, None, catalog);
+                    app.log(format!("[warn] Using the bundled catalog for this session. Fix {} and press F5 to load it.", error.path.display()));
+                }
+                Err(e) => app.log(format!("[error] {e}")),
+            }
+        }
+    }
+}
+
+/// Opens `path` in `$VISUAL` or `$EDITOR`, falling back to vi or Notepad,
+/// and waits for it to exit. `line` is passed as `+N`, which vi, nano,
+/// Emacs and most terminal editors understand, or `--goto` for VS Code.
+pub fn edit(path: &Path, line: Option<usize>) -> Result<(), String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()))
+        .unwrap_or_else(|| if Platform::current() == Platform::Windows { "notepad" } else { "vi" }.to_string());
+    // `code --wait` and the like carry their own arguments.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let name = Path::new(program).file_stem().and_then(|s| s.to_str()).unwrap_or(program);
+    let mut command = Command::new(program);
+    command.args(words);
+    match line {
+        Some(line) if matches!(name, "code" | "codium" | "code-insiders") => command.arg("--goto").arg(format!("{}:{line}", path.display())),
+        Some(line) if name != "notepad" => command.arg(format!("+{line}")).arg(path),
+        _ => command.arg(path),
+    };
+    let status = command.status().map_err(|e| format!("failed to start {program}: {e}"))?;
+    if status.success() { Ok(()) } else { Err(format!("{program} exited with {status}")) }
+}
+
+/// Picks the config-dependent defaults up again once a config loads.
+fn apply_config(app: &mut App) {
+    let config = crate::config::current();
+    app.dry_run = config.dry_run != Some(false);
+    app.download_warn_mb = config.download_warn_mb.unwrap_or(crate::preflight::DEFAULT_DOWNLOAD_WARN_MB);
+    app.theme = config.theme.unwrap_or_default();
+    match crate::resolver::build_client() {
+        Ok(client) => app.client = client,
+        Err(e) => app.log(format!("[error] {e}")),
+    }
+}
+
+fn use_catalog(app: &mut App, path: Option<PathBuf>, catalog: CatalogFile) {
+    app.tools = super::tools_from_catalog(&catalog);
+    app.catalog = catalog;
+    app.catalog_mtime = path.as_deref().and_then(actions::modified_time);
+    app.catalog_path = path;
+    app.cursor = 0;
+    app.state = ViewState::Browsing;
+}
@@ -1,3 +1,7 @@
+use std::time::Instant;
+
+use crate::error::Error;
+use crate::events::{ArchivePreview, LogLevel};
 use crate::resolver::ResolvedAsset;
 
 #[derive(Default, Clone)]
@@ -13,12 +17,163 @@ pub struct ProgressInfo {
     pub eta: Option<String>,
     pub sub_ratio: f64,
     pub done_items: Vec<String>,
+    pub skipped_items: Vec<String>,
+    pub banner: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub tool: Option<String>,
+    pub level: LogLevel,
+    pub text: String,
+}
+
+impl LogEntry {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::tagged(None, text)
+    }
+
+    pub fn tagged(tool: Option<String>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self { tool, level: LogLevel::classify(&text), text }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum ResolveStatus {
+    Idle,
+    Pending,
+    Resolved,
+    Failed(String),
 }
 
 pub struct ToolItem {
     pub key: String,
     pub selected: bool,
     pub resolved: Option<ResolvedAsset>,
+    pub status: ResolveStatus,
+    /// Reason the last resolution or install failed; cleared on success.
+    pub last_error: Option<String>,
+    /// Resolutions that failed in a row, which sets the retry backoff.
+    pub resolve_failures: u32,
+    /// When retrying failed resolutions may pick this entry up again.
+    pub retry_at: Option<Instant>,
+    /// When `resolved` was resolved, if it came from the cache.
+    pub cached_at: Option<u64>,
+}
+
+/// One row of the grouped catalog list: a category header or an entry of
+/// `App::tools`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum CatalogRow {
+    Category(String),
+    Tool(usize),
+}
+
+/// Popup with everything known about one catalog entry.
+pub struct DetailPopup {
+    pub key: String,
+    /// Artifact size from a HEAD request, once known.
+    pub size: Option<u64>,
+    pub sizing: bool,
+    /// From the lockfiles next to local manifests.
+    pub installed_at: Option<u64>,
+    pub scroll: u16,
+    /// Archive listing requested with `p`; `None` until it arrives.
+    pub contents: Option<Result<ArchivePreview, Error>>,
+    pub previewing: bool,
+    /// Known vulnerabilities of the resolved version, requested with `v`.
+    pub advisories: Option<crate::advisory::Advisories>,
+    pub checking: bool,
+}
+
+/// Popup with the test resolution of one entry.
+pub struct ProbePopup {
+    pub key: String,
+    /// `None` while the resolution runs.
+    pub probe: Option<crate::resolver::Probe>,
+    /// Whether the page text around the regex matches is shown.
+    pub show_snippets: bool,
+}
+
+/// Masked input shown over the progress view while a secret step waits.
+pub struct SecretPrompt {
+    pub prompt: String,
+    /// Wiped when dropped; see [`secret_input`].
+    pub input: zeroize::Zeroizing<String>,
+    pub reply: std::sync::mpsc::Sender<Option<String>>,
+}
+
+/// Room reserved for typed secrets, so they rarely need to grow.
+const SECRET_CAPACITY: usize = 64;
+
+/// An empty buffer for typed secrets.
+pub fn secret_input() -> zeroize::Zeroizing<String> {
+    zeroize::Zeroizing::new(String::with_capacity(SECRET_CAPACITY))
+}
+
+/// Appends `c` to a secret buffer. A full buffer is copied into a larger one
+/// by hand rather than reallocated by `push`, which would free the old one
+/// without wiping it.
+pub fn push_secret(input: &mut zeroize::Zeroizing<String>, c: char) {
+    if input.len() + c.len_utf8() > input.capacity() {
+        let mut grown = zeroize::Zeroizing::new(String::with_capacity((input.capacity() * 2).max(SECRET_CAPACITY)));
+        grown.push_str(input);
+        *input = grown;
+    }
+    input.push(c);
+}
+
+/// sudo password prompt shown when a privileged step needs root, so the
+/// TUI never has to leave the screen for it.
+pub struct SudoPrompt {
+    /// Never drawn; wiped when dropped, see [`secret_input`].
+    pub input: zeroize::Zeroizing<String>,
+    pub reply: std::sync::mpsc::Sender<bool>,
+    /// Why the last attempt failed.
+    pub error: Option<String>,
+    pub attempts: u32,
+    /// Whether sudo is checking the password.
+    pub checking: bool,
+}
+
+/// One row of the pre-install summary.
+#[derive(PartialEq, Clone)]
+pub struct PlannedInstall {
+    pub key: String,
+    pub name: String,
+    pub version: Option<String>,
+    /// Download size from a HEAD request, once known.
+    pub size: Option<u64>,
+    /// Whether the size lookup is still running.
+    pub sizing: bool,
+    /// Why the pre-flight HEAD request says the download would fail.
+    pub problem: Option<String>,
+}
+
+/// A part of a backup the restore picker can leave out.
+#[derive(PartialEq, Clone)]
+pub struct RestorePart {
+    /// Group name; `None` for the files of the backed-up folder.
+    pub group: Option<String>,
+    pub label: String,
+    pub selected: bool,
+}
+
+/// One catalog entry in the maintenance view.
+#[derive(PartialEq, Clone)]
+pub struct MaintenanceRow {
+    pub key: String,
+    pub name: String,
+    pub health: crate::state::EntryHealth,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum OnboardingStep {
+    Profile,
+    InstallRoot,
+    DownloadDir,
+    Confirm,
 }
 
 #[derive(PartialEq, Clone)]
@@ -31,13 +186,51 @@ pub enum ViewState {
         entries: Vec<std::path::PathBuf>,
         cursor: usize,
     },
+    /// The parts of the backup picked in the file picker, for backups
+    /// that have groups besides their files.
+    RestorePicker {
+        json_file: std::path::PathBuf,
+        parts: Vec<RestorePart>,
+        cursor: usize,
+    },
     Restoring,
-}
-
-pub enum InstallMsg {
-    Progress(String, String, Option<String>),
-    SubProgress(f64),
-    Log(String),
-    Done(String, Result<Vec<String>, String>),
-    Finished,
+    Onboarding {
+        step: OnboardingStep,
+        profile: usize,
+        install_root: String,
+        download_dir: String,
+    },
+    DiskUsage {
+        entries: Vec<crate::usage::UsageEntry>,
+        cursor: usize,
+        by_size: bool,
+    },
+    ConfirmInstall {
+        items: Vec<PlannedInstall>,
+        /// Steps that change the system beyond the install root.
+        changes: Vec<String>,
+        /// Checked when the summary opens, to warn before a big run drains
+        /// the battery.
+        on_battery: bool,
+    },
+    /// Downloads set aside after failing verification.
+    Quarantine {
+        entries: Vec<crate::quarantine::QuarantinedDownload>,
+        cursor: usize,
+    },
+    /// How each catalog entry fared across sessions, entries that keep
+    /// failing first.
+    Maintenance {
+        rows: Vec<MaintenanceRow>,
+        cursor: usize,
+    },
+    /// The config or catalog failed to load at startup.
+    LoadError(Box<super::startup::LoadError>),
+    Settings {
+        cursor: usize,
+        /// Text typed for the setting under the cursor while editing it.
+        editing: Option<String>,
+    },
+    /// Form adding an entry to the catalog file.
+    NewEntry(Box<super::new_entry::EntryForm>),
 }
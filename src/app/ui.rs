@@ -1,25 +1,551 @@
+use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Gauge, Wrap};
 use ratatui::Frame;
-use crate::app::{App, ViewState};
+use crate::app::{App, CatalogRow, DetailPopup, LogLevel, ProbePopup, ResolveStatus, SudoPrompt, ViewState};
+use crate::app::state::{MaintenanceRow, OnboardingStep, PlannedInstall, RestorePart};
+use crate::app::new_entry::{EntryForm, Row};
+use crate::app::palette::CommandPalette;
+use crate::app::settings::Setting;
+use crate::app::startup::LoadError;
+use crate::app::screen;
+use crate::quarantine::QuarantinedDownload;
+
+/// Below this height the ASCII banner gives way to a one-line title.
+const COMPACT_HEIGHT: u16 = 30;
+
+/// Below this width the CPU/RAM gauges are hidden; the banner needs about as
+/// much room, so it collapses too.
+const NARROW_WIDTH: u16 = 100;
+
+/// Share of the browsing view given to the catalog list, in percent, and the
+/// range the split can be moved within.
+pub const DEFAULT_CATALOG_SPLIT: u16 = 25;
+pub const MIN_CATALOG_SPLIT: u16 = 15;
+pub const MAX_CATALOG_SPLIT: u16 = 60;
+
+/// The banner is drawn with block characters, so ASCII-only terminals get
+/// the compact header too.
+fn is_compact(area: Rect) -> bool {
+    area.height < COMPACT_HEIGHT || area.width < NARROW_WIDTH || crate::run_context::current().ascii()
+}
 
 pub fn render(app: &App, frame: &mut Frame) {
     let area = frame.area();
+    let compact = is_compact(area);
 
     let main_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(8),
+            Constraint::Length(if compact { 1 } else { 8 }),
             Constraint::Min(0),
             Constraint::Length(3),
         ])
         .split(area);
 
-    render_header(app, frame, main_layout[0]);
-    render_body(app, frame, main_layout[1]);
+    if compact {
+        render_compact_header(app, frame, main_layout[0]);
+    } else {
+        render_header(app, frame, main_layout[0]);
+    }
+    screen::render_view(app, frame, main_layout[1]);
     render_footer(app, frame, main_layout[2]);
+    screen::render_modals(app, frame, area);
+
+    let context = crate::run_context::current();
+    let monochrome = app.theme == crate::config::Theme::Monochrome;
+    if context.ascii() || !context.rgb() || monochrome {
+        downgrade(frame.buffer_mut(), context.ascii(), context.rgb(), monochrome);
+    }
+}
+
+/// Rewrites the finished frame for limited terminals: non-ASCII glyphs get
+/// ASCII stand-ins and RGB colours become their 256-colour neighbours. The
+/// monochrome theme drops colours altogether, showing highlighted cells
+/// such as the cursor row in reverse video instead.
+fn downgrade(buf: &mut Buffer, ascii: bool, rgb: bool, monochrome: bool) {
+    for cell in buf.content.iter_mut() {
+        if ascii && !cell.symbol().is_ascii() {
+            let replacement = ascii_glyph(cell.symbol());
+            cell.set_symbol(replacement);
+        }
+        if monochrome {
+            if cell.bg != Color::Reset {
+                cell.modifier.insert(Modifier::REVERSED);
+            }
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+        } else if !rgb {
+            cell.fg = indexed_color(cell.fg);
+            cell.bg = indexed_color(cell.bg);
+        }
+    }
+}
+
+fn ascii_glyph(symbol: &str) -> &'static str {
+    match symbol.chars().next().unwrap_or(' ') {
+        '─' | '━' | '═' => "-",
+        '│' | '┃' | '║' => "|",
+        '┌' | '┐' | '└' | '┘' | '╭' | '╮' | '╰' | '╯' | '├' | '┤' | '┬' | '┴' | '┼' => "+",
+        '•' | '●' | '★' => "*",
+        '…' => ".",
+        '✓' => "+",
+        '✗' => "x",
+        '▸' | '→' => ">",
+        '▏' => "_",
+        '▾' => "v",
+        '←' => "<",
+        '↑' => "^",
+        '↓' => "v",
+        '⚠' => "!",
+        '🔒' => "#",
+        '📁' => "/",
+        '📄' => "-",
+        '\u{2580}'..='\u{259F}' => "#",
+        '\u{2800}'..='\u{28FF}' => "*",
+        _ => "?",
+    }
+}
+
+/// Nearest xterm-256 entry for an RGB colour: the grey ramp for greys, the
+/// 6x6x6 cube otherwise.
+fn indexed_color(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+    if r == g && g == b {
+        return match r {
+            0..=7 => Color::Indexed(16),
+            239..=255 => Color::Indexed(231),
+            grey => Color::Indexed(232 + (grey - 8) / 10),
+        };
+    }
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    Color::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+/// Everything about one entry, over the browsing view: metadata, source
+/// configuration, the resolved download and the commands an install runs.
+pub(super) fn render_detail_popup(app: &App, frame: &mut Frame, area: Rect, detail: &DetailPopup) {
+    let Some(spec) = app.catalog.software.get(&detail.key) else { return };
+    let tool = app.tools.iter().find(|t| t.key == detail.key);
+    let width = area.width.saturating_sub(4).min(110);
+    let height = area.height.saturating_sub(2);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let label = |name: &str| Span::styled(format!(" {name:<14}"), Style::default().fg(Color::Cyan));
+    let value = |text: String| Span::styled(text, Style::default().fg(Color::White));
+    let heading = |text: &str| Line::from(Span::styled(format!(" {text}"), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+
+    let homepage = spec.homepage.clone().or_else(|| match &spec.source {
+        crate::catalog::SourceSpec::Github { repo: Some(repo), .. } => Some(format!("https://github.com/{repo}")),
+        crate::catalog::SourceSpec::BuildFromSource { repo, .. } if repo.contains("://") => Some(repo.trim_end_matches(".git").to_string()),
+        crate::catalog::SourceSpec::BuildFromSource { repo, .. } if !repo.contains('@') => Some(format!("https://github.com/{repo}")),
+        crate::catalog::SourceSpec::OfficialSource { url: Some(url), .. } => Some(url.clone()),
+        _ => None,
+    });
+    let mut lines = vec![
+        Line::from(vec![label("Description"), value(spec.description.clone().unwrap_or_else(|| "No description available.".to_string()))]),
+        Line::from(vec![label("Category"), value(crate::app::category_of(&app.catalog, &detail.key))]),
+        Line::from(vec![label("Homepage"), value(homepage.unwrap_or_else(|| "-".to_string()))]),
+        Line::from(vec![label("Availability"), match app.unavailable(&detail.key) {
+            Some(reason) => Span::styled(reason, Style::default().fg(Color::Yellow)),
+            None => value(format!("suits this machine ({})", app.distro.environment.labels().join(", "))),
+        }]),
+        Line::from(""),
+        heading("Source"),
+        Line::from(vec![label("Kind"), value(spec.source.kind_key().to_string())]),
+    ];
+    let hardware_steps = spec.setup_steps.iter().any(|step| matches!(step, crate::catalog::SetupStep::Package { only_if_hardware: Some(_), .. }));
+    if spec.only_if_hardware.is_some() || hardware_steps {
+        lines.insert(4, Line::from(vec![label("Graphics"), value(crate::hardware::summary())]));
+    }
+    let mut field = |name: &str, text: &Option<String>| {
+        if let Some(text) = text {
+            lines.push(Line::from(vec![label(name), value(text.clone())]));
+        }
+    };
+    match &spec.source {
+        crate::catalog::SourceSpec::OfficialSource { id, url, version_regex, download_url_regex, date_regex } => {
+            field("Id", id);
+            field("Page", url);
+            field("Version regex", version_regex);
+            field("Download regex", download_url_regex);
+            field("Date regex", date_regex);
+        }
+        crate::catalog::SourceSpec::Github { repo, asset_pattern } => {
+            field("Repository", repo);
+            field("Asset pattern", &Some(asset_pattern.clone()));
+        }
+        crate::catalog::SourceSpec::Templated { version_url, version_regex, url_template } => {
+            field("Version URL", &Some(version_url.clone()));
+            field("Version regex", &Some(version_regex.clone()));
+            field("URL template", &Some(url_template.clone()));
+        }
+        crate::catalog::SourceSpec::JsonApi { url, version_path, version_regex, download_path, url_template, date_path } => {
+            field("Endpoint", &Some(url.clone()));
+            field("Version path", &Some(version_path.clone()));
+            field("Version regex", version_regex);
+            field("Download path", download_path);
+            field("URL template", url_template);
+            field("Date path", date_path);
+        }
+        crate::catalog::SourceSpec::BuildFromSource { repo, tag, build_steps } => {
+            field("Repository", &Some(repo.clone()));
+            field("Tag", tag);
+            for (i, step) in build_steps.iter().enumerate() {
+                field(&format!("Build step {}", i + 1), &Some(step.clone()));
+            }
+        }
+        crate::catalog::SourceSpec::PackageManager | crate::catalog::SourceSpec::None => {}
+    }
+    let root = crate::installer::install_root(spec).map(|p| p.display().to_string()).unwrap_or_else(|e| e);
+    lines.push(Line::from(vec![label("Install root"), value(root)]));
+    if let Some(sub) = &spec.extract_subdir {
+        lines.push(Line::from(vec![label("Subfolder"), value(sub.clone())]));
+    }
+    if spec.strip_components > 0 {
+        lines.push(Line::from(vec![label("Strip"), value(format!("{} leading path component(s)", spec.strip_components))]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(heading("Resolved"));
+    match tool.and_then(|t| t.resolved.as_ref()) {
+        Some(resolved) => {
+            let size = if detail.sizing {
+                "measuring…".to_string()
+            } else {
+                detail.size.map(crate::units::format_size).unwrap_or_else(|| "unknown".to_string())
+            };
+            lines.push(Line::from(vec![label("Version"), value(resolved.version.clone())]));
+            if let (Some(published), Some((age, stale))) = (resolved.published_at, release_age(resolved)) {
+                let date = crate::report::format_timestamp(published).chars().take(10).collect::<String>();
+                let text = if stale {
+                    Span::styled(format!("{date} ({age} ago) ⚠ stale: no release in over two years"), Style::default().fg(Color::Yellow))
+                } else {
+                    value(format!("{date} ({age} ago)"))
+                };
+                lines.push(Line::from(vec![label("Released"), text]));
+            }
+            lines.push(Line::from(vec![label("URL"), value(resolved.url.clone())]));
+            lines.push(Line::from(vec![label("File"), value(resolved.file_name.clone())]));
+            lines.push(Line::from(vec![label("Size"), value(size)]));
+        }
+        None => lines.push(Line::from(Span::styled("   not resolved yet, press [r] in the catalog", Style::default().fg(Color::DarkGray)))),
+    }
+    if let Some(err) = tool.and_then(|t| t.last_error.as_ref()) {
+        lines.push(Line::from(vec![label("Last error"), Span::styled(err.clone(), Style::default().fg(Color::Red))]));
+    }
+    lines.push(Line::from(""));
+    lines.push(heading("Advisories"));
+    lines.extend(advisory_lines(detail));
+    if spec.source.has_artifact() {
+        lines.push(Line::from(""));
+        lines.push(heading("Contents"));
+        lines.extend(contents_lines(detail));
+    }
+
+    let dependencies: Vec<String> = spec
+        .setup_steps
+        .iter()
+        .flat_map(|step| step.packages_for(&app.distro.id, app.distro.pkg_manager.name()))
+        .cloned()
+        .collect();
+    let installed = detail.installed_at.map(crate::report::format_timestamp).unwrap_or_else(|| "no record in local lockfiles".to_string());
+    lines.push(Line::from(""));
+    lines.push(heading("Installation"));
+    lines.push(Line::from(vec![label("Dependencies"), value(if dependencies.is_empty() { "none".to_string() } else { dependencies.join(", ") })]));
+    lines.push(Line::from(vec![label("Last install"), value(installed)]));
+
+    lines.push(Line::from(""));
+    lines.push(heading("Commands"));
+    lines.extend(step_lines(app, spec));
+    if spec.source.has_artifact() {
+        let file = tool.and_then(|t| t.resolved.as_ref()).map(|r| r.file_name.clone()).unwrap_or_else(|| "<artifact>".to_string());
+        let download = crate::installer::download_dir().map(|d| d.join(&file).display().to_string()).unwrap_or(file.clone());
+        lines.push(Line::from(Span::styled(format!("  v Download: {download}"), Style::default().fg(Color::Green))));
+        let unpack = if crate::installer::is_package_artifact(&file) {
+            format!("  $ Install {file} with {}", app.distro.pkg_manager)
+        } else {
+            format!("  > Extract into the install root (strip {})", spec.strip_components)
+        };
+        lines.push(Line::from(Span::styled(unpack, Style::default().fg(Color::Green))));
+    }
+
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("  {} ({})  ", spec.display_name, detail.key))
+                    .title_bottom(" [↑/↓] scroll • [p] list archive contents • [v] check advisories • [Esc/Enter/?] close ")
+                    .border_style(Style::default().fg(Color::Cyan)),
+            )
+            .wrap(Wrap { trim: false })
+            .scroll((detail.scroll, 0)),
+        popup,
+    );
+}
+
+/// Test resolution of one entry: what it resolved to, where the download
+/// really is and, for scrapers, where on the page the regexes matched.
+pub(super) fn render_probe_popup(frame: &mut Frame, area: Rect, popup_state: &ProbePopup) {
+    let width = area.width.saturating_sub(4).min(110);
+    let height = area.height.saturating_sub(2).min(30);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 3,
+        width,
+        height,
+    };
+    let label = |name: &str| Span::styled(format!(" {name:<14}"), Style::default().fg(Color::Cyan));
+    let value = |text: String| Span::styled(text, Style::default().fg(Color::White));
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let mut lines = Vec::new();
+    match &popup_state.probe {
+        None => lines.push(Line::from(Span::styled(format!(" {} resolving…", spinner_frame()), Style::default().fg(Color::Yellow)))),
+        Some(probe) => {
+            match &probe.result {
+                Ok(asset) => {
+                    lines.push(Line::from(vec![label("Version"), Span::styled(asset.version.clone(), Style::default().fg(Color::Green))]));
+                    lines.push(Line::from(vec![label("URL"), value(asset.url.clone())]));
+                    lines.push(Line::from(vec![label("File name"), value(asset.file_name.clone())]));
+                }
+                Err(e) => lines.push(Line::from(vec![label("Error"), Span::styled(e.to_string(), Style::default().fg(Color::Red))])),
+            }
+            if let Some(url) = probe.final_url.as_ref().filter(|url| probe.result.as_ref().is_ok_and(|a| a.url != **url)) {
+                lines.push(Line::from(vec![label("Redirected to"), value(url.clone())]));
+            }
+            match &probe.status {
+                Some(Ok(status)) => {
+                    let color = if (200..300).contains(status) { Color::Green } else { Color::Red };
+                    lines.push(Line::from(vec![label("HTTP status"), Span::styled(format!("{status} (HEAD)"), Style::default().fg(color))]));
+                }
+                Some(Err(e)) => lines.push(Line::from(vec![label("HTTP status"), Span::styled(e.clone(), Style::default().fg(Color::Red))])),
+                None => {}
+            }
+            if let Some(page) = &probe.page {
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![label("Page"), value(page.clone())]));
+                if !popup_state.show_snippets {
+                    lines.push(Line::from(Span::styled(" [h] shows where the regexes matched", dim)));
+                }
+                for snippet in probe.snippets.iter().filter(|_| popup_state.show_snippets) {
+                    lines.push(Line::from(vec![label(&format!("{} regex", snippet.label)), Span::styled(snippet.pattern.clone(), Style::default().fg(Color::Yellow))]));
+                    match &snippet.found {
+                        Some((before, matched, after)) => lines.push(Line::from(vec![
+                            Span::styled(format!("   …{before}"), dim),
+                            Span::styled(matched.clone(), Style::default().fg(Color::Black).bg(Color::Yellow)),
+                            Span::styled(format!("{after}…"), dim),
+                        ])),
+                        None => lines.push(Line::from(Span::styled("   no match on the page", Style::default().fg(Color::Red)))),
+                    }
+                }
+            }
+        }
+    }
+
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("  Test resolution: {}  ", popup_state.key))
+                    .title_bottom(" [r] again • [h] page snippets • [Esc/Enter] close ")
+                    .border_style(Style::default().fg(Color::Cyan)),
+            )
+            .wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+/// Releases older than this are flagged as stale in the catalog.
+const STALE_AFTER_SECS: u64 = 2 * 365 * 86_400;
+
+/// How long ago the resolved release was published, and whether that makes
+/// the upstream look abandoned.
+fn release_age(resolved: &crate::resolver::ResolvedAsset) -> Option<(String, bool)> {
+    let age = crate::manifest::now_secs().saturating_sub(resolved.published_at?);
+    Some((crate::report::format_age(age), age > STALE_AFTER_SECS))
+}
+
+/// The OSV vulnerability section of the details popup.
+fn advisory_lines(detail: &DetailPopup) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let advisories = match &detail.advisories {
+        _ if detail.checking => return vec![Line::from(Span::styled("   querying osv.dev…", dim))],
+        None => return vec![Line::from(Span::styled("   press [v] to look the resolved version up in the OSV database", dim))],
+        Some(Err(e)) => return vec![Line::from(Span::styled(format!("   {e}"), Style::default().fg(Color::Red)))],
+        Some(Ok(advisories)) => advisories,
+    };
+    if advisories.is_empty() {
+        return vec![Line::from(Span::styled("   ✓ no known vulnerabilities", Style::default().fg(Color::Green)))];
+    }
+    let mut lines = vec![Line::from(Span::styled(
+        format!("   ⚠ {} known vulnerabilities affect this version", advisories.len()),
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    ))];
+    for advisory in advisories {
+        let severity = advisory.severity.as_deref().map(|s| format!(" [{s}]")).unwrap_or_default();
+        lines.push(Line::from(vec![
+            Span::styled(format!("   {}{severity} ", advisory.id), Style::default().fg(Color::Yellow)),
+            Span::styled(advisory.summary.clone(), Style::default().fg(Color::White)),
+        ]));
+    }
+    lines
+}
+
+/// The archive preview section of the details popup.
+fn contents_lines(detail: &DetailPopup) -> Vec<Line<'static>> {
+    const SHOWN: usize = 40;
+    let dim = Style::default().fg(Color::DarkGray);
+    let preview = match &detail.contents {
+        _ if detail.previewing => return vec![Line::from(Span::styled("   listing… (downloading first when not cached)", dim))],
+        None => return vec![Line::from(Span::styled("   press [p] to list the top-level entries without extracting", dim))],
+        Some(Err(e)) => return vec![Line::from(Span::styled(format!("   {e}"), Style::default().fg(Color::Red)))],
+        Some(Ok(preview)) => preview,
+    };
+
+    let total: u64 = preview.entries.iter().map(|e| e.size).sum();
+    let source = if preview.fetched { "downloaded for this preview" } else { "from the download folder" };
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!(" {:<14}", "Format"), Style::default().fg(Color::Cyan)),
+        Span::styled(format!("{} ({source})", preview.format), Style::default().fg(Color::White)),
+    ])];
+    for entry in preview.entries.iter().take(SHOWN) {
+        let name = if entry.dir { format!("{}/", entry.name) } else { entry.name.clone() };
+        lines.push(Line::from(vec![
+            Span::styled(format!("   {name:<40} "), Style::default().fg(if entry.dir { Color::Blue } else { Color::White })),
+            Span::styled(format!("{:>10}", crate::units::format_size(entry.size)), dim),
+        ]));
+    }
+    if preview.entries.len() > SHOWN {
+        lines.push(Line::from(Span::styled(format!("   … {} more", preview.entries.len() - SHOWN), dim)));
+    }
+    lines.push(Line::from(vec![
+        Span::styled(format!(" {:<14}", "Total"), Style::default().fg(Color::Cyan)),
+        Span::styled(
+            format!("{} uncompressed in {} top-level entries", crate::units::format_size(total), preview.entries.len()),
+            Style::default().fg(Color::White),
+        ),
+    ]));
+    lines
+}
+
+pub(super) fn render_palette(app: &App, frame: &mut Frame, area: Rect, palette: &CommandPalette) {
+    let width = area.width.saturating_sub(4).min(80);
+    let height = area.height.saturating_sub(2).min(18);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 3,
+        width,
+        height,
+    };
+    let dim = Style::default().fg(Color::DarkGray);
+    let matches = palette.matches(app);
+    // Input, a blank line and the borders take four rows.
+    let visible = (height as usize).saturating_sub(4).max(1);
+    let first = palette.cursor.saturating_sub(visible - 1);
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(" > ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{}_", palette.query), Style::default().fg(Color::White)),
+        ]),
+        Line::from(""),
+    ];
+    if matches.is_empty() {
+        let hint = if palette.query.is_empty() { " No favorites or recent actions yet; type to search the catalog" } else { " No matching entries" };
+        lines.push(Line::from(Span::styled(hint, dim)));
+    }
+    for (index, found) in matches.iter().enumerate().skip(first).take(visible) {
+        let highlighted = index == palette.cursor;
+        let base = if highlighted { Style::default().bg(Color::DarkGray) } else { Style::default() };
+        let tag = match (found.favorite, found.recent) {
+            (true, true) => "★ recent",
+            (true, false) => "★",
+            (false, true) => "recent",
+            (false, false) => "",
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {:<8}", found.command.action.label()), base.fg(Color::Cyan)),
+            Span::styled(format!("{:<24}", found.command.key), base.fg(Color::White).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{:<30}", found.name), base.fg(Color::Gray)),
+            Span::styled(format!("{tag:<8}"), base.fg(Color::Yellow)),
+        ]));
+    }
+
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("  Command Palette ({})  ", matches.len()))
+                .title_bottom(" [Enter] run • [↑/↓] move • [Ctrl+F] favorite • [Esc] close ")
+                .border_style(Style::default().fg(Color::Yellow)),
+        ),
+        popup,
+    );
+}
+
+/// Like sudo itself, shows nothing of what is typed, not even its length.
+pub(super) fn render_sudo_prompt(frame: &mut Frame, area: Rect, prompt: &SudoPrompt) {
+    let width = area.width.min(60);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(6) / 2,
+        width,
+        height: 6.min(area.height),
+    };
+    let user = std::env::var("USER").or_else(|_| std::env::var("LOGNAME")).unwrap_or_else(|_| "your user".to_string());
+    let status = if prompt.checking {
+        Line::from(Span::styled(" Checking...", Style::default().fg(Color::Cyan)))
+    } else if let Some(error) = &prompt.error {
+        Line::from(Span::styled(format!(" {error} ({} of 3 attempts)", prompt.attempts), Style::default().fg(Color::Red)))
+    } else {
+        Line::from(Span::styled(" A privileged step needs root. Typing is not shown.", Style::default().fg(Color::DarkGray)))
+    };
+    let lines = vec![
+        Line::from(Span::styled(format!(" [sudo] password for {user}: _"), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        status,
+        Line::from(""),
+        Line::from(Span::styled(" [Enter] authenticate • [Esc] decline", Style::default().fg(Color::DarkGray))),
+    ];
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("  sudo  ").border_style(Style::default().fg(Color::Yellow))),
+        popup,
+    );
+}
+
+pub(super) fn render_secret_prompt(frame: &mut Frame, area: Rect, prompt: &str, len: usize) {
+    let width = area.width.min(60);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(5) / 2,
+        width,
+        height: 5.min(area.height),
+    };
+    let lines = vec![
+        Line::from(Span::styled(format!(" {prompt}"), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(format!(" {}_", "*".repeat(len)), Style::default().fg(Color::Yellow))),
+        Line::from(Span::styled(" [Enter] save to keyring • [Esc] cancel", Style::default().fg(Color::DarkGray))),
+    ];
+    frame.render_widget(ratatui::widgets::Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("  Secret  ").border_style(Style::default().fg(Color::Yellow))),
+        popup,
+    );
 }
 
 fn render_header(app: &App, frame: &mut Frame, area: Rect) {
@@ -39,7 +565,11 @@ fn render_header(app: &App, frame: &mut Frame, area: Rect) {
         " ██║  ██║╚██████╔╝███████║   ██║      ██║       ██║  ██║███████╗██████╔╝██║  ██║███████║███████╗",
         " ╚═╝  ╚═╝ ╚═════╝ ╚══════╝   ╚═╝      ╚═╝       ╚═╝  ╚═╝╚══════╝╚═════╝ ╚═╝  ╚═╝╚══════╝╚══════╝",
     ];
-    let banner: Vec<Line> = ascii.into_iter().map(|l| Line::from(Span::styled(l, Style::default().fg(Color::Cyan)))).collect();
+    let mut banner: Vec<Line> = ascii.into_iter().map(|l| Line::from(Span::styled(l, Style::default().fg(Color::Cyan)))).collect();
+    if let Some(network) = network_banner(app) {
+        banner.push(Line::from(""));
+        banner.push(Line::from(network));
+    }
     frame.render_widget(Paragraph::new(banner), chunks[0]);
 
     let stats_block = Block::default()
@@ -49,11 +579,6 @@ fn render_header(app: &App, frame: &mut Frame, area: Rect) {
     let stats_inner = stats_block.inner(chunks[1]);
     frame.render_widget(stats_block, chunks[1]);
 
-    let cpu_use = app.sys.global_cpu_usage();
-    let total_mem = app.sys.total_memory() as f64 / 1024.0 / 1024.0 / 1024.0;
-    let used_mem = app.sys.used_memory() as f64 / 1024.0 / 1024.0 / 1024.0;
-    let mem_percent = (used_mem / total_mem * 100.0) as u16;
-    
     let stats_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -63,104 +588,275 @@ fn render_header(app: &App, frame: &mut Frame, area: Rect) {
         ])
         .split(stats_inner);
 
-    let cpu_gauge = Gauge::default()
-        .block(Block::default().title(" CPU ").title_style(Style::default().fg(Color::Gray)))
-        .gauge_style(Style::default().fg(Color::Magenta))
-        .percent(cpu_use as u16)
-        .label(format!("{:.1}%", cpu_use));
+    let cpu_gauge = cpu_gauge(app, "").block(Block::default().title(" CPU ").title_style(Style::default().fg(Color::Gray)));
     frame.render_widget(cpu_gauge, stats_layout[0]);
 
-    let mem_gauge = Gauge::default()
-        .block(Block::default().title(" RAM ").title_style(Style::default().fg(Color::Gray)))
-        .gauge_style(Style::default().fg(Color::Yellow))
-        .percent(mem_percent)
-        .label(format!("{:.1} / {:.1} GB", used_mem, total_mem));
+    let mem_gauge = mem_gauge(app, "").block(Block::default().title(" RAM ").title_style(Style::default().fg(Color::Gray)));
     frame.render_widget(mem_gauge, stats_layout[1]);
 
-    let distro_info = Paragraph::new(vec![
-        Line::from(vec![
-            Span::styled(" OS: ", Style::default().fg(Color::Gray)),
-            Span::styled(&app.distro.id, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::styled(" | PACKAGE-MANAGER: ", Style::default().fg(Color::Gray)),
-            Span::styled(app.distro.pkg_manager.to_string(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::styled(" | DRY-RUN: ", Style::default().fg(Color::Gray)),
-            Span::styled(if app.dry_run { "ON" } else { "OFF" }, Style::default().fg(if app.dry_run { Color::Yellow } else { Color::Green }).add_modifier(Modifier::BOLD)),
+    frame.render_widget(Paragraph::new(Line::from(system_info_spans(app))).wrap(Wrap { trim: true }), stats_layout[2]);
+}
+
+/// Header for small terminals: the title and system info on one line, with
+/// the gauges squeezed in on the right when the width allows.
+fn render_compact_header(app: &App, frame: &mut Frame, area: Rect) {
+    let gauge_width = if area.width < NARROW_WIDTH { 0 } else { 24 };
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(gauge_width),
+            Constraint::Length(1.min(gauge_width)),
+            Constraint::Length(gauge_width),
         ])
-    ]);
-    frame.render_widget(distro_info, stats_layout[2]);
+        .split(area);
+
+    let mut spans = vec![Span::styled(" RUSTY REBASE ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))];
+    // Ahead of the system info, which gets cut off first on narrow screens.
+    spans.extend(network_banner(app));
+    spans.extend(system_info_spans(app));
+    frame.render_widget(Paragraph::new(Line::from(spans)), chunks[0]);
+
+    if gauge_width > 0 {
+        frame.render_widget(cpu_gauge(app, "CPU "), chunks[1]);
+        frame.render_widget(mem_gauge(app, "RAM "), chunks[3]);
+    }
 }
 
-fn render_body(app: &App, frame: &mut Frame, area: Rect) {
-    match app.state {
-        ViewState::Browsing => render_browsing(app, frame, area),
-        ViewState::Installing | ViewState::Completed | ViewState::Restoring => render_progress(app, frame, area),
-        ViewState::FilePicker { ref current_dir, ref entries, cursor } => {
-            render_file_picker(app, frame, area, current_dir, entries, cursor)
-        }
+/// Offline mode, or the problem the connectivity probe found.
+fn network_banner(app: &App) -> Option<Span<'static>> {
+    if app.offline {
+        let text = " OFFLINE MODE: package-manager entries and cached downloads only ";
+        return Some(Span::styled(text, Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)));
     }
+    let banner = app.connectivity.as_ref()?.banner()?;
+    Some(Span::styled(format!(" ⚠ {banner} • [o] offline mode "), Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)))
+}
+
+/// `prefix` names the gauge in its label when there is no room for a title.
+fn cpu_gauge(app: &App, prefix: &str) -> Gauge<'static> {
+    let cpu_use = app.sys.global_cpu_usage();
+    Gauge::default()
+        .gauge_style(Style::default().fg(Color::Magenta))
+        .percent(cpu_use as u16)
+        .label(format!("{prefix}{}%", crate::units::format_decimal(cpu_use as f64, 1)))
+}
+
+fn mem_gauge(app: &App, prefix: &str) -> Gauge<'static> {
+    let total_mem = app.sys.total_memory();
+    let used_mem = app.sys.used_memory();
+    let mem_percent = (used_mem as f64 / total_mem as f64 * 100.0) as u16;
+    Gauge::default()
+        .gauge_style(Style::default().fg(Color::Yellow))
+        .percent(mem_percent)
+        .label(format!("{prefix}{} / {}", crate::units::format_size(used_mem), crate::units::format_size(total_mem)))
+}
+
+/// Environment tags plus the C library when it is known.
+fn environment_labels(app: &App) -> Vec<String> {
+    let environment = app.distro.environment;
+    let mut labels: Vec<String> = environment.labels().into_iter().map(str::to_string).collect();
+    if environment.libc != crate::distro::Libc::Unknown {
+        labels.push(environment.libc.to_string());
+    }
+    labels
+}
+
+fn system_info_spans(app: &App) -> Vec<Span<'_>> {
+    let labels = crate::run_context::current().labels();
+    let context = if labels.is_empty() { String::new() } else { format!(" | {}", labels.join(" ")) };
+    vec![
+        Span::styled(" OS: ", Style::default().fg(Color::Gray)),
+        Span::styled(&app.distro.id, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::styled(format!(" ({})", environment_labels(app).join(", ")), Style::default().fg(Color::Gray)),
+        Span::styled(" | PACKAGE-MANAGER: ", Style::default().fg(Color::Gray)),
+        Span::styled(
+            if app.distro.pkg_manager_overridden { format!("{} (override)", app.distro.pkg_manager) } else { app.distro.pkg_manager.to_string() },
+            Style::default().fg(if app.distro.pkg_manager_overridden { Color::Yellow } else { Color::Green }).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(" | DRY-RUN: ", Style::default().fg(Color::Gray)),
+        Span::styled(if app.dry_run { "ON" } else { "OFF" }, Style::default().fg(if app.dry_run { Color::Yellow } else { Color::Green }).add_modifier(Modifier::BOLD)),
+        Span::styled(context, Style::default().fg(Color::Cyan)),
+        Span::styled(if crate::throttle::active() { " | THROTTLED (high CPU)" } else { "" }, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+    ]
 }
 
 pub fn render_logs(app: &App, frame: &mut Frame, area: Rect, title: &str, border_color: Color) {
-    let logs: Vec<ListItem> = app.logs.iter().rev().take(area.height as usize).map(|l| {
-        let color = if l.contains("[error]") || l.contains("failed") || l.contains("Error") { Color::Red }
-                    else if l.contains("[done]") || l.contains("succeeded") || l.contains("status 0") { Color::Green }
-                    else if l.contains("[resolve]") || l.starts_with("==") { Color::Cyan }
-                    else { Color::Gray };
-        ListItem::new(Line::from(Span::styled(l, Style::default().fg(color))))
-    }).collect();
+    let logs: Vec<ListItem> = app.logs.iter().rev()
+        .filter(|entry| app.log_filter.is_none() || entry.tool == app.log_filter)
+        .filter(|entry| app.level_filter.is_none_or(|f| f.admits(entry.level)))
+        .take(area.height as usize)
+        .map(|entry| {
+            let l = &entry.text;
+            let color = match entry.level {
+                LogLevel::Error => Color::Red,
+                LogLevel::Warn => Color::Yellow,
+                LogLevel::Command => Color::LightBlue,
+                LogLevel::Info if l.contains("[done]") || l.contains("succeeded") || l.contains("status 0") => Color::Green,
+                LogLevel::Info if l.contains("[resolve]") || l.starts_with("==") => Color::Cyan,
+                LogLevel::Info => Color::Gray,
+            };
+            ListItem::new(Line::from(Span::styled(l, Style::default().fg(color))))
+        })
+        .collect();
 
+    let mut filters = Vec::new();
+    if let Some(tool) = &app.log_filter {
+        filters.push(tool.clone());
+    }
+    if let Some(level) = app.level_filter {
+        filters.push(level.label().to_string());
+    }
+    let title = if filters.is_empty() {
+        format!("  {}  ", title)
+    } else {
+        format!("  {} [{}] (l: level)  ", title, filters.join(", "))
+    };
     let logs_list = List::new(logs)
-        .block(Block::default().borders(Borders::ALL).title(format!("  {}  ", title)).border_style(Style::default().fg(border_color)));
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(border_color)));
     frame.render_widget(logs_list, area);
 }
 
-fn render_browsing(app: &App, frame: &mut Frame, area: Rect) {
+/// First line of an error, trimmed to fit the detail pane.
+fn short_reason(err: &str) -> String {
+    let line = err.lines().next().unwrap_or_default();
+    if line.chars().count() > 100 {
+        format!("{}…", line.chars().take(99).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn spinner_frame() -> &'static str {
+    const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    FRAMES[(millis / 100) as usize % FRAMES.len()]
+}
+
+pub(super) fn render_browsing(app: &App, frame: &mut Frame, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(25),
-            Constraint::Percentage(75),
+            Constraint::Percentage(app.catalog_split),
+            Constraint::Percentage(100 - app.catalog_split),
         ])
         .split(area);
 
-    let items: Vec<ListItem> = app.tools.iter().enumerate().map(|(idx, tool)| {
+    let rows = app.catalog_rows();
+    let items: Vec<ListItem> = rows.iter().enumerate().map(|(row_idx, row)| {
+        let is_cursor = row_idx == app.cursor;
+        let tool = match row {
+            CatalogRow::Tool(idx) => &app.tools[*idx],
+            CatalogRow::Category(category) => return category_header(app, category, is_cursor),
+        };
         let spec = app.catalog.software.get(&tool.key);
         let name = spec.map(|s| s.display_name.as_str()).unwrap_or(&tool.key);
-        
-        let is_cursor = idx == app.cursor;
-        let symbol = if tool.selected { "[x] " } else { "[ ] " };
-        let base_style = if tool.selected { Style::default().fg(Color::Green) } else { Style::default().fg(Color::White) };
+
+        let unavailable = app.unavailable(&tool.key);
+        let hidden = app.hidden.contains(&tool.key);
+        let symbol = if tool.selected { "  [x] " } else { "  [ ] " };
+        let base_style = if tool.selected {
+            Style::default().fg(Color::Green)
+        } else if unavailable.is_some() || hidden {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::White)
+        };
         let final_style = if is_cursor { base_style.bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD).fg(Color::Blue) } else { base_style };
 
+        let status = match (&tool.status, &tool.resolved) {
+            _ if spec.is_some_and(|s| app.distro.environment.incompatibility(s).is_some()) => {
+                Span::styled("⚠ not for this machine", Style::default().fg(Color::DarkGray))
+            }
+            _ if unavailable.is_some() => Span::styled("⚠ needs the network", Style::default().fg(Color::DarkGray)),
+            (ResolveStatus::Pending, _) => Span::styled(format!("{} resolving…", spinner_frame()), Style::default().fg(Color::Yellow)),
+            (ResolveStatus::Failed(_), _) => Span::styled("✗ failed", Style::default().fg(Color::Red)),
+            (_, Some(r)) => match release_age(r) {
+                Some((age, true)) => Span::styled(format!("✓ {} ⚠ {age} old", r.version), Style::default().fg(Color::Yellow)),
+                _ => Span::styled(format!("✓ {}", r.version), Style::default().fg(Color::LightCyan)),
+            },
+            (_, None) => Span::styled("unresolved", Style::default().fg(Color::DarkGray)),
+        };
+
+        let mut title = vec![Span::styled(symbol, final_style), Span::styled(name, final_style)];
+        if hidden {
+            title.push(Span::styled(" (hidden)", Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)));
+        }
+        if tool.last_error.is_some() {
+            title.push(Span::styled(" ●", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+        }
+
+        let mut status_line = vec![Span::raw("      "), status];
+        if let Some(at) = tool.cached_at.filter(|_| tool.resolved.is_some() && unavailable.is_none()) {
+            let age = crate::report::format_age(crate::manifest::now_secs().saturating_sub(at));
+            status_line.push(Span::styled(format!(" (cached {age} ago)"), Style::default().fg(Color::DarkGray)));
+        }
+        if let Some(size) = tool.resolved.as_ref().and_then(|r| r.size).filter(|_| unavailable.is_none()) {
+            status_line.push(Span::styled(format!("  {}", crate::units::format_size(size)), Style::default().fg(Color::DarkGray)));
+        }
+        if let Some(installed) = app.installed.installed_version(&tool.key) {
+            status_line.push(match &tool.resolved {
+                Some(r) if r.version != installed => {
+                    Span::styled(format!("  ↑ update from {installed}"), Style::default().fg(Color::Yellow))
+                }
+                _ => Span::styled(format!("  • installed {installed}"), Style::default().fg(Color::Green)),
+            });
+        }
+
         ListItem::new(vec![
-            Line::from(vec![Span::styled(symbol, final_style), Span::styled(name, final_style)]),
-            Line::from(vec![
-                Span::raw("    "),
-                Span::styled(
-                    tool.resolved.as_ref().map(|r| r.version.as_str()).unwrap_or("unresolved"),
-                    Style::default().fg(if tool.resolved.is_some() { Color::LightCyan } else { Color::DarkGray })
-                )
-            ])
+            Line::from(title),
+            Line::from(status_line)
         ])
     }).collect();
 
+    let selected_size: u64 = app.tools.iter().filter(|t| t.selected).filter_map(|t| t.resolved.as_ref()?.size).sum();
+    let (catalog_title, catalog_color) = if selected_size == 0 {
+        ("  Software Catalog  ".to_string(), Color::Cyan)
+    } else if crate::preflight::download_warning(selected_size, app.download_warn_mb).is_some() {
+        (format!("  Software Catalog (⚠ {} to download)  ", crate::units::format_size(selected_size)), Color::Yellow)
+    } else {
+        (format!("  Software Catalog ({} to download)  ", crate::units::format_size(selected_size)), Color::Cyan)
+    };
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("  Software Catalog  ").border_style(Style::default().fg(Color::Cyan)));
+        .block(Block::default().borders(Borders::ALL).title(catalog_title).border_style(Style::default().fg(catalog_color)));
     let mut state = ListState::default();
     state.select(Some(app.cursor));
     frame.render_stateful_widget(list, chunks[0], &mut state);
 
+    let current = app.current_tool();
+    let has_error = current.is_some_and(|t| t.last_error.is_some());
+    let unavailable = current.and_then(|t| app.unavailable(&t.key));
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(5),
+            Constraint::Length(5 + has_error as u16 + unavailable.is_some() as u16),
             Constraint::Length(5),
             Constraint::Length(4),
             Constraint::Min(0),
         ])
         .split(chunks[1]);
 
-    if let Some(tool) = app.tools.get(app.cursor) {
+    if let Some(CatalogRow::Category(category)) = rows.get(app.cursor) {
+        let (selected, total) = category_counts(app, category);
+        let collapsed = app.collapsed.contains(category);
+        let info_text = vec![
+            Line::from(vec![Span::styled(" > Category: ", Style::default().fg(Color::Cyan)), Span::styled(category.as_str(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))]),
+            Line::from(vec![Span::styled(format!(" # {selected} of {total} entries selected"), Style::default().fg(Color::Gray))]),
+            Line::from(vec![Span::styled(
+                format!("   [Space] {} all • [Enter] {}", if selected == total { "deselect" } else { "select" }, if collapsed { "expand" } else { "collapse" }),
+                Style::default().fg(Color::Rgb(80, 80, 80)).add_modifier(Modifier::ITALIC),
+            )]),
+        ];
+        let info_box = Paragraph::new(info_text)
+            .block(Block::default().borders(Borders::ALL).title("  Category  ").border_style(Style::default().fg(Color::Cyan)))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(info_box, right_chunks[0]);
+    }
+
+    if let Some(tool) = current {
         let spec = app.catalog.software.get(&tool.key);
         let name = spec.map(|s| s.display_name.as_str()).unwrap_or(&tool.key);
         let desc = spec.and_then(|s| s.description.as_deref()).unwrap_or("No description available.");
@@ -169,6 +865,18 @@ fn render_browsing(app: &App, frame: &mut Frame, area: Rect) {
             Line::from(vec![Span::styled(" > Download: ", Style::default().fg(Color::Cyan)), Span::styled(name, Style::default().fg(Color::White).add_modifier(Modifier::BOLD))]),
             Line::from(vec![Span::styled(" # Description: ", Style::default().fg(Color::Cyan)), Span::styled(desc, Style::default().fg(Color::Gray))]),
         ];
+        if let Some(err) = &tool.last_error {
+            info_text.insert(1, Line::from(vec![
+                Span::styled(" ! Last error: ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Span::styled(short_reason(err), Style::default().fg(Color::Red)),
+            ]));
+        }
+        if let Some(reason) = &unavailable {
+            info_text.insert(1, Line::from(vec![
+                Span::styled(" ⚠ Unavailable: ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled(reason.clone(), Style::default().fg(Color::Yellow)),
+            ]));
+        }
 
         if let Some(spec) = spec {
             let readable_source = match spec.source.kind_key() {
@@ -179,6 +887,7 @@ fn render_browsing(app: &App, frame: &mut Frame, area: Rect) {
                 "package_only" => "Distro Package Manager",
                 "static_url" => "Universal Static URL",
                 "generic_scraper" => "Web Scraper Resolution",
+                "none" => "Setup Steps Only",
                 _ => spec.source.kind_key(),
             };
             info_text.push(Line::from(vec![Span::styled(" * Source: ", Style::default().fg(Color::Cyan)), Span::styled(readable_source, Style::default().fg(Color::Yellow))]));
@@ -195,52 +904,151 @@ fn render_browsing(app: &App, frame: &mut Frame, area: Rect) {
 
         let mut preview_text = vec![Line::from(Span::styled(" The following actions will be performed:", Style::default().fg(Color::DarkGray)))];
         if let Some(spec) = spec {
-            for step in &spec.setup_steps {
-                match step {
-                    crate::catalog::SetupStep::Package { packages } => {
-                        if let Some(cmd) = app.distro.pkg_manager.install_command(packages) {
-                            preview_text.push(Line::from(vec![Span::styled(format!("  $ {}", cmd), Style::default().fg(Color::Green))]));
-                        }
-                    }
-                    crate::catalog::SetupStep::Note { value } => {
-                        preview_text.push(Line::from(vec![Span::styled(format!("  # Note: {}", value), Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC))]));
-                    }
-                    crate::catalog::SetupStep::PathHint { value } => {
-                        preview_text.push(Line::from(vec![Span::styled(format!("  + Path: {}", value), Style::default().fg(Color::Blue))]));
-                    }
-                    crate::catalog::SetupStep::Shell { command } => {
-                        preview_text.push(Line::from(vec![Span::styled(format!("  $ Shell: {}", command), Style::default().fg(Color::Magenta))]));
-                    }
-                }
-            }
+            preview_text.extend(step_lines(app, spec));
         }
         let preview_box = Paragraph::new(preview_text)
             .block(Block::default().borders(Borders::ALL).title("  Action Preview  ").border_style(Style::default().fg(Color::DarkGray)));
         frame.render_widget(preview_box, right_chunks[1]);
+    }
 
-        render_logs(app, frame, right_chunks[3], "Live Activity", Color::Cyan);
+    render_logs(app, frame, right_chunks[3], "Live Activity", Color::Cyan);
 
-        let guide_text = vec![
-            Line::from(vec![Span::styled(" ? Quick Guide", Style::default().fg(Color::White).add_modifier(Modifier::BOLD))]),
-            Line::from(vec![
-                Span::styled("  [Space] Select ", Style::default().fg(Color::Yellow)), Span::raw("| "),
-                Span::styled("[r] Resolve ", Style::default().fg(Color::Yellow)), Span::raw("| "),
-                Span::styled("[d] Dry-run ", Style::default().fg(Color::Yellow)), Span::raw("| "),
-                Span::styled("[i] Install ", Style::default().fg(Color::Yellow)), Span::raw("| "),
-                Span::styled("[c] Clear Logs", Style::default().fg(Color::Yellow)),
-            ]),
-        ];
-        let guide_box = Paragraph::new(guide_text)
-            .block(Block::default().borders(Borders::ALL).title("  Usage  ").border_style(Style::default().fg(Color::DarkGray)));
-        frame.render_widget(guide_box, right_chunks[2]);
+    let guide_text = vec![
+        Line::from(vec![Span::styled(" ? Quick Guide", Style::default().fg(Color::White).add_modifier(Modifier::BOLD))]),
+        Line::from(vec![
+            Span::styled("  [Space] Select ", Style::default().fg(Color::Yellow)), Span::raw("| "),
+            Span::styled("[r] Resolve ", Style::default().fg(Color::Yellow)), Span::raw("| "),
+            Span::styled("[d] Dry-run ", Style::default().fg(Color::Yellow)), Span::raw("| "),
+            Span::styled("[i] Install ", Style::default().fg(Color::Yellow)), Span::raw("| "),
+            Span::styled("[c] Clear Logs", Style::default().fg(Color::Yellow)),
+        ]),
+    ];
+    let guide_box = Paragraph::new(guide_text)
+        .block(Block::default().borders(Borders::ALL).title("  Usage  ").border_style(Style::default().fg(Color::DarkGray)));
+    frame.render_widget(guide_box, right_chunks[2]);
+}
+
+/// Preview lines for an entry's setup steps, with the exact commands where
+/// they are known up front.
+fn step_lines(app: &App, spec: &crate::catalog::SoftwareSpec) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    if !spec.env.is_empty() {
+        let vars: Vec<String> = spec.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        lines.push(Line::from(vec![Span::styled(format!("  = Env: {}", vars.join(" ")), Style::default().fg(Color::Cyan))]));
     }
+    for step in &spec.setup_steps {
+        let lock = if step.requires_root() { "🔒 " } else { "" };
+        match step {
+            crate::catalog::SetupStep::Package { .. } => {
+                let packages = step.packages_for(&app.distro.id, app.distro.pkg_manager.name());
+                if packages.is_empty() {
+                    continue;
+                }
+                if let Some(script) = app.distro.pkg_manager.install(packages) {
+                    lines.push(Line::from(vec![Span::styled(format!("  {lock}$ {}", crate::command::display_script(&script)), Style::default().fg(Color::Green))]));
+                }
+            }
+            crate::catalog::SetupStep::Note { value } => {
+                lines.push(Line::from(vec![Span::styled(format!("  # Note: {}", value), Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC))]));
+            }
+            crate::catalog::SetupStep::PathHint { value } => {
+                lines.push(Line::from(vec![Span::styled(format!("  + Path: {}", value), Style::default().fg(Color::Blue))]));
+            }
+            crate::catalog::SetupStep::Shell { command, check_command, .. } => {
+                lines.push(Line::from(vec![Span::styled(format!("  {lock}$ Shell: {}", command), Style::default().fg(Color::Magenta))]));
+                if let Some(check) = check_command {
+                    lines.push(Line::from(vec![Span::styled(format!("      skipped if: {check}"), Style::default().fg(Color::DarkGray))]));
+                }
+            }
+            crate::catalog::SetupStep::Secret { name, env, .. } => {
+                lines.push(Line::from(vec![Span::styled(format!("  * Secret: {} -> ${}", name, env), Style::default().fg(Color::Yellow))]));
+            }
+            crate::catalog::SetupStep::EnsureDirs { paths } => {
+                lines.push(Line::from(vec![Span::styled(format!("  + Dirs: {}", paths.join(", ")), Style::default().fg(Color::Blue))]));
+            }
+            crate::catalog::SetupStep::CopyFile { from, to, mode, .. } => {
+                let mode = mode.as_deref().map(|m| format!(" ({m})")).unwrap_or_default();
+                lines.push(Line::from(vec![Span::styled(format!("  {lock}> Copy: {} -> {}{}", from, to, mode), Style::default().fg(Color::Blue))]));
+            }
+            crate::catalog::SetupStep::Template { source, dest, .. } => {
+                lines.push(Line::from(vec![Span::styled(format!("  ~ Template: {} -> {}", source, dest), Style::default().fg(Color::Blue))]));
+            }
+            crate::catalog::SetupStep::GitConfig { key, value, scope } => {
+                lines.push(Line::from(vec![Span::styled(format!("  {lock}= Git: {} {} {}", scope.flag(), key, value), Style::default().fg(Color::Cyan))]));
+            }
+            crate::catalog::SetupStep::Swap { method, size_mb } => {
+                let size = size_mb.map(|mb| crate::units::format_size(mb * 1024 * 1024)).unwrap_or_else(|| "sized from RAM".to_string());
+                lines.push(Line::from(vec![Span::styled(format!("  {lock}= Swap: {} ({})", method.label(), size), Style::default().fg(Color::Cyan))]));
+            }
+            crate::catalog::SetupStep::Cargo { .. } | crate::catalog::SetupStep::Npm { .. } | crate::catalog::SetupStep::Pipx { .. } => {
+                let Some((toolchain, packages, global)) = crate::toolchain::step_toolchain(step) else { continue };
+                let scope = if global { " (global)" } else { "" };
+                let mut spans = vec![Span::styled(format!("  $ {}{scope}: {}", toolchain.program(), packages.join(" ")), Style::default().fg(Color::Green))];
+                if toolchain.locate().is_none() {
+                    let hint = match crate::toolchain::provider(&app.catalog, toolchain) {
+                        Some(key) => format!(" ⚠ {} missing, install '{key}' first", toolchain.program()),
+                        None => format!(" ⚠ {} missing", toolchain.program()),
+                    };
+                    spans.push(Span::styled(hint, Style::default().fg(Color::Yellow)));
+                }
+                lines.push(Line::from(spans));
+            }
+            crate::catalog::SetupStep::VersionManager { manager, versions, default } => {
+                let default = default.as_deref().map(|d| format!(", default {d}")).unwrap_or_default();
+                let bootstrap = if manager.installed() { "" } else { " (installs it first)" };
+                lines.push(Line::from(vec![Span::styled(
+                    format!("  $ {}: {}{default}{bootstrap}", manager.label(), versions.join(", ")),
+                    Style::default().fg(Color::Green),
+                )]));
+            }
+            crate::catalog::SetupStep::Sysctl { values } => {
+                let extra = if values.is_empty() { String::new() } else { format!(" + {}", values.keys().cloned().collect::<Vec<_>>().join(", ")) };
+                lines.push(Line::from(vec![Span::styled(format!("  {lock}= Sysctl: defaults for this RAM size{}", extra), Style::default().fg(Color::Cyan))]));
+            }
+        }
+    }
+    lines
 }
 
-fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
+fn category_counts(app: &App, category: &str) -> (usize, usize) {
+    let members = app.tools.iter().filter(|t| app.is_listed(&t.key) && crate::app::category_of(&app.catalog, &t.key) == category);
+    members.fold((0, 0), |(selected, total), t| (selected + t.selected as usize, total + 1))
+}
+
+/// Header row of a category: fold marker, name and selected/total counts.
+fn category_header<'a>(app: &App, category: &'a str, is_cursor: bool) -> ListItem<'a> {
+    let (selected, total) = category_counts(app, category);
+    let marker = if app.collapsed.contains(category) { "▸ " } else { "▾ " };
+    let mut style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    if is_cursor {
+        style = style.bg(Color::Rgb(40, 40, 40)).fg(Color::Blue);
+    }
+    ListItem::new(Line::from(vec![
+        Span::styled(format!("{marker}{category}"), style),
+        Span::styled(format!(" ({selected}/{total})"), Style::default().fg(Color::DarkGray)),
+    ]))
+}
+
+pub(super) fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
+    let banner_height = if app.progress.banner.is_some() { 3 } else { 0 };
+    let outer = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(banner_height), Constraint::Min(0)])
+        .split(area);
+
+    if let Some(banner) = &app.progress.banner {
+        let banner_para = Paragraph::new(Line::from(Span::styled(
+            format!(" ⚠ {banner}"),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )))
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+        frame.render_widget(banner_para, outer[0]);
+    }
+
     let top_bottom = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(6), Constraint::Min(0)])
-        .split(area);
+        .split(outer[1]);
 
     let bars_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -252,9 +1060,10 @@ fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
     } else { 
         0.0 
     };
-    let total_ratio = total_ratio.min(1.0).max(0.0);
+    let total_ratio = total_ratio.clamp(0.0, 1.0);
     let eta_label = app.progress.eta.as_ref().map(|e| format!(" | ETA: {}", e)).unwrap_or_default();
-    let total_label = format!("Total: {:.1}% ({} / {}){}", total_ratio * 100.0, app.progress.done, app.progress.total, eta_label);
+    let skipped_label = if app.progress.skipped > 0 { format!(" | {} skipped", app.progress.skipped) } else { String::new() };
+    let total_label = format!("Total: {:.1}% ({} / {}){}{}", total_ratio * 100.0, app.progress.done, app.progress.total, skipped_label, eta_label);
     let total_gauge = Gauge::default()
         .block(Block::default().borders(Borders::ALL).title("  Overall Progress  ").border_style(Style::default().fg(Color::Cyan)))
         .gauge_style(Style::default().fg(Color::Cyan).bg(Color::Black).add_modifier(Modifier::BOLD))
@@ -263,10 +1072,12 @@ fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(total_gauge, bars_layout[0]);
 
     let is_done = app.state == crate::app::ViewState::Completed;
-    let sub_ratio = if is_done { 1.0 } else { app.progress.sub_ratio.min(1.0).max(0.0) };
+    let sub_ratio = if is_done { 1.0 } else { app.progress.sub_ratio.clamp(0.0, 1.0) };
     let sub_label = if is_done { "100.0%".to_string() } else { format!("{:.1}%", sub_ratio * 100.0) };
     let sub_title = if is_done { 
         "  Done  ".to_string() 
+    } else if let Some(speed) = &app.progress.speed {
+        format!("  {} - {} | {}  ", app.progress.operation, app.progress.current, speed)
     } else { 
         format!("  {} - {}  ", app.progress.operation, app.progress.current) 
     };
@@ -283,17 +1094,33 @@ fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
         .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
         .split(top_bottom[1]);
 
-    render_logs(app, frame, bottom_layout[0], "Terminal Output", Color::Magenta);
+    let log_area = if is_done && !app.records.is_empty() {
+        let rows = app.records.iter().filter(|r| r.kind == crate::report::RecordKind::Install).count() as u16;
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length((rows + 5 + app.run_resources.is_some() as u16).min(bottom_layout[0].height / 2)), Constraint::Min(0)])
+            .split(bottom_layout[0]);
+        render_run_summary(app, frame, split[0]);
+        split[1]
+    } else {
+        bottom_layout[0]
+    };
+    render_logs(app, frame, log_area, "Terminal Output", Color::Magenta);
 
     let selected_items: Vec<ListItem> = app.tools.iter().filter(|t| t.selected).map(|tool| {
         let is_done = app.progress.done_items.contains(&tool.key);
-        let symbol = if is_done { "[*] " } else { "[ ] " };
-        let color = if is_done { Color::Green } else { Color::DarkGray };
+        let is_skipped = app.progress.skipped_items.contains(&tool.key);
+        let symbol = if is_skipped { "[-] " } else if is_done { "[*] " } else { "[ ] " };
+        let color = if is_skipped { Color::Yellow } else if is_done { Color::Green } else { Color::DarkGray };
         let spec = app.catalog.software.get(&tool.key);
         let name = spec.map(|s| s.display_name.as_str()).unwrap_or(&tool.key);
+        let mut style = Style::default().fg(color);
+        if app.log_filter.as_deref() == Some(tool.key.as_str()) {
+            style = style.bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD);
+        }
         ListItem::new(Line::from(vec![
-            Span::styled(symbol, Style::default().fg(color)),
-            Span::styled(name, Style::default().fg(color)),
+            Span::styled(symbol, style),
+            Span::styled(name, style),
         ]))
     }).collect();
 
@@ -302,25 +1129,93 @@ fn render_progress(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(items_list, bottom_layout[1]);
 }
 
+/// Per-tool results of the finished run, slowest first, with totals.
+fn render_run_summary(app: &App, frame: &mut Frame, area: Rect) {
+    use crate::report::{format_bytes, format_duration, RecordStatus};
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::from(Span::styled(
+        format!("  {:<26}{:<18}{:>10}{:>12}  {}", "Tool", "Version", "Time", "Size", "Result"),
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    ))];
+    for record in crate::report::installs_by_duration(&app.records) {
+        let (result, color) = match &record.status {
+            RecordStatus::Succeeded => ("ok".to_string(), Color::Green),
+            RecordStatus::Failed(reason) => (format!("failed: {reason}"), Color::Red),
+            RecordStatus::Skipped => ("skipped".to_string(), Color::Yellow),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<26}", record.name), Style::default().fg(Color::White)),
+            Span::styled(format!("{:<18}", record.version), dim),
+            Span::raw(format!("{:>10}{:>12}  ", format_duration(record.duration), format_bytes(record.bytes))),
+            Span::styled(result, Style::default().fg(color)),
+        ]));
+    }
+    let totals = crate::report::totals(&app.records);
+    lines.push(Line::from(Span::styled(
+        format!(
+            "  {} ok, {} failed, {} skipped • {} total • {} downloaded",
+            totals.succeeded,
+            totals.failed,
+            totals.skipped,
+            format_duration(totals.duration),
+            crate::units::format_size(totals.bytes)
+        ),
+        Style::default().fg(Color::Yellow),
+    )));
+    if let Some(resources) = &app.run_resources {
+        lines.push(Line::from(Span::styled(format!("  {}", crate::report::resources_summary(resources)), dim)));
+    }
+
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("  Run Summary  ").border_style(Style::default().fg(Color::Cyan)));
+    frame.render_widget(summary, area);
+}
+
 fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
-    let help_lines = match app.state {
+    let compact = is_compact(frame.area());
+    let mut help_lines = match app.state {
         ViewState::Browsing => vec![
             Line::from(vec![
                 Span::styled("Keys: ", Style::default().fg(Color::Cyan)),
-                Span::raw("Arrows: Move • Space: Select/Deselect • A/N All/None • R: Resolve • I: Install • U: Restore • D: Dry-run • C: Clear • Q: Quit"),
+                Span::raw("Arrows: Move • Enter/←/→: Fold category • Space: Select/Deselect • Ctrl+P: Palette • A/N All/None • H: Hide entry (Shift+H: show hidden) • R: Resolve (Shift+R: ignore cache) • T: Retry failed (Shift+T: test highlighted) • I: Install • U: Restore • Z: Disk Usage • S: Settings • X: Quarantine • W: Maintenance • ?/Enter: Details • F: Focus log • L: Log level • F5: Reload • E: Edit entry • +: New entry • </>: Resize • M: Pkg manager • D: Dry-run • O: Offline • C: Clear • Q: Quit"),
             ]),
             Line::from(vec![
                 Span::styled("[Resolve] ", Style::default().fg(Color::Yellow)), Span::raw("Fetch latest metadata from network sources   "),
                 Span::styled("[Dry-run] ", Style::default().fg(Color::Yellow)), Span::raw("Preview actions without making system changes"),
             ]),
         ],
-        ViewState::Installing => vec![Line::from("installation in progress • please wait...")],
-        ViewState::Completed => vec![Line::from("Done! Press [Enter] or [Esc] to return to catalog • [q] to exit")],
+        ViewState::Installing => vec![Line::from("installation in progress • please wait... • [s] skip current tool • [c] cancel all • [Tab] focus output on one tool")],
+        ViewState::Completed => {
+            let mut lines = vec![Line::from("Done! Press [Enter] or [Esc] to return to catalog • [Tab] focus output on one tool • [q] to exit")];
+            if let Some(path) = &app.last_report {
+                lines.push(Line::from(vec![
+                    Span::styled("Report: ", Style::default().fg(Color::Cyan)),
+                    Span::raw(path.display().to_string()),
+                ]));
+            }
+            lines
+        }
         ViewState::FilePicker { .. } => vec![Line::from("Arrows to navigate • [Enter] to select folder/json • [Esc] to cancel")],
+        ViewState::RestorePicker { .. } => vec![Line::from("Arrows to navigate • [Space] toggle part • [a/n] all/none • [Enter] restore • [Esc] back to the file picker")],
         ViewState::Restoring => vec![Line::from("restoring user files • please wait...")],
+        ViewState::Onboarding { .. } => vec![Line::from("[Enter] next • [Esc] back (quit on the first step) • type to edit paths")],
+        ViewState::DiskUsage { .. } => vec![Line::from("Arrows to navigate • [s] toggle sort (size/name) • [Esc] to return")],
+        ViewState::ConfirmInstall { .. } => vec![Line::from("[Y/Enter] install and change the system • [N/Esc] back to catalog")],
+        ViewState::LoadError(_) => vec![Line::from("[e] edit the file • [d] load defaults • [r] load it again • [q] quit")],
+        ViewState::Settings { editing: Some(_), .. } => vec![Line::from("type the new value • [Enter] save • [Esc] cancel")],
+        ViewState::Settings { .. } => vec![Line::from("Arrows to navigate • [Enter/Space] toggle or edit • [Esc] to return")],
+        ViewState::NewEntry(ref form) if form.editing.is_some() => vec![Line::from("type the value • [Enter] keep • [Esc] cancel")],
+        ViewState::NewEntry(_) => vec![Line::from("Arrows to navigate • [Enter] edit • ←/→ source kind • [Ctrl+T] test • [Ctrl+S] test and save • [Esc] discard")],
+        ViewState::Quarantine { .. } => vec![Line::from("Arrows to navigate • [d/Del] purge the highlighted download • [D] purge all • [Esc] to return")],
+        ViewState::Maintenance { .. } => vec![Line::from("Arrows to navigate • [t/Enter] test-resolve the entry • [e] edit it in the catalog • [Esc] to return")],
     };
 
-    let mut help_para = Paragraph::new(help_lines).alignment(ratatui::layout::Alignment::Center);
+    if compact {
+        // The key list needs every row it can get once it wraps.
+        help_lines.truncate(1);
+    }
+    let mut help_para = Paragraph::new(help_lines).alignment(ratatui::layout::Alignment::Center).wrap(Wrap { trim: true });
 
     if app.is_resolving {
         help_para = help_para.block(Block::default().title(format!(" [Resolving: {}/{}] ", app.progress.done, app.progress.total)).title_style(Style::default().fg(Color::Cyan)));
@@ -329,7 +1224,7 @@ fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(help_para, area);
 }
 
-fn render_file_picker(_app: &App, frame: &mut Frame, area: Rect, current_dir: &std::path::Path, entries: &[std::path::PathBuf], cursor: usize) {
+pub(super) fn render_file_picker(_app: &App, frame: &mut Frame, area: Rect, current_dir: &std::path::Path, entries: &[std::path::PathBuf], cursor: usize) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0)])
@@ -360,3 +1255,460 @@ fn render_file_picker(_app: &App, frame: &mut Frame, area: Rect, current_dir: &s
     state.select(Some(cursor));
     frame.render_stateful_widget(list, chunks[0], &mut state);
 }
+
+pub(super) fn render_restore_picker(frame: &mut Frame, area: Rect, json_file: &std::path::Path, parts: &[RestorePart], cursor: usize) {
+    let items: Vec<ListItem> = parts.iter().enumerate().map(|(idx, part)| {
+        let style = if idx == cursor {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let check = if part.selected { "[x] " } else { "[ ] " };
+        ListItem::new(Line::from(vec![Span::styled(check, Style::default().fg(Color::Green)), Span::styled(part.label.clone(), style)]))
+    }).collect();
+    let folder = json_file.parent().unwrap_or(json_file);
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!("  Restore from {}  ", folder.display())).border_style(Style::default().fg(Color::Cyan)));
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+pub(super) fn render_disk_usage(frame: &mut Frame, area: Rect, entries: &[crate::usage::UsageEntry], cursor: usize, by_size: bool) {
+    let total: u64 = entries.iter().map(|e| e.bytes).sum();
+    let items: Vec<ListItem> = entries.iter().enumerate().map(|(idx, entry)| {
+        let kind_color = match entry.kind {
+            crate::usage::UsageKind::Install => Color::Green,
+            crate::usage::UsageKind::Download => Color::Yellow,
+        };
+        let style = if idx == cursor {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{:>10}  ", crate::units::format_size(entry.bytes)), style),
+            Span::styled(format!("{:<9}", entry.kind.to_string()), Style::default().fg(kind_color)),
+            Span::styled(format!("{:<22}", entry.label), style),
+            Span::styled(entry.path.display().to_string(), Style::default().fg(Color::DarkGray)),
+        ]))
+    }).collect();
+
+    let title = format!(
+        "  Disk Usage: {} total, sorted by {}  ",
+        crate::units::format_size(total),
+        if by_size { "size" } else { "name" }
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(Color::Cyan)));
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+pub(super) fn render_settings(app: &App, frame: &mut Frame, area: Rect, cursor: usize, editing: Option<&str>) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let config = crate::config::current();
+    let mut lines = vec![Line::from("")];
+    for (idx, setting) in Setting::ALL.into_iter().enumerate() {
+        let selected = idx == cursor;
+        let label = if selected {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        let value = match editing {
+            Some(text) if selected => Span::styled(format!("{text}▏"), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            _ => Span::styled(setting.value(&config), Style::default().fg(Color::White)),
+        };
+        lines.push(Line::from(vec![Span::styled(format!(" {:<22}", setting.label()), label), Span::raw(" "), value]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(format!(" {}", Setting::ALL[cursor].help()), Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC))));
+
+    let path = crate::config::config_path().map(|p| p.display().to_string()).unwrap_or_default();
+    let settings = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!("  Settings: {path}  ")).border_style(Style::default().fg(Color::Cyan)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(settings, chunks[0]);
+
+    render_logs(app, frame, chunks[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_new_entry(app: &App, frame: &mut Frame, area: Rect, form: &EntryForm) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let rows = form.rows();
+    let mut lines = vec![Line::from("")];
+    for (idx, row) in rows.iter().enumerate() {
+        let selected = idx == form.cursor;
+        let label_style = if selected {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        let label = match row {
+            Row::Field(field) => field.label().to_string(),
+            Row::Kind => "Source kind".to_string(),
+            Row::Step(index) => format!("Shell step {}", index + 1),
+            Row::AddStep => "+ Add shell step".to_string(),
+        };
+        let value = match &form.editing {
+            Some(text) if selected => Span::styled(format!("{text}▏"), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            _ if *row == Row::Kind => Span::styled(format!("◂ {} ▸", form.kind()), Style::default().fg(Color::White)),
+            _ => Span::styled(form.text(*row), Style::default().fg(Color::White)),
+        };
+        lines.push(Line::from(vec![Span::styled(format!(" {label:<22}"), label_style), Span::raw(" "), value]));
+    }
+    lines.push(Line::from(""));
+    let help = match rows.get(form.cursor) {
+        Some(Row::Field(field)) => field.help(),
+        Some(Row::Kind) => "←/→ to change. JSON APIs and source builds are added with [e] in the catalog.",
+        Some(Row::Step(_)) => "Runs as your user after the install. Empty it to remove the step.",
+        Some(Row::AddStep) | None => "Shell command run after the install, e.g. a symlink or a config tweak.",
+    };
+    lines.push(Line::from(Span::styled(format!(" {help}"), Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC))));
+    if let Some(error) = &form.error {
+        lines.push(Line::from(Span::styled(format!(" ✗ {error}"), Style::default().fg(Color::Red))));
+    }
+
+    let path = app.catalog_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+    let entry = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!("  New Entry: {path}  ")).border_style(Style::default().fg(Color::Cyan)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(entry, chunks[0]);
+
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Min(0)])
+        .split(chunks[1]);
+    let label = Style::default().fg(Color::Cyan);
+    let test = match &form.test {
+        _ if form.testing => vec![Line::from(Span::styled(format!(" {} resolving…", spinner_frame()), Style::default().fg(Color::Yellow)))],
+        None => vec![Line::from(Span::styled(" [Ctrl+T] resolves the entry without saving it", Style::default().fg(Color::DarkGray)))],
+        Some(Ok(asset)) => vec![
+            Line::from(vec![Span::styled(" Version: ", label), Span::styled(asset.version.clone(), Style::default().fg(Color::Green))]),
+            Line::from(vec![Span::styled(" URL: ", label), Span::raw(asset.url.clone())]),
+            Line::from(vec![Span::styled(" File: ", label), Span::raw(asset.file_name.clone())]),
+        ],
+        Some(Err(e)) => vec![
+            Line::from(Span::styled(format!(" ✗ {e}"), Style::default().fg(Color::Red))),
+            Line::from(Span::styled(" [Ctrl+S] again saves it anyway", Style::default().fg(Color::DarkGray))),
+        ],
+    };
+    let test = Paragraph::new(test)
+        .block(Block::default().borders(Borders::ALL).title("  Test Resolution  ").border_style(Style::default().fg(Color::DarkGray)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(test, right[0]);
+    render_logs(app, frame, right[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_quarantine(app: &App, frame: &mut Frame, area: Rect, entries: &[QuarantinedDownload], cursor: usize) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let now = crate::manifest::now_secs();
+    let items: Vec<ListItem> = entries.iter().enumerate().map(|(idx, entry)| {
+        let style = if idx == cursor {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{:>10}  ", crate::units::format_size(entry.bytes)), style),
+            Span::styled(format!("{:<18}", crate::report::format_age(now.saturating_sub(entry.reason.quarantined_at))), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<16}", entry.reason.key), style),
+            Span::styled(entry.reason.reason.clone(), Style::default().fg(Color::Yellow)),
+        ]))
+    }).collect();
+    let total: u64 = entries.iter().map(|e| e.bytes).sum();
+    let title = format!("  Quarantine: {} download(s), {}  ", entries.len(), crate::units::format_size(total));
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(Color::Cyan)));
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let label = Style::default().fg(Color::Cyan);
+    let lines = match entries.get(cursor) {
+        Some(entry) => {
+            let reason = &entry.reason;
+            let mut lines = vec![
+                Line::from(vec![Span::styled(" Entry: ", label), Span::raw(format!("{} {}", reason.key, reason.version))]),
+                Line::from(vec![Span::styled(" When: ", label), Span::raw(crate::report::format_timestamp(reason.quarantined_at))]),
+                Line::from(vec![Span::styled(" Reason: ", label), Span::styled(reason.reason.clone(), Style::default().fg(Color::Yellow))]),
+                Line::from(vec![Span::styled(" URL: ", label), Span::raw(reason.url.clone())]),
+                Line::from(vec![Span::styled(" File: ", label), Span::raw(entry.file.display().to_string())]),
+                Line::from(vec![Span::styled(" SHA-256: ", label), Span::raw(reason.sha256.clone())]),
+            ];
+            if let Some(expected) = &reason.expected_sha256 {
+                lines.push(Line::from(vec![Span::styled(" Expected: ", label), Span::raw(expected.clone())]));
+            }
+            lines
+        }
+        None => vec![Line::from(Span::styled(" Nothing quarantined. Downloads that fail verification are kept here.", Style::default().fg(Color::DarkGray)))],
+    };
+    let details = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("  Details  ").border_style(Style::default().fg(Color::Cyan)))
+        .wrap(Wrap { trim: false });
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+    frame.render_widget(details, rows[0]);
+    render_logs(app, frame, rows[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_maintenance(app: &App, frame: &mut Frame, area: Rect, rows: &[MaintenanceRow], cursor: usize) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let now = crate::manifest::now_secs();
+    let threshold = crate::state::attention_after_sessions();
+    let ago = |at: Option<u64>| at.map_or("never".to_string(), |at| format!("{} ago", crate::report::format_age(now.saturating_sub(at))));
+    let items: Vec<ListItem> = rows.iter().enumerate().map(|(idx, row)| {
+        let health = &row.health;
+        let style = if idx == cursor {
+            Style::default().fg(Color::Blue).bg(Color::Rgb(40, 40, 40)).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let (marker, status) = if health.needs_attention(threshold) {
+            (Span::styled("! ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)), Span::styled(format!("failing {} sessions", health.failing_sessions), Style::default().fg(Color::Red)))
+        } else if health.failing_sessions > 0 {
+            (Span::raw("  "), Span::styled(format!("failing {} session(s)", health.failing_sessions), Style::default().fg(Color::Yellow)))
+        } else {
+            (Span::raw("  "), Span::styled(format!("resolved {}", ago(health.last_resolved)), Style::default().fg(Color::DarkGray)))
+        };
+        ListItem::new(Line::from(vec![marker, Span::styled(format!("{:<24}", row.key), style), status]))
+    }).collect();
+    let attention = rows.iter().filter(|r| r.health.needs_attention(threshold)).count();
+    let title = format!("  Maintenance: {attention} need attention (failing {threshold}+ sessions)  ");
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(Color::Cyan)));
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    frame.render_stateful_widget(list, chunks[0], &mut state);
+
+    let label = Style::default().fg(Color::Cyan);
+    let lines = match rows.get(cursor) {
+        Some(row) => {
+            let health = &row.health;
+            let mut lines = vec![
+                Line::from(vec![Span::styled(" Entry: ", label), Span::raw(format!("{} ({})", row.name, row.key))]),
+                Line::from(vec![Span::styled(" Last resolved: ", label), Span::raw(ago(health.last_resolved))]),
+                Line::from(vec![Span::styled(" Last installed: ", label), Span::raw(ago(health.last_installed))]),
+            ];
+            if let Some(since) = health.failing_since {
+                lines.push(Line::from(vec![
+                    Span::styled(" Failing: ", label),
+                    Span::raw(format!("{} session(s) in a row, since {}", health.failing_sessions, crate::report::format_timestamp(since))),
+                ]));
+            }
+            if let Some(error) = &health.last_error {
+                lines.push(Line::from(vec![Span::styled(" Last error: ", label), Span::styled(error.clone(), Style::default().fg(Color::Yellow))]));
+            }
+            lines
+        }
+        None => vec![Line::from(Span::styled(" The catalog is empty.", Style::default().fg(Color::DarkGray)))],
+    };
+    let details = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("  Details  ").border_style(Style::default().fg(Color::Cyan)))
+        .wrap(Wrap { trim: false });
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+    frame.render_widget(details, right[0]);
+    render_logs(app, frame, right[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_load_error(app: &App, frame: &mut Frame, area: Rect, error: &LoadError) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let label = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Line::from(Span::styled(format!(" The {} could not be loaded.", error.file.label()), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(vec![Span::styled(" File: ", label), Span::raw(error.path.display().to_string())]),
+    ];
+    if let Some((line, column)) = error.position {
+        lines.push(Line::from(vec![Span::styled(" Where: ", label), Span::raw(format!("line {line}, column {column}"))]));
+    }
+    lines.push(Line::from(vec![Span::styled(" Error: ", label), Span::styled(error.message.clone(), Style::default().fg(Color::Red))]));
+    if !error.excerpt.is_empty() {
+        lines.push(Line::from(""));
+        let width = error.excerpt.last().map(|(n, _)| n.to_string().len()).unwrap_or(1);
+        for (number, text) in &error.excerpt {
+            let offending = error.position.is_some_and(|(line, _)| line == *number);
+            let style = if offending { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::Gray) };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {}{number:>width$} │ ", if offending { "▸" } else { " " }), dim),
+                Span::styled(text.clone(), style),
+            ]));
+            if let Some((_, column)) = error.position.filter(|_| offending) {
+                lines.push(Line::from(Span::styled(format!("  {} │ {}^", " ".repeat(width), " ".repeat(column - 1)), Style::default().fg(Color::Red))));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    let defaults = match error.file {
+        crate::app::startup::BrokenFile::Config => "move it aside and use the defaults",
+        crate::app::startup::BrokenFile::Catalog => "use the bundled catalog for now",
+    };
+    lines.push(Line::from(Span::styled(
+        format!(" [e] edit in $EDITOR  [d] {defaults}  [r] retry  [q] quit"),
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    )));
+
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!("  Broken {}  ", error.file.label())).border_style(Style::default().fg(Color::Red)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(summary, chunks[0]);
+
+    render_logs(app, frame, chunks[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_confirm_install(app: &App, frame: &mut Frame, area: Rect, items: &[PlannedInstall], changes: &[String], on_battery: bool) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let label = Style::default().fg(Color::Cyan);
+    let dim = Style::default().fg(Color::DarkGray);
+    let total: u64 = items.iter().filter_map(|i| i.size).sum();
+    let pending = items.iter().any(|i| i.sizing);
+
+    let mut lines = vec![
+        Line::from(Span::styled(" Dry-run is off: the following will be installed on this system.", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    for item in items {
+        let size = if item.sizing {
+            Span::styled(format!("{} checking", spinner_frame()), dim)
+        } else if let Some(problem) = &item.problem {
+            Span::styled(format!("✗ {problem}"), Style::default().fg(Color::Red))
+        } else {
+            Span::styled(item.size.map(crate::units::format_size).unwrap_or_else(|| "-".to_string()), dim)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<28}", item.name), Style::default().fg(Color::White)),
+            Span::styled(format!("{:<22}", item.version.as_deref().unwrap_or("resolved at install")), Style::default().fg(Color::Green)),
+            size,
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(" Total download: ", label),
+        Span::raw(crate::units::format_size(total)),
+        Span::styled(if pending { " (still measuring)" } else { "" }, dim),
+    ]));
+    let broken = items.iter().filter(|i| i.problem.is_some()).count();
+    let (verdict, color) = if broken > 0 {
+        (format!("NO-GO, {broken} of {} tools cannot be downloaded", items.len()), Color::Red)
+    } else if pending {
+        (format!("{} checking downloads", spinner_frame()), Color::DarkGray)
+    } else {
+        ("GO".to_string(), Color::Green)
+    };
+    lines.push(Line::from(vec![
+        Span::styled(" Pre-flight: ", label),
+        Span::styled(verdict, Style::default().fg(color).add_modifier(Modifier::BOLD)),
+    ]));
+    if on_battery {
+        let advice = if crate::config::current().defer_on_battery == Some(true) {
+            "large downloads will wait for AC power"
+        } else {
+            "consider plugging in before a large install"
+        };
+        lines.push(Line::from(Span::styled(format!(" ⚠ Running on battery: {advice}"), Style::default().fg(Color::Yellow))));
+    }
+    if let Some(warning) = crate::preflight::download_warning(total, app.download_warn_mb) {
+        lines.push(Line::from(Span::styled(format!(" ⚠ {warning}"), Style::default().fg(Color::Yellow))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(" System changes:", label)));
+    if changes.is_empty() {
+        lines.push(Line::from(Span::styled("  none outside the install roots", dim)));
+    }
+    for change in changes {
+        lines.push(Line::from(Span::styled(format!("  ! {change}"), Style::default().fg(Color::Red))));
+    }
+    lines.push(Line::from(""));
+    let prompt = if broken > 0 { " Proceed anyway?" } else { " Proceed?" };
+    lines.push(Line::from(Span::styled(format!("{prompt} [Y/Enter] yes  [N/Esc] no"), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))));
+
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!("  Confirm Installation ({} tools)  ", items.len())).border_style(Style::default().fg(Color::Yellow)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(summary, chunks[0]);
+
+    render_logs(app, frame, chunks[1], "Live Activity", Color::DarkGray);
+}
+
+pub(super) fn render_onboarding(app: &App, frame: &mut Frame, area: Rect, step: OnboardingStep, profile: usize, install_root: &str, download_dir: &str) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    let label = Style::default().fg(Color::Cyan);
+    let value = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    let active = |s: OnboardingStep| if s == step { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::DarkGray) };
+
+    let mut lines = vec![
+        Line::from(Span::styled(" Welcome to Rusty Rebase! No catalog was found, let's set one up.", value)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(" Detected system: ", label),
+            Span::styled(format!("{} ({})", app.distro.id, app.distro.pkg_manager), value),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(" 1. Starter catalog", active(OnboardingStep::Profile))),
+    ];
+    for (idx, starter) in crate::catalog::STARTER_PROFILES.iter().enumerate() {
+        let marker = if idx == profile { "(*)" } else { "( )" };
+        let style = if idx == profile { Style::default().fg(Color::Green) } else { Style::default().fg(Color::Gray) };
+        lines.push(Line::from(vec![
+            Span::styled(format!("    {marker} {:<22}", starter.name), style),
+            Span::styled(starter.description, Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+    let cursor = |s: OnboardingStep| if s == step { "_" } else { "" };
+    lines.extend([
+        Line::from(""),
+        Line::from(Span::styled(" 2. Default install root", active(OnboardingStep::InstallRoot))),
+        Line::from(Span::styled(format!("    {}{}", install_root, cursor(OnboardingStep::InstallRoot)), value)),
+        Line::from(""),
+        Line::from(Span::styled(" 3. Download directory", active(OnboardingStep::DownloadDir))),
+        Line::from(Span::styled(format!("    {}{}", download_dir, cursor(OnboardingStep::DownloadDir)), value)),
+        Line::from(""),
+        Line::from(Span::styled(" 4. Press [Enter] to write the config and catalog", active(OnboardingStep::Confirm))),
+    ]);
+    if let Ok(dir) = crate::config::config_dir() {
+        lines.push(Line::from(Span::styled(format!("    into {}", dir.display()), Style::default().fg(Color::DarkGray))));
+    }
+
+    let wizard = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("  First-run Setup  ").border_style(Style::default().fg(Color::Cyan)))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(wizard, chunks[0]);
+
+    render_logs(app, frame, chunks[1], "Live Activity", Color::DarkGray);
+}
@@ -0,0 +1,160 @@
+//! The one queue the event loop blocks on: terminal input from a reader
+//! thread and wake-ups from the channels background work reports through,
+//! so nothing is redrawn while nothing happens.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event;
+
+/// How long the reader waits for input before checking whether it should
+/// pause or stop.
+const POLL: Duration = Duration::from_millis(250);
+
+pub enum Event {
+    Input(event::Event),
+    /// A background channel has a message.
+    Wake,
+}
+
+/// Wakes the event loop; cloned into the channels handed to background work.
+#[derive(Clone)]
+pub struct Waker(Sender<Event>);
+
+impl Waker {
+    pub fn wake(&self) {
+        let _ = self.0.send(Event::Wake);
+    }
+
+    /// A channel whose messages wake the event loop. A relay thread passes
+    /// them on, so the sender stays a plain `mpsc::Sender` for the engine,
+    /// and exits once every sender is dropped.
+    pub fn channel<T: Send + 'static>(&self) -> (Sender<T>, Receiver<T>) {
+        let (tx, relay_rx) = mpsc::channel();
+        let (relay_tx, rx) = mpsc::channel();
+        let waker = self.clone();
+        thread::spawn(move || {
+            for msg in relay_rx {
+                if relay_tx.send(msg).is_err() {
+                    break;
+                }
+                waker.wake();
+            }
+        });
+        (tx, rx)
+    }
+}
+
+#[derive(Default)]
+struct ReaderState {
+    paused: bool,
+    stopped: bool,
+}
+
+/// Shared with the reader thread. `reading` is held while it polls, so
+/// pausing waits for a poll in progress instead of racing it for a key.
+#[derive(Default)]
+struct Reader {
+    state: Mutex<ReaderState>,
+    resumed: Condvar,
+    reading: Mutex<()>,
+}
+
+pub struct Events {
+    rx: Receiver<Event>,
+    waker: Waker,
+    reader: Arc<Reader>,
+    listening: bool,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Events { rx, waker: Waker(tx), reader: Arc::default(), listening: false }
+    }
+
+    pub fn waker(&self) -> &Waker {
+        &self.waker
+    }
+
+    /// Starts reading the terminal; called once raw mode is on.
+    pub fn listen(&mut self) {
+        if self.listening {
+            return;
+        }
+        self.listening = true;
+        let reader = self.reader.clone();
+        let tx = self.waker.0.clone();
+        thread::spawn(move || loop {
+            {
+                let mut state = reader.state.lock().unwrap_or_else(|e| e.into_inner());
+                while state.paused && !state.stopped {
+                    state = reader.resumed.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                if state.stopped {
+                    return;
+                }
+            }
+            let _reading = reader.reading.lock().unwrap_or_else(|e| e.into_inner());
+            let state = reader.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.paused || state.stopped {
+                continue;
+            }
+            drop(state);
+            match event::poll(POLL) {
+                Ok(true) => match event::read() {
+                    Ok(input) => {
+                        if tx.send(Event::Input(input)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                },
+                Ok(false) => {}
+                Err(_) => return,
+            }
+        });
+    }
+
+    /// Blocks until something happens or `timeout` passes, then returns it
+    /// with everything queued behind it.
+    pub fn wait(&self, timeout: Duration) -> Vec<Event> {
+        let first = match self.rx.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return Vec::new(),
+        };
+        std::iter::once(first).chain(self.rx.try_iter()).collect()
+    }
+
+    /// Stops reading the terminal while `f` runs, so sudo or an editor get
+    /// the keys typed into them.
+    pub fn paused<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.set_paused(true);
+        let reading = self.reader.reading.lock().unwrap_or_else(|e| e.into_inner());
+        let result = f();
+        drop(reading);
+        self.set_paused(false);
+        result
+    }
+
+    /// Ends the reader thread once it leaves its poll, so it does not take
+    /// keys meant for the shell once the TUI is gone.
+    pub fn stop(&self) {
+        self.reader.state.lock().unwrap_or_else(|e| e.into_inner()).stopped = true;
+        self.reader.resumed.notify_all();
+        let _reading = self.reader.reading.lock().unwrap_or_else(|e| e.into_inner());
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.reader.state.lock().unwrap_or_else(|e| e.into_inner()).paused = paused;
+        self.reader.resumed.notify_all();
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,567 @@
+//! Writes backups in the Rusty Sync layout that [`crate::restorer`] reads:
+//! archives per top-level folder of the source, a SHA-256 index of every
+//! file and `.rusty_sync_info.json`, plus groups such as the desktop
+//! settings that restore to a place of their own. Archives are zips, or
+//! tar.zst with [`BackupFormat::Zstd`], compressed and hashed on a pool of
+//! worker threads, one archive per worker at a time.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+
+use crate::error::{Error, Result};
+use crate::events::InstallMsg;
+use crate::restorer::{report, BackupFormat, BackupIndexEntry, BackupInfo, INFO_FILE};
+
+/// Groups `backup --groups` accepts.
+pub const GROUPS: &[&str] = &[crate::desktop_settings::GROUP, crate::browser_profiles::GROUP];
+
+/// File data per archive before a folder moves on to its next volume.
+pub const DEFAULT_ARCHIVE_MB: u64 = 1024;
+
+/// Zstandard level of tar.zst archives; zstd's own default.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Stem of the archives for the files directly inside the source folder.
+const ROOT_STEM: &str = "root";
+
+/// Starts the stems of group archives. [`archive_stem`] replaces `+`, so no
+/// source folder can end up with the same archive name as a group.
+const GROUP_STEM_PREFIX: &str = "group+";
+
+/// How often the throughput is reported while archives are written.
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A file to back up, with its path relative to the archive root using `/`.
+pub(crate) struct SourceFile {
+    pub relative: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// The target, when this is a symlink.
+    pub link: Option<String>,
+}
+
+/// One archive of the backup and the files that go into it.
+pub(crate) struct Archive {
+    pub name: String,
+    pub files: Vec<SourceFile>,
+}
+
+/// How the archives of a backup are written.
+pub(crate) struct ArchiveSettings {
+    pub format: BackupFormat,
+    /// Zstandard level, for [`BackupFormat::Zstd`].
+    pub level: i32,
+    /// File data per archive, in bytes.
+    pub cap: u64,
+}
+
+impl ArchiveSettings {
+    /// `backup_format`, `backup_zstd_level` and `backup_archive_mb` from the
+    /// config, with `format` in place of the configured one when given.
+    fn from_config(format: Option<BackupFormat>) -> Self {
+        let config = crate::config::current();
+        let levels = zstd::compression_level_range();
+        ArchiveSettings {
+            format: format.or(config.backup_format).unwrap_or_default(),
+            level: config.backup_zstd_level.unwrap_or(DEFAULT_ZSTD_LEVEL).clamp(*levels.start(), *levels.end()),
+            cap: config.backup_archive_mb.unwrap_or(DEFAULT_ARCHIVE_MB).max(1) * 1024 * 1024,
+        }
+    }
+
+    /// Splits `files` into archives holding at most `cap` bytes of file
+    /// data, named `<stem>.zip`, `<stem>.2.zip`, ... (or `.tar.zst`). A file
+    /// bigger than `cap` gets an archive of its own.
+    pub(crate) fn volumes(&self, stem: &str, files: Vec<SourceFile>) -> Vec<Archive> {
+        let extension = self.format.extension();
+        let mut archives: Vec<Archive> = Vec::new();
+        let mut filled = 0;
+        for file in files {
+            let full = archives.last().is_none_or(|last| !last.files.is_empty() && filled + file.size > self.cap);
+            if full {
+                let name = match archives.len() {
+                    0 => format!("{stem}{extension}"),
+                    n => format!("{stem}.{}{extension}", n + 1),
+                };
+                archives.push(Archive { name, files: Vec::new() });
+                filled = 0;
+            }
+            filled += file.size;
+            if let Some(last) = archives.last_mut() {
+                last.files.push(file);
+            }
+        }
+        archives
+    }
+
+    /// [`Self::volumes`] for a group's archives, named apart from those of
+    /// the source folders.
+    pub(crate) fn group_volumes(&self, stem: &str, files: Vec<SourceFile>) -> Vec<Archive> {
+        self.volumes(&format!("{GROUP_STEM_PREFIX}{stem}"), files)
+    }
+}
+
+/// Backs up the files under `source` and the given `groups` into `dest`,
+/// which must not hold a backup yet, with their permissions, owners, times,
+/// extended attributes and symlinks. `format`
+/// overrides `backup_format` from the config.
+pub fn create_backup(
+    source: Option<&Path>,
+    dest: &Path,
+    groups: &[String],
+    format: Option<BackupFormat>,
+    tx: Option<&Sender<InstallMsg>>,
+) -> Result<Vec<String>> {
+    if let Some(unknown) = groups.iter().find(|g| !GROUPS.contains(&g.as_str())) {
+        return Err(Error::Other(format!("unknown backup group '{unknown}' (known: {})", GROUPS.join(", "))));
+    }
+    if source.is_none() && groups.is_empty() {
+        return Err(Error::Other("nothing to back up: give a source folder, groups or both".to_string()));
+    }
+    if dest.join(INFO_FILE).exists() {
+        return Err(Error::Io(format!("{} already holds a backup", dest.display())));
+    }
+    fs::create_dir_all(dest).map_err(|e| Error::Io(format!("Failed to create {}: {e}", dest.display())))?;
+    let mut logs = Vec::new();
+    let home = crate::installer::home_dir().map_err(Error::Io)?;
+    let mut info = BackupInfo {
+        source_path: home.display().to_string(),
+        backup_time: crate::report::format_timestamp(crate::manifest::now_secs()),
+        zip_files: Vec::new(),
+        index: None,
+        format: BackupFormat::Zip,
+        groups: Vec::new(),
+    };
+    let settings = ArchiveSettings::from_config(format);
+    info.format = settings.format;
+
+    // Every archive of the backup with the group it belongs to, `None` for
+    // the source folder's files, so all of them are written in one pool.
+    let mut archives: Vec<(Option<usize>, Archive)> = Vec::new();
+    if let Some(source) = source {
+        let source = source.canonicalize().map_err(|e| Error::Io(format!("Failed to open {}: {e}", source.display())))?;
+        let dest = dest.canonicalize().map_err(|e| Error::Io(format!("Failed to open {}: {e}", dest.display())))?;
+        report(tx, &mut logs, format!("Backing up '{}' to '{}'", source.display(), dest.display()));
+        let mut files = Vec::new();
+        collect_files(&source, &source, Some(&dest), true, &mut files, tx, &mut logs)?;
+
+        let mut folders: BTreeMap<String, Vec<SourceFile>> = BTreeMap::new();
+        let mut stems: BTreeMap<String, String> = BTreeMap::new();
+        let mut used = HashSet::from([ROOT_STEM.to_string()]);
+        for file in files {
+            let stem = match file.relative.split_once('/') {
+                Some((top, _)) => stems.entry(top.to_string()).or_insert_with(|| archive_stem(top, &mut used)).clone(),
+                None => ROOT_STEM.to_string(),
+            };
+            folders.entry(stem).or_default().push(file);
+        }
+        for (stem, files) in folders {
+            for archive in settings.volumes(&stem, files) {
+                info.zip_files.push(archive.name.clone());
+                archives.push((None, archive));
+            }
+        }
+        info.source_path = source.display().to_string();
+        info.index = Some(Vec::new());
+    }
+
+    for group in groups {
+        let (captured, missing) = match group.as_str() {
+            crate::desktop_settings::GROUP => (crate::desktop_settings::capture(dest, &settings, tx, &mut logs)?, "No dconf database or KDE settings"),
+            crate::browser_profiles::GROUP => (crate::browser_profiles::capture(&settings, tx, &mut logs)?, "No browser profiles"),
+            _ => continue,
+        };
+        match captured {
+            Some((mut captured, group_archives)) => {
+                captured.zip_files = group_archives.iter().map(|a| a.name.clone()).collect();
+                archives.extend(group_archives.into_iter().map(|a| (Some(info.groups.len()), a)));
+                info.groups.push(captured);
+            }
+            None => report(tx, &mut logs, format!("[warn] {missing} found, the {group} group is left out")),
+        }
+    }
+
+    let total_files: usize = archives.iter().map(|(_, a)| a.files.len()).sum();
+    report(tx, &mut logs, format!("[info] Found {total_files} files for {} archives.", archives.len()));
+    let (owners, archives): (Vec<Option<usize>>, Vec<Archive>) = archives.into_iter().unzip();
+    let started = Instant::now();
+    let indexes = write_archives(dest, &archives, &settings, tx, &mut logs)?;
+    let mut bytes = 0;
+    for ((owner, archive), index) in owners.into_iter().zip(&archives).zip(indexes) {
+        bytes += index.iter().map(|e| e.original_size).sum::<u64>();
+        report(tx, &mut logs, format!("[done] Wrote archive: {}", archive.name));
+        match owner {
+            Some(group) => info.groups[group].index.extend(index),
+            None => info.index.get_or_insert_with(Vec::new).extend(index),
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&info).map_err(|e| Error::Other(format!("Failed to serialize backup info: {e}")))?;
+    let info_path = dest.join(INFO_FILE);
+    fs::write(&info_path, json).map_err(|e| Error::Io(format!("Failed to write {}: {e}", info_path.display())))?;
+    if let Some(s) = tx {
+        let _ = s.send(InstallMsg::SubProgress(1.0));
+    }
+    let elapsed = started.elapsed();
+    report(
+        tx,
+        &mut logs,
+        format!(
+            "✓ Backup completed: {} of files in {} ({}), {}",
+            crate::units::format_size(bytes),
+            crate::report::format_duration(elapsed),
+            crate::units::format_speed(bytes as f64 / elapsed.as_secs_f64().max(0.001)),
+            info_path.display()
+        ),
+    );
+    Ok(logs)
+}
+
+/// Regular files and symlinks under `dir`, with their paths relative to
+/// `root`. With `keep_links`, symlinks are kept as links; otherwise links
+/// to files are backed up with the file's contents, as for settings linked
+/// in from a dotfiles repository, and other links are left out. `skip` (the
+/// backup folder itself) is left out, and unreadable folders are reported
+/// and skipped.
+pub(crate) fn collect_files(
+    root: &Path,
+    dir: &Path,
+    skip: Option<&Path>,
+    keep_links: bool,
+    out: &mut Vec<SourceFile>,
+    tx: Option<&Sender<InstallMsg>>,
+    logs: &mut Vec<String>,
+) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            report(tx, logs, format!("[warn] Skipping {}: {e}", dir.display()));
+            return Ok(());
+        }
+    };
+    let mut entries: Vec<_> = entries.filter_map(std::result::Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let Ok(kind) = entry.file_type() else { continue };
+        if kind.is_dir() && skip != Some(path.as_path()) {
+            collect_files(root, &path, skip, keep_links, out, tx, logs)?;
+        } else if kind.is_file() || (kind.is_symlink() && (keep_links || path.is_file())) {
+            out.push(source_file(root, path, keep_links));
+        }
+    }
+    Ok(())
+}
+
+/// `path` as a file to back up, relative to `root`; a symlink as a link with
+/// `keep_links`, or else as the file it points to.
+pub(crate) fn source_file(root: &Path, path: PathBuf, keep_links: bool) -> SourceFile {
+    let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+    let link = keep_links.then(|| fs::read_link(&path).ok()).flatten().map(|target| target.to_string_lossy().into_owned());
+    let size = if link.is_some() { 0 } else { fs::metadata(&path).map(|m| m.len()).unwrap_or(0) };
+    SourceFile { relative, path, size, link }
+}
+
+/// The index entry of `file` in `archive`, with its attributes; the size
+/// and hash are filled in once its contents are written. A symlink's are
+/// those of its target path.
+fn index_entry(file: &SourceFile, archive: &str) -> BackupIndexEntry {
+    let mut entry = BackupIndexEntry {
+        relative_path: file.relative.clone(),
+        zip_file: Some(archive.to_string()),
+        symlink_target: file.link.clone(),
+        ..BackupIndexEntry::default()
+    };
+    if let Some(target) = &file.link {
+        entry.original_size = target.len() as u64;
+        entry.sha256_hash = format!("{:x}", Sha256::digest(target.as_bytes()));
+    }
+    crate::file_attrs::record(&file.path, &mut entry);
+    entry
+}
+
+/// `folder` with characters unsafe in file names replaced, numbered when
+/// it had to be changed, when it could pass for another folder's volume
+/// (`photos.2`), or when a stem in `used` differs from it only in case, as
+/// FAT32 and exFAT sticks do not tell those apart. The stem is added to
+/// `used`, lowercased.
+fn archive_stem(folder: &str, used: &mut HashSet<String>) -> String {
+    let safe: String = folder.chars().map(|c| if c.is_alphanumeric() || "._-".contains(c) { c } else { '_' }).collect();
+    let volume_like = safe.rsplit_once('.').is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    let mut stem = safe.clone();
+    let mut n = 1;
+    while safe != folder || volume_like || used.contains(&stem.to_lowercase()) {
+        stem = format!("{safe}-{n}");
+        if !used.contains(&stem.to_lowercase()) {
+            break;
+        }
+        n += 1;
+    }
+    used.insert(stem.to_lowercase());
+    stem
+}
+
+/// Totals shared by the workers, for the throughput reports.
+struct Throughput {
+    files: AtomicUsize,
+    bytes: AtomicU64,
+    failed: AtomicBool,
+    /// Files that changed size while a tar stream was reading them.
+    changed: Mutex<Vec<String>>,
+}
+
+/// Writes every archive into `dir` on a pool of worker threads and returns
+/// the index entries of each, in the order of `archives`. The first failure
+/// stops the workers from starting more archives.
+pub(crate) fn write_archives(
+    dir: &Path,
+    archives: &[Archive],
+    settings: &ArchiveSettings,
+    tx: Option<&Sender<InstallMsg>>,
+    logs: &mut Vec<String>,
+) -> Result<Vec<Vec<BackupIndexEntry>>> {
+    let total_files: usize = archives.iter().map(|a| a.files.len()).sum();
+    let total_bytes: u64 = archives.iter().flat_map(|a| &a.files).map(|f| f.size).sum();
+    let workers = thread::available_parallelism().map_or(2, |n| n.get()).min(archives.len()).max(1);
+    let next = AtomicUsize::new(0);
+    let done = Throughput {
+        files: AtomicUsize::new(0),
+        bytes: AtomicU64::new(0),
+        failed: AtomicBool::new(false),
+        changed: Mutex::default(),
+    };
+    let started = Instant::now();
+
+    thread::scope(|scope| {
+        // Each worker holds a sender; the channel disconnects once all exit.
+        let (alive_tx, alive_rx) = mpsc::channel::<()>();
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let alive = alive_tx.clone();
+                let (next, done) = (&next, &done);
+                scope.spawn(move || {
+                    let _alive = alive;
+                    let mut written = Vec::new();
+                    while !done.failed.load(Ordering::Relaxed) {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(archive) = archives.get(i) else { break };
+                        let written_archive = match settings.format {
+                            BackupFormat::Zip => write_zip(dir, archive, done),
+                            BackupFormat::Zstd => write_tar_zstd(dir, archive, settings.level, done),
+                        };
+                        match written_archive {
+                            Ok(index) => written.push((i, index)),
+                            Err(e) => {
+                                done.failed.store(true, Ordering::Relaxed);
+                                return Err(e);
+                            }
+                        }
+                    }
+                    Ok(written)
+                })
+            })
+            .collect();
+        drop(alive_tx);
+        while let Err(RecvTimeoutError::Timeout) = alive_rx.recv_timeout(REPORT_INTERVAL) {
+            let Some(s) = tx else { continue };
+            let (files, bytes) = (done.files.load(Ordering::Relaxed), done.bytes.load(Ordering::Relaxed));
+            let bps = bytes as f64 / started.elapsed().as_secs_f64().max(0.001);
+            let mut speed = crate::units::format_speed(bps);
+            if bps > 0.0 {
+                let left = total_bytes.saturating_sub(bytes) as f64 / bps;
+                speed.push_str(&format!(" • {} left", crate::units::format_eta(left as u64)));
+            }
+            let msg = format!(
+                "Backing up ({} / {}, {files}/{total_files} files)",
+                crate::units::format_size(bytes),
+                crate::units::format_size(total_bytes)
+            );
+            let _ = s.send(InstallMsg::Progress(String::new(), msg, Some(speed)));
+            let _ = s.send(InstallMsg::SubProgress(bytes as f64 / total_bytes.max(1) as f64));
+        }
+        let mut indexes = vec![Vec::new(); archives.len()];
+        for handle in handles {
+            let written = handle.join().unwrap_or_else(|_| Err(Error::Other("a backup worker panicked".to_string())))?;
+            for (i, index) in written {
+                indexes[i] = index;
+            }
+        }
+        let changed = done.changed.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(first) = changed.first() {
+            let n = changed.len();
+            report(tx, logs, format!("[warn] {n} files changed size while being backed up, so the archive holds them cut short or padded with zeros; back them up again once nothing writes to them (e.g. {first})"));
+        }
+        Ok(indexes)
+    })
+}
+
+/// Writes one archive, hashing each file as it is compressed.
+fn write_zip(dir: &Path, archive: &Archive, done: &Throughput) -> Result<Vec<BackupIndexEntry>> {
+    let name = &archive.name;
+    let zip_path = dir.join(name);
+    let file = File::create(&zip_path).map_err(|e| Error::Io(format!("Failed to create {}: {e}", zip_path.display())))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let mut index = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    for source in &archive.files {
+        let SourceFile { relative, path, size, link } = source;
+        if done.failed.load(Ordering::Relaxed) {
+            return Err(Error::Other("backup stopped after another archive failed".to_string()));
+        }
+        let mut entry = index_entry(source, name);
+        let mut options = SimpleFileOptions::default().large_file(*size >= u32::MAX as u64);
+        if let Some(mode) = entry.mode {
+            options = options.unix_permissions(mode);
+        }
+        if let Some(target) = link {
+            zip.add_symlink(relative.as_str(), target.as_str(), options)
+                .map_err(|e| Error::Io(format!("Failed to add {relative} to {name}: {e}")))?;
+            index.push(entry);
+            done.files.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        let mut input = File::open(path).map_err(|e| Error::Io(format!("Failed to read {}: {e}", path.display())))?;
+        zip.start_file(relative.as_str(), options).map_err(|e| Error::Io(format!("Failed to add {relative} to {name}: {e}")))?;
+        let mut hasher = Sha256::new();
+        let mut original_size = 0;
+        loop {
+            let n = input.read(&mut buffer).map_err(|e| Error::Io(format!("Failed to read {}: {e}", path.display())))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            zip.write_all(&buffer[..n]).map_err(|e| Error::Io(format!("Failed to write {name}: {e}")))?;
+            original_size += n as u64;
+            done.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        entry.original_size = original_size;
+        entry.sha256_hash = format!("{:x}", hasher.finalize());
+        index.push(entry);
+        done.files.fetch_add(1, Ordering::Relaxed);
+    }
+    zip.finish().map_err(|e| Error::Io(format!("Failed to write {}: {e}", zip_path.display())))?;
+    Ok(index)
+}
+
+/// Writes one tar.zst archive, hashing each file as it is compressed.
+fn write_tar_zstd(dir: &Path, archive: &Archive, level: i32, done: &Throughput) -> Result<Vec<BackupIndexEntry>> {
+    let name = &archive.name;
+    let tar_path = dir.join(name);
+    let file = File::create(&tar_path).map_err(|e| Error::Io(format!("Failed to create {}: {e}", tar_path.display())))?;
+    let encoder = zstd::stream::write::Encoder::new(file, level).map_err(|e| Error::Io(format!("Failed to start {name}: {e}")))?;
+    let mut tar = tar::Builder::new(encoder);
+    let mut index = Vec::new();
+    for source in &archive.files {
+        let SourceFile { relative, path, size, link } = source;
+        if done.failed.load(Ordering::Relaxed) {
+            return Err(Error::Other("backup stopped after another archive failed".to_string()));
+        }
+        let mut entry = index_entry(source, name);
+        if let Some(target) = link {
+            let mut header = tar::Header::new_gnu();
+            if let Ok(metadata) = fs::symlink_metadata(path) {
+                header.set_metadata(&metadata);
+            }
+            tar.append_link(&mut header, relative, target).map_err(|e| Error::Io(format!("Failed to add {relative} to {name}: {e}")))?;
+            index.push(entry);
+            done.files.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        let mut input = File::open(path).map_err(|e| Error::Io(format!("Failed to read {}: {e}", path.display())))?;
+        let mut header = tar::Header::new_gnu();
+        let mut size = *size;
+        if let Ok(metadata) = input.metadata() {
+            header.set_metadata(&metadata);
+            size = metadata.len();
+        }
+        // The header is written before the contents and cannot be rewritten,
+        // so a file that changes size while it is read is cut or padded to
+        // the size it had when opened, and reported.
+        header.set_size(size);
+        let mut file_part = (&mut input).take(size);
+        let contents = (&mut file_part).chain(std::io::repeat(0)).take(size);
+        let mut reader = HashingReader { inner: contents, hasher: Sha256::new(), read: 0, done };
+        tar.append_data(&mut header, relative, &mut reader).map_err(|e| Error::Io(format!("Failed to add {relative} to {name}: {e}")))?;
+        let HashingReader { hasher, read, .. } = reader;
+        if file_part.limit() > 0 || input.read(&mut [0u8]).is_ok_and(|n| n > 0) {
+            done.changed.lock().unwrap_or_else(|e| e.into_inner()).push(relative.clone());
+        }
+        entry.original_size = read;
+        entry.sha256_hash = format!("{:x}", hasher.finalize());
+        index.push(entry);
+        done.files.fetch_add(1, Ordering::Relaxed);
+    }
+    let encoder = tar.into_inner().map_err(|e| Error::Io(format!("Failed to write {name}: {e}")))?;
+    encoder.finish().map_err(|e| Error::Io(format!("Failed to write {}: {e}", tar_path.display())))?;
+    Ok(index)
+}
+
+/// Hashes what the tar builder reads and counts it towards the throughput.
+struct HashingReader<'a, R> {
+    inner: R,
+    hasher: Sha256,
+    read: u64,
+    done: &'a Throughput,
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.read += n as u64;
+        self.done.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(relative: &str, size: u64) -> SourceFile {
+        SourceFile { relative: relative.to_string(), path: PathBuf::from(relative), size, link: None }
+    }
+
+    fn contents(archives: &[Archive]) -> Vec<(&str, Vec<&str>)> {
+        archives.iter().map(|a| (a.name.as_str(), a.files.iter().map(|f| f.relative.as_str()).collect())).collect()
+    }
+
+    #[test]
+    fn volumes_split_at_the_cap() {
+        let settings = ArchiveSettings { format: BackupFormat::Zip, level: DEFAULT_ZSTD_LEVEL, cap: 100 };
+        let files = vec![file("a", 60), file("b", 30), file("c", 20), file("big", 150), file("d", 10)];
+        assert_eq!(contents(&settings.volumes("docs", files)), [
+            ("docs.zip", vec!["a", "b"]),
+            ("docs.2.zip", vec!["c"]),
+            ("docs.3.zip", vec!["big"]),
+            ("docs.4.zip", vec!["d"]),
+        ]);
+        assert!(settings.volumes("docs", Vec::new()).is_empty());
+
+        let settings = ArchiveSettings { format: BackupFormat::Zstd, ..settings };
+        assert_eq!(contents(&settings.group_volumes("desktop", vec![file("kdeglobals", 1)])), [("group+desktop.tar.zst", vec!["kdeglobals"])]);
+    }
+
+    #[test]
+    fn archive_stems_stay_apart() {
+        let mut used = HashSet::new();
+        assert_eq!(archive_stem("Photos", &mut used), "Photos");
+        assert_eq!(archive_stem("photos", &mut used), "photos-1");
+        assert_eq!(archive_stem("PHOTOS", &mut used), "PHOTOS-2");
+        assert_eq!(archive_stem("my docs", &mut used), "my_docs-1");
+        assert_eq!(archive_stem("my_docs", &mut used), "my_docs");
+        assert_eq!(archive_stem("photos.2", &mut used), "photos.2-1");
+        assert_eq!(archive_stem("group+x", &mut used), "group_x-1");
+        assert_eq!(archive_stem("v1.0", &mut used), "v1.0-1");
+        assert_eq!(archive_stem("notes.txt", &mut used), "notes.txt");
+    }
+}
@@ -0,0 +1,165 @@
+//! The `browsers` backup group: Firefox and Chromium-based profiles with
+//! their bookmarks, history, passwords and extensions. A browser that is
+//! running keeps its SQLite databases open and half-written, so backing up
+//! or restoring its profile is refused until it is closed.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+use crate::backup::{Archive, ArchiveSettings};
+use crate::error::{Error, Result};
+use crate::events::InstallMsg;
+use crate::restorer::{report, BackupGroup, BackupIndexEntry};
+
+pub const GROUP: &str = "browsers";
+
+struct Browser {
+    name: &'static str,
+    /// Stem of the archives of the browser's profiles in the backup folder.
+    archive: &'static str,
+    /// Profile folders relative to the home directory: native, Flatpak and
+    /// Snap installs.
+    dirs: &'static [&'static str],
+    /// Process names, as the kernel shows them (cut to 15 characters) or as
+    /// the executable is named.
+    processes: &'static [&'static str],
+}
+
+const BROWSERS: &[Browser] = &[
+    Browser {
+        name: "Firefox",
+        archive: "browser-firefox",
+        dirs: &[".mozilla/firefox", ".var/app/org.mozilla.firefox/.mozilla/firefox", "snap/firefox/common/.mozilla/firefox"],
+        processes: &["firefox", "firefox-bin", "firefox-esr"],
+    },
+    Browser {
+        name: "LibreWolf",
+        archive: "browser-librewolf",
+        dirs: &[".librewolf", ".var/app/io.gitlab.librewolf-community/.librewolf"],
+        processes: &["librewolf", "librewolf-bin"],
+    },
+    Browser {
+        name: "Chromium",
+        archive: "browser-chromium",
+        dirs: &[".config/chromium", ".var/app/org.chromium.Chromium/config/chromium", "snap/chromium/common/chromium"],
+        processes: &["chromium", "chromium-browse", "chromium-browser"],
+    },
+    Browser {
+        name: "Google Chrome",
+        archive: "browser-chrome",
+        dirs: &[".config/google-chrome"],
+        processes: &["chrome", "google-chrome"],
+    },
+    Browser {
+        name: "Brave",
+        archive: "browser-brave",
+        dirs: &[".config/BraveSoftware/Brave-Browser", ".var/app/com.brave.Browser/config/BraveSoftware/Brave-Browser"],
+        processes: &["brave", "brave-browser"],
+    },
+    Browser {
+        name: "Microsoft Edge",
+        archive: "browser-edge",
+        dirs: &[".config/microsoft-edge"],
+        processes: &["msedge", "microsoft-edge"],
+    },
+    Browser {
+        name: "Vivaldi",
+        archive: "browser-vivaldi",
+        dirs: &[".config/vivaldi"],
+        processes: &["vivaldi", "vivaldi-bin"],
+    },
+];
+
+/// Folders and files inside profiles that are rebuilt on demand or only
+/// mean something to the running instance: caches, crash dumps and locks.
+const SKIPPED: &[&str] = &[
+    "Cache",
+    "Code Cache",
+    "GPUCache",
+    "DawnCache",
+    "DawnGraphiteCache",
+    "DawnWebGPUCache",
+    "GrShaderCache",
+    "ShaderCache",
+    "CacheStorage",
+    "ScriptCache",
+    "cache2",
+    "startupCache",
+    "thumbnails",
+    "Crash Reports",
+    "crashes",
+    "minidumps",
+    "lock",
+    ".parentlock",
+    "parent.lock",
+];
+
+/// Lists the archives of every browser's profiles, one or more volumes
+/// per browser as `settings` splits them. `None` when there are none; an error
+/// while one of them runs.
+pub(crate) fn capture(settings: &ArchiveSettings, tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>) -> Result<Option<(BackupGroup, Vec<Archive>)>> {
+    let home = crate::installer::home_dir().map_err(Error::Io)?;
+    let found: Vec<&Browser> = BROWSERS.iter().filter(|b| b.dirs.iter().any(|dir| home.join(dir).is_dir())).collect();
+    if found.is_empty() {
+        return Ok(None);
+    }
+    ensure_closed(&found, "backing up")?;
+
+    let mut archives = Vec::new();
+    for browser in &found {
+        let mut files = Vec::new();
+        for dir in browser.dirs.iter().map(|dir| home.join(dir)).filter(|dir| dir.is_dir()) {
+            crate::backup::collect_files(&home, &dir, None, false, &mut files, tx, logs)?;
+        }
+        files.retain(|file| !file.relative.split('/').any(|part| SKIPPED.contains(&part)));
+        archives.extend(settings.group_volumes(browser.archive, files));
+    }
+    let names: Vec<&str> = found.iter().map(|b| b.name).collect();
+    let label = format!("Browser profiles ({})", names.join(", "));
+    let files = archives.iter().flat_map(|a| &a.files);
+    let (count, size) = files.fold((0, 0), |(count, size), file| (count + 1, size + file.size));
+    report(tx, logs, format!("[done] Captured {label}: {count} files, {}", crate::units::format_size(size)));
+    let group = BackupGroup { name: GROUP.to_string(), label, dest: "~".to_string(), zip_files: Vec::new(), index: Vec::new(), dconf: None };
+    Ok(Some((group, archives)))
+}
+
+/// Refuses to restore over the profiles in `index` while their browser runs.
+pub fn ensure_closed_for(index: &[BackupIndexEntry]) -> Result<()> {
+    let restored: Vec<&Browser> = BROWSERS
+        .iter()
+        .filter(|b| b.dirs.iter().any(|dir| index.iter().any(|e| e.relative_path.starts_with(&format!("{dir}/")))))
+        .collect();
+    ensure_closed(&restored, "restoring")
+}
+
+fn ensure_closed(browsers: &[&Browser], doing: &str) -> Result<()> {
+    match running(browsers) {
+        Some((name, pid)) => Err(Error::Other(format!("{name} is running (PID {pid}); close it before {doing} its profile"))),
+        None => Ok(()),
+    }
+}
+
+/// The first of `browsers` running as the current user, with its PID.
+fn running(browsers: &[&Browser]) -> Option<(&'static str, u32)> {
+    if browsers.is_empty() {
+        return None;
+    }
+    let mut sys = System::new();
+    let refresh = ProcessRefreshKind::nothing().with_user(UpdateKind::OnlyIfNotSet).with_exe(UpdateKind::OnlyIfNotSet);
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+    let own = sysinfo::get_current_pid().ok().and_then(|pid| sys.process(pid)).and_then(|p| p.user_id().cloned());
+    sys.processes().iter().find_map(|(pid, process)| {
+        if own.is_some() && process.user_id() != own.as_ref() {
+            return None;
+        }
+        let exe = process.exe().and_then(Path::file_name);
+        let names = [Some(process.name()), exe];
+        let browser = browsers
+            .iter()
+            .find(|b| names.iter().flatten().any(|name| b.processes.iter().any(|p| *name == OsStr::new(p))))?;
+        Some((browser.name, pid.as_u32()))
+    })
+}
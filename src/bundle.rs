@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::{load_catalog, CatalogFile, SoftwareSpec, SourceSpec};
+use crate::distro::DistroInfo;
+use crate::manifest::{Manifest, ToolPin};
+use crate::resolver::{resolve_asset, resolve_pinned, ResolvedAsset};
+
+pub const DEFAULT_DIR: &str = "rusty_rebase_bundle";
+
+// Layout of a bundle directory, e.g. on a USB stick.
+const INDEX: &str = "bundle.toml";
+const MANIFEST: &str = "manifest.toml";
+const CATALOG_DIR: &str = "catalog";
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// What `bundle` resolved and downloaded, read back on the offline machine.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BundleIndex {
+    pub created_at: u64,
+    /// OS and architecture the downloads were picked for, e.g. `linux-x86_64`.
+    pub platform: String,
+    /// Entries with a download; package-manager and steps-only entries are
+    /// only in the manifest.
+    #[serde(default)]
+    pub tools: BTreeMap<String, BundledTool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundledTool {
+    pub version: String,
+    /// Where the download came from.
+    pub url: String,
+    /// File name under `artifacts/`.
+    pub file: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+struct ActiveBundle {
+    dir: PathBuf,
+    index: BundleIndex,
+}
+
+static ACTIVE: OnceLock<ActiveBundle> = OnceLock::new();
+
+/// Resolves every tool in the manifest and downloads its artifact into
+/// `out`, next to a copy of the catalog, a manifest pinned to the resolved
+/// versions and an index of the downloads, so `install-bundle` can set a
+/// machine up from it without the network. Running it again on the same
+/// directory keeps the downloads already there.
+pub fn bundle(manifest_path: &Path, out: &Path) -> Result<(), String> {
+    let manifest = crate::manifest::load_manifest(manifest_path)?;
+    let catalog_path = crate::manifest::catalog_path(manifest_path, &manifest)?;
+    let catalog = load_catalog(&catalog_path)?;
+    let distro = crate::distro::detect_distro()?;
+    let client = crate::resolver::build_client()?;
+    let artifacts = out.join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts).map_err(|e| format!("failed to create {}: {e}", artifacts.display()))?;
+
+    let mut index = BundleIndex { created_at: crate::manifest::now_secs(), platform: platform(), tools: BTreeMap::new() };
+    let mut pins = BTreeMap::new();
+    let mut failures = Vec::new();
+    for (key, pin) in &manifest.tools {
+        println!("==> {key}");
+        match bundle_tool(&client, key, pin.version.as_deref(), &catalog, &distro, &artifacts) {
+            Ok(Some(tool)) => {
+                println!("    {} {} ({})", tool.file, tool.version, crate::units::format_size(tool.size));
+                pins.insert(key.clone(), ToolPin { version: Some(tool.version.clone()) });
+                index.tools.insert(key.clone(), tool);
+            }
+            Ok(None) => {
+                pins.insert(key.clone(), pin.clone());
+            }
+            Err(e) => {
+                println!("    [error] {e}");
+                failures.push(key.clone());
+            }
+        }
+    }
+    if !manifest.dotfiles.is_empty() || manifest.restore.is_some() {
+        println!("[warn] dotfiles and restore sections are not bundled");
+    }
+
+    for (relative, path) in crate::snapshot::catalog_files(&catalog_path, &catalog) {
+        let dest = out.join(CATALOG_DIR).join(relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        fs::copy(&path, &dest).map_err(|e| format!("failed to copy {}: {e}", path.display()))?;
+    }
+    let bundled = Manifest {
+        catalog: Some(format!("{CATALOG_DIR}/{}", crate::config::CATALOG_FILE_NAME)),
+        tools: pins,
+        ..Manifest::default()
+    };
+    write_toml(&out.join(MANIFEST), &bundled)?;
+    write_toml(&out.join(INDEX), &index)?;
+
+    let total: u64 = index.tools.values().map(|t| t.size).sum();
+    println!(
+        "Bundled {} tool(s), {} to install offline, into {}",
+        manifest.tools.len() - failures.len(),
+        crate::units::format_size(total),
+        out.display()
+    );
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} tool(s) could not be bundled: {}", failures.len(), failures.join(", ")))
+    }
+}
+
+/// Resolves and downloads one tool. `None` for entries without a download,
+/// which install from the target's package manager or steps alone.
+fn bundle_tool(
+    client: &Client,
+    key: &str,
+    pin: Option<&str>,
+    catalog: &CatalogFile,
+    distro: &DistroInfo,
+    artifacts: &Path,
+) -> Result<Option<BundledTool>, String> {
+    let spec = catalog.software.get(key).ok_or("not found in catalog")?;
+    if let SourceSpec::BuildFromSource { .. } = spec.source {
+        return Err("builds from source clone their repository, which needs the network".to_string());
+    }
+    if !spec.source.has_artifact() {
+        if matches!(spec.source, SourceSpec::PackageManager) {
+            println!("    [warn] installs from the package manager; the target needs a reachable mirror");
+        }
+        return Ok(None);
+    }
+    let resolved = match pin {
+        Some(version) => crate::runtime::block_on(resolve_pinned(client, spec, distro, version))?,
+        None => crate::runtime::block_on(resolve_asset(client, spec, distro))?,
+    };
+    if resolved.version.is_empty() {
+        return Err(format!("{} has no version to pin", resolved.url));
+    }
+    // Offline installs only trust cached downloads named after their version.
+    let file = if resolved.file_name.contains(&resolved.version) {
+        resolved.file_name.clone()
+    } else {
+        format!("{key}-{}-{}", resolved.version, resolved.file_name)
+    };
+    let artifact = ResolvedAsset { file_name: file.clone(), ..resolved.clone() };
+    let (path, _) = crate::runtime::block_on(crate::installer::fetch_artifact(client, key, &artifact, artifacts))?;
+    Ok(Some(BundledTool {
+        version: resolved.version,
+        url: resolved.url,
+        file,
+        sha256: crate::installer::sha256_file(&path)?,
+        size: fs::metadata(&path).map(|m| m.len()).unwrap_or_default(),
+    }))
+}
+
+/// Installs the manifest of the bundle at `dir` without the network, after
+/// checking every download against its recorded hash. The lockfile is
+/// written next to the bundled manifest.
+pub fn install_bundle(dir: &Path, dry_run: bool, assume_yes: bool) -> Result<(), String> {
+    activate(dir)?;
+    crate::manifest::apply_manifest(&dir.join(MANIFEST), dry_run, assume_yes, false, None)
+}
+
+/// Reads the bundle at `dir` and verifies it, then resolves and downloads
+/// from it for the rest of the run.
+pub fn activate(dir: &Path) -> Result<(), String> {
+    let path = dir.join(INDEX);
+    let content = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let index: BundleIndex = toml::from_str(&content).map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+    if index.platform != platform() {
+        return Err(format!("{} was bundled for {}, this machine is {}", dir.display(), index.platform, platform()));
+    }
+    let artifacts = dir.join(ARTIFACTS_DIR);
+    let corrupt: Vec<&str> = index
+        .tools
+        .iter()
+        .filter(|(_, tool)| crate::installer::sha256_file(&artifacts.join(&tool.file)).ok().as_deref() != Some(tool.sha256.as_str()))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if !corrupt.is_empty() {
+        return Err(format!("missing or corrupt downloads in {}: {}", artifacts.display(), corrupt.join(", ")));
+    }
+    println!("Using the bundle at {} ({} download(s) verified)", dir.display(), index.tools.len());
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let _ = ACTIVE.set(ActiveBundle { dir, index });
+    Ok(())
+}
+
+/// The downloads folder of the bundle in use, if any.
+pub fn artifacts_dir() -> Option<PathBuf> {
+    ACTIVE.get().map(|bundle| bundle.dir.join(ARTIFACTS_DIR))
+}
+
+/// Resolves `key` from the bundle in use, `None` without one: bundled
+/// downloads at their recorded version, other entries as in offline mode.
+pub fn resolve(key: &str, spec: &SoftwareSpec, distro: &DistroInfo) -> Option<Result<ResolvedAsset, crate::error::Error>> {
+    let bundle = ACTIVE.get()?;
+    let artifacts = bundle.dir.join(ARTIFACTS_DIR);
+    Some(match bundle.index.tools.get(key) {
+        Some(tool) => Ok(ResolvedAsset {
+            version: tool.version.clone(),
+            url: artifacts.join(&tool.file).display().to_string(),
+            file_name: tool.file.clone(),
+            published_at: None,
+            size: Some(tool.size),
+            sha256: Some(tool.sha256.clone()),
+        }),
+        None => crate::resolver::resolve_offline(spec, distro, &artifacts),
+    })
+}
+
+fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+fn write_toml<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = toml::to_string_pretty(value).map_err(|e| format!("failed to serialize {}: {e}", path.display()))?;
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
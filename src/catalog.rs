@@ -4,20 +4,103 @@ use std::path::Path;
 
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::hardware::Hardware;
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct CatalogFile {
+    #[serde(default)]
+    pub settings: CatalogSettings,
+    #[serde(default)]
     pub software: BTreeMap<String, SoftwareSpec>,
 }
 
+/// Catalog-wide defaults that entries can override.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CatalogSettings {
+    pub step_timeout_secs: Option<u64>,
+    pub download_timeout_secs: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct SoftwareSpec {
     pub display_name: String,
     pub description: Option<String>,
+    /// Group the entry is listed under in the catalog view, e.g.
+    /// "Development" or "Browsers".
+    pub category: Option<String>,
+    /// Project page shown in the tool details.
+    pub homepage: Option<String>,
     pub enabled_by_default: bool,
     pub install_dir: Option<String>,
+    /// Leading path components dropped from every archive entry, e.g. 1 for
+    /// archives wrapped in a versioned `node-v20.1.0-linux-x64/` folder.
+    #[serde(default)]
+    pub strip_components: usize,
+    /// Directory under the install root that receives the extracted files.
+    pub extract_subdir: Option<String>,
     pub source: SourceSpec,
     #[serde(default)]
     pub setup_steps: Vec<SetupStep>,
+    /// Variables set for every shell and package step of the entry, e.g.
+    /// `CARGO_HOME` or `NONINTERACTIVE`. Values accept `~` and `{{ name }}`.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Longest a single command of this entry may run before it is killed.
+    pub step_timeout_secs: Option<u64>,
+    /// Longest the artifact download may take.
+    pub download_timeout_secs: Option<u64>,
+    /// Environments the entry is limited to, e.g. `["desktop"]` for GUI apps.
+    #[serde(default)]
+    pub only_on: Vec<EnvironmentTag>,
+    /// Environments the entry makes no sense in, e.g. `["wsl", "container"]`
+    /// for services that need systemd.
+    #[serde(default)]
+    pub skip_on: Vec<EnvironmentTag>,
+    /// CPU architectures the entry has builds for, spelled like Rust's
+    /// `std::env::consts::ARCH` (`x86_64`, `aarch64`, ...). Empty means all.
+    #[serde(default)]
+    pub supported_arch: Vec<String>,
+    /// The entry is a virtualization stack (QEMU, libvirt, ...) that needs
+    /// KVM; installing it warns first when KVM is unavailable.
+    #[serde(default)]
+    pub requires_kvm: bool,
+    /// How the tool is known to the OSV vulnerability database, for the
+    /// advisory check in the details. GitHub sources work without it.
+    pub osv: Option<OsvPackage>,
+    /// GPU vendor the entry is limited to (`nvidia`, `amd` or `intel`), for
+    /// drivers and vendor tools. Hidden on other machines.
+    #[serde(default)]
+    pub only_if_hardware: Option<Hardware>,
+}
+
+/// An OSV package identity, e.g. `{ ecosystem = "npm", name = "yarn" }`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OsvPackage {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+/// A trait of the machine that catalog entries can be limited to or
+/// excluded from.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentTag {
+    /// A graphical session is available.
+    Desktop,
+    Headless,
+    Wsl,
+    Container,
+}
+
+impl EnvironmentTag {
+    pub fn label(self) -> &'static str {
+        match self {
+            EnvironmentTag::Desktop => "desktop",
+            EnvironmentTag::Headless => "headless",
+            EnvironmentTag::Wsl => "wsl",
+            EnvironmentTag::Container => "container",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -28,12 +111,64 @@ pub enum SourceSpec {
         id: Option<String>,
         url: Option<String>, 
         version_regex: Option<String>, 
-        download_url_regex: Option<String> 
+        download_url_regex: Option<String>,
+        /// Captures the release date (`YYYY-MM-DD`) from the same page, for
+        /// the release age shown in the details.
+        #[serde(default)]
+        date_regex: Option<String>,
     },
     #[serde(rename = "package_manager")]
     PackageManager,
     #[serde(rename = "github")]
     Github { repo: Option<String>, asset_pattern: String },
+    /// Direct download from a predictable URL. The latest version is read
+    /// from `version_url` and substituted into `url_template`.
+    #[serde(rename = "templated")]
+    Templated {
+        version_url: String,
+        /// Captures the version from the `version_url` response; without a
+        /// group the whole match is used.
+        version_regex: String,
+        /// Download URL with `{version}` and the `{arch}` placeholders.
+        url_template: String,
+    },
+    /// Release metadata from a JSON endpoint, e.g. Node's `dist/index.json`.
+    /// Paths are JSON pointers (`/0/version`) whose segments may also be
+    /// `[key=value,...]` to pick the first array element with those fields.
+    #[serde(rename = "json_api")]
+    JsonApi {
+        url: String,
+        version_path: String,
+        /// Captures the version from the value at `version_path`, e.g. to
+        /// drop a `go` prefix; without a group the whole match is used.
+        #[serde(default)]
+        version_regex: Option<String>,
+        /// Download URL at this path, resolved against `url` when relative.
+        #[serde(default)]
+        download_path: Option<String>,
+        /// Used instead of `download_path`, like a templated source's.
+        #[serde(default)]
+        url_template: Option<String>,
+        #[serde(default)]
+        date_path: Option<String>,
+    },
+    /// Built from a git checkout, for tools without a usable binary release.
+    /// `repo` is a GitHub `owner/name` or any git URL.
+    #[serde(rename = "build_from_source")]
+    BuildFromSource {
+        repo: String,
+        /// Tag to build instead of the latest release (GitHub) or the highest
+        /// version tag (other remotes).
+        #[serde(default)]
+        tag: Option<String>,
+        /// Shell commands run in order inside the checkout, with `$PREFIX`
+        /// set to the install root.
+        build_steps: Vec<String>,
+    },
+    /// Configuration-only entry: nothing to resolve or download, only the
+    /// setup steps run.
+    #[serde(rename = "none")]
+    None,
 }
 
 impl SourceSpec {
@@ -42,23 +177,281 @@ impl SourceSpec {
             SourceSpec::OfficialSource { .. } => "official_source",
             SourceSpec::PackageManager => "package_manager",
             SourceSpec::Github { .. } => "github",
+            SourceSpec::Templated { .. } => "templated",
+            SourceSpec::JsonApi { .. } => "json_api",
+            SourceSpec::BuildFromSource { .. } => "build_from_source",
+            SourceSpec::None => "none",
         }
     }
+
+    /// Whether the entry downloads an artifact into its install root.
+    pub fn has_artifact(&self) -> bool {
+        matches!(self, SourceSpec::OfficialSource { .. } | SourceSpec::Github { .. } | SourceSpec::Templated { .. } | SourceSpec::JsonApi { .. })
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum SetupStep {
-    Package { packages: Vec<String> },
+    Package {
+        #[serde(default)]
+        packages: Vec<String>,
+        /// winget/scoop identifiers, used instead of `packages` on Windows.
+        #[serde(default)]
+        windows_packages: Vec<String>,
+        /// Package names that differ by distribution, keyed by distro ID
+        /// (`ubuntu`, `fedora`, ...) or package manager (`apt`, `dnf`, ...);
+        /// the distro ID wins. Distributions not listed use `packages`.
+        #[serde(default)]
+        distro_packages: BTreeMap<String, Vec<String>>,
+        /// GPU vendor the step is limited to, e.g. driver packages.
+        #[serde(default)]
+        only_if_hardware: Option<Hardware>,
+    },
     PathHint { value: String },
     Note { value: String },
-    Shell { command: String },
+    Shell {
+        command: String,
+        /// Run the command as root. Every other shell step runs as the user.
+        #[serde(default)]
+        requires_root: bool,
+        /// Exempt the step from sandbox mode.
+        #[serde(default)]
+        trusted: bool,
+        /// Runs first; the step is skipped when it exits 0.
+        #[serde(default)]
+        check_command: Option<String>,
+    },
+    /// Asks for a secret once, keeps it in the system keyring and exposes it
+    /// to the entry's later steps as `$env`.
+    Secret {
+        name: String,
+        env: String,
+        prompt: Option<String>,
+    },
+    /// Renders `source` (relative to the catalog) into `dest`, replacing
+    /// `{{ name }}` with the built-in variables and `vars`.
+    Template {
+        source: String,
+        dest: String,
+        #[serde(default)]
+        vars: BTreeMap<String, String>,
+    },
+    /// Creates directories up front, owned by the invoking user even when
+    /// running under sudo.
+    EnsureDirs { paths: Vec<String> },
+    /// Copies `from` (relative to the catalog) to `to`, optionally setting an
+    /// octal `mode` such as "0755". Both paths accept `~` and `{{ name }}`
+    /// variables.
+    CopyFile {
+        from: String,
+        to: String,
+        mode: Option<String>,
+        #[serde(default)]
+        requires_root: bool,
+    },
+    /// `git config` setting, only written when the current value differs.
+    GitConfig {
+        key: String,
+        value: String,
+        #[serde(default)]
+        scope: GitScope,
+    },
+    /// Sets up zram or a swapfile. `size_mb` defaults to a size picked from
+    /// the installed memory. Linux only.
+    Swap {
+        #[serde(default)]
+        method: SwapMethod,
+        size_mb: Option<u64>,
+    },
+    /// `cargo install` into the user's cargo home.
+    Cargo { crates: Vec<String> },
+    /// npm packages, into `~/.local` with `global = true` and the install
+    /// root otherwise.
+    Npm {
+        packages: Vec<String>,
+        #[serde(default)]
+        global: bool,
+    },
+    /// Python applications, each in its own pipx virtualenv.
+    Pipx { packages: Vec<String> },
+    /// Installs runtime versions through rustup, nvm or pyenv, bootstrapping
+    /// the manager itself when missing. Linux only.
+    VersionManager {
+        manager: VersionManager,
+        versions: Vec<String>,
+        /// Version made the default, e.g. `rustup default` or `nvm alias
+        /// default`.
+        default: Option<String>,
+    },
+    /// Writes sysctl values to /etc/sysctl.d and applies them. `values` are
+    /// layered over defaults picked from the installed memory. Linux only.
+    Sysctl {
+        #[serde(default)]
+        values: BTreeMap<String, String>,
+    },
+}
+
+impl SetupStep {
+    /// The packages a package step installs on distro `distro_id` with
+    /// `manager`; empty for other steps and when its hardware is missing.
+    pub fn packages_for(&self, distro_id: &str, manager: &str) -> &[String] {
+        let SetupStep::Package { packages, windows_packages, distro_packages, only_if_hardware } = self else { return &[] };
+        if only_if_hardware.is_some_and(|hardware| !crate::hardware::present(hardware)) {
+            return &[];
+        }
+        match distro_packages.get(distro_id).or_else(|| distro_packages.get(manager)) {
+            Some(packages) => packages,
+            None => platform_packages(packages, windows_packages),
+        }
+    }
+
+    /// Whether the step changes the system outside the user's home and so
+    /// has to run with root privileges.
+    pub fn requires_root(&self) -> bool {
+        match self {
+            SetupStep::Package { only_if_hardware, .. } => {
+                crate::platform::Platform::current().uses_sudo() && only_if_hardware.is_none_or(crate::hardware::present)
+            }
+            // Sandbox mode skips untrusted root steps instead of elevating them.
+            SetupStep::Shell { requires_root, trusted, .. } => *requires_root && (*trusted || !crate::sandbox::enabled()),
+            SetupStep::GitConfig { scope, .. } => *scope == GitScope::System,
+            SetupStep::CopyFile { requires_root, .. } => *requires_root,
+            SetupStep::Swap { .. } | SetupStep::Sysctl { .. } => crate::platform::Platform::current().uses_sudo(),
+            SetupStep::PathHint { .. }
+            | SetupStep::Note { .. }
+            | SetupStep::Secret { .. }
+            | SetupStep::Template { .. }
+            | SetupStep::EnsureDirs { .. }
+            | SetupStep::Cargo { .. }
+            | SetupStep::Npm { .. }
+            | SetupStep::Pipx { .. }
+            | SetupStep::VersionManager { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitScope {
+    #[default]
+    Global,
+    System,
+}
+
+impl GitScope {
+    pub fn flag(self) -> &'static str {
+        match self {
+            GitScope::Global => "--global",
+            GitScope::System => "--system",
+        }
+    }
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapMethod {
+    /// Compressed swap in RAM through systemd's zram-generator.
+    #[default]
+    Zram,
+    Swapfile,
+}
+
+impl SwapMethod {
+    pub fn label(self) -> &'static str {
+        match self {
+            SwapMethod::Zram => "zram",
+            SwapMethod::Swapfile => "swapfile",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionManager {
+    Rustup,
+    Nvm,
+    Pyenv,
+}
+
+impl VersionManager {
+    pub fn label(self) -> &'static str {
+        match self {
+            VersionManager::Rustup => "rustup",
+            VersionManager::Nvm => "nvm",
+            VersionManager::Pyenv => "pyenv",
+        }
+    }
+}
+
+/// Picks the package names for the running platform, falling back to the
+/// Linux names when a step has no Windows mapping.
+pub fn platform_packages<'a>(packages: &'a [String], windows_packages: &'a [String]) -> &'a [String] {
+    if crate::platform::Platform::current() == crate::platform::Platform::Windows && !windows_packages.is_empty() {
+        windows_packages
+    } else {
+        packages
+    }
+}
+
+/// Reads and parses a catalog file.
 pub fn load_catalog(path: &Path) -> Result<CatalogFile, String> {
     let content = fs::read_to_string(path)
         .map_err(|e| format!("failed to read catalog at {}: {e}", path.display()))?;
-    let parsed: CatalogFile = toml::from_str(&content)
-        .map_err(|e| format!("failed to parse catalog at {}: {e}", path.display()))?;
+    parse_catalog(&content).map_err(|e| format!("failed to parse catalog at {}: {e}", path.display()))
+}
+
+/// Parses catalog TOML, filling per-entry timeouts from `[settings]`.
+pub fn parse_catalog(content: &str) -> Result<CatalogFile, toml::de::Error> {
+    let mut parsed: CatalogFile = toml::from_str(content)?;
+    for spec in parsed.software.values_mut() {
+        spec.step_timeout_secs = spec.step_timeout_secs.or(parsed.settings.step_timeout_secs);
+        spec.download_timeout_secs = spec.download_timeout_secs.or(parsed.settings.download_timeout_secs);
+    }
     Ok(parsed)
-}
\ No newline at end of file
+}
+
+/// The line, from 1, of the `[software.<key>]` table in catalog TOML.
+pub fn entry_line(content: &str, key: &str) -> Option<usize> {
+    let headers = [format!("[software.{key}]"), format!("[software.\"{key}\"]"), format!("[software.'{key}']")];
+    content
+        .lines()
+        .position(|line| {
+            let line = line.split('#').next().unwrap_or_default().replace(' ', "");
+            headers.contains(&line)
+        })
+        .map(|index| index + 1)
+}
+
+pub const BUNDLED_CATALOG: &str = include_str!("../software_catalog.toml");
+
+pub struct StarterProfile {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub category: Option<&'static str>,
+    pub empty: bool,
+}
+
+pub const STARTER_PROFILES: &[StarterProfile] = &[
+    StarterProfile { name: "Full starter catalog", description: "Every bundled entry: SDKs, browsers, media and utilities", category: None, empty: false },
+    StarterProfile { name: "Developer essentials", description: "Only the bundled Development entries", category: Some("Development"), empty: false },
+    StarterProfile { name: "Empty catalog", description: "Start from scratch and add your own entries", category: None, empty: true },
+];
+
+/// Renders the bundled catalog for a starter profile, keeping only entries of
+/// the profile's category when it has one.
+pub fn starter_catalog(profile: &StarterProfile) -> Result<String, String> {
+    if profile.empty {
+        return Ok("# Add entries as [software.<key>] tables, see README.md\n".to_string());
+    }
+    let Some(category) = profile.category else {
+        return Ok(BUNDLED_CATALOG.to_string());
+    };
+
+    let mut value: toml::Table = toml::from_str(BUNDLED_CATALOG)
+        .map_err(|e| format!("failed to parse bundled catalog: {e}"))?;
+    if let Some(toml::Value::Table(software)) = value.get_mut("software") {
+        software.retain(|_, entry| entry.get("category").and_then(|c| c.as_str()) == Some(category));
+    }
+    toml::to_string_pretty(&value).map_err(|e| format!("failed to render starter catalog: {e}"))
+}
@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::catalog::{parse_catalog, CatalogFile};
+
+/// One side of the diff: the raw entry tables, for field-level comparison,
+/// and the parsed catalog, for the install plan.
+struct Side {
+    label: String,
+    entries: toml::Table,
+    catalog: CatalogFile,
+}
+
+/// Compares two catalogs and how the difference changes what would be
+/// installed: the entries in `manifest` when given, otherwise the ones
+/// enabled by default that suit this machine and aren't hidden. `old` and
+/// `new` are files, or git revisions (`HEAD~1`, `origin/main:catalog.toml`)
+/// read from the repository of the configured catalog.
+pub fn catalog_diff(old: &str, new: &str, manifest: Option<&Path>) -> Result<(), String> {
+    let old = load_side(old)?;
+    let new = load_side(new)?;
+
+    let added: Vec<&String> = new.entries.keys().filter(|k| !old.entries.contains_key(*k)).collect();
+    let removed: Vec<&String> = old.entries.keys().filter(|k| !new.entries.contains_key(*k)).collect();
+    let changed: BTreeMap<&String, Vec<String>> = old
+        .entries
+        .iter()
+        .filter_map(|(key, before)| {
+            let fields = changed_fields("", before, new.entries.get(key)?);
+            (!fields.is_empty()).then_some((key, fields))
+        })
+        .collect();
+
+    println!("Old: {}", old.label);
+    println!("New: {}", new.label);
+    println!();
+    println!("Added ({}):", added.len());
+    for key in &added {
+        println!("  + {:<20} {}", key, display_name(&new.catalog, key));
+    }
+    println!("Removed ({}):", removed.len());
+    for key in &removed {
+        println!("  - {:<20} {}", key, display_name(&old.catalog, key));
+    }
+    println!("Changed ({}):", changed.len());
+    for (key, fields) in &changed {
+        println!("  ~ {:<20} {}", key, fields.join(", "));
+    }
+
+    let wanted = match manifest {
+        Some(path) => Some(crate::manifest::load_manifest(path)?.tools.into_keys().collect::<BTreeSet<String>>()),
+        None => None,
+    };
+    let before = plan(&old.catalog, wanted.as_ref());
+    let after = plan(&new.catalog, wanted.as_ref());
+    println!();
+    match manifest {
+        Some(path) => println!("Install plan for {}:", path.display()),
+        None => println!("Install plan (entries enabled by default on this machine):"),
+    }
+    let mut unchanged = 0;
+    for key in before.union(&after) {
+        match (before.contains(key), after.contains(key)) {
+            (false, true) => println!("  + {key} would now be installed{}", why_joined(&old.catalog, key)),
+            (true, false) => println!("  - {key} would no longer be installed{}", why_left(&new.catalog, key, wanted.as_ref())),
+            _ => match changed.get(key) {
+                Some(fields) => println!("  ~ {key} would install differently: {}", fields.join(", ")),
+                None => unchanged += 1,
+            },
+        }
+    }
+    if let Some(wanted) = &wanted {
+        for key in wanted.iter().filter(|k| !new.catalog.software.contains_key(*k) && !old.catalog.software.contains_key(*k)) {
+            println!("  ! {key} is in the manifest but in neither catalog");
+        }
+    }
+    println!("{unchanged} planned entr{} unchanged.", if unchanged == 1 { "y" } else { "ies" });
+    Ok(())
+}
+
+fn load_side(spec: &str) -> Result<Side, String> {
+    let (label, content) = if Path::new(spec).is_file() {
+        let content = std::fs::read_to_string(spec).map_err(|e| format!("failed to read {spec}: {e}"))?;
+        (spec.to_string(), content)
+    } else {
+        git_show(spec)?
+    };
+    let table: toml::Table = toml::from_str(&content).map_err(|e| format!("failed to parse {label}: {e}"))?;
+    let entries = match table.get("software") {
+        Some(toml::Value::Table(software)) => software.clone(),
+        _ => toml::Table::new(),
+    };
+    let catalog = parse_catalog(&content).map_err(|e| format!("failed to parse {label}: {e}"))?;
+    Ok(Side { label, entries, catalog })
+}
+
+/// Reads a catalog from git: `rev:path` as given, or a bare revision of the
+/// configured catalog file.
+fn git_show(spec: &str) -> Result<(String, String), String> {
+    let catalog = crate::config::require_catalog()?;
+    let dir = catalog.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let object = if spec.contains(':') {
+        spec.to_string()
+    } else {
+        let name = catalog.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        format!("{spec}:./{name}")
+    };
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["show", &object])
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{spec} is neither a file nor a git revision: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok((format!("{object} (git)"), String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Dotted names of the fields that differ, descending into tables such as
+/// `source`.
+fn changed_fields(prefix: &str, before: &toml::Value, after: &toml::Value) -> Vec<String> {
+    match (before, after) {
+        (toml::Value::Table(a), toml::Value::Table(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            keys.into_iter()
+                .flat_map(|key| {
+                    let name = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                    match (a.get(key), b.get(key)) {
+                        (Some(x), Some(y)) => changed_fields(&name, x, y),
+                        _ => vec![name],
+                    }
+                })
+                .collect()
+        }
+        _ if before == after => Vec::new(),
+        _ => vec![prefix.to_string()],
+    }
+}
+
+/// Keys that would be installed from `catalog`.
+fn plan(catalog: &CatalogFile, wanted: Option<&BTreeSet<String>>) -> BTreeSet<String> {
+    let hidden = crate::config::current().hidden;
+    let environment = crate::distro::Environment::current();
+    catalog
+        .software
+        .iter()
+        .filter(|(key, spec)| match wanted {
+            Some(wanted) => wanted.contains(*key),
+            None => spec.enabled_by_default && !hidden.contains(key) && environment.incompatibility(spec).is_none(),
+        })
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+fn display_name(catalog: &CatalogFile, key: &str) -> String {
+    catalog.software.get(key).map(|spec| spec.display_name.clone()).unwrap_or_default()
+}
+
+fn why_joined(old: &CatalogFile, key: &str) -> &'static str {
+    if old.software.contains_key(key) { " (now enabled by default or suits this machine)" } else { " (new entry)" }
+}
+
+fn why_left(new: &CatalogFile, key: &str, wanted: Option<&BTreeSet<String>>) -> String {
+    match new.software.get(key) {
+        None => " (removed from the catalog)".to_string(),
+        Some(_) if wanted.is_some() => String::new(),
+        Some(spec) if !spec.enabled_by_default => " (no longer enabled by default)".to_string(),
+        Some(spec) => crate::distro::Environment::current().incompatibility(spec).map(|why| format!(" ({why})")).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_fields_are_named_by_path() {
+        let before: toml::Value = toml::from_str(
+            r#"
+            display_name = "Ripgrep"
+            category = "Search"
+            source = { type = "github", repo = "BurntSushi/ripgrep", asset_pattern = "x86_64.*musl" }
+            "#,
+        )
+        .unwrap();
+        let after: toml::Value = toml::from_str(
+            r#"
+            display_name = "Ripgrep"
+            description = "Fast grep"
+            source = { type = "github", repo = "BurntSushi/ripgrep", asset_pattern = "{xarch}.*musl" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(changed_fields("", &before, &after), ["category", "description", "source.asset_pattern"]);
+        assert!(changed_fields("", &before, &before).is_empty());
+        assert_eq!(changed_fields("version", &toml::Value::from(1), &toml::Value::from("1")), ["version"]);
+    }
+}
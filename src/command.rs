@@ -0,0 +1,299 @@
+use std::fmt;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{Error, Result};
+use crate::platform::Platform;
+
+/// A program and its arguments. It is spawned directly rather than through a
+/// shell, so package names and paths taken from the catalog or a download can
+/// never be read as shell syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandLine {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Variables set for the child, on top of the inherited environment
+    /// unless `clear_env` is set.
+    pub env: Vec<(String, String)>,
+    pub clear_env: bool,
+    /// A failure does not stop the script, like `;` rather than `&&` in a
+    /// shell.
+    pub keep_going: bool,
+}
+
+impl CommandLine {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self { program: program.into(), args: Vec::new(), env: Vec::new(), clear_env: false, keep_going: false }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Lets the script go on when this command fails.
+    pub fn keep_going(mut self) -> Self {
+        self.keep_going = true;
+        self
+    }
+
+    /// Runs the command through sudo on platforms that use it.
+    pub fn elevated(self) -> Self {
+        if Platform::current().uses_sudo() {
+            CommandLine { program: "sudo".to_string(), args: [vec![self.program], self.args].concat(), ..self }
+        } else {
+            self
+        }
+    }
+
+    /// Lets the named variables through when the command runs via sudo,
+    /// which otherwise resets the environment.
+    pub fn keep_env(mut self, names: &[String]) -> Self {
+        if self.program == "sudo" && !names.is_empty() {
+            self.args.insert(0, format!("--preserve-env={}", names.join(",")));
+        }
+        self
+    }
+
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        cmd.args(&self.args).envs(self.env.iter().map(|(k, v)| (k, v)));
+        cmd
+    }
+}
+
+/// Renders the command the way a user would type it, quoting arguments that
+/// a shell would otherwise split or expand.
+impl fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", quote(&self.program))?;
+        for arg in &self.args {
+            write!(f, " {}", quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes `s` for a POSIX shell, leaving plain words untouched.
+pub fn quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%~".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Commands run one after another, stopping at the first failure of one
+/// not marked [`CommandLine::keep_going`].
+pub type Script = Vec<CommandLine>;
+
+pub fn display_script(script: &[CommandLine]) -> String {
+    let mut shown = String::new();
+    for (i, cmd) in script.iter().enumerate() {
+        if i > 0 {
+            shown.push_str(if script[i - 1].keep_going { " ; " } else { " && " });
+        }
+        shown.push_str(&cmd.to_string());
+    }
+    shown
+}
+
+/// Runs each command of `script` in turn, stopping at the first failure of
+/// one that does not keep going, and returns the last exit status.
+pub(crate) fn run_script(
+    script: &[CommandLine],
+    envs: &[(String, String)],
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<std::process::ExitStatus> {
+    let mut last = None;
+    for cmd in script {
+        let status = run_piped(cmd, envs, tx, cancel, timeout)?;
+        if !status.success() && !cmd.keep_going {
+            return Ok(status);
+        }
+        last = Some(status);
+    }
+    last.ok_or_else(|| Error::Other("nothing to run".to_string()))
+}
+
+pub(crate) fn run_piped(
+    cmd: &CommandLine,
+    envs: &[(String, String)],
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<std::process::ExitStatus> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let mut command = cmd.to_command();
+    // Nothing is ever typed into it: in its own process group a child that
+    // read the terminal would be stopped by SIGTTIN, and the TUI reads the
+    // keys.
+    command
+        .envs(envs.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        // Own process group, so a timeout can take down everything it spawned.
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| Error::ChildProcess(format!("failed to spawn {}: {e}", cmd.program)))?;
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let (pipe_tx, pipe_rx) = std::sync::mpsc::channel();
+
+    let tx_stdout = pipe_tx.clone();
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stdout);
+        for line in reader.lines().map_while(Result::ok) {
+            let _ = tx_stdout.send((crate::events::LogLevel::Info, line));
+        }
+    });
+
+    let tx_stderr = pipe_tx;
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stderr);
+        for line in reader.lines().map_while(Result::ok) {
+            let _ = tx_stderr.send((crate::events::LogLevel::Warn, format!("[stderr] {}", line)));
+        }
+    });
+
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut pipes_open = true;
+    loop {
+        if pipes_open {
+            match pipe_rx.recv_timeout(Duration::from_millis(200)) {
+                Ok((level, line)) => {
+                    let _ = tx.send(crate::events::InstallMsg::Log(level, line));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => pipes_open = false,
+            }
+        } else {
+            if let Some(status) = child.try_wait().map_err(|e| Error::ChildProcess(format!("failed to wait for child: {e}")))? {
+                return Ok(status);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if cancel.is_cancelled() {
+            stop(&mut child, cmd, tx);
+            return Err(Error::Cancelled("Operation"));
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            stop(&mut child, cmd, tx);
+            return Err(Error::ChildProcess(format!("{} timed out after {}s", cmd.program, timeout.unwrap_or_default().as_secs())));
+        }
+    }
+}
+
+/// Terminates a cancelled or timed-out command, warning when it would not
+/// stop.
+fn stop(child: &mut std::process::Child, cmd: &CommandLine, tx: &mpsc::Sender<crate::events::InstallMsg>) {
+    if !terminate_process_group(child) {
+        let _ = tx.send(crate::events::InstallMsg::Log(
+            crate::events::LogLevel::Warn,
+            format!("{} (pid {}) did not stop and is left running; end it by hand", cmd.program, child.id()),
+        ));
+    }
+}
+
+/// How long a cancelled or timed-out command gets to exit after SIGTERM
+/// before its process group is killed.
+const TERMINATE_GRACE: Duration = Duration::from_secs(5);
+
+/// Stops `child` and everything in the process group it leads. SIGTERM goes
+/// first so that sudo can relay it and apt/dpkg can release their locks.
+/// Whatever is still running after [`TERMINATE_GRACE`] is killed. A group
+/// that runs under sudo belongs to root, so the signals go through
+/// `sudo -n kill` when ours are refused. False when the command is still
+/// running after a second grace period; it is left to itself rather than
+/// waited for.
+fn terminate_process_group(child: &mut std::process::Child) -> bool {
+    #[cfg(unix)]
+    {
+        signal_process_group(child.id(), "-TERM");
+        if wait_until_exit(child, TERMINATE_GRACE) {
+            // Also catches grandchildren that outlived the group leader.
+            signal_process_group(child.id(), "-KILL");
+            return true;
+        }
+        signal_process_group(child.id(), "-KILL");
+    }
+    #[cfg(windows)]
+    let _ = std::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .output();
+    let _ = child.kill();
+    wait_until_exit(child, TERMINATE_GRACE)
+}
+
+/// Whether `child` exits within `grace`.
+fn wait_until_exit(child: &mut std::process::Child, grace: Duration) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < grace {
+        if !matches!(child.try_wait(), Ok(None)) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[cfg(unix)]
+fn signal_process_group(pgid: u32, signal: &str) {
+    let group = format!("-{pgid}");
+    let sent = std::process::Command::new("kill")
+        .args([signal, "--", &group])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !sent {
+        let _ = std::process::Command::new("sudo")
+            .args(["-n", "kill", signal, "--", &group])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn piped_commands_do_not_wait_for_input() {
+        let (tx, _logs) = mpsc::channel();
+        let cmd = CommandLine::new("sh").args(["-c", "read x; echo done"]);
+        let started = Instant::now();
+        let status = run_piped(&cmd, &[], &tx, &CancellationToken::new(), Some(Duration::from_secs(20))).unwrap();
+        assert!(status.success());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+pub const CATALOG_FILE_NAME: &str = "software_catalog.toml";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub catalog_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_dir: Option<String>,
+    /// Whether the TUI starts in dry-run mode; on unless `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    /// Network lookups (resolutions, download checks) run at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// Artifacts downloaded ahead while earlier tools install; 0 turns it
+    /// off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_downloads: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    /// HTTP(S) proxy for every request, e.g. `http://proxy:3128`. The
+    /// `HTTPS_PROXY` environment variables are used when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// URL probed at startup to detect being offline or behind a captive
+    /// portal; it must answer `204 No Content`. Empty turns the probe off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connectivity_url: Option<String>,
+    /// Expected extracted size as a multiple of the download, used by the
+    /// disk space preflight check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extract_multiplier: Option<f64>,
+    /// Total download size in MB above which the confirmation screen and
+    /// catalog title warn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_warn_mb: Option<u64>,
+    /// Minutes a resolved version is reused before asking the network
+    /// again; 0 always resolves afresh.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve_cache_ttl_mins: Option<u64>,
+    /// Sessions in a row an entry has to fail in before the maintenance
+    /// view marks it as needing attention.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attention_after_sessions: Option<u32>,
+    /// GPU vendors used instead of the detected ones for `only_if_hardware`,
+    /// e.g. `["nvidia"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hardware: Option<Vec<crate::hardware::Hardware>>,
+    /// File data in MB per backup archive before a folder continues in a
+    /// new volume (`<folder>.2.zip`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_archive_mb: Option<u64>,
+    /// Archive format of `backup`: `zip` (the default, readable by Rusty
+    /// Sync) or `zstd` for tar.zst.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_format: Option<crate::restorer::BackupFormat>,
+    /// Zstandard level of tar.zst backups, up to 22: higher is smaller but
+    /// slower. 3 when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_zstd_level: Option<i32>,
+    /// Run catalog shell steps not marked `trusted` inside a sandbox.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<bool>,
+    /// Hold downloads of at least `battery_threshold_mb` until AC power is
+    /// back instead of only warning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defer_on_battery: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_threshold_mb: Option<u64>,
+    /// CPU usage in percent that, sustained, slows extraction down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttle_cpu_percent: Option<f32>,
+    /// Distro ID used instead of the one in /etc/os-release.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distro: Option<String>,
+    /// Package manager used instead of the detected one: apt, dnf, pacman,
+    /// zypper, winget or scoop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pkg_manager: Option<String>,
+    /// Query OSV for known vulnerabilities whenever a details popup opens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_advisories: Option<bool>,
+    /// Desktop notification when a run finishes; on unless `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_on_finish: Option<bool>,
+    /// Desktop notification for each tool that fails; on unless `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_on_failure: Option<bool>,
+    /// Catalog keys pinned to the top of the command palette.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub favorites: Vec<String>,
+    /// Catalog keys left out of the catalog list and of select-all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<String>,
+    /// Sizes in KiB/MiB (`binary`, the default) or kB/MB (`decimal`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_units: Option<crate::units::SizeUnits>,
+    /// Locale whose decimal mark and digit grouping numbers use, e.g.
+    /// `de_DE`, instead of the one from `LC_NUMERIC` or `LANG`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_locale: Option<String>,
+}
+
+/// Colours the TUI draws with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Default,
+    /// Terminal default colours only, keeping bold and italics.
+    Monochrome,
+}
+
+impl Theme {
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Monochrome => "monochrome",
+        }
+    }
+}
+
+pub fn config_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|d| d.join("rusty_rebase"))
+        .ok_or_else(|| "config directory not found".to_string())
+}
+
+pub fn config_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+/// Loads the user config, returning `None` when it has not been written yet.
+pub fn load_config() -> Result<Option<Config>, String> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read config at {}: {e}", path.display()))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("failed to parse config at {}: {e}", path.display()))
+}
+
+/// The config [`current`] hands out, read on first use and replaced by
+/// [`save_config`].
+static CURRENT: Mutex<Option<Config>> = Mutex::new(None);
+
+/// The user config, falling back to defaults when missing or unreadable.
+/// The file is read once per process, so this is cheap enough for every
+/// frame; changes made through [`save_config`] show up straight away.
+pub fn current() -> Config {
+    read(Config::clone)
+}
+
+/// Runs `f` on the cached config without copying it, for checks made on
+/// every frame.
+pub fn read<T>(f: impl FnOnce(&Config) -> T) -> T {
+    let mut cached = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    f(cached.get_or_insert_with(|| load_config().ok().flatten().unwrap_or_default()))
+}
+
+pub fn save_config(config: &Config) -> Result<PathBuf, String> {
+    let path = config_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    let content = toml::to_string_pretty(config).map_err(|e| format!("failed to serialize config: {e}"))?;
+    fs::write(&path, content).map_err(|e| format!("failed to write config at {}: {e}", path.display()))?;
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(config.clone());
+    Ok(path)
+}
+
+/// Finds the catalog to use: one in the working directory wins, otherwise the
+/// path recorded in the user config.
+pub fn find_catalog() -> Option<PathBuf> {
+    let local = std::env::current_dir().ok()?.join(CATALOG_FILE_NAME);
+    if local.exists() {
+        return Some(local);
+    }
+    let configured = current().catalog_path?;
+    let path = crate::installer::expand_tilde(&configured).ok()?;
+    path.exists().then_some(path)
+}
+
+pub fn require_catalog() -> Result<PathBuf, String> {
+    find_catalog().ok_or_else(|| {
+        format!("no {CATALOG_FILE_NAME} in the current directory or config; run rusty_rebase once to set one up")
+    })
+}
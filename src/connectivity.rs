@@ -0,0 +1,118 @@
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use reqwest::Client;
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+
+/// Answers `204 No Content` when the internet is reachable. A portal that
+/// intercepts it answers with a redirect or its sign-in page instead.
+pub const DEFAULT_PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Public resolvers, dialled by address to tell a broken DNS from no
+/// network at all.
+const FALLBACK_ADDRS: [&str; 2] = ["1.1.1.1:443", "8.8.8.8:53"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Connectivity {
+    Online,
+    /// Nothing answers, by name or by address.
+    Offline(String),
+    /// Addresses answer but names do not resolve.
+    DnsFailure(String),
+    /// The probe was answered by something else, with where it redirected.
+    CaptivePortal(Option<String>),
+}
+
+impl Connectivity {
+    /// A short line for the header, `None` when online.
+    pub fn banner(&self) -> Option<String> {
+        match self {
+            Connectivity::Online => None,
+            Connectivity::Offline(_) => Some("OFFLINE: no network connection".to_string()),
+            Connectivity::DnsFailure(_) => Some("DNS FAILURE: host names do not resolve".to_string()),
+            Connectivity::CaptivePortal(Some(url)) => Some(format!("CAPTIVE PORTAL: sign in at {url}")),
+            Connectivity::CaptivePortal(None) => Some("CAPTIVE PORTAL: sign in to the network in a browser".to_string()),
+        }
+    }
+
+    /// The error behind an offline or DNS result, for the log.
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            Connectivity::Offline(reason) | Connectivity::DnsFailure(reason) => Some(reason),
+            Connectivity::Online | Connectivity::CaptivePortal(_) => None,
+        }
+    }
+}
+
+/// The probe URL from `connectivity_url` in the config; `None` when it is
+/// set to an empty string to turn the check off.
+pub fn probe_url() -> Option<String> {
+    match crate::config::current().connectivity_url {
+        Some(url) if url.trim().is_empty() => None,
+        Some(url) => Some(url.trim().to_string()),
+        None => Some(DEFAULT_PROBE_URL.to_string()),
+    }
+}
+
+/// Requests `url` without following redirects: a 204 or an empty success
+/// means online, anything else that answers is a portal. Failures are told
+/// apart by resolving the host and dialling well-known addresses.
+pub fn probe(url: &str) -> Connectivity {
+    let proxy = crate::config::current().proxy.filter(|p| !p.trim().is_empty());
+    // Through a proxy, names are resolved by the proxy rather than here.
+    if proxy.is_none()
+        && let Some(reason) = dns_failure(url)
+    {
+        return if reachable() { Connectivity::DnsFailure(reason) } else { Connectivity::Offline(reason) };
+    }
+
+    let mut builder = Client::builder().timeout(PROBE_TIMEOUT).redirect(Policy::none()).user_agent("rusty_rebase/0.1");
+    if let Some(proxy) = proxy {
+        match crate::resolver::parse_proxy(&proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(e) => return Connectivity::Offline(e.to_string()),
+        }
+    }
+    let client = match builder.build() {
+        Ok(client) => client,
+        Err(e) => return Connectivity::Offline(e.to_string()),
+    };
+    crate::runtime::block_on(async {
+        let response = match client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() => return Connectivity::Offline("probe timed out".to_string()),
+            Err(e) => return Connectivity::Offline(e.to_string()),
+        };
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response.headers().get(LOCATION).and_then(|v| v.to_str().ok()).map(str::to_string);
+            return Connectivity::CaptivePortal(location);
+        }
+        match response.bytes().await {
+            Ok(body) if status.as_u16() == 204 || (status.is_success() && body.is_empty()) => Connectivity::Online,
+            _ => Connectivity::CaptivePortal(None),
+        }
+    })
+}
+
+/// Why the probe host does not resolve, if it does not.
+fn dns_failure(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    match (host, port).to_socket_addrs().map(|mut addrs| addrs.next()) {
+        Ok(Some(_)) => None,
+        Ok(None) => Some(format!("{host} has no addresses")),
+        Err(e) => Some(format!("cannot resolve {host}: {e}")),
+    }
+}
+
+fn reachable() -> bool {
+    FALLBACK_ADDRS
+        .iter()
+        .filter_map(|addr| addr.parse::<SocketAddr>().ok())
+        .any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+}
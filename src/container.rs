@@ -0,0 +1,34 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Login shells in the image pick PATH additions up from here instead of a
+/// user's profile.
+pub const PROFILE_SCRIPT: &str = "/etc/profile.d/rusty_rebase.sh";
+
+/// Set by the `container` command: everything runs as the current (root)
+/// user without sudo, and PATH hints go to [`PROFILE_SCRIPT`].
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Provisions a container image from `manifest_path` and its lockfile: no
+/// prompts, no sudo, and only the versions recorded in the lockfile.
+pub fn provision(manifest_path: &Path, dry_run: bool) -> Result<(), String> {
+    if crate::platform::Platform::current() != crate::platform::Platform::Linux {
+        return Err("container mode is only supported on Linux".to_string());
+    }
+    if !dry_run && !running_as_root() {
+        return Err("container mode expects to run as root (e.g. in a Dockerfile RUN step)".to_string());
+    }
+    ACTIVE.store(true, Ordering::Relaxed);
+    crate::manifest::apply_manifest(manifest_path, dry_run, true, true, None)
+}
+
+fn running_as_root() -> bool {
+    std::process::Command::new("id")
+        .arg("-u")
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "0")
+}
@@ -0,0 +1,147 @@
+//! The `desktop` backup group: the dconf database GNOME (and Cinnamon,
+//! MATE, Budgie) keep their settings in, and KDE Plasma's config files,
+//! restored into the home directory.
+
+use std::fs::{self, File};
+use std::path::{Component, Path};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+
+use crate::backup::{Archive, ArchiveSettings};
+use crate::error::{Error, Result};
+use crate::events::InstallMsg;
+use crate::restorer::{report, BackupGroup};
+
+pub const GROUP: &str = "desktop";
+
+const ARCHIVE: &str = "desktop";
+const DCONF_DUMP: &str = "desktop-dconf.ini";
+
+/// Plasma's files in ~/.config: look and feel, panels and widgets,
+/// shortcuts, input devices, KWin and the core KDE apps.
+const KDE_CONFIGS: &[&str] = &[
+    "kdeglobals",
+    "kwinrc",
+    "kwinrulesrc",
+    "plasmarc",
+    "plasmashellrc",
+    "plasma-org.kde.plasma.desktop-appletsrc",
+    "plasma-localerc",
+    "kglobalshortcutsrc",
+    "khotkeysrc",
+    "kcminputrc",
+    "kxkbrc",
+    "touchpadxlibinputrc",
+    "kscreenlockerrc",
+    "ksmserverrc",
+    "krunnerrc",
+    "ksplashrc",
+    "breezerc",
+    "powerdevilrc",
+    "powermanagementprofilesrc",
+    "kactivitymanagerdrc",
+    "dolphinrc",
+    "konsolerc",
+    "katerc",
+    "spectaclerc",
+];
+
+/// Folders under the home directory with Konsole profiles, color schemes,
+/// window decorations and downloaded Plasma themes.
+const KDE_DIRS: &[&str] = &[
+    ".local/share/konsole",
+    ".local/share/color-schemes",
+    ".local/share/aurorae",
+    ".local/share/plasma",
+];
+
+/// Dumps dconf into `dest` and lists the archives of KDE files to write,
+/// split into volumes by `settings`. `None` when the machine has neither.
+pub(crate) fn capture(dest: &Path, settings: &ArchiveSettings, tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>) -> Result<Option<(BackupGroup, Vec<Archive>)>> {
+    let home = crate::installer::home_dir().map_err(Error::Io)?;
+    let mut found = Vec::new();
+
+    let dconf = match dump_dconf() {
+        Some(dump) => {
+            let path = dest.join(DCONF_DUMP);
+            fs::write(&path, dump).map_err(|e| Error::Io(format!("Failed to write {}: {e}", path.display())))?;
+            found.push("dconf");
+            Some(DCONF_DUMP.to_string())
+        }
+        None => None,
+    };
+
+    let mut files: Vec<_> = KDE_CONFIGS
+        .iter()
+        .map(|name| format!(".config/{name}"))
+        .map(|relative| home.join(relative))
+        .filter(|path| path.is_file())
+        .map(|path| crate::backup::source_file(&home, path, false))
+        .collect();
+    for dir in KDE_DIRS.iter().map(|dir| home.join(dir)).filter(|dir| dir.is_dir()) {
+        crate::backup::collect_files(&home, &dir, None, false, &mut files, tx, logs)?;
+    }
+    let count = files.len();
+    if count > 0 {
+        found.push("KDE Plasma");
+    }
+
+    if found.is_empty() {
+        return Ok(None);
+    }
+    let label = format!("Desktop settings ({})", found.join(", "));
+    report(tx, logs, format!("[done] Captured {label}: {count} files{}", if dconf.is_some() { " and a dconf dump" } else { "" }));
+    let group = BackupGroup { name: GROUP.to_string(), label, dest: "~".to_string(), zip_files: Vec::new(), index: Vec::new(), dconf };
+    Ok(Some((group, settings.group_volumes(ARCHIVE, files))))
+}
+
+/// Runs after the group's files are extracted: loads the dconf dump, and
+/// tells KDE users to log in again, since Plasma reads its files at login
+/// and writes its own settings back when the session ends.
+pub fn apply(backup_dir: &Path, group: &BackupGroup, tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>) {
+    if let Some(dump) = &group.dconf {
+        // A file of the backup folder itself, never a path elsewhere.
+        let mut parts = Path::new(dump).components();
+        if !matches!((parts.next(), parts.next()), (Some(Component::Normal(_)), None)) {
+            report(tx, logs, format!("[warn] Ignoring the dconf dump '{dump}': it is not a file of the backup folder"));
+        } else {
+            let dump = backup_dir.join(dump);
+            match load_dconf(&dump) {
+                Ok(()) => report(tx, logs, "[done] Loaded the dconf settings".to_string()),
+                Err(e) => report(
+                    tx,
+                    logs,
+                    format!("[warn] dconf load failed: {e}. Run `dconf load / < {}` from your desktop session", dump.display()),
+                ),
+            }
+        }
+    }
+    if !group.zip_files.is_empty() {
+        report(tx, logs, "[info] Log out of Plasma and back in for the restored KDE settings to apply".to_string());
+    }
+}
+
+/// `dconf dump /`, when dconf is installed and has settings.
+fn dump_dconf() -> Option<String> {
+    let out = Command::new("dconf").args(["dump", "/"]).stderr(Stdio::null()).output().ok()?;
+    let dump = String::from_utf8_lossy(&out.stdout).into_owned();
+    (out.status.success() && !dump.trim().is_empty()).then_some(dump)
+}
+
+/// `dconf load /` with the dump on stdin. Writing goes through the dconf
+/// service, so this needs the user's D-Bus session.
+fn load_dconf(dump: &Path) -> std::result::Result<(), String> {
+    let file = File::open(dump).map_err(|e| format!("failed to open {}: {e}", dump.display()))?;
+    let out = Command::new("dconf")
+        .args(["load", "/"])
+        .stdin(file)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run dconf: {e}"))?;
+    if out.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    Err(stderr.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or("dconf exited with an error").to_string())
+}
@@ -1,97 +1,267 @@
 use std::collections::HashMap;
 use std::fs;
-use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
 
+use crate::catalog::{EnvironmentTag, SoftwareSpec};
+use crate::package_manager::{Apt, Dnf, PackageManager, Pacman, Scoop, Unknown, Winget, Zypper};
+use crate::platform::Platform;
 
 #[derive(Debug, Clone)]
 pub struct DistroInfo {
     pub id: String,
-    pub pkg_manager: PackageManager,
+    pub pkg_manager: Arc<dyn PackageManager>,
+    /// Set when the manager was chosen by flag, config or in the TUI rather
+    /// than detected.
+    pub pkg_manager_overridden: bool,
+    pub environment: Environment,
+    /// Candidate versions looked up this session, shared between clones.
+    versions: Arc<Mutex<HashMap<String, Option<String>>>>,
 }
 
-#[derive(Debug, Clone)]
-pub enum PackageManager {
-    Apt,
-    Dnf,
-    Pacman,
-    Unknown,
-}
+impl DistroInfo {
+    pub fn new(id: String, pkg_manager: Arc<dyn PackageManager>) -> Self {
+        Self { id, pkg_manager, pkg_manager_overridden: false, environment: Environment::current(), versions: Arc::default() }
+    }
 
-impl std::fmt::Display for PackageManager {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = match self {
-            PackageManager::Apt => "apt",
-            PackageManager::Dnf => "dnf",
-            PackageManager::Pacman => "pacman",
-            PackageManager::Unknown => "unknown",
+    /// The same distro with another package manager. Cached versions came
+    /// from the old manager's repositories, so they are dropped.
+    pub fn with_package_manager(&self, pkg_manager: Arc<dyn PackageManager>) -> Self {
+        Self { pkg_manager_overridden: true, ..Self::new(self.id.clone(), pkg_manager) }
+    }
+
+    /// Candidate version for `package`, asking the package manager only on
+    /// the first lookup.
+    pub fn candidate_version(&self, package: &str) -> Option<String> {
+        if let Some(cached) = self.versions.lock().ok()?.get(package) {
+            return cached.clone();
+        }
+        let version = self.pkg_manager.query_version(package);
+        if let Ok(mut versions) = self.versions.lock() {
+            versions.insert(package.to_string(), version.clone());
+        }
+        version
+    }
+
+    /// Fills the version cache for all uncached `packages` with one batched query.
+    pub fn prefetch_versions(&self, packages: &[String]) {
+        let Ok(mut versions) = self.versions.lock() else {
+            return;
         };
-        write!(f, "{}", name)
+        let mut missing: Vec<String> = packages.iter().filter(|p| !versions.contains_key(*p)).cloned().collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return;
+        }
+        let mut found = self.pkg_manager.query_versions(&missing);
+        for package in missing {
+            let version = found.remove(&package);
+            versions.insert(package, version);
+        }
     }
 }
 
-impl PackageManager {
-    pub fn install_command(&self, packages: &[String]) -> Option<String> {
-        if packages.is_empty() {
-            return None;
+/// What kind of machine this is, beyond the distro: WSL and containers
+/// usually lack systemd, GUI apps need a graphical session, and many
+/// downloads exist for one CPU architecture only.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Environment {
+    pub arch: &'static str,
+    pub wsl: bool,
+    pub container: bool,
+    pub desktop: bool,
+    pub libc: Libc,
+}
+
+impl Environment {
+    /// Detected once per run.
+    pub fn current() -> Self {
+        static DETECTED: OnceLock<Environment> = OnceLock::new();
+        *DETECTED.get_or_init(Self::detect)
+    }
+
+    fn detect() -> Self {
+        if Platform::current() == Platform::Windows {
+            return Environment { arch: std::env::consts::ARCH, wsl: false, container: false, desktop: true, libc: Libc::Unknown };
         }
-        let joined = packages.join(" ");
-        match self {
-            PackageManager::Apt => Some(format!("sudo apt update && sudo apt install -y {joined}")),
-            PackageManager::Dnf => Some(format!("sudo dnf install -y {joined}")),
-            PackageManager::Pacman => Some(format!("sudo pacman -Sy --noconfirm {joined}")),
-            PackageManager::Unknown => None,
+        let var = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+        let wsl = var("WSL_DISTRO_NAME")
+            || fs::read_to_string("/proc/version").is_ok_and(|v| v.to_lowercase().contains("microsoft"));
+        let container = std::path::Path::new("/.dockerenv").exists()
+            || std::path::Path::new("/run/.containerenv").exists()
+            || var("container")
+            || fs::read_to_string("/proc/1/cgroup").is_ok_and(|cgroup| {
+                ["docker", "kubepods", "containerd", "lxc", "libpod"].iter().any(|marker| cgroup.contains(marker))
+            });
+        let desktop = var("DISPLAY") || var("WAYLAND_DISPLAY");
+        Environment { arch: std::env::consts::ARCH, wsl, container, desktop, libc: Libc::detect() }
+    }
+
+    pub fn has(&self, tag: EnvironmentTag) -> bool {
+        match tag {
+            EnvironmentTag::Desktop => self.desktop,
+            EnvironmentTag::Headless => !self.desktop,
+            EnvironmentTag::Wsl => self.wsl,
+            EnvironmentTag::Container => self.container,
+        }
+    }
+
+    /// Tags that apply here, for the header and messages.
+    pub fn labels(&self) -> Vec<&'static str> {
+        [EnvironmentTag::Wsl, EnvironmentTag::Container, EnvironmentTag::Desktop, EnvironmentTag::Headless]
+            .into_iter()
+            .filter(|tag| self.has(*tag))
+            .map(EnvironmentTag::label)
+            .collect()
+    }
+
+    /// Why `spec` should not be installed here, judged by its
+    /// `supported_arch`, `only_on`, `skip_on` and `only_if_hardware` fields.
+    pub fn incompatibility(&self, spec: &SoftwareSpec) -> Option<String> {
+        if !spec.supported_arch.is_empty() && !spec.supported_arch.iter().any(|arch| arch == self.arch) {
+            return Some(format!("built for {} only (this machine: {})", spec.supported_arch.join(", "), self.arch));
+        }
+        if let Some(tag) = spec.skip_on.iter().find(|tag| self.has(**tag)) {
+            return Some(format!("not meant for {} environments", tag.label()));
+        }
+        if !spec.only_on.is_empty() && !spec.only_on.iter().any(|tag| self.has(*tag)) {
+            let wanted: Vec<&str> = spec.only_on.iter().map(|tag| tag.label()).collect();
+            return Some(format!("only for {} (this machine: {})", wanted.join(" or "), self.labels().join(", ")));
+        }
+        if let Some(hardware) = spec.only_if_hardware
+            && !crate::hardware::present(hardware)
+        {
+            return Some(format!("needs {} graphics (found: {})", hardware.label(), crate::hardware::summary()));
         }
+        None
+    }
+}
+
+/// The C library of a Linux system. Binaries linked against glibc don't run
+/// on musl, and need at least the glibc they were built with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Libc {
+    Glibc { major: u32, minor: u32 },
+    Musl,
+    /// Not Linux, or neither library could be found.
+    Unknown,
+}
+
+impl Libc {
+    /// Looks for the musl loader first (Alpine, Void musl), then asks
+    /// `getconf` for the glibc version.
+    fn detect() -> Self {
+        let musl = ["/lib", "/usr/lib"].iter().any(|dir| {
+            fs::read_dir(dir).is_ok_and(|entries| entries.flatten().any(|e| e.file_name().to_string_lossy().starts_with("ld-musl-")))
+        });
+        if musl {
+            return Libc::Musl;
+        }
+        std::process::Command::new("getconf")
+            .arg("GNU_LIBC_VERSION")
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| Self::parse_glibc(&String::from_utf8_lossy(&out.stdout)))
+            .unwrap_or(Libc::Unknown)
     }
 
-    pub fn get_package_version(&self, package: &str) -> Option<String> {
+    /// Parses `getconf GNU_LIBC_VERSION` output such as `glibc 2.35`.
+    fn parse_glibc(text: &str) -> Option<Self> {
+        let (major, minor) = text.trim().strip_prefix("glibc ")?.split_once('.')?;
+        let minor: String = minor.chars().take_while(char::is_ascii_digit).collect();
+        Some(Libc::Glibc { major: major.parse().ok()?, minor: minor.parse().ok()? })
+    }
+
+    /// Whether the glibc is older than `major.minor`.
+    pub fn glibc_older_than(&self, major: u32, minor: u32) -> bool {
+        matches!(*self, Libc::Glibc { major: a, minor: b } if (a, b) < (major, minor))
+    }
+}
+
+impl std::fmt::Display for Libc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            PackageManager::Apt => {
-                let output = Command::new("apt-cache")
-                    .args(["policy", package])
-                    .output()
-                    .ok()?;
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    if line.contains("Candidate:") {
-                        let v = line.split(':').nth(1)?.trim().to_string();
-                        if v == "(none)" { return None; }
-                        return Some(v);
-                    }
-                }
-                None
-            }
-            PackageManager::Dnf => {
-                let output = Command::new("dnf")
-                    .args(["info", "-q", package])
-                    .output()
-                    .ok()?;
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    if line.contains("Version") {
-                        return Some(line.split(':').nth(1)?.trim().to_string());
-                    }
-                }
-                None
-            }
-            PackageManager::Pacman => {
-                let output = Command::new("pacman")
-                    .args(["-Si", package])
-                    .output()
-                    .ok()?;
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    if line.contains("Version") {
-                        return Some(line.split(':').nth(1)?.trim().to_string());
-                    }
-                }
-                None
-            }
-            PackageManager::Unknown => None,
+            Libc::Glibc { major, minor } => write!(f, "glibc {major}.{minor}"),
+            Libc::Musl => write!(f, "musl"),
+            Libc::Unknown => write!(f, "unknown libc"),
         }
     }
 }
 
+/// Distro ID and package manager given on the command line, taking
+/// precedence over the config and detection.
+#[derive(Debug, Default)]
+pub struct DetectionOverride {
+    pub distro: Option<String>,
+    pub pkg_manager: Option<String>,
+}
+
+static CLI_OVERRIDE: OnceLock<DetectionOverride> = OnceLock::new();
+
+/// Records `--distro` / `--pkg-manager`. Fails for manager names that are
+/// not supported, before anything runs with the wrong one.
+pub fn set_override(over: DetectionOverride) -> Result<(), String> {
+    if let Some(name) = &over.pkg_manager {
+        package_manager_by_name(name)?;
+    }
+    let _ = CLI_OVERRIDE.set(over);
+    Ok(())
+}
+
+pub fn package_manager_by_name(name: &str) -> Result<Arc<dyn PackageManager>, String> {
+    match name {
+        "apt" => Ok(Arc::new(Apt)),
+        "dnf" | "yum" => Ok(Arc::new(Dnf)),
+        "pacman" => Ok(Arc::new(Pacman)),
+        "zypper" => Ok(Arc::new(Zypper)),
+        "winget" => Ok(Arc::new(Winget)),
+        "scoop" => Ok(Arc::new(Scoop)),
+        other => Err(format!("unsupported package manager '{other}' (expected apt, dnf, pacman, zypper, winget or scoop)")),
+    }
+}
+
+/// Managers the TUI cycles through on this platform.
+pub fn selectable_managers() -> &'static [&'static str] {
+    match Platform::current() {
+        Platform::Linux => &["apt", "dnf", "pacman", "zypper"],
+        Platform::Windows => &["winget", "scoop"],
+    }
+}
+
+/// Detects the distro, then applies `--distro`/`--pkg-manager` or the
+/// `distro`/`pkg_manager` config keys. A distro override alone picks the
+/// manager that distro ID maps to.
 pub fn detect_distro() -> Result<DistroInfo, String> {
+    let config = crate::config::current();
+    let cli = CLI_OVERRIDE.get();
+    let distro = cli.and_then(|o| o.distro.clone()).or(config.distro);
+    let pkg_manager = cli.and_then(|o| o.pkg_manager.clone()).or(config.pkg_manager);
+
+    let pkg_manager = pkg_manager.as_deref().map(package_manager_by_name).transpose()?;
+    match (distro, pkg_manager) {
+        (Some(id), Some(pm)) => Ok(DistroInfo::new(id, Arc::new(Unknown)).with_package_manager(pm)),
+        (Some(id), None) => {
+            let pm = detect_package_manager(&id, "");
+            Ok(DistroInfo::new(id, pm))
+        }
+        (None, Some(pm)) => Ok(detect_system()?.with_package_manager(pm)),
+        (None, None) => detect_system(),
+    }
+}
+
+fn detect_system() -> Result<DistroInfo, String> {
+    if Platform::current() == Platform::Windows {
+        let pkg_manager: Arc<dyn PackageManager> = if Platform::Windows.has_command("winget") {
+            Arc::new(Winget)
+        } else if Platform::Windows.has_command("scoop") {
+            Arc::new(Scoop)
+        } else {
+            Arc::new(Unknown)
+        };
+        return Ok(DistroInfo::new("windows".to_string(), pkg_manager));
+    }
+
     let content = fs::read_to_string("/etc/os-release").map_err(|e| format!("failed to read /etc/os-release: {e}"))?;
     let mut pairs = HashMap::new();
 
@@ -114,54 +284,72 @@ pub fn detect_distro() -> Result<DistroInfo, String> {
 
     let pkg_manager = detect_package_manager(&id, &id_like);
 
-    Ok(DistroInfo { id, pkg_manager })
+    Ok(DistroInfo::new(id, pkg_manager))
 }
 
-fn detect_package_manager(id: &str, id_like: &str) -> PackageManager {
+fn detect_package_manager(id: &str, id_like: &str) -> Arc<dyn PackageManager> {
     let debian_ids = ["ubuntu", "debian", "linuxmint", "pop", "ubuntu-budgie", "kdeneon"];
     let fedora_ids = ["fedora", "rhel", "centos", "rocky"];
     let arch_ids = ["arch", "manjaro", "endeavouros", "artix"];
+    let suse_ids = ["opensuse-leap", "opensuse-tumbleweed", "sles", "sled"];
 
-    if debian_ids.iter().any(|&d| id == d) {
-        return PackageManager::Apt;
+    if debian_ids.contains(&id) {
+        return Arc::new(Apt);
+    }
+    if fedora_ids.contains(&id) {
+        return Arc::new(Dnf);
     }
-    if fedora_ids.iter().any(|&f| id == f) {
-        return PackageManager::Dnf;
+    if arch_ids.contains(&id) {
+        return Arc::new(Pacman);
     }
-    if arch_ids.iter().any(|&a| id == a) {
-        return PackageManager::Pacman;
+    if suse_ids.contains(&id) {
+        return Arc::new(Zypper);
     }
 
     if id_like.contains("debian") || id_like.contains("ubuntu") {
-        return PackageManager::Apt;
+        return Arc::new(Apt);
     }
     if id_like.contains("fedora") || id_like.contains("rhel") {
-        return PackageManager::Dnf;
+        return Arc::new(Dnf);
     }
     if id_like.contains("arch") {
-        return PackageManager::Pacman;
+        return Arc::new(Pacman);
+    }
+    if id_like.contains("suse") {
+        return Arc::new(Zypper);
     }
 
     detect_package_manager_runtime()
 }
 
-fn detect_package_manager_runtime() -> PackageManager {
-    let managers = [("apt", PackageManager::Apt),
-                    ("dnf", PackageManager::Dnf),
-                    ("pacman", PackageManager::Pacman),
-                    ("yum", PackageManager::Dnf),
-                    ("zypper", PackageManager::Unknown)];
-
-    for (cmd, manager) in &managers {
-        if Command::new("which")
-            .arg(cmd)
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-        {
-            return manager.clone();
+fn detect_package_manager_runtime() -> Arc<dyn PackageManager> {
+    for cmd in ["apt", "dnf", "pacman", "yum", "zypper"] {
+        if Platform::Linux.has_command(cmd) {
+            return match cmd {
+                "apt" => Arc::new(Apt),
+                "dnf" | "yum" => Arc::new(Dnf),
+                "pacman" => Arc::new(Pacman),
+                "zypper" => Arc::new(Zypper),
+                _ => Arc::new(Unknown),
+            };
         }
     }
 
-    PackageManager::Unknown
-}
\ No newline at end of file
+    Arc::new(Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glibc_versions_are_parsed() {
+        assert_eq!(Libc::parse_glibc("glibc 2.35\n"), Some(Libc::Glibc { major: 2, minor: 35 }));
+        assert_eq!(Libc::parse_glibc("glibc 2.28.9000"), Some(Libc::Glibc { major: 2, minor: 28 }));
+        assert_eq!(Libc::parse_glibc("musl libc"), None);
+        assert_eq!(Libc::parse_glibc(""), None);
+        assert!(Libc::Glibc { major: 2, minor: 28 }.glibc_older_than(2, 31));
+        assert!(!Libc::Glibc { major: 2, minor: 35 }.glibc_older_than(2, 31));
+        assert!(!Libc::Musl.glibc_older_than(2, 31));
+    }
+}
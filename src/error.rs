@@ -0,0 +1,77 @@
+/// Error of resolving, downloading, installing and restoring, with the kind
+/// of failure so callers can tell a cancellation from a network outage
+/// without reading the message. Variants hold the message rather than the
+/// underlying error so results can be cloned into reports and sent to the
+/// TUI.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// A request failed, timed out or came back with an error status.
+    #[error("{0}")]
+    Network(String),
+    /// Reading or writing a local file or directory failed.
+    #[error("{0}")]
+    Io(String),
+    /// The catalog entry asks for something that can't work, e.g. a missing
+    /// field, an invalid regex or an unsupported package manager.
+    #[error("{0}")]
+    Catalog(String),
+    /// The source answered, but no version or download matched.
+    #[error("{0}")]
+    Resolve(String),
+    /// The user stopped it; holds what was stopped, e.g. "Download".
+    #[error("{0} cancelled by user")]
+    Cancelled(&'static str),
+    /// A download arrived but failed its checks: it is not the archive its
+    /// name promises, or its SHA-256 is not the published one.
+    #[error("{0}")]
+    Verification(String),
+    /// A command could not be started, exited unsuccessfully or timed out.
+    #[error("{0}")]
+    ChildProcess(String),
+    /// Anything not classified yet, mostly from modules that still report
+    /// plain strings.
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Error::Cancelled(_))
+    }
+
+    /// The same kind of error with `f` applied to its message.
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Error::Network(m) => Error::Network(f(m)),
+            Error::Io(m) => Error::Io(f(m)),
+            Error::Catalog(m) => Error::Catalog(f(m)),
+            Error::Resolve(m) => Error::Resolve(f(m)),
+            Error::Cancelled(what) => Error::Cancelled(what),
+            Error::Verification(m) => Error::Verification(f(m)),
+            Error::ChildProcess(m) => Error::ChildProcess(f(m)),
+            Error::Other(m) => Error::Other(f(m)),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_string())
+    }
+}
+
+/// Lets modules that still return `Result<_, String>` use `?` on calls
+/// into the typed ones.
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,105 @@
+//! Messages the engine sends while it installs and restores, for whoever
+//! drives it: the TUI, the headless runners or an embedding program.
+
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+    Command,
+}
+
+impl LogLevel {
+    /// Infers the level of a message from the markers used across the code
+    /// base (`[error]`, `[warn]`, `running:` ...).
+    pub fn classify(text: &str) -> Self {
+        let lower = text.to_lowercase();
+        if lower.contains("[error]") || lower.contains(" failed") || lower.starts_with("error") {
+            LogLevel::Error
+        } else if lower.contains("[warn") || lower.contains("[stderr]") {
+            LogLevel::Warn
+        } else if lower.starts_with("running") || lower.starts_with("[dry-run]") || lower.starts_with("$ ") {
+            LogLevel::Command
+        } else {
+            LogLevel::Info
+        }
+    }
+
+    /// Whether an entry at `level` passes this filter: Warn also admits
+    /// errors, the others match exactly.
+    pub fn admits(self, level: LogLevel) -> bool {
+        match self {
+            LogLevel::Warn => matches!(level, LogLevel::Warn | LogLevel::Error),
+            other => other == level,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warnings",
+            LogLevel::Error => "errors",
+            LogLevel::Command => "commands",
+        }
+    }
+}
+
+/// Top level of a downloaded archive, listed without extracting it.
+pub struct ArchivePreview {
+    pub format: &'static str,
+    pub entries: Vec<crate::extract::ArchiveEntry>,
+    /// Whether the archive was downloaded for the preview rather than found
+    /// in the download folder.
+    pub fetched: bool,
+}
+
+/// Sent by [`crate::installer::install_software`] and
+/// [`crate::restorer::restore_backup`] over the channel they are given.
+/// `Elevate` and `Secret` carry a reply channel and block the install until
+/// it is answered or dropped. `Done`, `Skipped`, `Record` and `Finished`
+/// come from the queue around them, e.g. the TUI's install thread.
+pub enum InstallMsg {
+    /// Tool key (empty while downloading), what is happening and the
+    /// download speed once known.
+    Progress(String, String, Option<String>),
+    /// Completed fraction of the current step, 0.0 to 1.0.
+    SubProgress(f64),
+    Log(LogLevel, String),
+    /// The tool was abandoned through the skip key; the queue moves on.
+    Skipped(String),
+    /// Warning shown above the progress bars; an empty text clears it.
+    Banner(String),
+    /// A privileged step is about to run and no sudo credential is cached;
+    /// the receiver authenticates and answers whether it succeeded.
+    Elevate(std::sync::mpsc::Sender<bool>),
+    /// A secret step needs input; the reply carries the value, or `None` when
+    /// the user dismissed the prompt.
+    Secret(String, std::sync::mpsc::Sender<Option<String>>),
+    /// A tool finished, with its log lines or why it failed.
+    Done(String, Result<Vec<String>, Error>),
+    Record(crate::report::RunRecord),
+    /// The TUI's disk usage view finished measuring, one entry per install
+    /// directory and cache.
+    DiskUsage(Vec<crate::usage::UsageEntry>),
+    /// The whole queue is through.
+    Finished,
+}
+
+/// Runs `handler` on a thread for every message sent on the returned
+/// channel, until all senders are dropped; join the handle to wait for the
+/// last one. The handler answers `Elevate` and `Secret` through their reply
+/// channels.
+pub fn forward(mut handler: impl FnMut(InstallMsg) + Send + 'static) -> (Sender<InstallMsg>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        while let Ok(msg) = rx.recv() {
+            handler(msg);
+        }
+    });
+    (tx, handle)
+}
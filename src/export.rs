@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::catalog::{load_catalog, CatalogFile, SetupStep, SoftwareSpec};
+use crate::command::{quote, CommandLine};
+use crate::manifest::{catalog_path, load_lockfile, load_manifest, lockfile_path, Lockfile};
+
+/// Home of the user the image is built as; `~` in catalog paths maps here.
+const CONTAINER_HOME: &str = "/root";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Dockerfile,
+    Devcontainer,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "dockerfile" => Ok(ExportFormat::Dockerfile),
+            "devcontainer" => Ok(ExportFormat::Devcontainer),
+            other => Err(format!("unknown export format '{other}' (expected dockerfile or devcontainer)")),
+        }
+    }
+}
+
+/// Converts a manifest (with its lockfile) or a bare lockfile into a
+/// Dockerfile, or a `.devcontainer` folder holding one plus
+/// `devcontainer.json`. Downloads use the exact URLs from the lockfile.
+pub fn export(input: &Path, format: ExportFormat, base: Option<&str>, out: Option<&Path>) -> Result<(), String> {
+    let (catalog, keys, lock) = load_input(input)?;
+    let manager = crate::distro::detect_distro()?.pkg_manager.name();
+    let base = match base {
+        Some(image) => image.to_string(),
+        None => default_base_image(manager)?.to_string(),
+    };
+    let dockerfile = render_dockerfile(input, &catalog, &keys, &lock, manager, &base);
+
+    match format {
+        ExportFormat::Dockerfile => match out {
+            Some(path) => {
+                fs::write(path, dockerfile).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+                println!("Wrote {}", path.display());
+            }
+            None => print!("{dockerfile}"),
+        },
+        ExportFormat::Devcontainer => {
+            let dir = out.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(".devcontainer"));
+            fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+            let name = input.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let config = serde_json::json!({
+                "name": name,
+                "build": { "dockerfile": "Dockerfile" },
+                "remoteUser": "root",
+            });
+            let config = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+            for (file, content) in [("Dockerfile", dockerfile), ("devcontainer.json", config + "\n")] {
+                let path = dir.join(file);
+                fs::write(&path, content).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+                println!("Wrote {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Catalog, tool keys and lockfile for a manifest or a lockfile. A bare
+/// lockfile uses the configured catalog.
+fn load_input(input: &Path) -> Result<(CatalogFile, Vec<String>, Lockfile), String> {
+    if input.extension().is_some_and(|e| e == "lock") {
+        if !input.exists() {
+            return Err(format!("lockfile not found at {}", input.display()));
+        }
+        let lock = load_lockfile(input)?;
+        let catalog = load_catalog(&crate::config::require_catalog()?)?;
+        let keys = lock.tools.keys().cloned().collect();
+        return Ok((catalog, keys, lock));
+    }
+    let manifest = load_manifest(input)?;
+    let catalog = load_catalog(&catalog_path(input, &manifest)?)?;
+    let lock = load_lockfile(&lockfile_path(input))?;
+    Ok((catalog, manifest.tools.keys().cloned().collect(), lock))
+}
+
+fn default_base_image(manager: &str) -> Result<&'static str, String> {
+    match manager {
+        "apt" => Ok("ubuntu:24.04"),
+        "dnf" => Ok("fedora:latest"),
+        "pacman" => Ok("archlinux:latest"),
+        "zypper" => Ok("opensuse/tumbleweed:latest"),
+        other => Err(format!("no default base image for {other}; pass --base=<image>")),
+    }
+}
+
+/// One `RUN` installing `packages`, cleaning the package cache afterwards so
+/// the layer stays small.
+fn package_layer(manager: &str, packages: &[String]) -> String {
+    let list = packages.iter().map(|p| quote(p)).collect::<Vec<_>>().join(" ");
+    match manager {
+        "apt" => format!(
+            "RUN apt-get update && apt-get install -y --no-install-recommends {list} && rm -rf /var/lib/apt/lists/*"
+        ),
+        "dnf" => format!("RUN dnf install -y {list} && dnf clean all"),
+        "pacman" => format!("RUN pacman -Sy --noconfirm {list} && pacman -Scc --noconfirm"),
+        "zypper" => format!("RUN zypper --non-interactive install {list} && zypper clean --all"),
+        other => format!("# {other} packages: {list}"),
+    }
+}
+
+/// Maps a catalog path into the image: `~` becomes [`CONTAINER_HOME`].
+fn container_path(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) => format!("{CONTAINER_HOME}{rest}"),
+        None => path.to_string(),
+    }
+}
+
+fn container_install_root(spec: &SoftwareSpec) -> String {
+    spec.install_dir
+        .as_deref()
+        .or(crate::config::current().install_root.as_deref())
+        .map(|dir| container_path(dir.trim_end_matches('/')))
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| CONTAINER_HOME.to_string())
+}
+
+fn render_dockerfile(input: &Path, catalog: &CatalogFile, keys: &[String], lock: &Lockfile, manager: &str, base: &str) -> String {
+    let mut out = format!("# Generated by `rusty_rebase export` from {}\nFROM {base}\n\n", input.display());
+    out.push_str(&package_layer(manager, &["ca-certificates", "curl", "tar", "gzip", "xz-utils", "bzip2", "zstd", "unzip"]
+        .iter()
+        .map(|p| if *p == "xz-utils" && manager != "apt" { "xz".to_string() } else { p.to_string() })
+        .collect::<Vec<_>>()));
+    out.push_str("\n\n");
+
+    for key in keys {
+        let Some(spec) = catalog.software.get(key) else {
+            out.push_str(&format!("# {key}: not found in catalog\n\n"));
+            continue;
+        };
+        let root = container_install_root(spec);
+        out.push_str(&format!("# {}\n", spec.display_name));
+        let vars = [("install_root", root.as_str()), ("home", CONTAINER_HOME)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let env: String = spec.env
+            .iter()
+            .map(|(name, value)| {
+                let value = crate::template::render(value, &vars).unwrap_or_else(|_| value.clone());
+                format!("export {name}={} && ", quote(&container_path(&value)))
+            })
+            .collect();
+        for step in &spec.setup_steps {
+            out.push_str(&step_layer(step, manager, &root, &env));
+            out.push('\n');
+        }
+        if spec.source.has_artifact() {
+            match lock.tools.get(key) {
+                Some(locked) => {
+                    let dest = match &spec.extract_subdir {
+                        Some(sub) => format!("{root}/{sub}"),
+                        None => root.clone(),
+                    };
+                    out.push_str(&format!("# {} {}\n", key, locked.version));
+                    out.push_str(&artifact_layer(&locked.url, &dest, spec.strip_components, manager));
+                    out.push('\n');
+                }
+                None => out.push_str(&format!("# {key}: no locked download, run `rusty_rebase apply` first\n")),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The Dockerfile line for one step. `env` is the entry's `export ... && `
+/// prefix, applied to the shell and package layers.
+fn step_layer(step: &SetupStep, manager: &str, root: &str, env: &str) -> String {
+    match step {
+        // The image has none of this machine's hardware.
+        SetupStep::Package { only_if_hardware: Some(hardware), .. } => format!("# skipped: package step for {} graphics", hardware.label()),
+        SetupStep::Package { .. } => {
+            let layer = package_layer(manager, step.packages_for("", manager));
+            match layer.strip_prefix("RUN ") {
+                Some(run) => format!("RUN {env}{run}"),
+                None => layer,
+            }
+        }
+        SetupStep::PathHint { value } => {
+            format!("ENV PATH=\"$PATH:{}\"", container_path(&value.replace("<install_root>", root)))
+        }
+        SetupStep::Shell { command, check_command: None, .. } => format!("RUN {env}{}", crate::installer::expand_arch_vars(command)),
+        SetupStep::Shell { command, check_command: Some(check), .. } => format!(
+            "RUN {env}{{ {}; }} || {{ {}; }}",
+            crate::installer::expand_arch_vars(check),
+            crate::installer::expand_arch_vars(command)
+        ),
+        SetupStep::Note { value } => format!("# Note: {value}"),
+        SetupStep::EnsureDirs { paths } => {
+            format!("RUN {}", CommandLine::new("mkdir").arg("-p").args(paths.iter().map(|p| container_path(p))))
+        }
+        SetupStep::GitConfig { key, value, scope } => {
+            format!("RUN {}", CommandLine::new("git").args(["config", scope.flag(), key.as_str(), value.as_str()]))
+        }
+        SetupStep::Secret { name, .. } => format!("# skipped: secret {name} (pass it as a build secret instead)"),
+        SetupStep::Template { dest, .. } | SetupStep::CopyFile { to: dest, .. } => {
+            format!("# skipped: writing {dest} (COPY it into the image instead)")
+        }
+        SetupStep::Swap { .. } | SetupStep::Sysctl { .. } => "# skipped: swap/sysctl tuning belongs to the host".to_string(),
+        SetupStep::VersionManager { manager, versions, default } => {
+            // Fresh image: the manager is always bootstrapped first.
+            let mut commands = vec![manager.bootstrap()];
+            commands.extend(versions.iter().map(|v| manager.install_version(v)));
+            commands.extend(default.iter().map(|v| manager.set_default(v)));
+            let home = dirs::home_dir().map(|h| h.display().to_string()).unwrap_or_default();
+            let commands: Vec<String> = commands
+                .iter()
+                .map(|cmd| cmd.to_string())
+                .map(|cmd| if home.is_empty() { cmd } else { cmd.replace(&home, CONTAINER_HOME) })
+                .collect();
+            let path = manager.bin_dir().map(|dir| format!("ENV PATH=\"{CONTAINER_HOME}/{dir}:$PATH\"\n")).unwrap_or_default();
+            format!("{path}RUN {env}{}", commands.join(" && "))
+        }
+        SetupStep::Cargo { .. } | SetupStep::Npm { .. } | SetupStep::Pipx { .. } => {
+            let Some((toolchain, packages, global)) = crate::toolchain::step_toolchain(step) else { return String::new() };
+            let script = toolchain.install(Path::new(toolchain.program()), packages, global, Path::new(root));
+            // Global npm packages target the host's home; move them to the image's.
+            let home = dirs::home_dir().map(|h| h.display().to_string()).unwrap_or_default();
+            let commands: Vec<String> = script
+                .iter()
+                .map(|cmd| cmd.to_string())
+                .map(|cmd| if home.is_empty() { cmd } else { cmd.replace(&home, CONTAINER_HOME) })
+                .collect();
+            format!("RUN {env}{}", commands.join(" && "))
+        }
+    }
+}
+
+/// Fetches a locked artifact and unpacks or installs it in one layer, so the
+/// archive itself never ends up in the image.
+fn artifact_layer(url: &str, dest: &str, strip: usize, manager: &str) -> String {
+    let file_name = url.rsplit('/').next().unwrap_or_default().split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    let fetch = CommandLine::new("curl").args(["-fsSL", url]).to_string();
+    let mkdir = CommandLine::new("mkdir").args(["-p", dest]).to_string();
+    let strip_flag = if strip > 0 { format!(" --strip-components={strip}") } else { String::new() };
+    let dest_arg = quote(dest);
+    let tar_flags = if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        Some("-xz")
+    } else if file_name.ends_with(".tar.xz") || file_name.ends_with(".txz") {
+        Some("-xJ")
+    } else if file_name.ends_with(".tar.bz2") {
+        Some("-xj")
+    } else if file_name.ends_with(".tar.zst") {
+        Some("--zstd -x")
+    } else {
+        None
+    };
+    if let Some(flags) = tar_flags {
+        return format!("RUN {mkdir} && {fetch} | tar {flags} -C {dest_arg}{strip_flag}");
+    }
+
+    let tmp_path = format!("/tmp/{file_name}");
+    let download = CommandLine::new("curl").args(["-fsSL", "-o", &tmp_path, url]).to_string();
+    let tmp = quote(&tmp_path);
+    if file_name.ends_with(".zip") {
+        let note = if strip > 0 { format!("\n# note: strip_components = {strip} is not applied to zip archives") } else { String::new() };
+        return format!("RUN {mkdir} && {download} && unzip -q -o {tmp} -d {dest_arg} && rm {tmp}{note}");
+    }
+    if file_name.ends_with(".deb") && manager == "apt" {
+        return format!(
+            "RUN {download} && apt-get update && apt-get install -y {tmp} && rm -rf {tmp} /var/lib/apt/lists/*"
+        );
+    }
+    if file_name.ends_with(".rpm") && manager == "dnf" {
+        return format!("RUN {download} && dnf install -y {tmp} && rm {tmp} && dnf clean all");
+    }
+    // Anything else (AppImage, bare binary) is kept as an executable file.
+    let target = format!("{dest}/{file_name}");
+    let fetch_to = CommandLine::new("curl").args(["-fsSL", "-o", &target, url]);
+    format!("RUN {mkdir} && {fetch_to} && chmod +x {}", quote(&target))
+}
@@ -0,0 +1,703 @@
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::events::InstallMsg;
+use crate::command::{run_piped, CommandLine};
+use crate::error::{Error, Result};
+use crate::platform::Platform;
+use tokio_util::sync::CancellationToken;
+
+/// Everything a handler needs to unpack an archive.
+pub struct ExtractContext<'a> {
+    pub dest: &'a Path,
+    /// Leading path components dropped from every entry.
+    pub strip: usize,
+    pub tx: &'a mpsc::Sender<InstallMsg>,
+    pub cancel: &'a CancellationToken,
+    /// Limit for handlers that shell out to an external tool.
+    pub timeout: Option<Duration>,
+}
+
+/// One archive format. Handlers are looked up by magic bytes first, then by
+/// file extension, then by the `Content-Type` the server sent.
+pub trait ArchiveHandler: Send + Sync {
+    /// Short name used in logs, e.g. "tar.gz".
+    fn name(&self) -> &'static str;
+    /// Lowercase file name suffixes, with the leading dot.
+    fn extensions(&self) -> &'static [&'static str];
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Whether the first bytes of a file identify this format.
+    fn matches_magic(&self, header: &[u8]) -> bool;
+    /// False for formats without a reliable signature, which are then
+    /// never reported as mismatching their extension.
+    fn has_magic(&self) -> bool {
+        true
+    }
+    /// Unpacks `path` into `ctx.dest`, returning a line for the install log.
+    fn extract(&self, path: &Path, ctx: &ExtractContext) -> Result<String>;
+    /// Every entry of the archive, without unpacking anything.
+    fn list(&self, _path: &Path) -> Result<Vec<ArchiveEntry>> {
+        Err(Error::Other(format!("listing {} archives is not supported", self.name())))
+    }
+}
+
+/// An archive entry as listed by [`ArchiveHandler::list`], or a top-level
+/// entry with the sizes of everything beneath it summed up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    pub dir: bool,
+}
+
+fn registry() -> &'static RwLock<Vec<Arc<dyn ArchiveHandler>>> {
+    static REGISTRY: OnceLock<RwLock<Vec<Arc<dyn ArchiveHandler>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtin: Vec<Arc<dyn ArchiveHandler>> = vec![
+            Arc::new(TarHandler(Compression::Gzip)),
+            Arc::new(TarHandler(Compression::Xz)),
+            Arc::new(TarHandler(Compression::Bzip2)),
+            Arc::new(TarHandler(Compression::Zstd)),
+            Arc::new(ZipHandler),
+            Arc::new(SevenZipHandler),
+            Arc::new(TarHandler(Compression::None)),
+        ];
+        RwLock::new(builtin)
+    })
+}
+
+/// Adds a handler for a new format. Registered handlers are consulted before
+/// the built-in ones, the latest first, so they can also replace them.
+pub fn register(handler: Arc<dyn ArchiveHandler>) {
+    registry().write().unwrap_or_else(|e| e.into_inner()).insert(0, handler);
+}
+
+/// Picks the handler for a downloaded file: by its magic bytes, falling back
+/// to the file extension (e.g. when the file is unreadable) and then to the
+/// response's `content_type`.
+pub fn find_handler(path: &Path, content_type: Option<&str>) -> Option<Arc<dyn ArchiveHandler>> {
+    let header = read_header(path);
+    let header = header.as_slice();
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
+    let mime = content_type.and_then(|c| c.split(';').next()).map(|c| c.trim().to_lowercase());
+
+    let handlers = registry().read().unwrap_or_else(|e| e.into_inner());
+    let by_magic = || handlers.iter().find(|h| !header.is_empty() && h.matches_magic(header));
+    let by_extension = || handlers.iter().find(|h| h.extensions().iter().any(|ext| name.ends_with(ext)));
+    let by_mime = || {
+        let mime = mime.as_deref()?;
+        handlers.iter().find(|h| h.mime_types().contains(&mime))
+    };
+    by_magic().or_else(by_extension).or_else(by_mime).cloned()
+}
+
+/// Lists the top level of an archive for a preview: the handler used and the
+/// entries sorted by name.
+pub fn list_top_level(path: &Path) -> Result<(&'static str, Vec<ArchiveEntry>)> {
+    let handler = find_handler(path, None).ok_or_else(|| Error::Resolve(format!("{} is not a supported archive", path.display())))?;
+    let entries = handler.list(path)?;
+    Ok((handler.name(), top_level(&entries)))
+}
+
+fn top_level(entries: &[ArchiveEntry]) -> Vec<ArchiveEntry> {
+    let mut top: std::collections::BTreeMap<String, ArchiveEntry> = std::collections::BTreeMap::new();
+    for entry in entries {
+        let mut parts = Path::new(&entry.name).components().filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        });
+        let Some(first) = parts.next() else { continue };
+        let nested = parts.next().is_some();
+        let item = top.entry(first.clone()).or_insert(ArchiveEntry { name: first, size: 0, dir: false });
+        item.size += entry.size;
+        item.dir |= entry.dir || nested;
+    }
+    top.into_values().collect()
+}
+
+/// Up to 512 leading bytes of a file; empty when it cannot be read.
+fn read_header(path: &Path) -> Vec<u8> {
+    let mut header = Vec::with_capacity(512);
+    let _ = File::open(path).and_then(|f| f.take(512).read_to_end(&mut header));
+    header
+}
+
+/// Checks a fresh download against what its file `name` promises, so an
+/// error page or a truncated file saved as `foo.tar.gz` fails here with a
+/// clear reason instead of deep inside the extractor. `path` may still be
+/// the `.part` file it is downloaded to.
+pub fn check_download(path: &Path, name: &str, content_type: Option<&str>) -> Result<()> {
+    let header = read_header(path);
+    let name = name.to_lowercase();
+    if name.ends_with(".html") || name.ends_with(".htm") {
+        return Ok(());
+    }
+    let text = String::from_utf8_lossy(&header).trim_start_matches(['\u{feff}', ' ', '\t', '\r', '\n']).to_lowercase();
+    let html_type = content_type.is_some_and(|c| c.trim_start().to_lowercase().starts_with("text/html"));
+    if text.starts_with("<!doctype html") || text.starts_with("<html") || (html_type && text.starts_with('<')) {
+        return Err(Error::Verification("server returned HTML, not an archive".to_string()));
+    }
+
+    let handlers = registry().read().unwrap_or_else(|e| e.into_inner());
+    let Some(expected) = handlers.iter().find(|h| h.extensions().iter().any(|ext| name.ends_with(ext))) else {
+        return Ok(());
+    };
+    if header.is_empty() {
+        return Err(Error::Verification(format!("downloaded file {} is empty", path.display())));
+    }
+    if expected.has_magic() && !handlers.iter().any(|h| h.matches_magic(&header)) {
+        return Err(Error::Verification(format!(
+            "downloaded file {} is not a {} archive (unrecognised content)",
+            path.display(),
+            expected.name()
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compression {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+    None,
+}
+
+/// Tar archives, plain or wrapped in one of the supported compressors,
+/// streamed in-process.
+struct TarHandler(Compression);
+
+impl ArchiveHandler for TarHandler {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            Compression::Gzip => "tar.gz",
+            Compression::Xz => "tar.xz",
+            Compression::Bzip2 => "tar.bz2",
+            Compression::Zstd => "tar.zst",
+            Compression::None => "tar",
+        }
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        match self.0 {
+            Compression::Gzip => &[".tar.gz", ".tgz"],
+            Compression::Xz => &[".tar.xz", ".txz"],
+            Compression::Bzip2 => &[".tar.bz2", ".tbz2"],
+            Compression::Zstd => &[".tar.zst", ".tzst"],
+            Compression::None => &[".tar"],
+        }
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        match self.0 {
+            Compression::Gzip => &["application/gzip", "application/x-gzip", "application/x-gtar"],
+            Compression::Xz => &["application/x-xz"],
+            Compression::Bzip2 => &["application/x-bzip2"],
+            Compression::Zstd => &["application/zstd"],
+            Compression::None => &["application/x-tar"],
+        }
+    }
+
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        match self.0 {
+            Compression::Gzip => header.starts_with(&[0x1f, 0x8b]),
+            Compression::Xz => header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+            Compression::Bzip2 => header.starts_with(b"BZh"),
+            Compression::Zstd => header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Compression::None => header.len() >= 262 && &header[257..262] == b"ustar",
+        }
+    }
+
+    /// Pre-POSIX tar archives carry no `ustar` marker.
+    fn has_magic(&self) -> bool {
+        self.0 != Compression::None
+    }
+
+    fn extract(&self, path: &Path, ctx: &ExtractContext) -> Result<String> {
+        let entries = unpack_tar(path, self.0, ctx.dest, ctx.strip, ctx.tx, ctx.cancel)?;
+        Ok(format!("extracted {entries} entries into {}", ctx.dest.display()))
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<ArchiveEntry>> {
+        let file = File::open(path).map_err(|e| Error::Io(format!("failed to open {}: {e}", path.display())))?;
+        let mut archive = tar::Archive::new(decompress(io::BufReader::new(file), self.0)?);
+        let entries = archive.entries().map_err(|e| Error::Io(format!("failed to read archive {}: {e}", path.display())))?;
+        let mut listed = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| Error::Io(format!("corrupt archive entry in {}: {e}", path.display())))?;
+            let name = entry.path().map_err(|e| Error::Io(format!("invalid entry path: {e}")))?.display().to_string();
+            let dir = entry.header().entry_type().is_dir();
+            listed.push(ArchiveEntry { name, size: entry.size(), dir });
+        }
+        Ok(listed)
+    }
+}
+
+struct ZipHandler;
+
+impl ArchiveHandler for ZipHandler {
+    fn name(&self) -> &'static str {
+        "zip"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[".zip"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/zip", "application/x-zip-compressed"]
+    }
+
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.starts_with(b"PK\x03\x04")
+    }
+
+    fn extract(&self, path: &Path, ctx: &ExtractContext) -> Result<String> {
+        fs::create_dir_all(ctx.dest).map_err(|e| Error::Io(format!("failed to create {}: {e}", ctx.dest.display())))?;
+        let file = File::open(path).map_err(|e| Error::Io(format!("failed to open {}: {e}", path.display())))?;
+        let mut progress = ProgressReporter { tx: ctx.tx, last_percent: None };
+        let entries = unpack_zip(file, ctx.dest, ctx.strip, &mut progress, ctx.cancel)?;
+        Ok(format!("extracted {entries} entries into {}", ctx.dest.display()))
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<ArchiveEntry>> {
+        let file = File::open(path).map_err(|e| Error::Io(format!("failed to open {}: {e}", path.display())))?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| Error::Io(format!("failed to read zip: {e}")))?;
+        let mut listed = Vec::new();
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).map_err(|e| Error::Io(format!("failed to read zip entry: {e}")))?;
+            listed.push(ArchiveEntry { name: entry.name().to_string(), size: entry.size(), dir: entry.is_dir() });
+        }
+        Ok(listed)
+    }
+}
+
+/// 7z has no pure-Rust extractor here, so this shells out to 7z/7za/7zz.
+struct SevenZipHandler;
+
+impl ArchiveHandler for SevenZipHandler {
+    fn name(&self) -> &'static str {
+        "7z"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[".7z"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/x-7z-compressed"]
+    }
+
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.starts_with(&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c])
+    }
+
+    fn extract(&self, path: &Path, ctx: &ExtractContext) -> Result<String> {
+        let tool = seven_zip_tool()?;
+        // Checked once: 7z cannot change its thread count mid-run.
+        let threads = crate::throttle::active().then_some("-mmt1");
+        let seven_zip = |dest: &Path| {
+            CommandLine::new(tool)
+                .args(["x", "-y"])
+                .args(threads)
+                .arg(format!("-o{}", dest.display()))
+                .arg("--")
+                .arg(path.display().to_string())
+        };
+        let run = |command: &CommandLine| -> Result<()> {
+            let status = run_piped(command, &[], ctx.tx, ctx.cancel, ctx.timeout)?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(Error::ChildProcess(format!("{command} failed with {status}")))
+            }
+        };
+        if ctx.strip == 0 {
+            let command = seven_zip(ctx.dest);
+            run(&command)?;
+            return Ok(format!("extracted with {command}"));
+        }
+        // 7z has no strip option: unpack into a staging folder and hoist the nested entries.
+        let staging = ctx.dest.join(".rusty_rebase_staging");
+        let command = seven_zip(&staging);
+        if let Err(e) = run(&command) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        let moved = hoist_entries(&staging, ctx.dest, ctx.strip);
+        let _ = fs::remove_dir_all(&staging);
+        Ok(format!("extracted with {command}, moved {} entries", moved?))
+    }
+
+    /// Parses the technical listing (`7z l -slt`): one `Key = value` block
+    /// per entry after the `----------` separator.
+    fn list(&self, path: &Path) -> Result<Vec<ArchiveEntry>> {
+        let command = CommandLine::new(seven_zip_tool()?).args(["l", "-slt", "--"]).arg(path.display().to_string());
+        let output = command.to_command().output().map_err(|e| Error::ChildProcess(format!("failed to run {command}: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::ChildProcess(format!("{command} failed with {}", output.status)));
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut listed = Vec::new();
+        let mut current: Option<ArchiveEntry> = None;
+        for line in text.lines().skip_while(|l| !l.starts_with("----------")).skip(1) {
+            let Some((key, value)) = line.split_once(" = ") else { continue };
+            match key {
+                "Path" => {
+                    listed.extend(current.take());
+                    current = Some(ArchiveEntry { name: value.to_string(), size: 0, dir: false });
+                }
+                "Size" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.size = value.parse().unwrap_or(0);
+                    }
+                }
+                "Folder" | "Attributes" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.dir |= value == "+" || (key == "Attributes" && value.starts_with('D'));
+                    }
+                }
+                _ => {}
+            }
+        }
+        listed.extend(current);
+        Ok(listed)
+    }
+}
+
+fn seven_zip_tool() -> Result<&'static str> {
+    let platform = Platform::current();
+    ["7z", "7za", "7zz"]
+        .into_iter()
+        .find(|t| platform.has_command(t))
+        .ok_or_else(|| Error::ChildProcess("7z, 7za or 7zz is required for .7z archives, install p7zip first".to_string()))
+}
+
+/// Moves everything found `depth` levels below `staging` into `dest`,
+/// replacing entries that already exist.
+fn hoist_entries(staging: &Path, dest: &Path, depth: usize) -> Result<usize> {
+    let mut level = vec![staging.to_path_buf()];
+    for _ in 0..depth {
+        level = level
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|rd| rd.filter_map(Result::ok).map(|e| e.path()))
+            .filter(|p| p.is_dir())
+            .collect();
+    }
+    let mut moved = 0;
+    for dir in level {
+        for entry in fs::read_dir(&dir).map_err(|e| Error::Io(format!("failed to read {}: {e}", dir.display())))?.filter_map(Result::ok) {
+            merge_into(&entry.path(), &dest.join(entry.file_name()))?;
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Moves `from` to `to`. A folder is merged into a folder already at `to`
+/// entry by entry, so hoisting `bin/` into `$HOME` adds to the user's
+/// `~/bin` instead of replacing it; only files are ever replaced, never a
+/// folder that was there before.
+fn merge_into(from: &Path, to: &Path) -> Result<()> {
+    let existing = fs::symlink_metadata(to).ok();
+    if existing.as_ref().is_some_and(|m| m.is_dir()) {
+        if !from.is_dir() {
+            return Err(Error::Io(format!("{} is a folder, not replacing it with a file", to.display())));
+        }
+        for entry in fs::read_dir(from).map_err(|e| Error::Io(format!("failed to read {}: {e}", from.display())))?.filter_map(Result::ok) {
+            merge_into(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return fs::remove_dir(from).map_err(|e| Error::Io(format!("failed to remove {}: {e}", from.display())));
+    }
+    if existing.is_some() {
+        fs::remove_file(to).map_err(|e| Error::Io(format!("failed to replace {}: {e}", to.display())))?;
+    }
+    fs::rename(from, to).map_err(|e| Error::Io(format!("failed to move into {}: {e}", to.display())))
+}
+
+/// Refuses archive paths that could write outside `dest`: anything but
+/// plain names (`..`, a root, a drive prefix), or a folder on the way that
+/// is a symlink, which an earlier entry of the same archive may have made.
+fn check_enclosed(dest: &Path, name: &Path, rel: &Path) -> Result<()> {
+    if !name.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(Error::Io(format!("refusing to extract {}: it points outside the destination", name.display())));
+    }
+    let mut ancestor = dest.to_path_buf();
+    let parents: Vec<_> = rel.components().collect();
+    for part in parents.iter().take(parents.len().saturating_sub(1)) {
+        ancestor.push(part);
+        if fs::symlink_metadata(&ancestor).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(Error::Io(format!("refusing to extract {}: {} is a symlink", name.display(), ancestor.display())));
+        }
+    }
+    Ok(())
+}
+
+/// Reader that tallies consumed bytes so progress can be derived from the
+/// position in the compressed stream.
+struct CountingReader<R> {
+    inner: R,
+    read: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// Sends SubProgress/Progress only when the whole percentage changes, so huge
+/// archives don't flood the UI channel.
+struct ProgressReporter<'a> {
+    tx: &'a mpsc::Sender<InstallMsg>,
+    last_percent: Option<u64>,
+}
+
+impl ProgressReporter<'_> {
+    fn update(&mut self, done: u64, total: u64, entries: usize) {
+        let percent = (done * 100).checked_div(total).unwrap_or(0).min(100);
+        if self.last_percent == Some(percent) {
+            return;
+        }
+        self.last_percent = Some(percent);
+        let _ = self.tx.send(InstallMsg::SubProgress(percent as f64 / 100.0));
+        let _ = self.tx.send(InstallMsg::Progress(String::new(), format!("Extracting ({entries} entries, {percent}%)"), None));
+    }
+}
+
+/// Drops the first `strip` components of an archive path. Returns `None` for
+/// entries that vanish entirely or would escape the destination.
+fn strip_path(path: &Path, strip: usize) -> Option<PathBuf> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let rest: PathBuf = parts.into_iter().skip(strip).collect();
+    if rest.as_os_str().is_empty() { None } else { Some(rest) }
+}
+
+fn decompress<'a, R: io::BufRead + 'a>(reader: R, compression: Compression) -> Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Compression::Zstd => Box::new(
+            zstd::stream::read::Decoder::with_buffer(reader).map_err(|e| Error::Io(format!("failed to init zstd decoder: {e}")))?,
+        ),
+        Compression::None => Box::new(reader),
+    })
+}
+
+/// Streams a (possibly compressed) tar archive into `dest`, reporting
+/// progress from the position in the compressed file. Returns the number of
+/// entries written.
+fn unpack_tar(
+    path: &Path,
+    compression: Compression,
+    dest: &Path,
+    strip: usize,
+    tx: &mpsc::Sender<InstallMsg>,
+    cancel: &CancellationToken,
+) -> Result<usize> {
+    fs::create_dir_all(dest).map_err(|e| Error::Io(format!("failed to create {}: {e}", dest.display())))?;
+    let file = File::open(path).map_err(|e| Error::Io(format!("failed to open {}: {e}", path.display())))?;
+    let total = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut progress = ProgressReporter { tx, last_percent: None };
+
+    let read = Rc::new(Cell::new(0));
+    let counted = io::BufReader::new(CountingReader { inner: file, read: read.clone() });
+    let mut archive = tar::Archive::new(decompress(counted, compression)?);
+    archive.set_overwrite(true);
+    archive.set_preserve_mtime(true);
+
+    let mut count = 0;
+    let mut pacer = crate::throttle::Pacer::new();
+    let entries = archive.entries().map_err(|e| Error::Io(format!("failed to read archive {}: {e}", path.display())))?;
+    for entry in entries {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled("Operation"));
+        }
+        let mut entry = entry.map_err(|e| Error::Io(format!("corrupt archive entry in {}: {e}", path.display())))?;
+        let name = entry.path().map(|p| p.into_owned()).map_err(|e| Error::Io(format!("invalid entry path: {e}")))?;
+        if strip == 0 {
+            entry
+                .unpack_in(dest)
+                .map_err(|e| Error::Io(format!("failed to extract {}: {e}", name.display())))?;
+        } else {
+            let Some(rel) = strip_path(&name, strip) else {
+                continue;
+            };
+            // `unpack_in` checks these itself; `unpack` does not.
+            check_enclosed(dest, &name, &rel)?;
+            let out_path = dest.join(rel);
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::Io(format!("failed to create {}: {e}", parent.display())))?;
+            }
+            if entry.header().entry_type().is_hard_link() {
+                // Hard link targets are archive paths too and need the same stripping.
+                let target = entry
+                    .link_name()
+                    .ok()
+                    .flatten()
+                    .and_then(|t| strip_path(&t, strip))
+                    .ok_or_else(|| Error::Io(format!("invalid hard link target for {}", name.display())))?;
+                let _ = fs::remove_file(&out_path);
+                fs::hard_link(dest.join(target), &out_path)
+                    .map_err(|e| Error::Io(format!("failed to link {}: {e}", out_path.display())))?;
+            } else {
+                entry
+                    .unpack(&out_path)
+                    .map_err(|e| Error::Io(format!("failed to extract {}: {e}", name.display())))?;
+            }
+        }
+        count += 1;
+        progress.update(read.get(), total, count);
+        pacer.tick();
+    }
+    progress.update(total, total, count);
+    Ok(count)
+}
+
+fn unpack_zip(
+    file: File,
+    dest: &Path,
+    strip: usize,
+    progress: &mut ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<usize> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| Error::Io(format!("failed to read zip: {e}")))?;
+    let total = archive.len() as u64;
+    let mut count = 0;
+    let mut pacer = crate::throttle::Pacer::new();
+
+    for i in 0..archive.len() {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled("Operation"));
+        }
+        let mut entry = archive.by_index(i).map_err(|e| Error::Io(format!("failed to read zip entry: {e}")))?;
+        let Some(rel) = entry.enclosed_name().and_then(|p| strip_path(&p, strip)) else {
+            continue;
+        };
+        let out_path = dest.join(rel);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| Error::Io(format!("failed to create {}: {e}", out_path.display())))?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::Io(format!("failed to create {}: {e}", parent.display())))?;
+            }
+            let mut out = File::create(&out_path).map_err(|e| Error::Io(format!("failed to create {}: {e}", out_path.display())))?;
+            io::copy(&mut entry, &mut out).map_err(|e| Error::Io(format!("failed to extract {}: {e}", out_path.display())))?;
+
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o7777));
+            }
+        }
+        count += 1;
+        progress.update(i as u64 + 1, total, count);
+        pacer.tick();
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rusty_rebase-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn merge_keeps_existing_folders() {
+        let dir = scratch("merge");
+        fs::create_dir_all(dir.join("home/bin")).unwrap();
+        fs::write(dir.join("home/bin/mine"), "mine").unwrap();
+        fs::write(dir.join("home/bin/tool"), "old").unwrap();
+        fs::create_dir_all(dir.join("staging/bin")).unwrap();
+        fs::write(dir.join("staging/bin/tool"), "new").unwrap();
+
+        merge_into(&dir.join("staging/bin"), &dir.join("home/bin")).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("home/bin/mine")).unwrap(), "mine");
+        assert_eq!(fs::read_to_string(dir.join("home/bin/tool")).unwrap(), "new");
+        assert!(!dir.join("staging/bin").exists());
+
+        fs::write(dir.join("file"), "x").unwrap();
+        assert!(merge_into(&dir.join("file"), &dir.join("home/bin")).is_err());
+        assert!(dir.join("home/bin/mine").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Claims gzip files that start with a marker, as a replacement for the
+    /// built-in handler would.
+    struct MarkedGzip;
+
+    impl ArchiveHandler for MarkedGzip {
+        fn name(&self) -> &'static str {
+            "marked gzip"
+        }
+        fn extensions(&self) -> &'static [&'static str] {
+            &[".marked.gz"]
+        }
+        fn matches_magic(&self, header: &[u8]) -> bool {
+            header.starts_with(b"\x1f\x8b\x08\x00MARKED")
+        }
+        fn extract(&self, _path: &Path, _ctx: &ExtractContext) -> Result<String> {
+            Ok("extracted".to_string())
+        }
+    }
+
+    #[test]
+    fn registered_handlers_win_over_builtin_ones() {
+        let dir = scratch("register");
+        let (marked, plain) = (dir.join("tool.tar.gz"), dir.join("other.tar.gz"));
+        fs::write(&marked, b"\x1f\x8b\x08\x00MARKED").unwrap();
+        fs::write(&plain, b"\x1f\x8b\x08\x00\x00\x00").unwrap();
+
+        register(Arc::new(MarkedGzip));
+        assert_eq!(find_handler(&marked, None).unwrap().name(), "marked gzip");
+        assert_eq!(find_handler(&plain, None).unwrap().name(), "tar.gz");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn strip_drops_leading_components() {
+        assert_eq!(strip_path(Path::new("top/bin/tool"), 1), Some(PathBuf::from("bin/tool")));
+        assert_eq!(strip_path(Path::new("top"), 1), None);
+        assert_eq!(strip_path(Path::new("top/../etc/passwd"), 1), None);
+    }
+
+    #[test]
+    fn enclosed_paths_are_checked() {
+        let dir = scratch("enclosed");
+        assert!(check_enclosed(&dir, Path::new("top/a/b"), Path::new("a/b")).is_ok());
+        assert!(check_enclosed(&dir, Path::new("/etc/passwd"), Path::new("etc/passwd")).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/tmp", dir.join("link")).unwrap();
+            assert!(check_enclosed(&dir, Path::new("top/link/x"), Path::new("link/x")).is_err());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
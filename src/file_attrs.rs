@@ -0,0 +1,193 @@
+//! What a backup keeps of a file beside its contents: permission bits,
+//! owner, modification time and extended attributes, recorded in the index
+//! and put back once the file or symlink is restored.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use filetime::FileTime;
+
+use crate::events::InstallMsg;
+use crate::restorer::{report, BackupIndexEntry};
+
+/// Fills the attribute fields of `entry` from the file at `path`, or from
+/// the link itself when `entry` is a symlink.
+pub(crate) fn record(path: &Path, entry: &mut BackupIndexEntry) {
+    let link = entry.symlink_target.is_some();
+    let Ok(metadata) = (if link { fs::symlink_metadata(path) } else { fs::metadata(path) }) else { return };
+    entry.mtime = Some(FileTime::from_last_modification_time(&metadata).unix_seconds());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        entry.mode = Some(metadata.mode() & 0o7777);
+        entry.uid = Some(metadata.uid());
+        entry.gid = Some(metadata.gid());
+        if !link && let Ok(names) = xattr::list_deref(path) {
+            for name in names {
+                if let Ok(Some(value)) = xattr::get_deref(path, &name) {
+                    entry.xattrs.insert(name.to_string_lossy().into_owned(), hex(&value));
+                }
+            }
+        }
+    }
+}
+
+/// How one restore puts attributes back, and the files where it could not,
+/// reported once at the end instead of once per file.
+#[derive(Default)]
+pub(crate) struct AttrRestore {
+    /// Give files an owner this machine does not have, and keep the setuid
+    /// bits of root's.
+    keep_owners: bool,
+    /// Users in `/etc/passwd`; other owners are foreign.
+    local_uids: HashSet<u32>,
+    owners: Vec<String>,
+    foreign: Vec<String>,
+    setid: Vec<String>,
+    xattrs: Vec<String>,
+    /// Mode or time, with the first error.
+    other: Vec<String>,
+    error: Option<String>,
+}
+
+impl AttrRestore {
+    pub(crate) fn new(keep_owners: bool) -> Self {
+        AttrRestore { keep_owners, local_uids: local_uids(), ..Default::default() }
+    }
+
+    pub(crate) fn report(self, tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>) {
+        if let Some(first) = self.owners.first() {
+            let n = self.owners.len();
+            report(tx, logs, format!("[warn] The owner of {n} files could not be restored without root; they now belong to you (e.g. {first})"));
+        }
+        if let Some(first) = self.foreign.first() {
+            let n = self.foreign.len();
+            report(tx, logs, format!("[warn] {n} files belonged to a user this machine does not have and now belong to you; restore with --keep-owners to keep their owner (e.g. {first})"));
+        }
+        if let Some(first) = self.setid.first() {
+            report(tx, logs, format!("[warn] The setuid and setgid bits of {} files were dropped, as their owner is root or could not be restored (e.g. {first})", self.setid.len()));
+        }
+        if let Some(first) = self.xattrs.first() {
+            report(tx, logs, format!("[warn] The extended attributes of {} files could not be restored (e.g. {first})", self.xattrs.len()));
+        }
+        if let Some(first) = self.other.first() {
+            let error = self.error.unwrap_or_default();
+            report(tx, logs, format!("[warn] The permissions or times of {} files could not be restored (e.g. {first}: {error})", self.other.len()));
+        }
+    }
+
+    fn failed(&mut self, relative: &str, error: std::io::Error) {
+        self.other.push(relative.to_string());
+        self.error.get_or_insert_with(|| error.to_string());
+    }
+}
+
+/// The user ids `/etc/passwd` lists.
+fn local_uids() -> HashSet<u32> {
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    passwd.lines().filter_map(|line| line.split(':').nth(2)?.parse().ok()).collect()
+}
+
+/// Creates a symlink at `path`, replacing a file or link already there.
+pub(crate) fn symlink(target: &str, path: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(path).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(path)?;
+    }
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, path)
+    }
+    #[cfg(not(unix))]
+    {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+/// Puts the attributes in `entry` back on the restored file or symlink at
+/// `path`. Owner before mode, as changing the owner clears setuid bits, and
+/// everything before the mode, which may make the file read-only. The index
+/// is not trusted with privileges: an owner this machine does not have is
+/// only given with `keep_owners`, and setuid and setgid bits are dropped
+/// unless the file went back to a local owner (root only with `keep_owners`).
+pub(crate) fn apply(path: &Path, entry: &BackupIndexEntry, attrs: &mut AttrRestore) {
+    let relative = entry.relative_path.as_str();
+    let link = entry.symlink_target.is_some();
+    if let Some(mtime) = entry.mtime {
+        let time = FileTime::from_unix_time(mtime, 0);
+        let set = if link { filetime::set_symlink_file_times(path, time, time) } else { filetime::set_file_mtime(path, time) };
+        if let Err(e) = set {
+            attrs.failed(relative, e);
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        if !link {
+            let mut failed = false;
+            for (name, value) in &entry.xattrs {
+                let set = unhex(value).map(|value| xattr::set(path, name, &value));
+                failed |= !matches!(set, Some(Ok(())));
+            }
+            if failed {
+                attrs.xattrs.push(relative.to_string());
+            }
+        }
+        let current = fs::symlink_metadata(path).ok();
+        let owned_by = |uid: u32, gid: u32| current.as_ref().is_some_and(|m| m.uid() == uid && m.gid() == gid);
+        let mut trusted = false;
+        if let (Some(uid), Some(gid)) = (entry.uid, entry.gid) {
+            let known = attrs.local_uids.contains(&uid);
+            let owned = if owned_by(uid, gid) {
+                true
+            } else if !known && !attrs.keep_owners {
+                attrs.foreign.push(relative.to_string());
+                false
+            } else if std::os::unix::fs::lchown(path, Some(uid), Some(gid)).is_ok() {
+                true
+            } else {
+                attrs.owners.push(relative.to_string());
+                false
+            };
+            trusted = owned && known && (uid != 0 || attrs.keep_owners);
+        }
+        if let Some(mut mode) = entry.mode
+            && !link
+        {
+            if mode & 0o6000 != 0 && !trusted {
+                mode &= !0o6000;
+                attrs.setid.push(relative.to_string());
+            }
+            if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+                attrs.failed(relative, e);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(unix)]
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = b"user.comment\x00\xff".to_vec();
+        assert_eq!(unhex(&hex(&bytes)), Some(bytes));
+        assert_eq!(unhex("00FFa0"), Some(vec![0x00, 0xff, 0xa0]));
+        assert_eq!(unhex(""), Some(Vec::new()));
+        assert_eq!(unhex("abc"), None);
+        assert_eq!(unhex("zz"), None);
+        assert_eq!(unhex("aé"), None);
+    }
+}
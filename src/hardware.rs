@@ -0,0 +1,228 @@
+//! Graphics hardware detection, for catalog entries and package steps
+//! limited with `only_if_hardware`.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+/// A GPU vendor that entries can be limited to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Hardware {
+    Nvidia,
+    Amd,
+    Intel,
+}
+
+impl Hardware {
+    pub fn label(self) -> &'static str {
+        match self {
+            Hardware::Nvidia => "NVIDIA",
+            Hardware::Amd => "AMD",
+            Hardware::Intel => "Intel",
+        }
+    }
+
+    fn from_pci_vendor(id: u32) -> Option<Self> {
+        match id {
+            0x10de => Some(Hardware::Nvidia),
+            0x1002 | 0x1022 => Some(Hardware::Amd),
+            0x8086 => Some(Hardware::Intel),
+            _ => None,
+        }
+    }
+}
+
+/// A graphics card or integrated GPU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gpu {
+    pub vendor: Hardware,
+    pub name: String,
+    /// Kernel or Vulkan driver in use, e.g. `nouveau`, `nvidia` or `radv`.
+    pub driver: Option<String>,
+}
+
+/// The GPUs of this machine, detected once per run: from `lspci`, then
+/// sysfs where lspci is not installed, then `vulkaninfo` (WSL and Windows,
+/// where the GPU is not a PCI device of the system).
+pub fn gpus() -> &'static [Gpu] {
+    static DETECTED: OnceLock<Vec<Gpu>> = OnceLock::new();
+    DETECTED.get_or_init(|| {
+        let lspci = output_of("lspci", &["-nnk"]).map(|out| parse_lspci(&out)).unwrap_or_default();
+        if !lspci.is_empty() {
+            return lspci;
+        }
+        let sysfs = from_sysfs();
+        if !sysfs.is_empty() {
+            return sysfs;
+        }
+        output_of("vulkaninfo", &["--summary"]).map(|out| parse_vulkaninfo(&out)).unwrap_or_default()
+    })
+}
+
+/// Whether this machine has `hardware`, or `hardware` in the config says so.
+/// Both are cached, so this is cheap enough for every frame.
+pub fn present(hardware: Hardware) -> bool {
+    match crate::config::read(|config| config.hardware.as_ref().map(|configured| configured.contains(&hardware))) {
+        Some(configured) => configured,
+        None => gpus().iter().any(|gpu| gpu.vendor == hardware),
+    }
+}
+
+/// The detected GPUs for messages, e.g. `NVIDIA GeForce RTX 3070 (nouveau)`.
+pub fn summary() -> String {
+    if let Some(configured) = crate::config::current().hardware {
+        let labels: Vec<&str> = configured.iter().map(|h| h.label()).collect();
+        return format!("{} (from config.toml)", if labels.is_empty() { "none".to_string() } else { labels.join(", ") });
+    }
+    if gpus().is_empty() {
+        return "no GPU found".to_string();
+    }
+    gpus()
+        .iter()
+        .map(|gpu| match &gpu.driver {
+            Some(driver) => format!("{} ({driver})", gpu.name),
+            None => gpu.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn output_of(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok().filter(|out| out.status.success())?;
+    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Display controllers (PCI class 03xx) from `lspci -nnk`, e.g.
+/// `01:00.0 VGA compatible controller [0300]: NVIDIA Corporation GA104 [GeForce RTX 3070] [10de:2484] (rev a1)`
+/// followed by an indented `Kernel driver in use: nouveau`.
+fn parse_lspci(output: &str) -> Vec<Gpu> {
+    let mut gpus: Vec<Gpu> = Vec::new();
+    let mut current = false;
+    for line in output.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if current
+                && let Some(driver) = line.trim().strip_prefix("Kernel driver in use:")
+                && let Some(gpu) = gpus.last_mut()
+            {
+                gpu.driver = Some(driver.trim().to_string());
+            }
+            continue;
+        }
+        current = false;
+        let Some((_, rest)) = line.split_once(' ') else { continue };
+        let Some((class, device)) = rest.split_once("]: ") else { continue };
+        if !class.rsplit('[').next().is_some_and(|code| code.starts_with("03")) {
+            continue;
+        }
+        // The last `[vendor:device]` pair; the name may contain brackets.
+        let Some(ids_at) = device.rfind(" [") else { continue };
+        let ids = device[ids_at + 2..].split(']').next().unwrap_or_default();
+        let Some(vendor) = ids.split(':').next().and_then(|id| u32::from_str_radix(id, 16).ok()).and_then(Hardware::from_pci_vendor) else {
+            continue;
+        };
+        gpus.push(Gpu { vendor, name: device[..ids_at].trim().to_string(), driver: None });
+        current = true;
+    }
+    gpus
+}
+
+/// Display controllers in /sys/bus/pci/devices, named by vendor only.
+fn from_sysfs() -> Vec<Gpu> {
+    let Ok(devices) = fs::read_dir("/sys/bus/pci/devices") else { return Vec::new() };
+    let read_hex = |path: &Path| {
+        let text = fs::read_to_string(path).ok()?;
+        u32::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok()
+    };
+    devices
+        .flatten()
+        .filter_map(|device| {
+            let path = device.path();
+            if read_hex(&path.join("class"))? >> 16 != 0x03 {
+                return None;
+            }
+            let vendor = Hardware::from_pci_vendor(read_hex(&path.join("vendor"))?)?;
+            let driver = fs::read_link(path.join("driver")).ok().and_then(|link| Some(link.file_name()?.to_string_lossy().into_owned()));
+            let id = read_hex(&path.join("device")).unwrap_or_default();
+            Some(Gpu { vendor, name: format!("{} GPU [{id:04x}]", vendor.label()), driver })
+        })
+        .collect()
+}
+
+/// Physical devices from `vulkaninfo --summary`, which lists
+/// `vendorID = 0x10de`, `deviceName = ...` and `driverName = ...` for each.
+/// Software renderers such as llvmpipe have no PCI vendor and are left out.
+fn parse_vulkaninfo(output: &str) -> Vec<Gpu> {
+    let mut gpus = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut flush = |fields: &mut Vec<(String, String)>| {
+        let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+        let vendor = field("vendorID")
+            .and_then(|id| u32::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+            .and_then(Hardware::from_pci_vendor);
+        if let Some(vendor) = vendor {
+            let name = field("deviceName").unwrap_or_else(|| format!("{} GPU", vendor.label()));
+            gpus.push(Gpu { vendor, name, driver: field("driverName") });
+        }
+        fields.clear();
+    };
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("GPU") && trimmed.ends_with(':') {
+            flush(&mut fields);
+        } else if let Some((key, value)) = trimmed.split_once('=') {
+            fields.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    flush(&mut fields);
+    gpus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lspci_lists_display_controllers_with_drivers() {
+        let output = "\
+00:02.0 VGA compatible controller [0300]: Intel Corporation Alder Lake-P GT2 [Iris Xe Graphics] [8086:46a6] (rev 0c)
+\tSubsystem: Lenovo Device [17aa:22e6]
+\tKernel driver in use: i915
+00:14.0 USB controller [0c03]: Intel Corporation Alder Lake PCH USB 3.2 xHCI Host Controller [8086:51ed] (rev 01)
+\tKernel driver in use: xhci_hcd
+01:00.0 3D controller [0302]: NVIDIA Corporation GA107M [GeForce RTX 3050 Mobile] [10de:25a2] (rev a1)
+\tKernel modules: nouveau, nvidia
+";
+        assert_eq!(parse_lspci(output), vec![
+            Gpu { vendor: Hardware::Intel, name: "Intel Corporation Alder Lake-P GT2 [Iris Xe Graphics]".to_string(), driver: Some("i915".to_string()) },
+            Gpu { vendor: Hardware::Nvidia, name: "NVIDIA Corporation GA107M [GeForce RTX 3050 Mobile]".to_string(), driver: None },
+        ]);
+        assert!(parse_lspci("").is_empty());
+    }
+
+    #[test]
+    fn vulkaninfo_skips_software_renderers() {
+        let output = "\
+Devices:
+========
+GPU0:
+\tapiVersion         = 1.3.255
+\tvendorID           = 0x1002
+\tdeviceName         = AMD Radeon RX 6700 XT (RADV NAVI22)
+\tdriverName         = radv
+GPU1:
+\tvendorID           = 0x10005
+\tdeviceName         = llvmpipe (LLVM 15.0.7, 256 bits)
+\tdriverName         = llvmpipe
+";
+        assert_eq!(parse_vulkaninfo(output), vec![Gpu {
+            vendor: Hardware::Amd,
+            name: "AMD Radeon RX 6700 XT (RADV NAVI22)".to_string(),
+            driver: Some("radv".to_string()),
+        }]);
+        assert!(parse_vulkaninfo("").is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Plain-text record of every install run, appended to in the working
+/// directory.
+pub const LOG_FILE: &str = "rusty_rebase_install.log";
+
+enum LogCmd {
+    Line(String),
+    Flush(Sender<()>),
+}
+
+/// Handle to the thread that owns the install log. Clones share the same
+/// writer, so lines sent from several threads never interleave mid-line.
+#[derive(Clone)]
+pub struct InstallLog {
+    tx: Sender<LogCmd>,
+}
+
+impl InstallLog {
+    /// Starts the writer thread. The file is opened on the first line, so
+    /// sessions that install nothing leave no log behind.
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || write_loop(PathBuf::from(LOG_FILE), rx));
+        InstallLog { tx }
+    }
+
+    pub fn write(&self, line: impl Into<String>) {
+        let _ = self.tx.send(LogCmd::Line(line.into()));
+    }
+
+    /// Blocks until every line sent so far is on disk.
+    pub fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        if self.tx.send(LogCmd::Flush(tx)).is_ok() {
+            let _ = rx.recv();
+        }
+    }
+}
+
+/// Appends lines as they arrive, flushing whenever the queue runs dry so a
+/// burst of output costs one write.
+fn write_loop(path: PathBuf, rx: Receiver<LogCmd>) {
+    let mut file: Option<BufWriter<File>> = None;
+    while let Ok(cmd) = rx.recv() {
+        let mut next = Some(cmd);
+        while let Some(cmd) = next {
+            match cmd {
+                LogCmd::Line(line) => {
+                    if file.is_none() {
+                        file = OpenOptions::new().create(true).append(true).open(&path).ok().map(BufWriter::new);
+                    }
+                    if let Some(file) = file.as_mut() {
+                        let _ = writeln!(file, "{line}");
+                    }
+                }
+                LogCmd::Flush(done) => {
+                    if let Some(file) = file.as_mut() {
+                        let _ = file.flush();
+                    }
+                    let _ = done.send(());
+                }
+            }
+            next = rx.try_recv().ok();
+        }
+        if let Some(file) = file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
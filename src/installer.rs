@@ -1,25 +1,41 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs;
-use std::sync::mpsc;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::{mpsc, Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
-use reqwest::blocking::Client;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use tokio_util::sync::CancellationToken;
 
-use crate::catalog::{SetupStep, SoftwareSpec, SourceSpec};
-use crate::distro::{DistroInfo, PackageManager};
+use crate::catalog::{GitScope, SetupStep, SoftwareSpec, SourceSpec, SwapMethod, VersionManager};
+use crate::command::{display_script, run_piped, run_script, CommandLine};
+use crate::distro::DistroInfo;
+use crate::error::{Error, Result};
+use crate::extract::{find_handler, ExtractContext};
+use crate::package_manager::check_package_names;
+use crate::platform::Platform;
 use crate::resolver::ResolvedAsset;
+use crate::units::format_size;
 
 #[derive(Debug)]
 pub struct InstallOutcome {
     pub logs: Vec<String>,
+    /// Directories created by `ensure_dirs` steps, for the lockfile.
+    pub created_dirs: Vec<PathBuf>,
+    /// Install folders and files put in place by the artifact and by
+    /// `copy_file`/`template` steps, for the state database.
+    pub files: Vec<PathBuf>,
+    /// Bytes fetched for the artifact, `None` when nothing was downloaded.
+    pub downloaded: Option<u64>,
 }
 
-fn home_dir() -> Result<PathBuf, String> {
+pub fn home_dir() -> Result<PathBuf, String> {
     dirs::home_dir().ok_or_else(|| "home directory not found".to_string())
 }
 
-fn expand_tilde(input: &str) -> Result<PathBuf, String> {
+pub fn expand_tilde(input: &str) -> Result<PathBuf, String> {
     if input == "~" {
         return home_dir();
     }
@@ -29,7 +45,59 @@ fn expand_tilde(input: &str) -> Result<PathBuf, String> {
     Ok(PathBuf::from(input))
 }
 
+/// The entry's `env` table with `~` and template variables expanded.
+pub fn entry_env(spec: &SoftwareSpec) -> Result<Vec<(String, String)>, String> {
+    let vars = crate::template::builtin_vars(spec);
+    spec.env
+        .iter()
+        .map(|(name, value)| {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid environment variable name '{name}'"));
+            }
+            let value = crate::template::render(value, &vars)?;
+            let value = if value == "~" || value.starts_with("~/") {
+                expand_tilde(&value)?.display().to_string()
+            } else {
+                value
+            };
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+pub fn default_download_dir() -> Result<PathBuf, String> {
+    Ok(home_dir()?.join("Downloads/rusty_rebase"))
+}
 
+/// The configured download folder, or the artifacts folder of the bundle
+/// being installed from.
+pub fn download_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = crate::bundle::artifacts_dir() {
+        return Ok(dir);
+    }
+    match crate::config::current().download_dir {
+        Some(dir) => expand_tilde(&dir),
+        None => default_download_dir(),
+    }
+}
+
+pub fn install_root(spec: &SoftwareSpec) -> Result<PathBuf, String> {
+    match spec.install_dir.as_deref() {
+        Some(dir) => expand_tilde(dir),
+        None => match crate::config::current().install_root {
+            Some(dir) => expand_tilde(&dir),
+            None => home_dir(),
+        },
+    }
+}
+
+/// Installs one catalog entry from its resolved artifact: runs the pre steps,
+/// downloads and verifies the artifact, installs or extracts it, then runs
+/// the post steps. Progress and prompts go out on `tx`; cancelling `cancel`
+/// stops it with [`Error::Cancelled`]. With `dry_run` nothing is changed and
+/// the logs list what would run. Blocks the calling thread, so call it from
+/// a plain thread or `spawn_blocking` rather than from async code.
+#[allow(clippy::too_many_arguments)]
 pub fn install_software(
     client: &Client,
     name: &str,
@@ -37,37 +105,126 @@ pub fn install_software(
     resolved: &ResolvedAsset,
     distro: &DistroInfo,
     dry_run: bool,
-    tx: &mpsc::Sender<crate::app::InstallMsg>,
-    cancel_rx: &mpsc::Receiver<()>,
-) -> Result<InstallOutcome, String> {
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+) -> Result<InstallOutcome> {
     let mut logs = Vec::new();
 
-    let pipe_log = |msg: String, tx: &mpsc::Sender<crate::app::InstallMsg>, logs: &mut Vec<String>| {
-        let _ = tx.send(crate::app::InstallMsg::Log(msg.clone()));
+    let pipe_log = |msg: String, tx: &mpsc::Sender<crate::events::InstallMsg>, logs: &mut Vec<String>| {
+        let _ = tx.send(crate::events::InstallMsg::Log(crate::events::LogLevel::classify(&msg), msg.clone()));
         logs.push(msg);
     };
 
     pipe_log(format!("== {name} ({}) ==", spec.display_name), tx, &mut logs);
     pipe_log(format!("resolved version: {}", resolved.version), tx, &mut logs);
+    if spec.requires_kvm {
+        for problem in crate::virt::kvm_problems() {
+            pipe_log(format!("[warn] kvm: {problem}"), tx, &mut logs);
+        }
+    }
 
-    let download_dir = home_dir().map_err(|e| e.to_string())?.join("Downloads/rusty_rebase");
+    let download_dir = download_dir()?;
     if !dry_run {
-        fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+        fs::create_dir_all(&download_dir).map_err(|e| Error::Io(e.to_string()))?;
+    }
+
+    if spec.source.has_artifact() {
+        // A dry run sends no requests and goes by the size seen when resolving.
+        let remote_size = if dry_run {
+            resolved.size
+        } else {
+            crate::runtime::block_on(crate::preflight::remote_size(client, &resolved.url)).or(resolved.size)
+        };
+        match remote_size {
+            Some(size) => {
+                let multiplier = crate::config::current()
+                    .extract_multiplier
+                    .unwrap_or(crate::preflight::DEFAULT_EXTRACT_MULTIPLIER);
+                match crate::preflight::check_disk_space(size, &download_dir, &install_root(spec)?, multiplier) {
+                    Ok(None) => {}
+                    Ok(Some(warning)) => {
+                        pipe_log(format!("[warning] {warning}"), tx, &mut logs);
+                        let _ = tx.send(crate::events::InstallMsg::Banner(format!("{}: {warning}", spec.display_name)));
+                    }
+                    Err(refusal) => {
+                        let _ = tx.send(crate::events::InstallMsg::Banner(format!("{}: {refusal}", spec.display_name)));
+                        return Err(Error::Io(refusal));
+                    }
+                }
+            }
+            None => pipe_log("download size unknown, skipping disk space check".to_string(), tx, &mut logs),
+        }
+
+        let config = crate::config::current();
+        let threshold = config.battery_threshold_mb.unwrap_or(crate::power::DEFAULT_BATTERY_THRESHOLD_MB) * 1024 * 1024;
+        if !dry_run
+            && remote_size.is_some_and(|size| size >= threshold)
+            && crate::power::on_battery()
+        {
+            let warning = format!("running on battery, {} download ahead", format_size(remote_size.unwrap_or_default()));
+            pipe_log(format!("[warning] {warning}"), tx, &mut logs);
+            let _ = tx.send(crate::events::InstallMsg::Banner(format!("{}: {warning}", spec.display_name)));
+            if config.defer_on_battery == Some(true) {
+                pipe_log("waiting for AC power before downloading".to_string(), tx, &mut logs);
+                wait_for_ac_power(name, tx, cancel)?;
+                pipe_log("AC power is back, continuing".to_string(), tx, &mut logs);
+                let _ = tx.send(crate::events::InstallMsg::Banner(String::new()));
+            }
+        }
     }
 
+    let step_timeout = spec.step_timeout_secs.map(Duration::from_secs);
+    let download_timeout = spec.download_timeout_secs.map(Duration::from_secs);
+    let runner = Runner { tx, cancel, timeout: step_timeout };
+
+    let mut created_dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut downloaded = None;
+    let mut content_type = None;
+    // The entry's `env` table plus secrets collected by earlier steps, handed
+    // to later commands as env vars.
+    let mut step_env = entry_env(spec)?;
+    if !step_env.is_empty() {
+        // Names only: values may be tokens.
+        let names: Vec<&str> = step_env.iter().map(|(k, _)| k.as_str()).collect();
+        pipe_log(format!("step environment: {}", names.join(" ")), tx, &mut logs);
+    }
+    // HOME of the sandboxed steps, shared by them and deleted when the
+    // install ends.
+    let mut sandbox_home: Option<ScratchDir> = None;
     for step in &spec.setup_steps {
-        if cancel_rx.try_recv().is_ok() {
-            return Err("Installation cancelled by user".to_string());
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled("Installation"));
+        }
+        if !dry_run && step.requires_root() {
+            ensure_root(tx)?;
         }
         match step {
-            SetupStep::Package { packages } => {
-                if let Some(cmd) = distro.pkg_manager.install_command(packages) {
+            SetupStep::Package { only_if_hardware: Some(hardware), .. } if !crate::hardware::present(*hardware) => {
+                pipe_log(format!("no {} graphics found, skipping package step", hardware.label()), tx, &mut logs);
+            }
+            SetupStep::Package { .. } => {
+                let (present, missing): (Vec<String>, Vec<String>) = step
+                    .packages_for(&distro.id, distro.pkg_manager.name())
+                    .iter()
+                    .cloned()
+                    .partition(|p| distro.pkg_manager.is_installed(p));
+                if !present.is_empty() {
+                    pipe_log(format!("already installed, skipping: {}", present.join(" ")), tx, &mut logs);
+                }
+                if missing.is_empty() {
+                    continue;
+                }
+                check_package_names(&missing)?;
+                if let Some(script) = distro.pkg_manager.install(&missing) {
+                    let env_names: Vec<String> = step_env.iter().map(|(k, _)| k.clone()).collect();
+                    let script: Vec<CommandLine> = script.into_iter().map(|cmd| cmd.keep_env(&env_names)).collect();
+                    let shown = display_script(&script);
                     if dry_run {
-                        pipe_log(format!("[dry-run] {cmd}"), tx, &mut logs);
+                        pipe_log(format!("[dry-run] {shown}"), tx, &mut logs);
                     } else {
-                        pipe_log(format!("running: {cmd}"), tx, &mut logs);
-                        let status = run_piped(&cmd, tx, cancel_rx)
-                            .map_err(|e| e.to_string())?;
+                        pipe_log(format!("running: {shown}"), tx, &mut logs);
+                        let status = run_script(&script, &step_env, tx, cancel, step_timeout)?;
                         pipe_log(format!("package install exit status: {status}"), tx, &mut logs);
                     }
                 } else {
@@ -75,280 +232,1347 @@ pub fn install_software(
                 }
             }
             SetupStep::PathHint { value } => {
-                let install_root = match spec.install_dir.as_deref() {
-                    Some(dir) => expand_tilde(dir).map_err(|e| e.to_string())?,
-                    None => home_dir().map_err(|e| e.to_string())?,
-                };
+                let install_root = install_root(spec)?;
                 let rendered = value.replace("<install_root>", &install_root.to_string_lossy());
                 
-                let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-                let profile_name = if shell.contains("zsh") {
-                    ".zshrc"
-                } else if shell.contains("fish") {
-                    ".config/fish/config.fish"
-                } else {
-                    ".bashrc"
-                };
-                
-                let profile_path = home_dir().map_err(|e| e.to_string())?.join(profile_name);
-                
-                let export_line = if shell.contains("fish") {
-                    format!("fish_add_path {}", rendered)
-                } else {
-                    format!("export PATH=\"$PATH:{}\"", rendered)
-                };
+                let platform = Platform::current();
+                let profile_path = platform.profile_path(&home_dir()?);
+                let export_line = platform.path_export_line(&rendered);
 
-                if dry_run {
+                let content = fs::read_to_string(&profile_path).unwrap_or_default();
+                if profile_has_path(&content, &rendered) {
+                    pipe_log(format!("path already configured in {}", profile_path.display()), tx, &mut logs);
+                } else if dry_run {
                     pipe_log(format!("[dry-run] append to {}: {}", profile_path.display(), export_line), tx, &mut logs);
                 } else {
-                    let content = fs::read_to_string(&profile_path).unwrap_or_default();
-                    if content.contains(&export_line) {
-                        pipe_log(format!("path already configured in {}", profile_path.display()), tx, &mut logs);
-                    } else {
-                        match std::fs::OpenOptions::new()
-                            .create(true)
-                            .append(true)
-                            .open(&profile_path)
-                        {
-                            Ok(mut file) => {
-                                if let Err(e) = writeln!(file, "\n# Added by rusty_rebase\n{}", export_line) {
-                                    logs.push(format!("failed to write to profile: {e}"));
-                                } else {
-                                    pipe_log(format!("added {} to {}", rendered, profile_path.display()), tx, &mut logs);
-                                }
-                            }
-                            Err(e) => {
-                                logs.push(format!("failed to open profile: {e}"));
+                    match std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&profile_path)
+                    {
+                        Ok(mut file) => {
+                            if let Err(e) = writeln!(file, "\n# Added by rusty_rebase\n{}", export_line) {
+                                logs.push(format!("failed to write to profile: {e}"));
+                            } else {
+                                pipe_log(format!("added {} to {}", rendered, profile_path.display()), tx, &mut logs);
                             }
                         }
+                        Err(e) => {
+                            logs.push(format!("failed to open profile: {e}"));
+                        }
                     }
                 }
             }
             SetupStep::Note { value } => {
                 logs.push(format!("note: {value}"));
             }
-            SetupStep::Shell { command } => {
-                let sys_arch = match std::env::consts::ARCH {
-                    "x86_64" => "amd64",
-                    "aarch64" => "arm64",
-                    "x86" => "386",
-                    other => other,
+            SetupStep::Secret { name, env, prompt } => {
+                if dry_run {
+                    pipe_log(format!("[dry-run] read secret {name} into ${env}"), tx, &mut logs);
+                    continue;
+                }
+                let value = match crate::secrets::lookup(name) {
+                    Some(value) => {
+                        pipe_log(format!("secret {name} loaded from keyring"), tx, &mut logs);
+                        value
+                    }
+                    None => match crate::answers::secret(name) {
+                        Some(answer) => {
+                            let value = answer?;
+                            pipe_log(format!("secret {name} taken from the answers file"), tx, &mut logs);
+                            value
+                        }
+                        None => {
+                            let prompt = prompt.clone().unwrap_or_else(|| format!("Enter {name}"));
+                            let value = request_secret(&prompt, tx).ok_or_else(|| format!("secret {name} was not provided"))?;
+                            match crate::secrets::store(name, &value) {
+                                Ok(()) => pipe_log(format!("secret {name} stored in keyring"), tx, &mut logs),
+                                Err(e) => pipe_log(format!("[warn] could not store secret {name} in keyring: {e}"), tx, &mut logs),
+                            }
+                            value
+                        }
+                    },
                 };
-                let dash_arch = std::env::consts::ARCH.replace('_', "-");
-                let processed_command = command
-                    .replace("{arch}", sys_arch)
-                    .replace("{xarch}", std::env::consts::ARCH)
-                    .replace("{xarch_dash}", &dash_arch);
+                step_env.push((env.clone(), value));
+            }
+            SetupStep::EnsureDirs { paths } => {
+                let vars = crate::template::builtin_vars(spec);
+                for raw in paths {
+                    let dir = expand_tilde(&crate::template::render(raw, &vars)?)?;
+                    if dir.is_dir() {
+                        continue;
+                    }
+                    if dry_run {
+                        pipe_log(format!("[dry-run] create {}", dir.display()), tx, &mut logs);
+                        continue;
+                    }
+                    create_owned_dir(&dir)?;
+                    pipe_log(format!("created {}", dir.display()), tx, &mut logs);
+                    created_dirs.push(dir);
+                }
+            }
+            SetupStep::CopyFile { from, to, mode, requires_root } => {
+                let (res, dest) = apply_copy_file(spec, from, to, mode.as_deref(), *requires_root, dry_run, &runner)?;
+                pipe_log(res, tx, &mut logs);
+                files.push(dest);
+            }
+            SetupStep::Template { source, dest, vars } => {
+                let (res, dest) = apply_template(spec, source, dest, vars, dry_run)?;
+                pipe_log(res, tx, &mut logs);
+                files.push(dest);
+            }
+            SetupStep::GitConfig { key, value, scope } => {
+                let res = apply_git_config(key, value, *scope, dry_run, &runner)?;
+                pipe_log(res, tx, &mut logs);
+            }
+            SetupStep::Swap { method, size_mb } => {
+                for line in apply_swap(*method, *size_mb, distro, dry_run, &runner)? {
+                    pipe_log(line, tx, &mut logs);
+                }
+            }
+            SetupStep::Sysctl { values } => {
+                for line in apply_sysctl(values, dry_run, &runner)? {
+                    pipe_log(line, tx, &mut logs);
+                }
+            }
+            SetupStep::Cargo { .. } | SetupStep::Npm { .. } | SetupStep::Pipx { .. } => {
+                let Some((toolchain, packages, global)) = crate::toolchain::step_toolchain(step) else { continue };
+                check_package_names(packages)?;
+                let program = match toolchain.locate() {
+                    Some(program) => program,
+                    None if dry_run => std::path::PathBuf::from(toolchain.program()),
+                    None => {
+                        return Err(Error::Catalog(format!("{} not found; install the entry that provides it before this one", toolchain.program())));
+                    }
+                };
+                let root = install_root(spec)?;
+                let installed = toolchain.installed(&program, global, &root);
+                let (present, missing): (Vec<String>, Vec<String>) =
+                    packages.iter().cloned().partition(|p| installed.contains(p));
+                if !present.is_empty() {
+                    pipe_log(format!("already installed with {}, skipping: {}", toolchain.program(), present.join(" ")), tx, &mut logs);
+                }
+                if missing.is_empty() {
+                    continue;
+                }
+                let script = toolchain.install(&program, &missing, global, &root);
+                let shown = display_script(&script);
+                if dry_run {
+                    pipe_log(format!("[dry-run] {shown}"), tx, &mut logs);
+                } else {
+                    pipe_log(format!("running: {shown}"), tx, &mut logs);
+                    let status = run_script(&script, &step_env, tx, cancel, step_timeout)?;
+                    if !status.success() {
+                        return Err(Error::ChildProcess(format!("{shown} failed with {status}")));
+                    }
+                    pipe_log(format!("installed with {}: {}", toolchain.program(), missing.join(" ")), tx, &mut logs);
+                }
+            }
+            SetupStep::VersionManager { manager, versions, default } => {
+                for line in apply_version_manager(*manager, versions, default.as_deref(), &step_env, dry_run, &runner)? {
+                    pipe_log(line, tx, &mut logs);
+                }
+            }
+            SetupStep::Shell { command, requires_root, trusted, check_command } => {
+                let processed_command = expand_arch_vars(command);
+
+                let platform = Platform::current();
+                let sandboxed = !trusted && crate::sandbox::enabled();
+                if sandboxed && *requires_root {
+                    pipe_log(format!("[warn] sandbox: skipped untrusted root step: {processed_command}"), tx, &mut logs);
+                    continue;
+                }
+                let env_names: Vec<String> = step_env.iter().map(|(k, _)| k.clone()).collect();
+                if sandboxed && (!dry_run || check_command.is_some()) && sandbox_home.is_none() {
+                    let dir = private_dir().map_err(|e| Error::Io(format!("failed to create sandbox home: {e}")))?;
+                    sandbox_home = Some(ScratchDir(dir));
+                }
+                // A dry run without a check runs nothing, so it needs no folder.
+                let home = sandbox_home.as_ref().map_or_else(|| std::env::temp_dir().join("rusty_rebase-sandbox"), |dir| dir.0.clone());
+                let invoke = |command: &str| {
+                    if sandboxed {
+                        crate::sandbox::wrap(platform.shell(command), &home)
+                    } else if *requires_root {
+                        Ok((platform.root_shell(command, &env_names), ""))
+                    } else {
+                        Ok((platform.user_shell(command, &env_names), ""))
+                    }
+                };
+
+                // The check is expected to be read-only, so it also runs in
+                // dry-run mode to show what a real run would skip.
+                if let Some(check) = check_command {
+                    let check = expand_arch_vars(check);
+                    let (invocation, _) = invoke(&check)?;
+                    let satisfied = run_piped(&invocation, &step_env, tx, cancel, step_timeout)?.success();
+                    if satisfied {
+                        pipe_log(format!("already satisfied ({check}), skipping: {processed_command}"), tx, &mut logs);
+                        continue;
+                    }
+                }
+                let (invocation, kind) = invoke(&processed_command)?;
+                if sandboxed {
+                    pipe_log(format!("sandbox: {kind}, HOME={}", home.display()), tx, &mut logs);
+                }
 
                 if dry_run {
                     pipe_log(format!("[dry-run] shell: {}", processed_command), tx, &mut logs);
                 } else {
                     pipe_log(format!("running shell: {}", processed_command), tx, &mut logs);
-                    let status = run_piped(&processed_command, tx, cancel_rx)
-                        .map_err(|e| e.to_string())?;
+                    let status = run_piped(&invocation, &step_env, tx, cancel, step_timeout)?;
                     pipe_log(format!("shell command exit status: {status}"), tx, &mut logs);
                 }
             }
         }
     }
 
-    if !matches!(spec.source, SourceSpec::PackageManager) {
-        let archive_path = download_dir.join(&resolved.file_name);
+    if spec.source.has_artifact() {
+        let archive_path = artifact_path(&download_dir, resolved)?;
+        let lock = download_lock(&archive_path);
+        let _download = match lock.clone().try_lock_owned() {
+            Ok(guard) => guard,
+            Err(_) => {
+                let _ = tx.send(crate::events::InstallMsg::Progress(name.to_string(), "Waiting for the download fetched ahead".to_string(), None));
+                crate::runtime::block_on(async {
+                    tokio::select! {
+                        _ = cancel.cancelled() => Err(Error::Cancelled("Download")),
+                        guard = lock.lock_owned() => Ok(guard),
+                    }
+                })?
+            }
+        };
+        let ahead = take_fetched_ahead(&archive_path);
+        if let Some((_, mime)) = &ahead {
+            content_type = mime.clone();
+        }
+        // A cached copy that fails verification is set aside, not reused. One
+        // in the bundle is left alone and the install fails instead.
+        if !dry_run
+            && archive_path.is_file()
+            && let Err(e) = verify_download(&archive_path, resolved, content_type.as_deref())
+        {
+            let e = e.map_message(|m| format!("cached copy failed verification: {m}"));
+            if in_bundle(&archive_path) {
+                return Err(e);
+            }
+            if let Some(line) = set_aside(&archive_path, name, resolved, &e) {
+                pipe_log(line, tx, &mut logs);
+            }
+        }
         if dry_run {
             pipe_log(format!("[dry-run] download {} -> {}", resolved.url, archive_path.display()), tx, &mut logs);
+        } else if let Some((bytes, _)) = ahead.filter(|_| archive_path.is_file()) {
+            pipe_log(format!("using the download fetched ahead {}", archive_path.display()), tx, &mut logs);
+            downloaded = Some(bytes);
+        } else if is_cached(&archive_path, resolved) {
+            pipe_log(format!("using cached download {}", archive_path.display()), tx, &mut logs);
         } else {
             pipe_log(format!("downloading from {}", resolved.url), tx, &mut logs);
-            
-            download_to_file(client, &resolved.url, &archive_path, tx, cancel_rx)?;
-            
+            let partial = download_dir.join(format!("{}.part", resolved.file_name));
+            let fetched = crate::runtime::block_on(download_to_file(client, &resolved.url, &partial, tx, cancel, download_timeout))
+                .map(|(bytes, mime)| {
+                    downloaded = Some(bytes);
+                    content_type = mime;
+                });
+            // Only a verified download takes its final name.
+            if let Err(e) = fetched.and_then(|()| verify_download(&partial, resolved, content_type.as_deref())) {
+                if let Some(line) = set_aside(&partial, name, resolved, &e) {
+                    pipe_log(line, tx, &mut logs);
+                }
+                return Err(e);
+            }
+            fs::rename(&partial, &archive_path).map_err(|e| Error::Io(format!("failed to move {}: {e}", partial.display())))?;
+
             pipe_log(format!("downloaded to {}", archive_path.display()), tx, &mut logs);
         }
 
-        let install_root = match spec.install_dir.as_deref() {
-            Some(dir) => expand_tilde(dir).map_err(|e| e.to_string())?,
-            None => home_dir().map_err(|e| e.to_string())?,
-        };
+        let install_root = install_root(spec)?;
 
         if !dry_run {
-            fs::create_dir_all(&install_root).map_err(|e| e.to_string())?;
+            fs::create_dir_all(&install_root).map_err(|e| Error::Io(e.to_string()))?;
         }
 
-        let is_vscode = match &spec.source {
-            SourceSpec::OfficialSource { id: Some(v), .. } if v == "vscode" => true,
-            _ => false,
-        };
-        if is_vscode {
-            let res = handle_vscode_install(&archive_path, distro, dry_run, tx, cancel_rx)?;
+        let is_vscode = matches!(&spec.source, SourceSpec::OfficialSource { id: Some(v), .. } if v == "vscode");
+        if is_package_artifact(&resolved.file_name) {
+            if !dry_run {
+                ensure_root(tx)?;
+            }
+            let res = install_package_artifact(&archive_path, distro, dry_run, tx, cancel, step_timeout)?;
+            pipe_log(res, tx, &mut logs);
+        } else if is_vscode && Platform::current() == Platform::Linux {
+            let res = handle_vscode_install(&archive_path, distro, dry_run, tx, cancel, step_timeout)?;
             pipe_log(res, tx, &mut logs);
         } else {
-            let extracted = extract_archive(&archive_path, &install_root, dry_run, tx, cancel_rx)?;
+            let target = match &spec.extract_subdir {
+                Some(sub) => install_root.join(sub),
+                None => install_root.clone(),
+            };
+            let ctx = ExtractContext { dest: &target, strip: spec.strip_components, tx, cancel, timeout: step_timeout };
+            let extracted = extract_archive(&archive_path, content_type.as_deref(), dry_run, &ctx)?;
             pipe_log(extracted, tx, &mut logs);
+            files.push(target);
         }
-    } else {
+    } else if let SourceSpec::BuildFromSource { build_steps, .. } = &spec.source {
+        for line in build_from_source(name, spec, resolved, build_steps, &step_env, dry_run, &runner)? {
+            pipe_log(line, tx, &mut logs);
+        }
+        files.push(install_root(spec)?);
+    } else if matches!(spec.source, SourceSpec::PackageManager) {
         logs.push("source is package-only, skipping download/extract".to_string());
+    } else {
+        logs.push("entry has setup steps only, nothing to download".to_string());
     }
 
-    Ok(InstallOutcome { logs })
+    Ok(InstallOutcome { logs, created_dirs, files, downloaded })
 }
 
-fn download_to_file(
+/// Clones the resolved tag into the download folder and runs `build_steps`
+/// inside the checkout with `$PREFIX` set to the install root.
+fn build_from_source(
+    key: &str,
+    spec: &SoftwareSpec,
+    resolved: &ResolvedAsset,
+    build_steps: &[String],
+    step_env: &[(String, String)],
+    dry_run: bool,
+    runner: &Runner,
+) -> Result<Vec<String>> {
+    let checkout = artifact_path(&download_dir()?, resolved)?;
+    let root = install_root(spec)?;
+    // The tag, as published or with the `v` the resolver dropped.
+    let tags = [format!("v{}", resolved.version), resolved.version.clone()];
+    let clone = |tag: &str| {
+        CommandLine::new("git")
+            .args(["-c", "advice.detachedHead=false", "clone", "--depth", "1", "--branch", tag, "--", resolved.url.as_str()])
+            .arg(checkout.display().to_string())
+    };
+    if dry_run {
+        let mut lines = vec![format!("[dry-run] {}", clone(&tags[0]))];
+        lines.extend(build_steps.iter().map(|step| format!("[dry-run] build: {}", expand_arch_vars(step))));
+        return Ok(lines);
+    }
+    if crate::sandbox::enabled() {
+        return Err(Error::Catalog("sandbox mode is on and build steps cannot run sandboxed; build this entry without --sandbox".to_string()));
+    }
+    let platform = Platform::current();
+    if !platform.has_command("git") {
+        return Err(Error::ChildProcess("git is required to build from source".to_string()));
+    }
+
+    let _ = runner.tx.send(crate::events::InstallMsg::Progress(key.to_string(), "Cloning".to_string(), None));
+    if checkout.exists() {
+        fs::remove_dir_all(&checkout).map_err(|e| Error::Io(format!("failed to clear old checkout {}: {e}", checkout.display())))?;
+    }
+    let mut cloned = None;
+    for tag in &tags {
+        if runner.run(&clone(tag))?.success() {
+            cloned = Some(tag);
+            break;
+        }
+    }
+    let tag = cloned.ok_or_else(|| Error::ChildProcess(format!("could not clone tag {} of {}", resolved.version, resolved.url)))?;
+    let mut lines = vec![format!("cloned {tag} of {} into {}", resolved.url, checkout.display())];
+
+    fs::create_dir_all(&root).map_err(|e| Error::Io(format!("failed to create {}: {e}", root.display())))?;
+    let mut env = step_env.to_vec();
+    env.push(("PREFIX".to_string(), root.display().to_string()));
+    let env_names: Vec<String> = env.iter().map(|(k, _)| k.clone()).collect();
+    let dir = checkout.display().to_string();
+    for (i, step) in build_steps.iter().enumerate() {
+        let _ = runner.tx.send(crate::events::InstallMsg::Progress(
+            key.to_string(),
+            format!("Building ({}/{})", i + 1, build_steps.len()),
+            None,
+        ));
+        let _ = runner.tx.send(crate::events::InstallMsg::SubProgress(i as f64 / build_steps.len() as f64));
+        let command = expand_arch_vars(step);
+        let script = match platform {
+            Platform::Linux => format!("cd {} && {command}", crate::command::quote(&dir)),
+            Platform::Windows => format!("Set-Location '{}'; {command}", dir.replace('\'', "''")),
+        };
+        let status = run_piped(&platform.user_shell(&script, &env_names), &env, runner.tx, runner.cancel, runner.timeout)?;
+        if !status.success() {
+            return Err(Error::ChildProcess(format!("build step '{command}' failed with {status}")));
+        }
+        lines.push(format!("build step {}/{} done: {command}", i + 1, build_steps.len()));
+    }
+    let _ = runner.tx.send(crate::events::InstallMsg::SubProgress(1.0));
+    Ok(lines)
+}
+
+/// Fills `{arch}` (amd64/arm64), `{xarch}` (x86_64/aarch64) and
+/// `{xarch_dash}` (x86-64) into a shell step or a source's patterns.
+pub fn expand_arch_vars(command: &str) -> String {
+    let sys_arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        other => other,
+    };
+    let dash_arch = std::env::consts::ARCH.replace('_', "-");
+    command
+        .replace("{arch}", sys_arch)
+        .replace("{xarch}", std::env::consts::ARCH)
+        .replace("{xarch_dash}", &dash_arch)
+}
+
+/// Post-install sanity check: package steps must report an installed
+/// version and path hints must point at an existing directory.
+pub fn verify_install(spec: &SoftwareSpec, distro: &DistroInfo) -> String {
+    let mut problems = Vec::new();
+    let mut checks = 0;
+    for step in &spec.setup_steps {
+        match step {
+            SetupStep::Package { .. } => {
+                for package in step.packages_for(&distro.id, distro.pkg_manager.name()) {
+                    checks += 1;
+                    if !distro.pkg_manager.is_installed(package) {
+                        problems.push(format!("package {package} missing"));
+                    }
+                }
+            }
+            SetupStep::PathHint { value } => {
+                checks += 1;
+                if let Ok(root) = install_root(spec) {
+                    let rendered = value.replace("<install_root>", &root.to_string_lossy());
+                    if !Path::new(&rendered).exists() {
+                        problems.push(format!("{rendered} missing"));
+                    }
+                }
+            }
+            SetupStep::GitConfig { key, value, scope } => {
+                checks += 1;
+                if git_config_value(key, *scope).as_deref() != Some(value.as_str()) {
+                    problems.push(format!("git {key} not set"));
+                }
+            }
+            SetupStep::Cargo { .. } | SetupStep::Npm { .. } | SetupStep::Pipx { .. } => {
+                let Some((toolchain, packages, global)) = crate::toolchain::step_toolchain(step) else { continue };
+                let Some(program) = toolchain.locate() else {
+                    checks += 1;
+                    problems.push(format!("{} missing", toolchain.program()));
+                    continue;
+                };
+                let installed = install_root(spec).map(|root| toolchain.installed(&program, global, &root)).unwrap_or_default();
+                for package in packages {
+                    checks += 1;
+                    if !installed.contains(package) {
+                        problems.push(format!("{} package {package} missing", toolchain.program()));
+                    }
+                }
+            }
+            SetupStep::VersionManager { manager, versions, .. } if Platform::current() == Platform::Linux => {
+                checks += 1;
+                if !manager.installed() {
+                    problems.push(format!("{} missing", manager.label()));
+                    continue;
+                }
+                for version in versions {
+                    checks += 1;
+                    if !manager.has_version(version) {
+                        problems.push(format!("{} {version} missing", manager.label()));
+                    }
+                }
+            }
+            SetupStep::Swap { method, .. } if Platform::current() == Platform::Linux => {
+                checks += 1;
+                let active = match method {
+                    SwapMethod::Zram => crate::tuning::zram_active(),
+                    SwapMethod::Swapfile => crate::tuning::active_swaps().iter().any(|s| s == crate::tuning::SWAPFILE),
+                };
+                if !active {
+                    problems.push(format!("{} swap not active", method.label()));
+                }
+            }
+            SetupStep::Sysctl { .. } if Platform::current() == Platform::Linux => {
+                checks += 1;
+                if !Path::new(crate::tuning::SYSCTL_FILE).exists() {
+                    problems.push(format!("{} missing", crate::tuning::SYSCTL_FILE));
+                }
+            }
+            _ => {}
+        }
+    }
+    if checks == 0 {
+        "not verifiable".to_string()
+    } else if problems.is_empty() {
+        format!("ok ({checks} checks)")
+    } else {
+        problems.join(", ")
+    }
+}
+
+/// Blocks until the machine is plugged in, re-checking every few seconds.
+fn wait_for_ac_power(
+    key: &str,
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let _ = tx.send(crate::events::InstallMsg::Progress(key.to_string(), "Waiting for AC power".to_string(), None));
+    while crate::power::on_battery() {
+        let woken = crate::runtime::block_on(tokio::time::timeout(Duration::from_secs(5), cancel.cancelled()));
+        if woken.is_ok() {
+            return Err(Error::Cancelled("Installation"));
+        }
+    }
+    let _ = tx.send(crate::events::InstallMsg::Progress(key.to_string(), "Installing".to_string(), None));
+    Ok(())
+}
+
+/// Makes sure the next sudo call will not prompt. When no credential is
+/// cached the front end is asked to authenticate, so the password prompt
+/// appears right before the first privileged step rather than up front.
+fn ensure_root(tx: &mpsc::Sender<crate::events::InstallMsg>) -> Result<()> {
+    if !Platform::current().needs_sudo_auth() {
+        return Ok(());
+    }
+    if crate::sudo::cached() {
+        return Ok(());
+    }
+    let (reply_tx, reply_rx) = mpsc::channel();
+    tx.send(crate::events::InstallMsg::Elevate(reply_tx))
+        .map_err(|_| "no front end to authenticate with".to_string())?;
+    match reply_rx.recv() {
+        Ok(true) => Ok(()),
+        _ => Err(Error::ChildProcess("root authentication failed".to_string())),
+    }
+}
+
+/// Creates `dir` and its missing parents. When rusty_rebase runs under sudo
+/// the new directories are handed to the invoking user, so they do not end
+/// up owned by root inside their home.
+fn create_owned_dir(dir: &Path) -> Result<()> {
+    let mut missing = Vec::new();
+    let mut cursor = Some(dir);
+    while let Some(path) = cursor
+        && !path.exists()
+    {
+        missing.push(path.to_path_buf());
+        cursor = path.parent();
+    }
+    fs::create_dir_all(dir).map_err(|e| Error::Io(format!("failed to create {}: {e}", dir.display())))?;
+
+    #[cfg(unix)]
+    if let (Ok(uid), Ok(gid)) = (std::env::var("SUDO_UID"), std::env::var("SUDO_GID"))
+        && let (Ok(uid), Ok(gid)) = (uid.parse::<u32>(), gid.parse::<u32>())
+    {
+        for path in &missing {
+            std::os::unix::fs::chown(path, Some(uid), Some(gid))
+                .map_err(|e| Error::Io(format!("failed to hand {} to uid {uid}: {e}", path.display())))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = missing;
+    Ok(())
+}
+
+/// Expands `~` in a catalog path and resolves relative paths against the
+/// directory holding the catalog.
+fn catalog_relative(path: &str) -> Result<PathBuf> {
+    let path = expand_tilde(path)?;
+    if path.is_relative() {
+        Ok(crate::config::find_catalog()
+            .and_then(|c| c.parent().map(|p| p.join(&path)))
+            .unwrap_or(path))
+    } else {
+        Ok(path)
+    }
+}
+
+/// Copies a file into place and applies `mode`. Root-owned destinations go
+/// through `install -D -m` under sudo.
+fn apply_copy_file(
+    spec: &SoftwareSpec,
+    from: &str,
+    to: &str,
+    mode: Option<&str>,
+    requires_root: bool,
+    dry_run: bool,
+    runner: &Runner,
+) -> Result<(String, PathBuf)> {
+    let vars = crate::template::builtin_vars(spec);
+    let from_path = catalog_relative(&crate::template::render(from, &vars)?)?;
+    let to_path = expand_tilde(&crate::template::render(to, &vars)?)?;
+    let mode = mode
+        .map(|m| u32::from_str_radix(m.trim_start_matches("0o"), 8).map_err(|_| format!("invalid file mode '{m}'")))
+        .transpose()?;
+
+    if !from_path.is_file() {
+        return Err(Error::Io(format!("{} does not exist", from_path.display())));
+    }
+    let same_content = fs::read(&to_path).ok() == fs::read(&from_path).ok();
+    if same_content && mode.is_none_or(|m| file_mode(&to_path) == Some(m)) {
+        return Ok((format!("{} already up to date", to_path.display()), to_path.clone()));
+    }
+
+    if requires_root && Platform::current() == Platform::Linux {
+        let cmd = CommandLine::new("install")
+            .args(["-D", "-m", &format!("{:o}", mode.unwrap_or(0o644))])
+            .arg("--")
+            .args([from_path.display().to_string(), to_path.display().to_string()])
+            .elevated();
+        if dry_run {
+            return Ok((format!("[dry-run] {cmd}"), to_path.clone()));
+        }
+        let status = runner.run(&cmd)?;
+        return if status.success() {
+            Ok((format!("copied {} -> {}", from_path.display(), to_path.display()), to_path.clone()))
+        } else {
+            Err(Error::ChildProcess(format!("copying to {} failed with {status}", to_path.display())))
+        };
+    }
+
+    if dry_run {
+        return Ok((format!("[dry-run] copy {} -> {}", from_path.display(), to_path.display()), to_path.clone()));
+    }
+    if let Some(parent) = to_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::Io(format!("failed to create {}: {e}", parent.display())))?;
+    }
+    fs::copy(&from_path, &to_path).map_err(|e| Error::Io(format!("failed to copy to {}: {e}", to_path.display())))?;
+    if let Some(mode) = mode {
+        set_file_mode(&to_path, mode)?;
+    }
+    let copied = format!("copied {} -> {}", from_path.display(), to_path.display());
+    Ok((copied, to_path))
+}
+
+/// Writes `content` to a root-owned `dest` through `install -m` under sudo,
+/// leaving it alone when it already matches.
+fn write_root_file(content: &str, dest: &str, dry_run: bool, runner: &Runner) -> Result<String> {
+    if fs::read_to_string(dest).is_ok_and(|current| current == content) {
+        return Ok(format!("{dest} already up to date"));
+    }
+    if dry_run {
+        return Ok(format!("[dry-run] write {dest}"));
+    }
+    let name = Path::new(dest).file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let (dir, staged) = stage_private(&name, content).map_err(|e| Error::Io(format!("failed to stage {dest}: {e}")))?;
+    let cmd = CommandLine::new("install")
+        .args(["-D", "-m", "644", "--"])
+        .args([staged.display().to_string(), dest.to_string()])
+        .elevated();
+    let result = runner.run_checked(std::slice::from_ref(&cmd));
+    let _ = fs::remove_dir_all(&dir);
+    result.map(|()| format!("wrote {dest}"))
+}
+
+/// Creates a new folder in the temp dir that only this user can enter. It
+/// never reuses an existing one, so no other user can prepare it ahead.
+fn private_dir() -> std::io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let dir = std::env::temp_dir().join(format!("rusty_rebase-{}-{nanos:x}-{attempt}", std::process::id()));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A [`private_dir`] deleted with its contents when dropped.
+struct ScratchDir(PathBuf);
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes `content` to a new file in a [`private_dir`], so nobody can swap
+/// the file or plant a symlink before root copies it. Returns the folder
+/// and the file.
+fn stage_private(name: &str, content: &str) -> std::io::Result<(PathBuf, PathBuf)> {
+    let dir = private_dir()?;
+    let staged = dir.join(name);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&staged).and_then(|mut file| file.write_all(content.as_bytes()));
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    Ok((dir, staged))
+}
+
+/// Installs `manager` if needed, then each missing version, then points
+/// the default at `default`. Versions already present are left alone.
+fn apply_version_manager(
+    manager: VersionManager,
+    versions: &[String],
+    default: Option<&str>,
+    step_env: &[(String, String)],
+    dry_run: bool,
+    runner: &Runner,
+) -> Result<Vec<String>> {
+    if Platform::current() != Platform::Linux {
+        return Ok(vec![format!("{} setup is only supported on Linux, skipped", manager.label())]);
+    }
+    let mut out = Vec::new();
+    let run = |out: &mut Vec<String>, cmd: CommandLine, done: String| -> Result<()> {
+        if dry_run {
+            out.push(format!("[dry-run] {cmd}"));
+            return Ok(());
+        }
+        let status = run_piped(&cmd, step_env, runner.tx, runner.cancel, runner.timeout)?;
+        if !status.success() {
+            return Err(Error::ChildProcess(format!("{cmd} failed with {status}")));
+        }
+        out.push(done);
+        Ok(())
+    };
+
+    // Without the manager nothing can be queried, so everything is installed.
+    let present = manager.installed();
+    if !present {
+        run(&mut out, manager.bootstrap(), format!("installed {}", manager.label()))?;
+    }
+    let mut skipped = Vec::new();
+    for version in versions.iter().map(String::as_str).chain(default) {
+        if present && manager.has_version(version) {
+            if !skipped.contains(&version) {
+                skipped.push(version);
+            }
+            continue;
+        }
+        run(&mut out, manager.install_version(version), format!("{} installed {version}", manager.label()))?;
+    }
+    if !skipped.is_empty() {
+        out.push(format!("{} already has {}", manager.label(), skipped.join(", ")));
+    }
+    if let Some(version) = default {
+        if present && manager.is_default(version) {
+            out.push(format!("{} default is already {version}", manager.label()));
+        } else {
+            run(&mut out, manager.set_default(version), format!("{} default set to {version}", manager.label()))?;
+        }
+    }
+    Ok(out)
+}
+
+/// Enables zram or swapfile swap sized for the installed memory unless that
+/// kind of swap is already active.
+fn apply_swap(
+    method: SwapMethod,
+    size_mb: Option<u64>,
+    distro: &DistroInfo,
+    dry_run: bool,
+    runner: &Runner,
+) -> Result<Vec<String>> {
+    if Platform::current() != Platform::Linux {
+        return Ok(vec!["swap setup is only supported on Linux, skipped".to_string()]);
+    }
+    let ram_mb = crate::tuning::total_memory_mb();
+    let size_mb = size_mb.unwrap_or_else(|| crate::tuning::swap_size_mb(method, ram_mb));
+    let show = |script: &[CommandLine]| format!("[dry-run] {}", display_script(script));
+
+    match method {
+        SwapMethod::Zram => {
+            if crate::tuning::zram_active() {
+                return Ok(vec!["zram swap already active".to_string()]);
+            }
+            let package = crate::tuning::zram_generator_package(distro.pkg_manager.name()).to_string();
+            let mut out = Vec::new();
+            if !distro.pkg_manager.is_installed(&package) {
+                let script = distro
+                    .pkg_manager
+                    .install(std::slice::from_ref(&package))
+                    .ok_or_else(|| Error::Catalog(format!("cannot install {package} without a package manager")))?;
+                if dry_run {
+                    out.push(show(&script));
+                } else {
+                    runner.run_checked(&script)?;
+                }
+            }
+            out.push(write_root_file(&crate::tuning::zram_config(size_mb), crate::tuning::ZRAM_CONFIG, dry_run, runner)?);
+            let start = crate::tuning::zram_start_script();
+            if dry_run {
+                out.push(show(&start));
+            } else {
+                runner.run_checked(&start)?;
+                out.push(format!("zram swap of {} enabled ({} RAM)", format_size(size_mb * 1024 * 1024), format_size(ram_mb * 1024 * 1024)));
+            }
+            Ok(out)
+        }
+        SwapMethod::Swapfile => {
+            let swapfile = crate::tuning::SWAPFILE;
+            let mut out = Vec::new();
+            if crate::tuning::active_swaps().iter().any(|s| s == swapfile) {
+                out.push(format!("{swapfile} already in use"));
+            } else if Path::new(swapfile).exists() {
+                return Err(Error::Io(format!("{swapfile} exists but is not in use; remove it or enable it by hand")));
+            } else {
+                let script = crate::tuning::swapfile_script(size_mb);
+                if dry_run {
+                    out.push(show(&script));
+                } else {
+                    runner.run_checked(&script)?;
+                    out.push(format!("{swapfile} of {} enabled ({} RAM)", format_size(size_mb * 1024 * 1024), format_size(ram_mb * 1024 * 1024)));
+                }
+            }
+            let fstab = fs::read_to_string("/etc/fstab").unwrap_or_default();
+            if !fstab.lines().any(|l| l.split_whitespace().next() == Some(swapfile)) {
+                let mut updated = fstab;
+                if !updated.is_empty() && !updated.ends_with('\n') {
+                    updated.push('\n');
+                }
+                updated.push_str(&crate::tuning::fstab_swap_line());
+                updated.push('\n');
+                out.push(write_root_file(&updated, "/etc/fstab", dry_run, runner)?);
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Writes the memory-based sysctl defaults, overridden by `values`, and
+/// loads them.
+fn apply_sysctl(values: &BTreeMap<String, String>, dry_run: bool, runner: &Runner) -> Result<Vec<String>> {
+    if Platform::current() != Platform::Linux {
+        return Ok(vec!["sysctl tuning is only supported on Linux, skipped".to_string()]);
+    }
+    let mut merged = crate::tuning::recommended_sysctl(crate::tuning::total_memory_mb(), crate::tuning::zram_configured());
+    merged.extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let file = crate::tuning::SYSCTL_FILE;
+    let written = write_root_file(&crate::tuning::sysctl_conf(&merged), file, dry_run, runner)?;
+    let load = CommandLine::new("sysctl").args(["-p", file]).elevated();
+    if dry_run {
+        return Ok(vec![written, format!("[dry-run] {load}")]);
+    }
+    runner.run_checked(std::slice::from_ref(&load))?;
+    Ok(vec![written, format!("applied {} sysctl values", merged.len())])
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).ok().map(|m| m.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| Error::Io(format!("failed to set mode on {}: {e}", path.display())))
+}
+
+/// Windows has no Unix permission bits; the mode is ignored there.
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Renders a template step into place, returning the log line and the
+/// destination. An existing file with other content is kept as
+/// `<dest>.rusty_rebase.bak`.
+fn apply_template(
+    spec: &SoftwareSpec,
+    source: &str,
+    dest: &str,
+    vars: &std::collections::BTreeMap<String, String>,
+    dry_run: bool,
+) -> Result<(String, PathBuf)> {
+    let mut all_vars = crate::template::builtin_vars(spec);
+    all_vars.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    let source_path = catalog_relative(source)?;
+    let text = fs::read_to_string(&source_path)
+        .map_err(|e| Error::Io(format!("failed to read template {}: {e}", source_path.display())))?;
+    let rendered = crate::template::render(&text, &all_vars)?;
+    let dest_path = expand_tilde(&crate::template::render(dest, &all_vars)?)?;
+
+    if fs::read_to_string(&dest_path).is_ok_and(|current| current == rendered) {
+        return Ok((format!("{} already up to date", dest_path.display()), dest_path.clone()));
+    }
+    if dry_run {
+        return Ok((format!("[dry-run] render {} -> {}", source_path.display(), dest_path.display()), dest_path.clone()));
+    }
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::Io(format!("failed to create {}: {e}", parent.display())))?;
+    }
+    if dest_path.exists() {
+        let backup = PathBuf::from(format!("{}.rusty_rebase.bak", dest_path.display()));
+        fs::copy(&dest_path, &backup).map_err(|e| Error::Io(format!("failed to back up {}: {e}", dest_path.display())))?;
+    }
+    fs::write(&dest_path, rendered).map_err(|e| Error::Io(format!("failed to write {}: {e}", dest_path.display())))?;
+    Ok((format!("rendered {} -> {}", source_path.display(), dest_path.display()), dest_path))
+}
+
+/// Asks the front end for a secret; `None` when the prompt was dismissed.
+fn request_secret(prompt: &str, tx: &mpsc::Sender<crate::events::InstallMsg>) -> Option<String> {
+    let (reply_tx, reply_rx) = mpsc::channel();
+    tx.send(crate::events::InstallMsg::Secret(prompt.to_string(), reply_tx)).ok()?;
+    reply_rx.recv().ok().flatten()
+}
+
+/// Current value of a git setting, `None` when unset or git is missing.
+fn git_config_value(key: &str, scope: GitScope) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["config", scope.flag(), "--get", key])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Sets a git config value unless it already holds `value`. System scope goes
+/// through sudo where the platform uses it.
+fn apply_git_config(key: &str, value: &str, scope: GitScope, dry_run: bool, runner: &Runner) -> Result<String> {
+    if git_config_value(key, scope).as_deref() == Some(value) {
+        return Ok(format!("git {key} already set"));
+    }
+    let mut cmd = CommandLine::new("git").args(["config", scope.flag(), "--", key, value]);
+    if scope == GitScope::System {
+        cmd = cmd.elevated();
+    }
+    if dry_run {
+        return Ok(format!("[dry-run] {cmd}"));
+    }
+    let status = runner.run(&cmd)?;
+    if status.success() {
+        Ok(format!("set git {key} = {value}"))
+    } else {
+        Err(Error::ChildProcess(format!("git config {key} failed with {status}")))
+    }
+}
+
+/// Streams `url` into `dest`, returning the byte count and the response's
+/// `Content-Type`. Cancelling `cancel` stops it at once, even while the
+/// server is slow to send the next chunk.
+async fn download_to_file(
     client: &Client,
     url: &str,
     dest: &Path,
-    tx: &mpsc::Sender<crate::app::InstallMsg>,
-    cancel_rx: &mpsc::Receiver<()>,
-) -> Result<(), String> {
-    let mut response = client
-        .get(url)
-        .send()
-        .map_err(|e| format!("failed to download from {url}: {e}"))?;
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<(u64, Option<String>)> {
+    let mut request = client.get(url);
+    if let Some(limit) = timeout {
+        request = request.timeout(limit);
+    }
+    let context = format!("failed to download from {url}");
+    let mut response = tokio::select! {
+        _ = cancel.cancelled() => return Err(Error::Cancelled("Download")),
+        response = crate::resolver::send(request, &context) => response?,
+    };
+    let started = Instant::now();
  
     let total_size = response.content_length();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
     let mut file = fs::File::create(dest)
-        .map_err(|e| format!("failed to create destination {}: {e}", dest.display()))?;
+        .map_err(|e| Error::Io(format!("failed to create destination {}: {e}", dest.display())))?;
  
-    let mut buffer = [0; 8192];
     let mut downloaded: u64 = 0;
-    
+    let mut rate = TransferRate::new();
+    let mut last_update = Instant::now();
+
     loop {
-        if cancel_rx.try_recv().is_ok() {
-            return Err("Download cancelled by user".to_string());
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => return Err(Error::Cancelled("Download")),
+            chunk = response.chunk() => chunk,
+        };
+        let chunk = match chunk {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                return Err(match timeout {
+                    Some(limit) if e.is_timeout() || started.elapsed() >= limit => {
+                        Error::Network(format!("download timed out after {}s", limit.as_secs()))
+                    }
+                    _ => Error::Network(format!("failed to read from response: {e}")),
+                });
+            }
+        };
+        file.write_all(&chunk).map_err(|e| Error::Io(format!("failed to write to file: {e}")))?;
+        let n = chunk.len() as u64;
+        downloaded += n;
+        crate::resources::add_downloaded(n);
+        rate.record(downloaded);
+
+        if last_update.elapsed() < Duration::from_millis(100) {
+            continue;
         }
-        let n = response.read(&mut buffer).map_err(|e| format!("failed to read from response: {e}"))?;
-        if n == 0 { break; }
-        file.write_all(&buffer[..n]).map_err(|e| format!("failed to write to file: {e}"))?;
-        downloaded += n as u64;
+        last_update = Instant::now();
+
+        let speed = rate.bytes_per_sec().map(|bps| {
+            let mut label = crate::units::format_speed(bps);
+            if let Some(t) = total_size
+                && bps > 0.0
+            {
+                let left = t.saturating_sub(downloaded) as f64 / bps;
+                label.push_str(&format!(" • {} left", crate::units::format_eta(left as u64)));
+            }
+            label
+        });
 
         if let Some(t) = total_size {
             let ratio = downloaded as f64 / t as f64;
-            let _ = tx.send(crate::app::InstallMsg::SubProgress(ratio));
-            let msg = format!("Downloading ({:.1}/{:.1} MB)", downloaded as f64 / 1024.0 / 1024.0, t as f64 / 1024.0 / 1024.0);
-            let _ = tx.send(crate::app::InstallMsg::Progress("".to_string(), msg, None));
+            let _ = tx.send(crate::events::InstallMsg::SubProgress(ratio));
+            let msg = format!("Downloading ({} / {})", format_size(downloaded), format_size(t));
+            let _ = tx.send(crate::events::InstallMsg::Progress("".to_string(), msg, speed));
         } else {
-            let msg = format!("Downloading ({:.1} MB)", downloaded as f64 / 1024.0 / 1024.0);
-            let _ = tx.send(crate::app::InstallMsg::Progress("".to_string(), msg, None));
+            let msg = format!("Downloading ({})", format_size(downloaded));
+            let _ = tx.send(crate::events::InstallMsg::Progress("".to_string(), msg, speed));
         }
     }
- 
-    Ok(())
+
+    let _ = tx.send(crate::events::InstallMsg::SubProgress(1.0));
+    Ok((downloaded, content_type))
 }
 
-fn extract_archive(
-    path: &Path,
-    install_root: &Path,
-    dry_run: bool,
-    tx: &mpsc::Sender<crate::app::InstallMsg>,
-    cancel_rx: &mpsc::Receiver<()>,
-) -> Result<String, String> {
-    let name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| "invalid archive file name".to_string())?;
- 
+/// Transfer rate over a sliding window of recent samples, so the speed
+/// reacts to stalls instead of averaging over the whole download.
+struct TransferRate {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl TransferRate {
+    const WINDOW: Duration = Duration::from_secs(3);
+
+    fn new() -> Self {
+        Self { samples: VecDeque::from([(Instant::now(), 0)]) }
+    }
+
+    fn record(&mut self, total_bytes: u64) {
+        let now = Instant::now();
+        self.samples.push_back((now, total_bytes));
+        while self.samples.len() > 2 && self.samples.front().is_some_and(|(t, _)| now.duration_since(*t) > Self::WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+
+    fn bytes_per_sec(&self) -> Option<f64> {
+        let (start, first) = self.samples.front()?;
+        let (end, last) = self.samples.back()?;
+        let secs = end.duration_since(*start).as_secs_f64();
+        (secs > 0.0).then(|| (last - first) as f64 / secs)
+    }
+}
+
+fn extract_archive(path: &Path, content_type: Option<&str>, dry_run: bool, ctx: &ExtractContext) -> Result<String> {
     if dry_run {
         return Ok(format!(
-            "[dry-run] extract {} into {}",
+            "[dry-run] extract {} into {} (strip {})",
             path.display(),
-            install_root.display()
+            ctx.dest.display(),
+            ctx.strip
         ));
     }
- 
-    let command = if name.ends_with(".tar.gz") {
-        format!("tar -xzf '{}' -C '{}'", path.display(), install_root.display())
-    } else if name.ends_with(".tar.xz") {
-        format!("tar -xJf '{}' -C '{}'", path.display(), install_root.display())
-    } else if name.ends_with(".zip") {
-        format!("unzip -o -q '{}' -d '{}'", path.display(), install_root.display())
-    } else {
+
+    let Some(handler) = find_handler(path, content_type) else {
         return Ok(format!("downloaded artifact at {}, extraction skipped", path.display()));
     };
- 
-    let status = run_piped(&command, tx, cancel_rx).map_err(|e| e.to_string())?;
- 
-    Ok(format!(
-        "extraction command exit status {} ({command})",
-        status
-    ))
+    handler.extract(path, ctx).map(|line| format!("{}: {line}", handler.name()))
 }
 
-fn handle_vscode_install(
+/// Where a resolved artifact is downloaded to.
+fn artifact_path(download_dir: &Path, resolved: &ResolvedAsset) -> Result<PathBuf> {
+    if resolved.file_name.contains(['/', '\\']) || resolved.file_name.starts_with("..") {
+        return Err(Error::Resolve(format!("refusing unsafe artifact file name '{}'", resolved.file_name)));
+    }
+    Ok(download_dir.join(&resolved.file_name))
+}
+
+/// Whether a profile already puts `dir` on PATH, in our export line or one
+/// written by hand (`export PATH="$PATH:dir"`, `fish_add_path dir`, ...).
+fn profile_has_path(content: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches(['/', '\\']);
+    content.lines().map(str::trim).filter(|l| !l.starts_with('#')).any(|line| {
+        let mentions_path = line.contains("PATH") || line.starts_with("fish_add_path");
+        let has_dir = line.match_indices(dir).any(|(i, _)| {
+            let rest = &line[i + dir.len()..];
+            rest.is_empty() || rest.starts_with(['/', '\\', ':', ';', '"', '\'', ' '])
+        });
+        mentions_path && has_dir
+    })
+}
+
+/// Whether the download folder already holds this release's artifact.
+/// Downloads land under their final name only once complete, and only names
+/// carrying the version are trusted, since `latest.tar.gz` may be stale.
+fn is_cached(path: &Path, resolved: &ResolvedAsset) -> bool {
+    !resolved.version.is_empty()
+        && resolved.file_name.contains(&resolved.version)
+        && path.is_file()
+        && crate::extract::check_download(path, &resolved.file_name, None).is_ok()
+}
+
+/// Whether the configured download folder holds `resolved`'s artifact, so
+/// installing it needs no download.
+pub fn cached_artifact(resolved: &ResolvedAsset) -> bool {
+    download_dir()
+        .map_err(Error::Io)
+        .and_then(|dir| artifact_path(&dir, resolved))
+        .is_ok_and(|path| is_cached(&path, resolved))
+}
+
+/// Lists the top level of a resolved archive for the details popup, reusing
+/// the copy in the download folder when there is one.
+pub fn fetch_and_list(client: &Client, key: &str, resolved: &ResolvedAsset) -> Result<crate::events::ArchivePreview> {
+    let (path, fetched) = crate::runtime::block_on(fetch_artifact(client, key, resolved, &download_dir()?))?;
+    let (format, entries) = crate::extract::list_top_level(&path)?;
+    Ok(crate::events::ArchivePreview { format, entries, fetched })
+}
+
+/// Downloads `resolved`'s artifact into `dir` unless it is already there,
+/// returning its path and whether it was fetched. A fresh download goes
+/// through a `.part` file so an interrupted one is never mistaken for it.
+pub async fn fetch_artifact(client: &Client, key: &str, resolved: &ResolvedAsset, dir: &Path) -> Result<(PathBuf, bool)> {
+    let path = artifact_path(dir, resolved)?;
+    let _download = download_lock(&path).lock_owned().await;
+    let fetched = !path.is_file();
+    if fetched {
+        fs::create_dir_all(dir).map_err(|e| Error::Io(format!("failed to create {}: {e}", dir.display())))?;
+        let partial = dir.join(format!("{}.part", resolved.file_name));
+        // Progress goes nowhere: the popup only shows the outcome.
+        let (tx, _progress) = mpsc::channel();
+        let failed = |file: &Path, e: Error| match set_aside(file, key, resolved, &e) {
+            Some(line) => e.map_message(|message| format!("{message} ({line})")),
+            None => e,
+        };
+        let mime = match download_to_file(client, &resolved.url, &partial, &tx, &CancellationToken::new(), None).await {
+            Ok((_, mime)) => mime,
+            Err(e) => return Err(failed(&partial, e)),
+        };
+        if let Err(e) = verify_download(&partial, resolved, mime.as_deref()) {
+            return Err(failed(&partial, e));
+        }
+        fs::rename(&partial, &path).map_err(|e| Error::Io(format!("failed to move {}: {e}", partial.display())))?;
+    }
+    Ok((path, fetched))
+}
+
+/// Locks on artifact paths, so a download fetched ahead and the install or
+/// preview that needs the same file never write it at once.
+static DOWNLOAD_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// Artifacts downloaded by [`fetch_ahead`] and not installed yet, with their
+/// size and `Content-Type`. Trusted like a cached download even when the
+/// file name carries no version.
+static FETCHED_AHEAD: Mutex<BTreeMap<PathBuf, (u64, Option<String>)>> = Mutex::new(BTreeMap::new());
+
+fn download_lock(path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = DOWNLOAD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks.entry(path.to_path_buf()).or_default().clone()
+}
+
+fn take_fetched_ahead(path: &Path) -> Option<(u64, Option<String>)> {
+    FETCHED_AHEAD.lock().unwrap_or_else(|e| e.into_inner()).remove(path)
+}
+
+/// Downloads `resolved`'s artifact into the download folder while earlier
+/// tools are still installing; its install then picks the file up instead
+/// of downloading it. Progress is not reported, and a failed download is
+/// left to the install to retry and report.
+pub async fn fetch_ahead(client: &Client, resolved: &ResolvedAsset, cancel: &CancellationToken) -> Result<()> {
+    let dir = download_dir()?;
+    let path = artifact_path(&dir, resolved)?;
+    let _download = download_lock(&path).lock_owned().await;
+    if path.is_file() {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir).map_err(|e| Error::Io(format!("failed to create {}: {e}", dir.display())))?;
+    let partial = dir.join(format!("{}.part", resolved.file_name));
+    let (tx, _progress) = mpsc::channel();
+    match download_to_file(client, &resolved.url, &partial, &tx, cancel, None).await {
+        Ok(fetched) => {
+            fs::rename(&partial, &path).map_err(|e| Error::Io(format!("failed to move {}: {e}", partial.display())))?;
+            FETCHED_AHEAD.lock().unwrap_or_else(|e| e.into_inner()).insert(path, fetched);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Checks a finished download: it looks like the archive `resolved`'s file
+/// name promises and, when the source publishes one, has the expected
+/// SHA-256.
+fn verify_download(path: &Path, resolved: &ResolvedAsset, content_type: Option<&str>) -> Result<()> {
+    crate::extract::check_download(path, &resolved.file_name, content_type)?;
+    if let Some(expected) = &resolved.sha256 {
+        let actual = sha256_file(path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::Verification(format!("SHA-256 mismatch for {}: expected {expected}, got {actual}", resolved.file_name)));
+        }
+    }
+    Ok(())
+}
+
+/// Moves a failed download into the quarantine instead of deleting it,
+/// returning a line for the log. Cancelled and empty downloads are simply
+/// deleted. A bundle's own files are never moved, only `.part` downloads
+/// made into its folder.
+fn set_aside(file: &Path, key: &str, resolved: &ResolvedAsset, reason: &Error) -> Option<String> {
+    let partial = file.extension().is_some_and(|e| e == "part");
+    if in_bundle(file) && !partial {
+        return Some(format!("[warn] left {} in the bundle", file.display()));
+    }
+    let empty = fs::metadata(file).map(|m| m.len() == 0).unwrap_or(true);
+    if empty || reason.is_cancelled() {
+        let _ = fs::remove_file(file);
+        return None;
+    }
+    match crate::quarantine::quarantine(file, key, resolved, &reason.to_string()) {
+        Ok(dest) => Some(format!("[warn] quarantined the download as {}", dest.display())),
+        Err(e) => {
+            let _ = fs::remove_file(file);
+            Some(format!("[warn] {e}"))
+        }
+    }
+}
+
+/// Whether `file` is one of the bundle's artifacts rather than a download.
+fn in_bundle(file: &Path) -> bool {
+    crate::bundle::artifacts_dir().is_some_and(|dir| file.starts_with(dir))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).map_err(|e| Error::Io(format!("failed to open {}: {e}", path.display())))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| Error::Io(format!("failed to read {}: {e}", path.display())))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn is_package_artifact(file_name: &str) -> bool {
+    file_name.ends_with(".deb") || file_name.ends_with(".rpm") || file_name.ends_with(".msi")
+}
+
+/// Installs a downloaded .deb/.rpm through the native package manager, falling
+/// back to dpkg + `apt -f install` or plain `rpm -i` when the primary route fails.
+/// On Windows, .msi installers run unattended through msiexec.
+fn install_package_artifact(
     path: &Path,
     distro: &DistroInfo,
     dry_run: bool,
-    tx: &mpsc::Sender<crate::app::InstallMsg>,
-    cancel_rx: &mpsc::Receiver<()>,
-) -> Result<String, String> {
-    let cmd = match distro.pkg_manager {
-        PackageManager::Apt => Some(format!("sudo apt install -y '{}'", path.display())),
-        PackageManager::Dnf => Some(format!("sudo dnf install -y '{}'", path.display())),
-        PackageManager::Pacman => Some(format!(
-            "mkdir -p \"$HOME\"/.local/opt && tar -xzf '{}' -C \"$HOME\"/.local/opt",
-            path.display()
-        )),
-        PackageManager::Unknown => None,
-    };
- 
-    if let Some(cmd) = cmd {
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    if path.extension().is_some_and(|e| e == "msi") {
+        if Platform::current() != Platform::Windows {
+            return Err(Error::Catalog(format!("cannot install {} outside Windows, pick a different asset", path.display())));
+        }
+        let cmd = CommandLine::new("msiexec")
+            .arg("/i")
+            .arg(path.display().to_string())
+            .args(["/qn", "/norestart"]);
         if dry_run {
-            Ok(format!("[dry-run] {cmd}"))
-        } else {
-            let status = run_piped(&cmd, tx, cancel_rx)?;
-            Ok(format!("vscode install exit status {} ({cmd})", status))
+            return Ok(format!("[dry-run] {cmd}"));
         }
+        let status = run_piped(&cmd, &[], tx, cancel, timeout)?;
+        return if status.success() {
+            Ok(format!("msi install exit status {} ({cmd})", status))
+        } else {
+            Err(Error::ChildProcess(format!("msi install failed with {status} ({cmd})")))
+        };
+    }
+
+    let Some((primary, fallback)) = distro.pkg_manager.install_artifact(path) else {
+        return Err(Error::Catalog(format!(
+            "cannot install {} with package manager '{}', pick a different asset",
+            path.file_name().and_then(|n| n.to_str()).unwrap_or("artifact"),
+            distro.pkg_manager
+        )));
+    };
+
+    let (primary_shown, fallback_shown) = (display_script(&primary), display_script(&fallback));
+    if dry_run {
+        return Ok(format!("[dry-run] {primary_shown} (fallback: {fallback_shown})"));
+    }
+
+    let status = run_script(&primary, &[], tx, cancel, timeout)?;
+    if status.success() {
+        return Ok(format!("package artifact install exit status {} ({primary_shown})", status));
+    }
+
+    let _ = tx.send(crate::events::InstallMsg::Log(
+        crate::events::LogLevel::Warn,
+        format!("{primary_shown} failed ({status}), retrying with: {fallback_shown}"),
+    ));
+    let status = run_script(&fallback, &[], tx, cancel, timeout)?;
+    if status.success() {
+        Ok(format!("package artifact install exit status {} ({fallback_shown})", status))
     } else {
-        Ok("unknown package manager: please install vscode artifact manually".to_string())
+        Err(Error::ChildProcess(format!("package artifact install failed with {status} ({fallback_shown})")))
     }
 }
 
-fn run_piped(
-    cmd: &str,
-    tx: &mpsc::Sender<crate::app::InstallMsg>,
-    cancel_rx: &mpsc::Receiver<()>,
-) -> Result<std::process::ExitStatus, String> {
-    use std::io::{BufRead, BufReader};
-    use std::process::Stdio;
-
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("failed to spawn command: {e}"))?;
+fn handle_vscode_install(
+    path: &Path,
+    distro: &DistroInfo,
+    dry_run: bool,
+    tx: &mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    if !distro.pkg_manager.is_available() {
+        return Ok("unknown package manager: please install vscode artifact manually".to_string());
+    }
+    let dest = home_dir()?.join(".local/opt");
+    extract_archive(path, None, dry_run, &ExtractContext { dest: &dest, strip: 0, tx, cancel, timeout })
+}
 
-    let stdout = child.stdout.take().unwrap();
-    let stderr = child.stderr.take().unwrap();
+/// Streams a step's commands into the log while honouring cancellation and
+/// the entry's step timeout.
+struct Runner<'a> {
+    tx: &'a mpsc::Sender<crate::events::InstallMsg>,
+    cancel: &'a CancellationToken,
+    timeout: Option<Duration>,
+}
 
-    let (pipe_tx, pipe_rx) = std::sync::mpsc::channel();
+impl Runner<'_> {
+    fn run(&self, cmd: &CommandLine) -> Result<std::process::ExitStatus> {
+        run_piped(cmd, &[], self.tx, self.cancel, self.timeout)
+    }
 
-    let tx_stdout = pipe_tx.clone();
-    std::thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let _ = tx_stdout.send(line);
-            }
+    /// Runs `script`, turning a failing command into an error naming it.
+    fn run_checked(&self, script: &[CommandLine]) -> Result<()> {
+        let status = run_script(script, &[], self.tx, self.cancel, self.timeout)?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::ChildProcess(format!("{} failed with {status}", display_script(script))))
         }
-    });
+    }
+}
 
-    let tx_stderr = pipe_tx;
-    std::thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let _ = tx_stderr.send(format!("[stderr] {}", line));
-            }
-        }
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    while let Ok(line) = pipe_rx.recv() {
-        if cancel_rx.try_recv().is_ok() {
-            let _ = child.kill();
-            return Err("Operation cancelled by user".to_string());
+    #[test]
+    fn staged_files_are_private() {
+        let (dir, staged) = stage_private("fstab", "content").unwrap();
+        assert_eq!(fs::read_to_string(&staged).unwrap(), "content");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+            assert_eq!(fs::metadata(&staged).unwrap().permissions().mode() & 0o777, 0o600);
         }
-        let _ = tx.send(crate::app::InstallMsg::Log(line));
+        let (other, _) = stage_private("fstab", "content").unwrap();
+        assert_ne!(dir, other);
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(other).unwrap();
     }
-
-    let status = child.wait().map_err(|e| format!("failed to wait for child: {e}"))?;
-    Ok(status)
-}
\ No newline at end of file
+}
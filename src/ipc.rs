@@ -0,0 +1,161 @@
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::events::{InstallMsg, LogLevel};
+
+/// A subscriber that stops reading for this long is dropped, so it cannot
+/// stall the install.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connected subscribers; set once `--listen` starts the server.
+static CLIENTS: OnceLock<Mutex<Vec<Box<dyn Write + Send>>>> = OnceLock::new();
+
+/// One line of JSON sent to subscribers, mirroring [`InstallMsg`] plus the
+/// start and end of a run.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Started { source: String, total: usize },
+    Progress { key: String, operation: String, #[serde(skip_serializing_if = "Option::is_none")] speed: Option<String> },
+    SubProgress { ratio: f64 },
+    Log { level: &'static str, line: String },
+    Banner { text: String },
+    Skipped { key: String },
+    Done { key: String, ok: bool, #[serde(skip_serializing_if = "Option::is_none")] error: Option<String> },
+    Finished { succeeded: usize, failed: usize, skipped: usize },
+}
+
+/// Keeps the server's socket file until the process is done with it.
+pub struct ProgressServer {
+    socket: Option<PathBuf>,
+}
+
+impl Drop for ProgressServer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Starts accepting subscribers on `addr`: `tcp:127.0.0.1:7070` (or a bare
+/// `host:port`) for local TCP, anything else is a Unix socket path. TCP is
+/// limited to loopback addresses, since progress lines include commands
+/// and paths.
+pub fn listen(addr: &str) -> Result<ProgressServer, String> {
+    let tcp = addr.strip_prefix("tcp:").unwrap_or(addr);
+    if let Ok(socket_addr) = tcp.parse::<SocketAddr>() {
+        if !socket_addr.ip().is_loopback() {
+            return Err(format!("--listen only accepts loopback addresses, not {socket_addr}"));
+        }
+        let listener = TcpListener::bind(socket_addr).map_err(|e| format!("failed to listen on {socket_addr}: {e}"))?;
+        start(move || {
+            let (stream, _) = listener.accept().ok()?;
+            stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok()?;
+            Some(Box::new(stream))
+        });
+        eprintln!("Progress events on tcp:{socket_addr}");
+        return Ok(ProgressServer { socket: None });
+    }
+    listen_unix(PathBuf::from(addr))
+}
+
+#[cfg(unix)]
+fn listen_unix(path: PathBuf) -> Result<ProgressServer, String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    // A socket left behind by a run that did not exit cleanly; a live one
+    // still accepts connections and is left alone. Anything that is not a
+    // socket is never removed.
+    if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and is not a socket; pick another path for --listen", path.display()));
+        }
+        if UnixStream::connect(&path).is_err() {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    let listener = UnixListener::bind(&path).map_err(|e| format!("failed to listen on {}: {e}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("failed to restrict {}: {e}", path.display()))?;
+    start(move || {
+        let (stream, _) = listener.accept().ok()?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok()?;
+        Some(Box::new(stream))
+    });
+    eprintln!("Progress events on {}", path.display());
+    Ok(ProgressServer { socket: Some(path) })
+}
+
+#[cfg(not(unix))]
+fn listen_unix(path: PathBuf) -> Result<ProgressServer, String> {
+    Err(format!("Unix sockets are not available here; use --listen tcp:127.0.0.1:<port> instead of {}", path.display()))
+}
+
+/// Adds every connection `accept` returns to the subscribers, from a
+/// background thread.
+fn start(mut accept: impl FnMut() -> Option<Box<dyn Write + Send>> + Send + 'static) {
+    let clients = CLIENTS.get_or_init(Mutex::default);
+    std::thread::spawn(move || {
+        loop {
+            match accept() {
+                Some(client) => {
+                    if let Ok(mut clients) = clients.lock() {
+                        clients.push(client);
+                    }
+                }
+                None => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    });
+}
+
+/// Whether anyone can be listening; events are not built otherwise.
+pub fn active() -> bool {
+    CLIENTS.get().is_some()
+}
+
+/// Sends `event` to every subscriber, dropping the ones that went away.
+pub fn publish(event: ProgressEvent) {
+    let Some(clients) = CLIENTS.get() else { return };
+    let Ok(mut line) = serde_json::to_vec(&event) else { return };
+    line.push(b'\n');
+    if let Ok(mut clients) = clients.lock() {
+        clients.retain_mut(|client| client.write_all(&line).and_then(|_| client.flush()).is_ok());
+    }
+}
+
+/// Publishes the subscriber-facing part of an install message. Prompts and
+/// report records stay in-process.
+pub fn publish_msg(msg: &InstallMsg) {
+    if !active() {
+        return;
+    }
+    let event = match msg {
+        InstallMsg::Progress(key, operation, speed) => {
+            ProgressEvent::Progress { key: key.clone(), operation: operation.clone(), speed: speed.clone() }
+        }
+        InstallMsg::SubProgress(ratio) => ProgressEvent::SubProgress { ratio: *ratio },
+        InstallMsg::Log(level, line) => ProgressEvent::Log { level: level_name(*level), line: line.clone() },
+        InstallMsg::Banner(text) => ProgressEvent::Banner { text: text.clone() },
+        InstallMsg::Skipped(key) => ProgressEvent::Skipped { key: key.clone() },
+        InstallMsg::Done(key, result) => ProgressEvent::Done { key: key.clone(), ok: result.is_ok(), error: result.as_ref().err().map(|e| e.to_string()) },
+        InstallMsg::Elevate(_) | InstallMsg::Secret(..) | InstallMsg::Record(_) | InstallMsg::DiskUsage(_) | InstallMsg::Finished => return,
+    };
+    publish(event);
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Info => "info",
+        LogLevel::Warn => "warn",
+        LogLevel::Error => "error",
+        LogLevel::Command => "command",
+    }
+}
@@ -0,0 +1,135 @@
+//! The resolution and installation engine behind the `rusty_rebase` TUI,
+//! usable on its own without ratatui or crossterm (build with
+//! `default-features = false`).
+//!
+//! The core API:
+//!
+//! - [`catalog`]: load a `software_catalog.toml` into a [`catalog::CatalogFile`].
+//! - [`distro`]: detect the distribution, package manager and environment.
+//! - [`resolver`]: turn a catalog entry into a concrete download (async).
+//! - [`installer`]: download, verify, extract and run the setup steps.
+//! - [`restorer`]: restore a Rusty Sync backup.
+//! - [`backup`]: write one in the same layout, with groups such as the
+//!   desktop settings and browser profiles.
+//! - [`events`]: the messages these send while they work, and [`events::forward`]
+//!   to handle them with a callback.
+//! - [`error`]: the error type, with the kind of failure.
+//! - [`runtime`]: the tokio runtime the network work runs on, and
+//!   [`runtime::block_on`] to wait for it from synchronous code.
+//!
+//! Settings such as the download directory and the proxy come from
+//! `~/.config/rusty_rebase/config.toml`, as for the TUI.
+//!
+//! ```no_run
+//! use rusty_rebase::events::{forward, InstallMsg};
+//! use rusty_rebase::{catalog, distro, installer, resolver, runtime};
+//! use tokio_util::sync::CancellationToken;
+//!
+//! let catalog = catalog::load_catalog("software_catalog.toml".as_ref())?;
+//! let distro = distro::detect_distro()?;
+//! let client = resolver::build_client()?;
+//! let spec = &catalog.software["ripgrep"];
+//! let resolved = runtime::block_on(resolver::resolve_asset(&client, spec, &distro))?;
+//!
+//! let (tx, events) = forward(|msg| match msg {
+//!     InstallMsg::Log(_, line) => println!("{line}"),
+//!     InstallMsg::Elevate(reply) => drop(reply.send(false)),
+//!     InstallMsg::Secret(_, reply) => drop(reply.send(None)),
+//!     _ => {}
+//! });
+//! let cancel = CancellationToken::new();
+//! let outcome = installer::install_software(&client, "ripgrep", spec, &resolved, &distro, false, &tx, &cancel);
+//! drop(tx);
+//! events.join().ok();
+//! println!("installed {} files", outcome?.files.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod backup;
+pub mod catalog;
+pub mod config;
+pub mod distro;
+pub mod error;
+pub mod events;
+pub mod extract;
+pub mod installer;
+pub mod package_manager;
+pub mod resolver;
+pub mod restorer;
+pub mod runtime;
+
+// Used by the binary and the modules above; not part of the stable API.
+#[doc(hidden)]
+pub mod advisory;
+#[doc(hidden)]
+pub mod answers;
+#[doc(hidden)]
+pub mod browser_profiles;
+#[doc(hidden)]
+pub mod bundle;
+#[doc(hidden)]
+pub mod catalog_diff;
+#[doc(hidden)]
+pub mod command;
+#[doc(hidden)]
+pub mod connectivity;
+#[doc(hidden)]
+pub mod container;
+#[doc(hidden)]
+pub mod desktop_settings;
+#[doc(hidden)]
+pub mod export;
+#[doc(hidden)]
+pub mod file_attrs;
+#[doc(hidden)]
+pub mod hardware;
+#[doc(hidden)]
+pub mod install_log;
+#[doc(hidden)]
+pub mod ipc;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod notify;
+#[doc(hidden)]
+pub mod platform;
+#[doc(hidden)]
+pub mod power;
+#[doc(hidden)]
+pub mod preflight;
+#[doc(hidden)]
+pub mod quarantine;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod resolve_cache;
+#[doc(hidden)]
+pub mod resources;
+#[doc(hidden)]
+pub mod run_context;
+#[doc(hidden)]
+pub mod sandbox;
+#[doc(hidden)]
+pub mod secrets;
+#[doc(hidden)]
+pub mod snapshot;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod sudo;
+#[doc(hidden)]
+pub mod template;
+#[doc(hidden)]
+pub mod throttle;
+#[doc(hidden)]
+pub mod toolchain;
+#[doc(hidden)]
+pub mod tuning;
+#[doc(hidden)]
+pub mod uninstall;
+#[doc(hidden)]
+pub mod units;
+#[doc(hidden)]
+pub mod usage;
+#[doc(hidden)]
+pub mod virt;
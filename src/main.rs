@@ -1,26 +1,174 @@
 mod app;
-mod catalog;
-mod distro;
-mod installer;
-mod resolver;
-mod restorer;
+
+// Under `crate::` too, so the TUI refers to them as the library does.
+use rusty_rebase::{
+    advisory, answers, backup, bundle, catalog, catalog_diff, command, config, connectivity, container,
+    distro, error, events, export, hardware, install_log, installer, ipc, manifest, notify,
+    platform, power, preflight, quarantine, report, resolve_cache, resolver, resources, restorer, run_context,
+    runtime, sandbox, snapshot, state, sudo, throttle, toolchain, tuning, uninstall, units, usage, virt,
+};
 
 fn main() -> Result<(), String> {
     let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--sandbox") {
+        sandbox::force_enable();
+    }
+    if args.iter().any(|a| a == "--safe-mode") {
+        run_context::force_safe_mode();
+    }
+    // `--name value` or `--name=value`.
+    let flag_value = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+            .or_else(|| args.iter().find_map(|a| a.strip_prefix(name)?.strip_prefix('=')))
+            .map(str::to_string)
+    };
+    // `--name a,b` as a list.
+    let list_value = |name: &str| -> Vec<String> {
+        flag_value(name)
+            .map(|list| list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    distro::set_override(distro::DetectionOverride {
+        distro: flag_value("--distro"),
+        pkg_manager: flag_value("--pkg-manager"),
+    })?;
+    // Removes the socket file when main returns.
+    let _progress_server = flag_value("--listen").map(|addr| ipc::listen(&addr)).transpose()?;
+    if let Some(path) = flag_value("--answers") {
+        answers::load(std::path::Path::new(&path))?;
+    }
+    if args.len() >= 3 && args[1] == "backup" {
+        let source = flag_value("--source").map(|s| installer::expand_tilde(&s)).transpose()?;
+        let groups = list_value("--groups");
+        let format = flag_value("--format").map(|f| restorer::BackupFormat::parse(&f)).transpose()?;
+        let logs = backup::create_backup(source.as_deref(), std::path::Path::new(&args[2]), &groups, format, None)?;
+        for log in logs {
+            println!("{log}");
+        }
+        return Ok(());
+    }
+
     if args.len() >= 3 && args[1] == "restore" {
         let backup_dir = std::path::Path::new(&args[2]);
-        match restorer::restore_backup(backup_dir, None) {
+        // `--only=files,desktop` picks parts; `files` is the source folder.
+        let keep_owners = args.iter().any(|a| a == "--keep-owners");
+        let result = match list_value("--only") {
+            parts if parts.is_empty() => restorer::load_backup_info(backup_dir).and_then(|info| {
+                let selection = restorer::RestoreSelection { keep_owners, ..restorer::RestoreSelection::all(&info) };
+                restorer::restore_selected(backup_dir, &selection, None)
+            }),
+            mut parts => {
+                let files = parts.iter().any(|p| p == "files");
+                parts.retain(|p| p != "files");
+                restorer::restore_selected(backup_dir, &restorer::RestoreSelection { files, groups: parts, keep_owners }, None)
+            }
+        };
+        match result {
             Ok(logs) => {
                 for log in logs {
                     println!("{}", log);
                 }
                 return Ok(());
             }
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         }
     }
 
+    if args.len() >= 3 && args[1] == "apply" {
+        let manifest_path = std::path::Path::new(&args[2]);
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        let max_duration = flag_value("--max-duration").map(|s| report::parse_duration(&s)).transpose()?;
+        return manifest::apply_manifest(manifest_path, dry_run, assume_yes, false, max_duration);
+    }
+
+    if args.len() >= 3 && args[1] == "export" {
+        let format = export::ExportFormat::parse(flag_value("--format").as_deref().unwrap_or("dockerfile"))?;
+        let out = flag_value("--out");
+        let base = flag_value("--base");
+        return export::export(std::path::Path::new(&args[2]), format, base.as_deref(), out.as_deref().map(std::path::Path::new));
+    }
+
+    if args.len() >= 3 && args[1] == "container" {
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        return container::provision(std::path::Path::new(&args[2]), dry_run);
+    }
+
+    if args.len() >= 4 && args[1] == "diff-machines" {
+        let write_to = args
+            .iter()
+            .position(|a| a == "--write-manifest")
+            .and_then(|i| args.get(i + 1))
+            .map(std::path::Path::new);
+        return manifest::diff_machines(std::path::Path::new(&args[2]), std::path::Path::new(&args[3]), write_to);
+    }
+
+    if args.len() >= 4 && args[1] == "catalog-diff" {
+        let manifest = flag_value("--manifest");
+        return catalog_diff::catalog_diff(&args[2], &args[3], manifest.as_deref().map(std::path::Path::new));
+    }
+
+    if args.len() >= 3 && args[1] == "uninstall" {
+        let keys: Vec<String> = args[2..]
+            .iter()
+            .zip(&args[1..])
+            .filter(|(a, prev)| !a.starts_with('-') && !matches!(prev.as_str(), "--distro" | "--pkg-manager" | "--answers" | "--listen"))
+            .map(|(a, _)| a.clone())
+            .collect();
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        return uninstall::uninstall(&keys, dry_run, assume_yes);
+    }
+
+    if args.len() >= 2 && args[1] == "snapshot" {
+        let out = flag_value("--out").unwrap_or_else(|| snapshot::DEFAULT_BUNDLE.to_string());
+        let dotfiles = snapshot::parse_dotfiles(flag_value("--dotfiles").as_deref());
+        return snapshot::snapshot(std::path::Path::new(&out), &dotfiles);
+    }
+
+    if args.len() >= 3 && args[1] == "bundle" {
+        let out = flag_value("--out").unwrap_or_else(|| bundle::DEFAULT_DIR.to_string());
+        return bundle::bundle(std::path::Path::new(&args[2]), std::path::Path::new(&out));
+    }
+
+    if args.len() >= 3 && args[1] == "install-bundle" {
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        return bundle::install_bundle(std::path::Path::new(&args[2]), dry_run, assume_yes);
+    }
+
+    if args.len() >= 3 && args[1] == "apply-snapshot" {
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        let latest = args.iter().any(|a| a == "--latest");
+        return snapshot::apply_snapshot(std::path::Path::new(&args[2]), dry_run, assume_yes, latest);
+    }
+
+    if args.len() >= 2 && args[1] == "history" {
+        let limit = flag_value("--limit").map(|n| n.parse().map_err(|_| format!("invalid --limit '{n}'"))).transpose()?;
+        state::print_history(limit.unwrap_or(20));
+        return Ok(());
+    }
+
+    if args.len() >= 2 && args[1] == "du" {
+        let catalog = catalog::load_catalog(&config::require_catalog()?)?;
+        let by_size = !args.iter().any(|a| a == "--sort=name");
+        usage::print_usage(&catalog, by_size);
+        return Ok(());
+    }
+
+    let bundle_dir = flag_value("--bundle");
+    if let Some(dir) = &bundle_dir {
+        bundle::activate(std::path::Path::new(dir))?;
+    }
+
     let mut app = app::App::new().map_err(|e| e.to_string())?;
+    app.report_html = args.iter().any(|a| a == "--html-report");
+    app.watch_catalog = args.iter().any(|a| a == "--watch-catalog");
+    app.offline = bundle_dir.is_some() || args.iter().any(|a| a == "--offline");
     if let Err(e) = app.run() {
         eprintln!("Error: {}", e);
         std::process::exit(1);
@@ -0,0 +1,553 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::answers::ConflictPolicy;
+use crate::events::InstallMsg;
+use crate::catalog::{load_catalog, CatalogFile, SourceSpec};
+use crate::distro::DistroInfo;
+use crate::installer::{expand_tilde, install_software};
+use crate::ipc::ProgressEvent;
+use crate::resolver::{resolve_asset, resolve_pinned};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Manifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub catalog: Option<String>,
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolPin>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dotfiles: BTreeMap<String, DotfileGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore: Option<RestoreSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ToolPin {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DotfileGroup {
+    pub source: String,
+    #[serde(default = "default_dotfile_target")]
+    pub target: String,
+    pub files: Vec<String>,
+}
+
+fn default_dotfile_target() -> String {
+    "~".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreSource {
+    pub backup_dir: String,
+}
+
+/// Record of what `apply` has installed, written next to the manifest.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub tools: BTreeMap<String, LockedTool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LockedTool {
+    pub version: String,
+    pub url: String,
+    pub installed_at: u64,
+    /// Directories the install created, kept for auditing and cleanup.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created_dirs: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlanAction {
+    Install { key: String, pin: Option<String>, reason: String },
+    UpToDate { key: String, version: String },
+    Invalid { key: String, reason: String },
+    CopyDotfiles { group: String, files: Vec<(PathBuf, PathBuf)> },
+    DotfilesInSync { group: String },
+    Restore { backup_dir: PathBuf, missing: usize, total: usize },
+    RestoreInSync { backup_dir: PathBuf },
+}
+
+pub fn load_manifest(path: &Path) -> Result<Manifest, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read manifest at {}: {e}", path.display()))?;
+    toml::from_str(&content)
+        .map_err(|e| format!("failed to parse manifest at {}: {e}", path.display()))
+}
+
+pub fn lockfile_path(manifest_path: &Path) -> PathBuf {
+    manifest_path.with_extension("lock")
+}
+
+pub fn load_lockfile(path: &Path) -> Result<Lockfile, String> {
+    if !path.exists() {
+        return Ok(Lockfile::default());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read lockfile at {}: {e}", path.display()))?;
+    toml::from_str(&content)
+        .map_err(|e| format!("failed to parse lockfile at {}: {e}", path.display()))
+}
+
+pub fn save_lockfile(path: &Path, lock: &Lockfile) -> Result<(), String> {
+    let content = toml::to_string_pretty(lock).map_err(|e| format!("failed to serialize lockfile: {e}"))?;
+    fs::write(path, content).map_err(|e| format!("failed to write lockfile at {}: {e}", path.display()))
+}
+
+/// Resolves the catalog a manifest refers to, relative to the manifest itself.
+pub fn catalog_path(manifest_path: &Path, manifest: &Manifest) -> Result<PathBuf, String> {
+    match manifest.catalog.as_deref() {
+        Some(path) => {
+            let expanded = expand_tilde(path)?;
+            if expanded.is_absolute() {
+                Ok(expanded)
+            } else {
+                Ok(manifest_path.parent().unwrap_or(Path::new(".")).join(expanded))
+            }
+        }
+        None => crate::config::require_catalog(),
+    }
+}
+
+/// Returns the version currently present on the system for a catalog entry,
+/// asking the package manager for package-only entries (without its epoch
+/// and packaging revision, as they are pinned and locked) and falling back
+/// to the lockfile for downloaded artifacts.
+pub fn current_version(key: &str, catalog: &CatalogFile, distro: &DistroInfo, lock: &Lockfile) -> Option<String> {
+    let spec = catalog.software.get(key)?;
+    if matches!(spec.source, SourceSpec::PackageManager) {
+        let package = crate::resolver::primary_package(spec)?;
+        return distro.pkg_manager.installed_version(package).map(|v| distro.pkg_manager.upstream_version(&v));
+    }
+    lock.tools.get(key).map(|t| t.version.clone())
+}
+
+pub fn compute_plan(manifest: &Manifest, catalog: &CatalogFile, distro: &DistroInfo, lock: &Lockfile) -> Result<Vec<PlanAction>, String> {
+    let mut plan = Vec::new();
+
+    for (key, pin) in &manifest.tools {
+        let Some(spec) = catalog.software.get(key) else {
+            plan.push(PlanAction::Invalid { key: key.clone(), reason: "not found in catalog".to_string() });
+            continue;
+        };
+        if let Some(reason) = distro.environment.incompatibility(spec) {
+            plan.push(PlanAction::Invalid { key: key.clone(), reason: format!("skipped: {reason}") });
+            continue;
+        }
+        let action = match (current_version(key, catalog, distro, lock), pin.version.as_deref()) {
+            (None, pinned) => PlanAction::Install {
+                key: key.clone(),
+                pin: pinned.map(str::to_string),
+                reason: "not installed".to_string(),
+            },
+            (Some(installed), Some(pinned)) if installed != pinned => PlanAction::Install {
+                key: key.clone(),
+                pin: Some(pinned.to_string()),
+                reason: format!("{installed} -> {pinned}"),
+            },
+            (Some(installed), _) => PlanAction::UpToDate { key: key.clone(), version: installed },
+        };
+        plan.push(action);
+    }
+
+    for (group, dotfiles) in &manifest.dotfiles {
+        let source = expand_tilde(&dotfiles.source)?;
+        let target = expand_tilde(&dotfiles.target)?;
+        let mut pending = Vec::new();
+        for file in &dotfiles.files {
+            let from = source.join(file);
+            let to = target.join(file);
+            let same = match (fs::read(&from), fs::read(&to)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            };
+            if !same {
+                pending.push((from, to));
+            }
+        }
+        if pending.is_empty() {
+            plan.push(PlanAction::DotfilesInSync { group: group.clone() });
+        } else {
+            plan.push(PlanAction::CopyDotfiles { group: group.clone(), files: pending });
+        }
+    }
+
+    if let Some(restore) = &manifest.restore {
+        let backup_dir = expand_tilde(&restore.backup_dir)?;
+        let info = crate::restorer::load_backup_info(&backup_dir)?;
+        let dest = PathBuf::from(&info.source_path);
+        let index = info.index.unwrap_or_default();
+        let missing = index.iter().filter(|e| !dest.join(&e.relative_path).exists()).count();
+        if index.is_empty() || missing > 0 {
+            plan.push(PlanAction::Restore { backup_dir, missing, total: index.len() });
+        } else {
+            plan.push(PlanAction::RestoreInSync { backup_dir });
+        }
+    }
+
+    Ok(plan)
+}
+
+pub fn print_plan(plan: &[PlanAction]) {
+    for action in plan {
+        match action {
+            PlanAction::Install { key, reason, .. } => println!("  + {:<20} install ({reason})", key),
+            PlanAction::UpToDate { key, version } => println!("  = {:<20} up to date ({version})", key),
+            PlanAction::Invalid { key, reason } => println!("  ! {:<20} {reason}", key),
+            PlanAction::CopyDotfiles { group, files } => {
+                println!("  + {:<20} copy {} dotfile(s)", format!("dotfiles/{group}"), files.len());
+                for (from, to) in files {
+                    println!("      {} -> {}", from.display(), to.display());
+                }
+            }
+            PlanAction::DotfilesInSync { group } => println!("  = {:<20} in sync", format!("dotfiles/{group}")),
+            PlanAction::Restore { backup_dir, missing, total } => {
+                println!("  + {:<20} restore from {} ({missing}/{total} files missing)", "restore", backup_dir.display())
+            }
+            PlanAction::RestoreInSync { backup_dir } => println!("  = {:<20} {} already restored", "restore", backup_dir.display()),
+        }
+    }
+}
+
+/// Latest `installed_at` recorded for `key` in any lockfile found in `dirs`,
+/// e.g. next to the manifests in the working directory.
+pub fn last_installed(key: &str, dirs: &[PathBuf]) -> Option<u64> {
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok).map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "lock"))
+        .filter_map(|path| load_lockfile(&path).ok())
+        .filter_map(|lock| lock.tools.get(key).map(|t| t.installed_at))
+        .max()
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Runs a single tool install outside the TUI, echoing its log lines to stdout.
+pub fn install_headless(
+    client: &Client,
+    key: &str,
+    pin: Option<&str>,
+    catalog: &CatalogFile,
+    distro: &DistroInfo,
+    dry_run: bool,
+) -> Result<(crate::resolver::ResolvedAsset, crate::installer::InstallOutcome), String> {
+    let spec = catalog.software.get(key).ok_or_else(|| "Missing spec".to_string())?;
+    let resolved = match (crate::bundle::resolve(key, spec, distro), pin) {
+        (Some(bundled), _) => bundled?,
+        (None, Some(version)) => crate::runtime::block_on(resolve_pinned(client, spec, distro, version))?,
+        (None, None) => crate::runtime::block_on(resolve_asset(client, spec, distro))?,
+    };
+
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let (tx, printer) = crate::events::forward(|msg| {
+        crate::ipc::publish_msg(&msg);
+        match msg {
+            InstallMsg::Log(_, line) => println!("    {line}"),
+            // Secrets the answers file covers never get here.
+            InstallMsg::Secret(prompt, reply) if !crate::answers::active() => {
+                let _ = reply.send(rpassword::prompt_password(format!("    {prompt}: ")).ok());
+            }
+            InstallMsg::Elevate(reply) => {
+                let ok = if crate::answers::active() {
+                    crate::answers::elevate()
+                } else {
+                    std::process::Command::new("sudo").arg("-v").status().is_ok_and(|s| s.success())
+                };
+                if ok {
+                    crate::sudo::keep_alive();
+                }
+                let _ = reply.send(ok);
+            }
+            _ => {}
+        }
+    });
+
+    let result = install_software(client, key, spec, &resolved, distro, dry_run, &tx, &cancel);
+    drop(tx);
+    let _ = printer.join();
+    Ok((resolved, result?))
+}
+
+/// Pins every tool to its lockfile version for `locked` runs, failing for
+/// tools that are neither locked nor pinned in the manifest.
+fn lock_pins(manifest: &mut Manifest, lock: &Lockfile) -> Result<(), String> {
+    let mut unlocked = Vec::new();
+    for (key, pin) in manifest.tools.iter_mut() {
+        match lock.tools.get(key) {
+            Some(locked) => pin.version = Some(locked.version.clone()),
+            None if pin.version.is_some() => {}
+            None => unlocked.push(key.clone()),
+        }
+    }
+    if unlocked.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("no locked or pinned version for: {}; run `rusty_rebase apply` once to write the lockfile", unlocked.join(", ")))
+    }
+}
+
+/// Applies a manifest. With `locked`, the lockfile is treated as input
+/// rather than machine state: every tool installs exactly its locked version
+/// and the lockfile is left untouched. With `max_duration`, actions still
+/// queued once the budget is spent are skipped and reported as deferred; the
+/// action running at that moment is allowed to finish.
+pub fn apply_manifest(
+    manifest_path: &Path,
+    dry_run: bool,
+    assume_yes: bool,
+    locked: bool,
+    max_duration: Option<Duration>,
+) -> Result<(), String> {
+    let mut manifest = load_manifest(manifest_path)?;
+    let catalog = load_catalog(&catalog_path(manifest_path, &manifest)?)?;
+    let distro = crate::distro::detect_distro()?;
+    let lock_path = lockfile_path(manifest_path);
+    let mut lock = load_lockfile(&lock_path)?;
+
+    let plan = if locked {
+        lock_pins(&mut manifest, &lock)?;
+        compute_plan(&manifest, &catalog, &distro, &Lockfile::default())?
+    } else {
+        compute_plan(&manifest, &catalog, &distro, &lock)?
+    };
+    println!("Plan for {} ({} on {}):", manifest_path.display(), distro.pkg_manager, distro.id);
+    print_plan(&plan);
+
+    let pending = plan.iter().filter(|a| matches!(a,
+        PlanAction::Install { .. } | PlanAction::CopyDotfiles { .. } | PlanAction::Restore { .. })).count();
+    if pending == 0 {
+        println!("Nothing to do, machine matches the manifest.");
+        return Ok(());
+    }
+
+    if !dry_run && !assume_yes && !crate::answers::confirm("apply", &format!("Apply {pending} change(s)?"))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let client = crate::resolver::build_client()?;
+    let deadline = max_duration.map(|budget| Instant::now() + budget);
+    let source = format!("apply {}", manifest_path.display());
+    crate::ipc::publish(ProgressEvent::Started { source: source.clone(), total: pending });
+    let mut succeeded = 0;
+    let mut failures = 0;
+    let mut deferred = Vec::new();
+    let recorder = (!dry_run).then(|| crate::state::RunRecorder::start(source));
+    for action in plan {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            match &action {
+                PlanAction::Install { key, .. } => deferred.push(key.clone()),
+                PlanAction::CopyDotfiles { group, .. } => deferred.push(format!("dotfiles/{group}")),
+                PlanAction::Restore { backup_dir, .. } => deferred.push(format!("restore {}", backup_dir.display())),
+                _ => {}
+            }
+            continue;
+        }
+        match action {
+            PlanAction::Install { key, pin, .. } => {
+                println!("==> installing {key}");
+                crate::ipc::publish(ProgressEvent::Progress { key: key.clone(), operation: "Installing".to_string(), speed: None });
+                let result = install_headless(&client, &key, pin.as_deref(), &catalog, &distro, dry_run);
+                if let Some(recorder) = &recorder {
+                    let recorded = match &result {
+                        Ok((resolved, outcome)) => {
+                            recorder.record(&key, &resolved.version, Ok(&[outcome.created_dirs.as_slice(), &outcome.files].concat()))
+                        }
+                        Err(e) => recorder.record(&key, pin.as_deref().unwrap_or_default(), Err(e)),
+                    };
+                    let event = match &result {
+                        Ok(_) => crate::state::HealthEvent::Installed,
+                        Err(e) => crate::state::HealthEvent::InstallFailed(e.clone()),
+                    };
+                    if let Err(e) = recorded.and_then(|()| crate::state::record_health([(key.clone(), event)])) {
+                        println!("[warn] state database: {e}");
+                    }
+                }
+                crate::ipc::publish(ProgressEvent::Done { key: key.clone(), ok: result.is_ok(), error: result.as_ref().err().cloned() });
+                match result {
+                    Ok((resolved, outcome)) if !dry_run && !locked => {
+                        succeeded += 1;
+                        lock.tools.insert(key, LockedTool {
+                            version: resolved.version,
+                            url: resolved.url,
+                            installed_at: now_secs(),
+                            created_dirs: outcome.created_dirs.iter().map(|d| d.display().to_string()).collect(),
+                        });
+                        save_lockfile(&lock_path, &lock)?;
+                    }
+                    Ok(_) => succeeded += 1,
+                    Err(e) => {
+                        crate::notify::tool_failed(&key, &e);
+                        println!("[error] {key} failed: {e}");
+                        failures += 1;
+                    }
+                }
+            }
+            PlanAction::CopyDotfiles { group, files } => {
+                println!("==> dotfiles/{group}");
+                let failed_before = failures;
+                for (from, to) in files {
+                    if dry_run {
+                        println!("    [dry-run] copy {} -> {}", from.display(), to.display());
+                        continue;
+                    }
+                    if let Some(parent) = to.parent() {
+                        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                    }
+                    if to.exists() {
+                        match crate::answers::dotfile_conflicts() {
+                            ConflictPolicy::Overwrite => {}
+                            ConflictPolicy::Skip => {
+                                println!("    kept {}, which differs (conflicts.dotfiles = \"skip\")", to.display());
+                                continue;
+                            }
+                            ConflictPolicy::Backup => {
+                                let backup = PathBuf::from(format!("{}.rusty_rebase.bak", to.display()));
+                                if let Err(e) = fs::copy(&to, &backup) {
+                                    println!("[error] failed to back up {}: {e}", to.display());
+                                    failures += 1;
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                    match fs::copy(&from, &to) {
+                        Ok(_) => println!("    copied {} -> {}", from.display(), to.display()),
+                        Err(e) => {
+                            println!("[error] failed to copy {}: {e}", from.display());
+                            failures += 1;
+                        }
+                    }
+                }
+                let error = (failures > failed_before).then(|| format!("{} file(s) failed to copy", failures - failed_before));
+                succeeded += usize::from(error.is_none());
+                crate::ipc::publish(ProgressEvent::Done { key: format!("dotfiles/{group}"), ok: error.is_none(), error });
+            }
+            PlanAction::Restore { backup_dir, .. } => {
+                println!("==> restore from {}", backup_dir.display());
+                if dry_run {
+                    println!("    [dry-run] restore {}", backup_dir.display());
+                    continue;
+                }
+                let result = crate::restorer::restore_backup(&backup_dir, None);
+                crate::ipc::publish(ProgressEvent::Done {
+                    key: format!("restore {}", backup_dir.display()),
+                    ok: result.is_ok(),
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                });
+                match result {
+                    Ok(logs) => {
+                        logs.iter().for_each(|l| println!("    {l}"));
+                        succeeded += 1;
+                    }
+                    Err(e) => {
+                        println!("[error] restore failed: {e}");
+                        failures += 1;
+                    }
+                }
+            }
+            PlanAction::Invalid { .. } | PlanAction::UpToDate { .. }
+            | PlanAction::DotfilesInSync { .. } | PlanAction::RestoreInSync { .. } => {}
+        }
+    }
+
+    crate::ipc::publish(ProgressEvent::Finished { succeeded, failed: failures, skipped: deferred.len() });
+    if !dry_run {
+        crate::notify::run_finished(succeeded, failures, deferred.len());
+    }
+    if !deferred.is_empty() {
+        let budget = crate::report::format_duration(max_duration.unwrap_or_default());
+        println!("Time budget of {budget} exceeded, deferred {} action(s):", deferred.len());
+        for item in &deferred {
+            println!("    [deferred] {item}");
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{failures} manifest action(s) failed"));
+    }
+    if !deferred.is_empty() {
+        println!("Manifest partially applied; run it again to continue.");
+        return Ok(());
+    }
+    println!("Manifest applied.");
+    Ok(())
+}
+
+/// Loads the tool -> version view of a machine from either a lockfile or a
+/// manifest. A manifest's sibling lockfile, when present, overrides its
+/// pinned versions since it reflects what was actually installed.
+pub fn load_machine_state(path: &Path) -> Result<BTreeMap<String, Option<String>>, String> {
+    if path.extension().is_some_and(|e| e == "lock") {
+        let lock = load_lockfile(path)?;
+        if lock.tools.is_empty() && !path.exists() {
+            return Err(format!("lockfile not found at {}", path.display()));
+        }
+        return Ok(lock.tools.into_iter().map(|(k, t)| (k, Some(t.version))).collect());
+    }
+
+    let manifest = load_manifest(path)?;
+    let lock = load_lockfile(&lockfile_path(path))?;
+    Ok(manifest
+        .tools
+        .into_iter()
+        .map(|(key, pin)| {
+            let version = lock.tools.get(&key).map(|t| t.version.clone()).or(pin.version);
+            (key, version)
+        })
+        .collect())
+}
+
+pub fn diff_machines(left_path: &Path, right_path: &Path, write_to: Option<&Path>) -> Result<(), String> {
+    let left = load_machine_state(left_path)?;
+    let right = load_machine_state(right_path)?;
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "any".to_string());
+
+    let only_left: Vec<_> = left.iter().filter(|(k, _)| !right.contains_key(*k)).collect();
+    let only_right: Vec<_> = right.iter().filter(|(k, _)| !left.contains_key(*k)).collect();
+    let changed: Vec<_> = left
+        .iter()
+        .filter_map(|(k, v)| right.get(k).filter(|rv| rv.is_some() && v.is_some() && *rv != v).map(|rv| (k, v, rv)))
+        .collect();
+    let common = left.keys().filter(|k| right.contains_key(*k)).count();
+
+    println!("A: {}", left_path.display());
+    println!("B: {}", right_path.display());
+    println!();
+    println!("Only on A ({}):", only_left.len());
+    for (key, version) in &only_left {
+        println!("  - {:<20} {}", key, show(version));
+    }
+    println!("Only on B ({}):", only_right.len());
+    for (key, version) in &only_right {
+        println!("  + {:<20} {}", key, show(version));
+    }
+    println!("Version differences ({}):", changed.len());
+    for (key, a, b) in &changed {
+        println!("  ~ {:<20} {} (A) vs {} (B)", key, show(a), show(b));
+    }
+    println!("{common} tool(s) present on both, {} with matching versions.", common - changed.len());
+
+    if let Some(out) = write_to {
+        let mut manifest = Manifest::default();
+        for (key, version) in only_right.iter().map(|(k, v)| (*k, *v)).chain(changed.iter().map(|(k, _, b)| (*k, *b))) {
+            manifest.tools.insert(key.clone(), ToolPin { version: version.clone() });
+        }
+        let content = toml::to_string_pretty(&manifest).map_err(|e| format!("failed to serialize manifest: {e}"))?;
+        fs::write(out, content).map_err(|e| format!("failed to write manifest {}: {e}", out.display()))?;
+        println!();
+        println!("Wrote {} tool(s) to {}. Run `rusty_rebase apply {}` on A to match B.", manifest.tools.len(), out.display(), out.display());
+    }
+    Ok(())
+}
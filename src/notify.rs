@@ -0,0 +1,77 @@
+use std::process::{Command, Stdio};
+
+use crate::platform::Platform;
+
+const APP_NAME: &str = "Rusty Rebase";
+
+/// Tells the user a tool failed, unless `notify_on_failure = false`.
+pub fn tool_failed(key: &str, error: &str) {
+    if crate::config::current().notify_on_failure != Some(false) {
+        send(&format!("{key} failed to install"), error, true);
+    }
+}
+
+/// Sums a finished run up, unless `notify_on_finish = false`.
+pub fn run_finished(succeeded: usize, failed: usize, skipped: usize) {
+    if crate::config::current().notify_on_finish == Some(false) {
+        return;
+    }
+    let mut body = format!("{succeeded} succeeded, {failed} failed");
+    if skipped > 0 {
+        body.push_str(&format!(", {skipped} skipped"));
+    }
+    let summary = if failed > 0 { "Install finished with failures" } else { "Install finished" };
+    send(summary, &body, failed > 0);
+}
+
+/// Shows a desktop notification through notify-send or the freedesktop
+/// D-Bus service on Linux and a tray balloon on Windows. Does nothing
+/// without a desktop session or a notification daemon; the command is not
+/// waited for, so a missing daemon cannot hold the run up.
+fn send(summary: &str, body: &str, failure: bool) {
+    let platform = Platform::current();
+    let mut command = match platform {
+        Platform::Linux => {
+            let session = ["DBUS_SESSION_BUS_ADDRESS", "DISPLAY", "WAYLAND_DISPLAY"].iter().any(|var| std::env::var_os(var).is_some());
+            if !session {
+                return;
+            }
+            let icon = if failure { "dialog-error" } else { "dialog-information" };
+            if platform.has_command("notify-send") {
+                let mut command = Command::new("notify-send");
+                command.args(["--app-name", APP_NAME, "--icon", icon, "--", summary, body]);
+                command
+            } else if platform.has_command("gdbus") {
+                let mut command = Command::new("gdbus");
+                command.args([
+                    "call", "--session",
+                    "--dest", "org.freedesktop.Notifications",
+                    "--object-path", "/org/freedesktop/Notifications",
+                    "--method", "org.freedesktop.Notifications.Notify",
+                    APP_NAME, "0", icon, summary, body, "[]", "{}", "-1",
+                ]);
+                command
+            } else {
+                return;
+            }
+        }
+        Platform::Windows => {
+            let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+            let script = format!(
+                "Add-Type -AssemblyName System.Windows.Forms; $n = New-Object System.Windows.Forms.NotifyIcon; \
+                 $n.Icon = [System.Drawing.SystemIcons]::{}; $n.Visible = $true; \
+                 $n.ShowBalloonTip(5000, {}, {}, '{}'); Start-Sleep -Seconds 6; $n.Dispose()",
+                if failure { "Error" } else { "Information" },
+                quote(summary),
+                quote(body),
+                if failure { "Error" } else { "Info" },
+            );
+            let mut command = Command::new("powershell");
+            command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+            command
+        }
+    };
+    let Ok(mut child) = command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else { return };
+    // Reaps the process; it outlives a headless run that exits first.
+    std::thread::spawn(move || child.wait());
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::command::{CommandLine, Script};
+
+/// A system package manager. Mutating operations return the commands to run
+/// so callers can stream their output (or print them in dry-run); queries run
+/// immediately.
+pub trait PackageManager: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn install(&self, packages: &[String]) -> Option<Script>;
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script>;
+
+    /// Installed packages that depend on any of `packages`, excluding the
+    /// packages themselves. Empty when the manager cannot tell.
+    fn reverse_dependencies(&self, _packages: &[String]) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine>;
+
+    /// Version the repositories would install.
+    fn query_version(&self, package: &str) -> Option<String>;
+
+    /// Candidate versions for several packages at once. Packages the
+    /// repositories don't know are left out of the map.
+    fn query_versions(&self, packages: &[String]) -> HashMap<String, String> {
+        packages
+            .iter()
+            .filter_map(|p| Some((p.clone(), self.query_version(p)?)))
+            .collect()
+    }
+
+    /// Version currently installed on the system.
+    fn installed_version(&self, package: &str) -> Option<String>;
+
+    fn is_installed(&self, package: &str) -> bool {
+        self.installed_version(package).is_some()
+    }
+
+    /// The upstream part of a version this manager reports, comparable with
+    /// the versions other sources resolve. Drops the epoch (`1:`) and the
+    /// packaging revision (`-1ubuntu1`, `-1.fc40`) of dpkg, rpm and pacman
+    /// versions, so `1:2.3-1ubuntu1` becomes `2.3`.
+    fn upstream_version(&self, version: &str) -> String {
+        let version = version.trim();
+        let version = match version.split_once(':') {
+            Some((epoch, rest)) if !epoch.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) => rest,
+            _ => version,
+        };
+        version.rsplit_once('-').map_or(version, |(upstream, _)| upstream).to_string()
+    }
+
+    /// Extension of the native package format preferred for downloaded assets.
+    fn artifact_ext(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Primary and fallback commands installing a downloaded package file,
+    /// or `None` when this manager cannot handle the file.
+    fn install_artifact(&self, _path: &Path) -> Option<(Script, Script)> {
+        None
+    }
+
+    /// False for the placeholder used when no supported manager was found.
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+impl std::fmt::Display for dyn PackageManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Rejects catalog package names that a package manager could read as an
+/// option, or that contain characters no package name uses.
+pub fn check_package_names(packages: &[String]) -> Result<(), String> {
+    for name in packages {
+        let valid = !name.is_empty()
+            && !name.starts_with('-')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || "._+-:@/~".contains(c));
+        if !valid {
+            return Err(format!("refusing suspicious package name '{name}'"));
+        }
+    }
+    Ok(())
+}
+
+fn stdout_of(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn success_stdout_of(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn non_empty(version: String) -> Option<String> {
+    if version.is_empty() { None } else { Some(version) }
+}
+
+/// Returns the value of the first `<label>: value` line.
+fn field_value(stdout: &str, label: &str) -> Option<String> {
+    stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with(label))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, v)| v.trim().to_string())
+}
+
+fn collect_dependents(packages: &[String], query: impl Fn(&str) -> Vec<String>) -> Vec<String> {
+    let mut dependents: Vec<String> = packages
+        .iter()
+        .flat_map(|p| query(p))
+        .filter(|d| !packages.contains(d))
+        .collect();
+    dependents.sort();
+    dependents.dedup();
+    dependents
+}
+
+/// Splits `Name : x` / `Version : y` record output (pacman -Si, dnf/zypper info)
+/// into name -> version, keeping the first record seen for each name.
+fn parse_name_version_records(stdout: &str) -> HashMap<String, String> {
+    let mut versions = HashMap::new();
+    let mut name: Option<String> = None;
+    for line in stdout.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            "Name" => name = Some(value.trim().to_string()),
+            "Version" => {
+                if let Some(n) = name.take() {
+                    versions.entry(n).or_insert_with(|| value.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    versions
+}
+
+/// Reads the column after `package` from tabular listing output.
+fn column_after(stdout: &str, package: &str) -> Option<String> {
+    let row = stdout.lines().find(|l| l.split_whitespace().any(|w| w.eq_ignore_ascii_case(package)))?;
+    let mut cols = row.split_whitespace().skip_while(|w| !w.eq_ignore_ascii_case(package));
+    cols.nth(1).map(str::to_string)
+}
+
+#[derive(Debug)]
+pub struct Apt;
+
+impl PackageManager for Apt {
+    fn name(&self) -> &'static str {
+        "apt"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        if packages.is_empty() {
+            return None;
+        }
+        Some(vec![
+            self.refresh_index()?,
+            CommandLine::new("apt").args(["install", "-y", "--"]).args(packages).elevated(),
+        ])
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![CommandLine::new("apt").args(["remove", "-y", "--"]).args(packages).elevated()])
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(CommandLine::new("apt").arg("update").elevated())
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        let v = field_value(&stdout_of("apt-cache", &["policy", package])?, "Candidate")?;
+        if v == "(none)" { None } else { Some(v) }
+    }
+
+    fn query_versions(&self, packages: &[String]) -> HashMap<String, String> {
+        let mut args = vec!["policy"];
+        args.extend(packages.iter().map(String::as_str));
+        let Some(stdout) = stdout_of("apt-cache", &args) else {
+            return HashMap::new();
+        };
+        // Each package block starts with an unindented `name:` line.
+        let mut versions = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in stdout.lines() {
+            if !line.starts_with(' ') && let Some(name) = line.strip_suffix(':') {
+                current = Some(name.to_string());
+            } else if let Some(v) = line.trim().strip_prefix("Candidate:")
+                && let Some(name) = current.take()
+                && v.trim() != "(none)"
+            {
+                versions.insert(name, v.trim().to_string());
+            }
+        }
+        versions
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        non_empty(success_stdout_of("dpkg-query", &["-W", "-f=${Version}", package])?.trim().to_string())
+    }
+
+    fn reverse_dependencies(&self, packages: &[String]) -> Vec<String> {
+        collect_dependents(packages, |p| {
+            let Some(stdout) = stdout_of("apt-cache", &["rdepends", "--installed", "--no-recommends", "--no-suggests", p]) else {
+                return Vec::new();
+            };
+            stdout
+                .lines()
+                .skip_while(|l| !l.starts_with("Reverse Depends:"))
+                .skip(1)
+                .map(|l| l.trim().trim_start_matches('|').to_string())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+    }
+
+    fn artifact_ext(&self) -> Option<&'static str> {
+        Some(".deb")
+    }
+
+    fn install_artifact(&self, path: &Path) -> Option<(Script, Script)> {
+        path.extension().is_some_and(|e| e == "deb").then(|| {
+            let file = path.display().to_string();
+            (
+                vec![CommandLine::new("apt").args(["install", "-y", file.as_str()]).elevated()],
+                vec![
+                    CommandLine::new("dpkg").args(["-i", file.as_str()]).elevated().keep_going(),
+                    CommandLine::new("apt").args(["-f", "install", "-y"]).elevated(),
+                ],
+            )
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Dnf;
+
+impl PackageManager for Dnf {
+    fn name(&self) -> &'static str {
+        "dnf"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![CommandLine::new("dnf").args(["install", "-y", "--"]).args(packages).elevated()])
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![CommandLine::new("dnf").args(["remove", "-y", "--"]).args(packages).elevated()])
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(CommandLine::new("dnf").arg("makecache").elevated())
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        field_value(&stdout_of("dnf", &["info", "-q", package])?, "Version")
+    }
+
+    fn query_versions(&self, packages: &[String]) -> HashMap<String, String> {
+        let mut args = vec!["info", "-q"];
+        args.extend(packages.iter().map(String::as_str));
+        stdout_of("dnf", &args).map(|out| parse_name_version_records(&out)).unwrap_or_default()
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        non_empty(success_stdout_of("rpm", &["-q", "--qf", "%{VERSION}-%{RELEASE}", package])?.trim().to_string())
+    }
+
+    fn reverse_dependencies(&self, packages: &[String]) -> Vec<String> {
+        collect_dependents(packages, |p| {
+            stdout_of("dnf", &["repoquery", "-q", "--installed", "--whatrequires", p, "--qf", "%{name}"])
+                .map(|out| out.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn artifact_ext(&self) -> Option<&'static str> {
+        Some(".rpm")
+    }
+
+    fn install_artifact(&self, path: &Path) -> Option<(Script, Script)> {
+        path.extension().is_some_and(|e| e == "rpm").then(|| {
+            let file = path.display().to_string();
+            (
+                vec![CommandLine::new("dnf").args(["install", "-y", file.as_str()]).elevated()],
+                vec![CommandLine::new("rpm").args(["-i", file.as_str()]).elevated()],
+            )
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Pacman;
+
+impl PackageManager for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![CommandLine::new("pacman").args(["-Sy", "--noconfirm", "--"]).args(packages).elevated()])
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![CommandLine::new("pacman").args(["-R", "--noconfirm", "--"]).args(packages).elevated()])
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(CommandLine::new("pacman").arg("-Sy").elevated())
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        field_value(&stdout_of("pacman", &["-Si", package])?, "Version")
+    }
+
+    fn query_versions(&self, packages: &[String]) -> HashMap<String, String> {
+        let mut args = vec!["-Si"];
+        args.extend(packages.iter().map(String::as_str));
+        stdout_of("pacman", &args).map(|out| parse_name_version_records(&out)).unwrap_or_default()
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        let stdout = success_stdout_of("pacman", &["-Q", package])?;
+        stdout.split_whitespace().nth(1).map(str::to_string)
+    }
+
+    fn reverse_dependencies(&self, packages: &[String]) -> Vec<String> {
+        collect_dependents(packages, |p| {
+            success_stdout_of("pacman", &["-Qi", p])
+                .and_then(|out| field_value(&out, "Required By"))
+                .filter(|v| v != "None")
+                .map(|v| v.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Zypper;
+
+impl PackageManager for Zypper {
+    fn name(&self) -> &'static str {
+        "zypper"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| {
+            vec![CommandLine::new("zypper").args(["--non-interactive", "install", "--"]).args(packages).elevated()]
+        })
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| {
+            vec![CommandLine::new("zypper").args(["--non-interactive", "remove", "--"]).args(packages).elevated()]
+        })
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(CommandLine::new("zypper").args(["--non-interactive", "refresh"]).elevated())
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        field_value(&stdout_of("zypper", &["--non-interactive", "info", package])?, "Version")
+    }
+
+    fn query_versions(&self, packages: &[String]) -> HashMap<String, String> {
+        let mut args = vec!["--non-interactive", "info"];
+        args.extend(packages.iter().map(String::as_str));
+        stdout_of("zypper", &args).map(|out| parse_name_version_records(&out)).unwrap_or_default()
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        non_empty(success_stdout_of("rpm", &["-q", "--qf", "%{VERSION}-%{RELEASE}", package])?.trim().to_string())
+    }
+
+    fn reverse_dependencies(&self, packages: &[String]) -> Vec<String> {
+        collect_dependents(packages, |p| {
+            success_stdout_of("rpm", &["-q", "--whatrequires", p, "--qf", "%{NAME}\\n"])
+                .map(|out| out.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn artifact_ext(&self) -> Option<&'static str> {
+        Some(".rpm")
+    }
+
+    fn install_artifact(&self, path: &Path) -> Option<(Script, Script)> {
+        path.extension().is_some_and(|e| e == "rpm").then(|| {
+            let file = path.display().to_string();
+            (
+                vec![CommandLine::new("zypper")
+                    .args(["--non-interactive", "install", "--allow-unsigned-rpm", file.as_str()])
+                    .elevated()],
+                vec![CommandLine::new("rpm").args(["-i", file.as_str()]).elevated()],
+            )
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Winget;
+
+impl PackageManager for Winget {
+    fn name(&self) -> &'static str {
+        "winget"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| {
+            packages
+                .iter()
+                .map(|p| {
+                    CommandLine::new("winget")
+                        .args(["install", "-e", "--id", p.as_str(), "--silent", "--accept-package-agreements", "--accept-source-agreements"])
+                        .keep_going()
+                })
+                .collect()
+        })
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| {
+            packages
+                .iter()
+                .map(|p| CommandLine::new("winget").args(["uninstall", "-e", "--id", p.as_str(), "--silent"]).keep_going())
+                .collect()
+        })
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(CommandLine::new("winget").args(["source", "update"]))
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        field_value(&stdout_of("winget", &["show", "-e", "--id", package, "--accept-source-agreements"])?, "Version")
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        let stdout = success_stdout_of("winget", &["list", "-e", "--id", package, "--accept-source-agreements"])?;
+        column_after(&stdout, package)
+    }
+
+    /// Windows package versions carry no epoch or revision, and a hyphen
+    /// starts a pre-release tag.
+    fn upstream_version(&self, version: &str) -> String {
+        version.trim().to_string()
+    }
+
+    fn artifact_ext(&self) -> Option<&'static str> {
+        Some(".msi")
+    }
+}
+
+#[derive(Debug)]
+pub struct Scoop;
+
+/// Scoop is a PowerShell script with `.cmd` and `.ps1` shims, which
+/// `CreateProcess` cannot start by name, so its `.ps1` shim runs through
+/// PowerShell. `-File` hands the arguments over as they are, where
+/// `-Command` would parse them as PowerShell.
+fn scoop(args: &[&str]) -> CommandLine {
+    CommandLine::new("powershell").args(["-NoProfile", "-File", &scoop_script()]).args(args.iter().copied())
+}
+
+/// The shim in `$SCOOP\shims`, `~\scoop\shims` unless Scoop was installed
+/// elsewhere.
+fn scoop_script() -> String {
+    let root = std::env::var_os("SCOOP").map(PathBuf::from).or_else(|| dirs::home_dir().map(|home| home.join("scoop"))).unwrap_or_default();
+    root.join("shims").join("scoop.ps1").display().to_string()
+}
+
+impl PackageManager for Scoop {
+    fn name(&self) -> &'static str {
+        "scoop"
+    }
+
+    fn install(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![scoop(&["install"]).args(packages)])
+    }
+
+    fn remove_command(&self, packages: &[String]) -> Option<Script> {
+        (!packages.is_empty()).then(|| vec![scoop(&["uninstall"]).args(packages)])
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        Some(scoop(&["update"]))
+    }
+
+    fn query_version(&self, package: &str) -> Option<String> {
+        field_value(&stdout_of("powershell", &["-NoProfile", "-File", &scoop_script(), "info", package])?, "Version")
+    }
+
+    fn installed_version(&self, package: &str) -> Option<String> {
+        column_after(&success_stdout_of("powershell", &["-NoProfile", "-File", &scoop_script(), "list", package])?, package)
+    }
+
+    /// Windows package versions carry no epoch or revision, and a hyphen
+    /// starts a pre-release tag.
+    fn upstream_version(&self, version: &str) -> String {
+        version.trim().to_string()
+    }
+
+    fn artifact_ext(&self) -> Option<&'static str> {
+        Some(".msi")
+    }
+}
+
+#[derive(Debug)]
+pub struct Unknown;
+
+impl PackageManager for Unknown {
+    fn name(&self) -> &'static str {
+        "unknown"
+    }
+
+    fn install(&self, _packages: &[String]) -> Option<Script> {
+        None
+    }
+
+    fn remove_command(&self, _packages: &[String]) -> Option<Script> {
+        None
+    }
+
+    fn refresh_index(&self) -> Option<CommandLine> {
+        None
+    }
+
+    fn query_version(&self, _package: &str) -> Option<String> {
+        None
+    }
+
+    fn installed_version(&self, _package: &str) -> Option<String> {
+        None
+    }
+
+    fn is_available(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upstream_versions_drop_epoch_and_revision() {
+        assert_eq!(Apt.upstream_version("1:2.3-1ubuntu1"), "2.3");
+        assert_eq!(Apt.upstream_version("2.3.1-2+deb12u1"), "2.3.1");
+        assert_eq!(Dnf.upstream_version("2.43.0-1.fc40"), "2.43.0");
+        assert_eq!(Pacman.upstream_version("2:1.0.0-3"), "1.0.0");
+        assert_eq!(Apt.upstream_version("9.0"), "9.0");
+        assert_eq!(Winget.upstream_version("1.2.0-rc1"), "1.2.0-rc1");
+    }
+
+    #[test]
+    fn scoop_arguments_are_not_parsed_as_powershell() {
+        let script = Scoop.install(&["foo; Remove-Item ~".to_string()]).unwrap();
+        let args = &script[0].args;
+        assert_eq!(args[..2], ["-NoProfile", "-File"]);
+        assert!(args[2].ends_with("scoop.ps1"));
+        assert_eq!(args[3..], ["install", "foo; Remove-Item ~"]);
+    }
+}
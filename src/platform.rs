@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::command::CommandLine;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Platform {
+    Linux,
+    Windows,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(windows) { Platform::Windows } else { Platform::Linux }
+    }
+
+    /// Name used by vendors in release file names and metadata endpoints.
+    pub fn os_name(&self) -> &'static str {
+        match self {
+            Platform::Linux => "linux",
+            Platform::Windows => "windows",
+        }
+    }
+
+    /// Runs `cmd` through the platform shell. Only explicit catalog shell
+    /// steps go through here; everything else is spawned directly.
+    pub fn shell(&self, cmd: &str) -> CommandLine {
+        match self {
+            Platform::Linux => CommandLine::new("sh").args(["-c", cmd]),
+            Platform::Windows => CommandLine::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", cmd]),
+        }
+    }
+
+    /// Whether privileged steps need to be prefixed with sudo. Container
+    /// mode already runs as root.
+    pub fn uses_sudo(&self) -> bool {
+        matches!(self, Platform::Linux) && !crate::container::active()
+    }
+
+    /// Whether privileged commands wait on a sudo credential. pkexec prompts
+    /// through its own polkit agent instead.
+    pub fn needs_sudo_auth(&self) -> bool {
+        self.uses_sudo() && self.has_command("sudo")
+    }
+
+    /// Runs shell `cmd` as root: through sudo, or pkexec when sudo is not
+    /// installed. `keep_env` names variables sudo should pass through.
+    pub fn root_shell(&self, cmd: &str, keep_env: &[String]) -> CommandLine {
+        match self {
+            Platform::Windows => self.shell(cmd),
+            Platform::Linux if crate::container::active() => self.shell(cmd),
+            Platform::Linux if !self.has_command("sudo") && self.has_command("pkexec") => {
+                CommandLine::new("pkexec").args(["sh", "-c", cmd])
+            }
+            Platform::Linux => CommandLine::new("sudo").args(preserve_env_flag(keep_env)).args(["sh", "-c", cmd]),
+        }
+    }
+
+    /// Runs shell `cmd` as the invoking user when rusty_rebase itself was
+    /// started through sudo, and as the current user otherwise.
+    pub fn user_shell(&self, cmd: &str, keep_env: &[String]) -> CommandLine {
+        let invoking_user = std::env::var("SUDO_USER")
+            .ok()
+            .filter(|_| std::env::var("USER").as_deref() == Ok("root"));
+        match (self, invoking_user) {
+            (Platform::Linux, Some(user)) => CommandLine::new("sudo")
+                .args(["-u", user.as_str(), "-H"])
+                .args(preserve_env_flag(keep_env))
+                .args(["sh", "-c", cmd]),
+            _ => self.shell(cmd),
+        }
+    }
+
+    /// Looks up an executable on PATH.
+    pub fn has_command(&self, name: &str) -> bool {
+        let finder = match self {
+            Platform::Linux => "which",
+            Platform::Windows => "where",
+        };
+        Command::new(finder)
+            .arg(name)
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    /// Shell profile that receives PATH additions, relative to the home directory.
+    pub fn profile_path(&self, home: &std::path::Path) -> PathBuf {
+        if crate::container::active() {
+            return PathBuf::from(crate::container::PROFILE_SCRIPT);
+        }
+        match self {
+            Platform::Windows => home.join("Documents/PowerShell/Microsoft.PowerShell_profile.ps1"),
+            Platform::Linux => {
+                let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+                if shell.contains("zsh") {
+                    home.join(".zshrc")
+                } else if shell.contains("fish") {
+                    home.join(".config/fish/config.fish")
+                } else {
+                    home.join(".bashrc")
+                }
+            }
+        }
+    }
+
+    pub fn path_export_line(&self, dir: &str) -> String {
+        match self {
+            Platform::Windows => format!("$env:Path += \";{}\"", dir),
+            Platform::Linux => {
+                let shell = std::env::var("SHELL").unwrap_or_default();
+                if shell.contains("fish") && !crate::container::active() {
+                    format!("fish_add_path {}", dir)
+                } else {
+                    format!("export PATH=\"$PATH:{}\"", dir)
+                }
+            }
+        }
+    }
+
+    /// Substrings marking a release asset as built for this OS.
+    pub fn asset_keywords(&self) -> &'static [&'static str] {
+        match self {
+            Platform::Linux => &["linux"],
+            Platform::Windows => &["windows", "win64", "win32", "msvc", ".msi", ".exe"],
+        }
+    }
+
+    /// Substrings marking a release asset as built for another OS.
+    pub fn foreign_asset_keywords(&self) -> &'static [&'static str] {
+        match self {
+            Platform::Linux => &["windows", "win64", "win32", ".msi", ".exe", "darwin", "macos", ".dmg"],
+            Platform::Windows => &["linux", ".deb", ".rpm", ".appimage", "darwin", "macos", ".dmg"],
+        }
+    }
+}
+
+fn preserve_env_flag(names: &[String]) -> Option<String> {
+    (!names.is_empty()).then(|| format!("--preserve-env={}", names.join(",")))
+}
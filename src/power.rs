@@ -0,0 +1,52 @@
+use std::fs;
+use std::process::Command;
+
+use crate::platform::Platform;
+
+/// Downloads at least this large count as heavy when running on battery.
+pub const DEFAULT_BATTERY_THRESHOLD_MB: u64 = 500;
+
+/// Whether the machine is running on battery. Desktops and machines whose
+/// power state cannot be read count as plugged in.
+pub fn on_battery() -> bool {
+    match Platform::current() {
+        Platform::Linux => sysfs_on_battery().or_else(upower_on_battery).unwrap_or(false),
+        Platform::Windows => windows_on_battery(),
+    }
+}
+
+/// Reads /sys/class/power_supply: any online mains/USB supply means AC, a
+/// discharging battery means battery. `None` when neither is present.
+fn sysfs_on_battery() -> Option<bool> {
+    let read = |path: std::path::PathBuf| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+    let mut discharging = None;
+    for entry in fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = entry.path();
+        match read(path.join("type")).as_deref() {
+            Some("Mains" | "USB") if read(path.join("online")).as_deref() == Some("1") => return Some(false),
+            Some("Battery") => {
+                let status = read(path.join("status"));
+                discharging = Some(discharging.unwrap_or(false) || status.as_deref() == Some("Discharging"));
+            }
+            _ => {}
+        }
+    }
+    discharging
+}
+
+fn upower_on_battery() -> Option<bool> {
+    let output = Command::new("upower").arg("-d").output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("on-battery:"))
+        .map(|v| v.trim() == "yes")
+}
+
+/// BatteryStatus 1 is "discharging" in Win32_Battery.
+fn windows_on_battery() -> bool {
+    Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", "(Get-CimInstance Win32_Battery).BatteryStatus"])
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).lines().any(|l| l.trim() == "1"))
+}
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use reqwest::Client;
+use reqwest::header::CONTENT_LENGTH;
+use sysinfo::Disks;
+use tokio_util::sync::CancellationToken;
+
+use crate::units::format_size;
+
+/// How much larger than the download an extracted install is assumed to be
+/// when the config does not say otherwise.
+pub const DEFAULT_EXTRACT_MULTIPLIER: f64 = 3.0;
+
+/// Total download size, in MB, that is warned about when the config does
+/// not say otherwise.
+pub const DEFAULT_DOWNLOAD_WARN_MB: u64 = 2048;
+
+/// Outcome of a HEAD request against a download: the Content-Length, if the
+/// server sends one, or why the download would fail.
+pub type LinkCheck = Result<Option<u64>, String>;
+
+/// Network lookups run at once when `concurrency` is not configured.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// `concurrency` from the config, at least one.
+pub fn concurrency() -> usize {
+    crate::config::current().concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)
+}
+
+/// Artifacts downloaded ahead of their install when `parallel_downloads` is
+/// not configured.
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 2;
+
+/// `parallel_downloads` from the config; 0 turns downloading ahead off.
+pub fn parallel_downloads() -> usize {
+    crate::config::current().parallel_downloads.unwrap_or(DEFAULT_PARALLEL_DOWNLOADS)
+}
+
+/// Sends a HEAD request to `url`, telling dead links and auth walls apart.
+/// Servers that refuse HEAD itself are given the benefit of the doubt.
+pub async fn check_link(client: &Client, url: &str) -> LinkCheck {
+    let resp = client.head(url).send().await.map_err(|e| format!("unreachable: {e}"))?;
+    let status = resp.status();
+    match status.as_u16() {
+        _ if status.is_success() => {}
+        405 | 501 => return Ok(None),
+        404 | 410 => return Err(format!("dead link ({status})")),
+        401 | 403 => return Err(format!("login wall or blocked ({status})")),
+        _ => return Err(format!("HTTP {status}")),
+    }
+    Ok(resp.headers().get(CONTENT_LENGTH).and_then(|v| v.to_str().ok()?.parse().ok()))
+}
+
+/// Reads the Content-Length of `url` with a HEAD request.
+pub async fn remote_size(client: &Client, url: &str) -> Option<u64> {
+    check_link(client, url).await.ok().flatten()
+}
+
+/// The warning for downloading `total` bytes, once it is past `limit_mb`.
+pub fn download_warning(total: u64, limit_mb: u64) -> Option<String> {
+    (total > limit_mb.saturating_mul(1024 * 1024))
+        .then(|| format!("{} to download, over the {} warning threshold", format_size(total), format_size(limit_mb * 1024 * 1024)))
+}
+
+/// Checks every `(key, url)` pair, `parallel` at a time, sending each
+/// result as it arrives. Stops early once the receiver is gone.
+pub async fn check_links(client: Client, urls: Vec<(String, String)>, parallel: usize, tx: Sender<(String, LinkCheck)>) {
+    crate::runtime::bounded(urls, parallel, &CancellationToken::new(), |(key, url)| {
+        let (client, tx) = (client.clone(), tx.clone());
+        async move { tx.send((key, check_link(&client, &url).await)).is_ok() }
+    })
+    .await;
+}
+
+/// Returns the mount point holding `path` and its free bytes. Paths that do
+/// not exist yet are resolved through their closest existing ancestor.
+fn free_space(disks: &Disks, path: &Path) -> Option<(PathBuf, u64)> {
+    let mut existing = path;
+    while !existing.exists() {
+        existing = existing.parent()?;
+    }
+    let existing = existing.canonicalize().ok()?;
+    disks
+        .list()
+        .iter()
+        .filter(|d| existing.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| (d.mount_point().to_path_buf(), d.available_space()))
+}
+
+struct Need {
+    mount: PathBuf,
+    free: u64,
+    required: u64,
+    estimated: u64,
+}
+
+fn add_need(needs: &mut Vec<Need>, disks: &Disks, path: &Path, required: u64, estimated: u64) {
+    let Some((mount, free)) = free_space(disks, path) else {
+        return;
+    };
+    match needs.iter_mut().find(|n| n.mount == mount) {
+        Some(need) => {
+            need.required += required;
+            need.estimated += estimated;
+        }
+        None => needs.push(Need { mount, free, required, estimated }),
+    }
+}
+
+/// Compares an artifact of `size` bytes against free space on the download and
+/// install filesystems. Refuses when the download (or at least one extracted
+/// copy of it) cannot fit, and returns a warning when only the extraction
+/// estimate (`size * multiplier`) exceeds the free space.
+pub fn check_disk_space(size: u64, download_dir: &Path, install_root: &Path, multiplier: f64) -> Result<Option<String>, String> {
+    let disks = Disks::new_with_refreshed_list();
+    let extracted = (size as f64 * multiplier) as u64;
+    let mut needs = Vec::new();
+    add_need(&mut needs, &disks, download_dir, size, size);
+    add_need(&mut needs, &disks, install_root, size, extracted);
+
+    let mut warnings = Vec::new();
+    for need in needs {
+        if need.free < need.required {
+            return Err(format!(
+                "not enough disk space on {}: {} needed, {} free",
+                need.mount.display(),
+                format_size(need.required),
+                format_size(need.free)
+            ));
+        }
+        if need.free < need.estimated {
+            warnings.push(format!(
+                "low disk space on {}: about {} needed after extraction, {} free",
+                need.mount.display(),
+                format_size(need.estimated),
+                format_size(need.free)
+            ));
+        }
+    }
+    Ok((!warnings.is_empty()).then(|| warnings.join("; ")))
+}
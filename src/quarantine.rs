@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::resolver::ResolvedAsset;
+
+const REASON_FILE: &str = "reason.toml";
+
+/// Why a download was set aside, written next to it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Reason {
+    pub key: String,
+    pub version: String,
+    pub url: String,
+    pub reason: String,
+    pub quarantined_at: u64,
+    /// What the source said the download should hash to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_sha256: Option<String>,
+    pub sha256: String,
+}
+
+/// A download kept in the quarantine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarantinedDownload {
+    /// Folder holding the file and its reason.
+    pub dir: PathBuf,
+    pub file: PathBuf,
+    pub bytes: u64,
+    pub reason: Reason,
+}
+
+/// `~/.local/share/rusty_rebase/quarantine` on Linux, one folder per
+/// download.
+pub fn quarantine_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("rusty_rebase").join("quarantine"))
+}
+
+/// Moves `file`, a failed download of `resolved`, into a fresh quarantine
+/// folder under its artifact name, with a reason file next to it.
+pub fn quarantine(file: &Path, key: &str, resolved: &ResolvedAsset, reason: &str) -> Result<PathBuf, String> {
+    let root = quarantine_dir().ok_or("no data directory for the quarantine")?;
+    let now = crate::manifest::now_secs();
+    let mut dir = root.join(format!("{now}-{key}"));
+    for n in 2.. {
+        if !dir.exists() {
+            break;
+        }
+        dir = root.join(format!("{now}-{key}-{n}"));
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    let name = Path::new(&resolved.file_name).file_name().map(Path::new).unwrap_or(Path::new("download"));
+    let dest = dir.join(name);
+    // A plain rename fails across filesystems, e.g. a download folder on
+    // another disk.
+    if fs::rename(file, &dest).is_err() {
+        fs::copy(file, &dest).map_err(|e| format!("failed to quarantine {}: {e}", file.display()))?;
+        let _ = fs::remove_file(file);
+    }
+    let reason = Reason {
+        key: key.to_string(),
+        version: resolved.version.clone(),
+        url: resolved.url.clone(),
+        reason: reason.to_string(),
+        quarantined_at: now,
+        expected_sha256: resolved.sha256.clone(),
+        sha256: crate::installer::sha256_file(&dest).unwrap_or_default(),
+    };
+    let content = toml::to_string_pretty(&reason).map_err(|e| format!("failed to serialize quarantine reason: {e}"))?;
+    let reason_path = dir.join(REASON_FILE);
+    fs::write(&reason_path, content).map_err(|e| format!("failed to write {}: {e}", reason_path.display()))?;
+    Ok(dest)
+}
+
+/// Every quarantined download, newest first. Folders without a readable
+/// reason file are left out.
+pub fn list() -> Vec<QuarantinedDownload> {
+    let Some(root) = quarantine_dir() else { return Vec::new() };
+    let Ok(entries) = fs::read_dir(&root) else { return Vec::new() };
+    let mut found: Vec<QuarantinedDownload> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| dir.is_dir())
+        .filter_map(|dir| {
+            let reason: Reason = toml::from_str(&fs::read_to_string(dir.join(REASON_FILE)).ok()?).ok()?;
+            let file = fs::read_dir(&dir)
+                .ok()?
+                .flatten()
+                .map(|entry| entry.path())
+                .find(|path| path.is_file() && path.file_name().is_some_and(|n| n != REASON_FILE))
+                .unwrap_or_default();
+            let bytes = fs::metadata(&file).map(|m| m.len()).unwrap_or_default();
+            Some(QuarantinedDownload { dir, file, bytes, reason })
+        })
+        .collect();
+    found.sort_by(|a, b| b.reason.quarantined_at.cmp(&a.reason.quarantined_at).then_with(|| a.dir.cmp(&b.dir)));
+    found
+}
+
+/// Deletes one quarantined download, refusing folders outside the
+/// quarantine.
+pub fn purge(dir: &Path) -> Result<(), String> {
+    let root = quarantine_dir().ok_or("no data directory for the quarantine")?;
+    if dir.parent() != Some(root.as_path()) {
+        return Err(format!("refusing to delete {}, it is not in the quarantine", dir.display()));
+    }
+    fs::remove_dir_all(dir).map_err(|e| format!("failed to delete {}: {e}", dir.display()))
+}
@@ -0,0 +1,344 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::resources::RunResources;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordKind {
+    Install,
+    Restore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordStatus {
+    Succeeded,
+    Failed(String),
+    /// Abandoned by the user mid-install.
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub kind: RecordKind,
+    pub key: String,
+    pub name: String,
+    pub version: String,
+    pub source: String,
+    pub duration: Duration,
+    /// Size of the downloaded artifact, when one was fetched.
+    pub bytes: Option<u64>,
+    pub status: RecordStatus,
+    pub verification: String,
+}
+
+/// Counts and sums over a run, shown under the summary table and in the
+/// report header.
+#[derive(Debug, Default)]
+pub struct RunTotals {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration: Duration,
+    pub bytes: u64,
+}
+
+pub fn totals(records: &[RunRecord]) -> RunTotals {
+    let mut totals = RunTotals::default();
+    for r in records {
+        match r.status {
+            RecordStatus::Succeeded => totals.succeeded += 1,
+            RecordStatus::Failed(_) => totals.failed += 1,
+            RecordStatus::Skipped => totals.skipped += 1,
+        }
+        totals.duration += r.duration;
+        totals.bytes += r.bytes.unwrap_or(0);
+    }
+    totals
+}
+
+/// Install records, slowest first: the entries worth caching or mirroring
+/// come out on top.
+pub fn installs_by_duration(records: &[RunRecord]) -> Vec<&RunRecord> {
+    let mut installs: Vec<&RunRecord> = records.iter().filter(|r| r.kind == RecordKind::Install).collect();
+    installs.sort_by_key(|r| std::cmp::Reverse(r.duration));
+    installs
+}
+
+/// Wall time, network bytes, peak memory and child CPU time of a run, on
+/// one line.
+pub fn resources_summary(resources: &RunResources) -> String {
+    let cpu = resources.child_cpu.map(format_duration).unwrap_or_else(|| "-".to_string());
+    format!(
+        "wall {}, {} over the network, peak RAM {}, child CPU {cpu}",
+        format_duration(resources.wall),
+        crate::units::format_size(resources.downloaded),
+        crate::units::format_size(resources.peak_ram)
+    )
+}
+
+pub fn format_bytes(bytes: Option<u64>) -> String {
+    bytes.map(crate::units::format_size).unwrap_or_else(|| "-".to_string())
+}
+
+pub const REPORT_BASENAME: &str = "rusty_rebase_report";
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days, Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Parses the date at the start of an ISO 8601 string (`2024-03-05` or
+/// `2024-03-05T10:00:00Z`) into seconds since the epoch at midnight UTC.
+pub fn parse_date(text: &str) -> Option<u64> {
+    let mut parts = text.trim().get(..10)?.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days-from-civil, the inverse of the algorithm in `format_timestamp`.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400).ok()
+}
+
+/// Rough age such as "5 days", "3 months" or "2.4 years".
+pub fn format_age(secs: u64) -> String {
+    let days = secs / 86_400;
+    match days {
+        0 => "less than a day".to_string(),
+        1 => "1 day".to_string(),
+        2..=59 => format!("{days} days"),
+        60..=729 => format!("{} months", days / 30),
+        _ => format!("{:.1} years", days as f64 / 365.25),
+    }
+}
+
+pub fn now_timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    format_timestamp(secs)
+}
+
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", crate::units::format_decimal(d.as_secs_f64(), 1))
+    }
+}
+
+/// Parses a duration such as `90s`, `30m`, `1h30m` or a bare number of
+/// seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{text}' (expected e.g. 45s, 30m or 1h30m)");
+    if let Ok(secs) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = digits.parse().map_err(|_| invalid())?;
+        total = value.checked_mul(unit).and_then(|secs| secs.checked_add(total)).ok_or_else(invalid)?;
+        digits.clear();
+    }
+    if !digits.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+fn status_text(status: &RecordStatus) -> String {
+    match status {
+        RecordStatus::Succeeded => "succeeded".to_string(),
+        RecordStatus::Failed(reason) => format!("failed: {reason}"),
+        RecordStatus::Skipped => "skipped by user".to_string(),
+    }
+}
+
+fn escape_md(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn render_markdown(records: &[RunRecord], resources: Option<&RunResources>, dry_run: bool) -> String {
+    let mut out = String::new();
+    out.push_str("# Rusty Rebase Install Report\n\n");
+    out.push_str(&format!("- Generated: {}\n", now_timestamp()));
+    out.push_str(&format!("- Mode: {}\n", if dry_run { "dry-run" } else { "live" }));
+    let totals = totals(records);
+    out.push_str(&format!(
+        "- Items: {} ({} succeeded, {} failed, {} skipped)\n",
+        records.len(),
+        totals.succeeded,
+        totals.failed,
+        totals.skipped
+    ));
+    out.push_str(&format!(
+        "- Total time: {}, downloaded: {}\n",
+        format_duration(totals.duration),
+        crate::units::format_size(totals.bytes)
+    ));
+    if let Some(resources) = resources {
+        out.push_str(&format!("- Resources: {}\n", resources_summary(resources)));
+    }
+    out.push('\n');
+
+    let installs = installs_by_duration(records);
+    if !installs.is_empty() {
+        out.push_str("## Tools\n\n");
+        out.push_str("| Tool | Version | Source | Duration | Size | Status | Verification |\n");
+        out.push_str("|------|---------|--------|----------|------|--------|--------------|\n");
+        for r in installs {
+            out.push_str(&format!(
+                "| {} (`{}`) | {} | {} | {} | {} | {} | {} |\n",
+                escape_md(&r.name),
+                r.key,
+                escape_md(&r.version),
+                escape_md(&r.source),
+                format_duration(r.duration),
+                format_bytes(r.bytes),
+                escape_md(&status_text(&r.status)),
+                escape_md(&r.verification),
+            ));
+        }
+        out.push('\n');
+    }
+
+    let restores: Vec<&RunRecord> = records.iter().filter(|r| r.kind == RecordKind::Restore).collect();
+    if !restores.is_empty() {
+        out.push_str("## Restore Activity\n\n");
+        for r in restores {
+            out.push_str(&format!(
+                "- `{}`: {} in {} ({})\n",
+                r.source,
+                status_text(&r.status),
+                format_duration(r.duration),
+                r.verification
+            ));
+        }
+    }
+    out
+}
+
+pub fn render_html(records: &[RunRecord], resources: Option<&RunResources>, dry_run: bool) -> String {
+    let mut rows = String::new();
+    for r in records {
+        let color = match r.status {
+            RecordStatus::Succeeded => "#2e7d32",
+            RecordStatus::Failed(_) => "#c62828",
+            RecordStatus::Skipped => "#757575",
+        };
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td style=\"color:{color}\">{}</td><td>{}</td></tr>\n",
+            escape_html(&r.name),
+            escape_html(&r.version),
+            escape_html(&r.source),
+            format_duration(r.duration),
+            format_bytes(r.bytes),
+            escape_html(&status_text(&r.status)),
+            escape_html(&r.verification),
+        ));
+    }
+    let resources = resources.map(|r| format!("<p>Resources: {}</p>\n", escape_html(&resources_summary(r)))).unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Rusty Rebase Install Report</title>\n\
+<style>body{{font-family:sans-serif}}table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:4px 8px}}</style>\n\
+</head><body>\n<h1>Rusty Rebase Install Report</h1>\n<p>Generated {} ({})</p>\n{resources}\
+<table>\n<tr><th>Item</th><th>Version</th><th>Source</th><th>Duration</th><th>Size</th><th>Status</th><th>Verification</th></tr>\n{rows}</table>\n</body></html>\n",
+        now_timestamp(),
+        if dry_run { "dry-run" } else { "live" },
+    )
+}
+
+/// Writes the Markdown report (and the HTML variant when requested) into
+/// `dir`, returning the path of the Markdown file.
+pub fn write_report(
+    dir: &Path,
+    records: &[RunRecord],
+    resources: Option<&RunResources>,
+    dry_run: bool,
+    html: bool,
+) -> Result<PathBuf, String> {
+    let md_path = dir.join(format!("{REPORT_BASENAME}.md"));
+    fs::write(&md_path, render_markdown(records, resources, dry_run))
+        .map_err(|e| format!("failed to write report {}: {e}", md_path.display()))?;
+    if html {
+        let html_path = dir.join(format!("{REPORT_BASENAME}.html"));
+        fs::write(&html_path, render_html(records, resources, dry_run))
+            .map_err(|e| format!("failed to write report {}: {e}", html_path.display()))?;
+    }
+    Ok(md_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_take_units() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(1_800)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn oversized_durations_are_rejected() {
+        assert!(parse_duration("18446744073709551615h").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+
+    #[test]
+    fn dates_start_at_midnight_utc() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-03-05"), Some(1_709_596_800));
+        assert_eq!(parse_date("2024-03-05T10:00:00Z"), Some(1_709_596_800));
+        assert_eq!(parse_date("2000-02-29"), Some(951_782_400));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-03"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::catalog::SoftwareSpec;
+use crate::distro::DistroInfo;
+use crate::resolver::ResolvedAsset;
+
+/// Six hours: new releases show up the same day without every start
+/// resolving the whole catalog again.
+pub const DEFAULT_TTL_MINS: u64 = 360;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResolution {
+    /// Of the entry's source, the architecture and the package manager, so
+    /// editing the entry or switching managers resolves it again.
+    fingerprint: String,
+    resolved_at: u64,
+    asset: ResolvedAsset,
+}
+
+/// `~/.cache/rusty_rebase/resolved.json` on Linux.
+fn cache_file() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("rusty_rebase").join("resolved.json"))
+}
+
+/// How long a resolution is reused, from `resolve_cache_ttl_mins` in the
+/// config; 0 turns the cache off.
+pub fn ttl_secs() -> u64 {
+    crate::config::current().resolve_cache_ttl_mins.unwrap_or(DEFAULT_TTL_MINS).saturating_mul(60)
+}
+
+fn fingerprint(spec: &SoftwareSpec, distro: &DistroInfo) -> String {
+    let text = format!("{:?}|{}|{}", spec.source, std::env::consts::ARCH, distro.pkg_manager);
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+fn load() -> BTreeMap<String, CachedResolution> {
+    cache_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// The cache file as read once, for looking up a batch of entries.
+#[derive(Debug, Default)]
+pub struct ResolveCache(BTreeMap<String, CachedResolution>);
+
+impl ResolveCache {
+    pub fn load() -> Self {
+        Self(load())
+    }
+
+    /// The cached resolution of `key` and when it was made, if it is younger
+    /// than `ttl` seconds and was made for the same source.
+    pub fn lookup(&self, key: &str, spec: &SoftwareSpec, distro: &DistroInfo, ttl: u64) -> Option<(ResolvedAsset, u64)> {
+        let cached = self.0.get(key)?;
+        let fresh = crate::manifest::now_secs().saturating_sub(cached.resolved_at) < ttl;
+        (fresh && cached.fingerprint == fingerprint(spec, distro)).then(|| (cached.asset.clone(), cached.resolved_at))
+    }
+}
+
+pub fn store(key: &str, spec: &SoftwareSpec, distro: &DistroInfo, asset: &ResolvedAsset) -> Result<(), String> {
+    let path = cache_file().ok_or("no cache directory for resolutions")?;
+    let mut cache = load();
+    cache.insert(key.to_string(), CachedResolution {
+        fingerprint: fingerprint(spec, distro),
+        resolved_at: crate::manifest::now_secs(),
+        asset: asset.clone(),
+    });
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    let json = serde_json::to_string(&cache).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
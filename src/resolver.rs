@@ -1,13 +1,22 @@
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Duration;
+
 use regex::Regex;
-use reqwest::blocking::Client;
-use serde::Deserialize;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
 
 use crate::catalog::{SoftwareSpec, SourceSpec};
-use crate::distro::{DistroInfo, PackageManager};
+use crate::distro::{DistroInfo, Libc};
+use crate::error::{Error, Result};
+use crate::installer::expand_arch_vars;
+use crate::platform::Platform;
 
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    published_at: Option<String>,
     assets: Vec<GitHubAsset>,
 }
 
@@ -15,35 +24,261 @@ struct GitHubRelease {
 struct GitHubAsset {
     name: String,
     browser_download_url: String,
+    #[serde(default)]
+    size: Option<u64>,
+    /// `sha256:<hex>`, published for assets uploaded since mid-2025.
+    #[serde(default)]
+    digest: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResolvedAsset {
     pub version: String,
     pub url: String,
     pub file_name: String,
+    /// When the release was published, in seconds since the epoch, if the
+    /// source says.
+    pub published_at: Option<u64>,
+    /// Download size in bytes, from the release listing or a HEAD request.
+    pub size: Option<u64>,
+    /// SHA-256 the download must have, when the source publishes one.
+    pub sha256: Option<String>,
+}
+
+/// HTTP client for resolving and downloading, using the configured proxy.
+pub fn build_client() -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("rusty_rebase/0.1");
+    if let Some(proxy) = crate::config::current().proxy.filter(|p| !p.trim().is_empty()) {
+        builder = builder.proxy(parse_proxy(&proxy)?);
+    }
+    builder.build().map_err(|e| Error::Network(e.to_string()))
+}
+
+/// Checks a `proxy` setting: an http or https URL with a host.
+pub fn parse_proxy(proxy: &str) -> Result<reqwest::Proxy> {
+    let url = reqwest::Url::parse(proxy.trim()).map_err(|e| Error::Network(format!("invalid proxy {proxy}: {e}")))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(Error::Network(format!("invalid proxy {proxy}: expected http://host:port")));
+    }
+    reqwest::Proxy::all(url).map_err(|e| Error::Network(format!("invalid proxy {proxy}: {e}")))
+}
+
+/// Token for the GitHub API from `GITHUB_TOKEN` or `GH_TOKEN`, with the
+/// variable it came from. Raises the rate limit from 60 requests an hour.
+pub fn github_token() -> Option<(&'static str, String)> {
+    ["GITHUB_TOKEN", "GH_TOKEN"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()).map(|v| (var, v)))
+}
+
+/// A GET against the GitHub API, authenticated when a token is set.
+fn github_get(client: &Client, url: &str) -> RequestBuilder {
+    let request = client.get(url).header("User-Agent", "rusty_rebase");
+    match github_token() {
+        Some((_, token)) => request.bearer_auth(token.trim()),
+        None => request,
+    }
+}
+
+/// Passes successful responses through. Anything else becomes an error with
+/// the status and the start of the body, which usually says what went wrong
+/// (rate limit, login wall, region block) where reqwest would not.
+pub async fn check_response(mut response: Response, context: &str) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let mut body = Vec::new();
+    while body.len() < 4096
+        && let Ok(Some(chunk)) = response.chunk().await
+    {
+        body.extend_from_slice(&chunk);
+    }
+    body.truncate(4096);
+    let excerpt = body_excerpt(&String::from_utf8_lossy(&body));
+    if excerpt.is_empty() {
+        Err(Error::Network(format!("{context}: HTTP {status}")))
+    } else {
+        Err(Error::Network(format!("{context}: HTTP {status}: {excerpt}")))
+    }
 }
 
-pub fn resolve_asset(client: &Client, spec: &SoftwareSpec, distro: &DistroInfo) -> Result<ResolvedAsset, String> {
+/// Sends `request`, failing with `context` when it can't be sent or the
+/// answer isn't a success.
+pub async fn send(request: RequestBuilder, context: &str) -> Result<Response> {
+    let response = request.send().await.map_err(|e| Error::Network(format!("{context}: {e}")))?;
+    check_response(response, context).await
+}
+
+/// HTML tags, and scripts and styles with their contents.
+static HTML_TAGS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<(script|style)[^>]*>.*?</(script|style)>|<[^>]*>").expect("valid regex"));
+
+/// The first 200 characters of a response body as one line, with HTML tags
+/// removed.
+fn body_excerpt(body: &str) -> String {
+    const LIMIT: usize = 200;
+    let text = HTML_TAGS.replace_all(body, " ");
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(LIMIT) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
+
+/// Finds the latest version of `spec` and the download that suits this
+/// machine.
+pub async fn resolve_asset(client: &Client, spec: &SoftwareSpec, distro: &DistroInfo) -> Result<ResolvedAsset> {
     match &spec.source {
-        SourceSpec::OfficialSource { id, url, version_regex, download_url_regex } => {
+        SourceSpec::OfficialSource { id, url, version_regex, download_url_regex, date_regex } => {
             match id.as_deref() {
-                Some("flutter") => resolve_flutter(client, "stable"),
-                Some("android_studio") => resolve_android_studio(client),
-                Some("vscode") => resolve_vscode(client, distro),
+                Some("flutter") => resolve_flutter(client, "stable").await,
+                Some("android_studio") => resolve_android_studio(client).await,
+                Some("vscode") => resolve_vscode(client, distro).await,
                 _ => {
                     if let (Some(u), Some(v_re), Some(d_re)) = (url, version_regex, download_url_regex) {
-                        resolve_generic_scraper(client, u, v_re, d_re)
+                        resolve_generic_scraper(client, u, v_re, d_re, date_regex.as_deref()).await
                     } else if let (Some(u), None, None) = (url, version_regex, download_url_regex) {
                         resolve_static(u, "download")
                     } else {
-                        Err("official_source missing valid configuration".into())
+                        Err(Error::Catalog("official_source missing valid configuration".into()))
                     }
                 }
             }
         },
-        SourceSpec::PackageManager => resolve_package_only(spec, distro),
-        SourceSpec::Github { repo, asset_pattern } => resolve_github(client, repo, asset_pattern, distro),
+        SourceSpec::PackageManager => {
+            let (spec, distro) = (spec.clone(), distro.clone());
+            blocking(move || resolve_package_only(&spec, &distro)).await
+        }
+        SourceSpec::None => Ok(steps_only()),
+        SourceSpec::Github { repo, asset_pattern } => resolve_github(client, repo, asset_pattern, distro).await,
+        SourceSpec::Templated { version_url, version_regex, url_template } => {
+            let version = fetch_templated_version(client, version_url, version_regex).await?;
+            Ok(render_template(url_template, &version))
+        }
+        SourceSpec::JsonApi { url, version_path, version_regex, download_path, url_template, date_path } => resolve_json_api(
+            client,
+            url,
+            version_path,
+            version_regex.as_deref(),
+            download_path.as_deref(),
+            url_template.as_deref(),
+            date_path.as_deref(),
+        ).await,
+        SourceSpec::BuildFromSource { repo, tag, .. } => resolve_source_checkout(client, repo, tag.as_deref()).await,
+    }
+}
+
+/// What test-resolving one entry found, for debugging catalog entries.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub result: Result<ResolvedAsset>,
+    /// Where the download ends up after redirects, and the status of a HEAD
+    /// request to it, or why that request failed.
+    pub final_url: Option<String>,
+    pub status: Option<Result<u16, String>>,
+    /// The page a scraper or templated source read its version from.
+    pub page: Option<String>,
+    /// The text around each regex match on that page.
+    pub snippets: Vec<Snippet>,
+}
+
+/// A regex match with the text around it, whitespace collapsed.
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub label: &'static str,
+    pub pattern: String,
+    /// `None` when the regex matched nothing on the page.
+    pub found: Option<(String, String, String)>,
+}
+
+/// Characters of context kept on each side of a match.
+const SNIPPET_CONTEXT: usize = 120;
+
+/// Resolves `spec` like [`resolve_asset`], then follows the download with a
+/// HEAD request and, for scraper and templated sources, shows where on the
+/// page their regexes matched.
+pub async fn probe(client: &Client, spec: &SoftwareSpec, distro: &DistroInfo) -> Probe {
+    let result = resolve_asset(client, spec, distro).await;
+    let (final_url, status) = match &result {
+        Ok(asset) if asset.url.starts_with("http") => match client.head(&asset.url).send().await {
+            Ok(response) => (Some(response.url().to_string()), Some(Ok(response.status().as_u16()))),
+            Err(e) => (None, Some(Err(e.to_string()))),
+        },
+        _ => (None, None),
+    };
+    let (page, patterns): (Option<&String>, Vec<(&'static str, &String)>) = match &spec.source {
+        SourceSpec::OfficialSource { id: None, url: Some(url), version_regex: Some(v), download_url_regex: Some(d), .. } => {
+            (Some(url), vec![("version", v), ("download", d)])
+        }
+        SourceSpec::Templated { version_url, version_regex, .. } => (Some(version_url), vec![("version", version_regex)]),
+        _ => (None, Vec::new()),
+    };
+    let body = match page {
+        Some(url) => match client.get(url).send().await {
+            Ok(response) => response.text().await.ok(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+    let snippets = match &body {
+        Some(body) => patterns
+            .into_iter()
+            .map(|(label, pattern)| Snippet { label, pattern: pattern.clone(), found: match_context(body, pattern) })
+            .collect(),
+        None => Vec::new(),
+    };
+    Probe { result, final_url, status, page: page.cloned(), snippets }
+}
+
+/// The first match of `pattern` in `body`, with up to [`SNIPPET_CONTEXT`]
+/// characters before and after it.
+fn match_context(body: &str, pattern: &str) -> Option<(String, String, String)> {
+    let found = Regex::new(&expand_arch_vars(pattern)).ok()?.find(body)?;
+    let mut start = found.start().saturating_sub(SNIPPET_CONTEXT);
+    while !body.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (found.end() + SNIPPET_CONTEXT).min(body.len());
+    while !body.is_char_boundary(end) {
+        end += 1;
+    }
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some((collapse(&body[start..found.start()]), collapse(found.as_str()), collapse(&body[found.end()..end])))
+}
+
+/// Resolves a specific version instead of the latest one. GitHub sources are
+/// looked up by tag, URL templates render the pinned version and source
+/// builds check it out; other
+/// sources only succeed when their latest release happens to match the pin.
+pub async fn resolve_pinned(client: &Client, spec: &SoftwareSpec, distro: &DistroInfo, version: &str) -> Result<ResolvedAsset> {
+    if let SourceSpec::Github { repo, asset_pattern } = &spec.source {
+        let repo = repo.as_ref()
+            .ok_or_else(|| Error::Catalog("github repo not configured for this software".to_string()))?;
+        let mut last_err = String::new();
+        for tag in [format!("v{version}"), version.to_string()] {
+            let api_url = format!("https://api.github.com/repos/{repo}/releases/tags/{tag}");
+            match resolve_github_release(client, repo, &api_url, asset_pattern, distro).await {
+                Ok(asset) => return Ok(asset),
+                Err(e) => last_err = e.to_string(),
+            }
+        }
+        return Err(Error::Resolve(format!("pinned version {version} not found in github:{repo}: {last_err}")));
+    }
+    if let SourceSpec::Templated { url_template, .. } | SourceSpec::JsonApi { url_template: Some(url_template), .. } = &spec.source {
+        return Ok(render_template(url_template, version));
+    }
+    if let SourceSpec::BuildFromSource { repo, .. } = &spec.source {
+        return Ok(checkout_asset(&clone_url(repo), version, None));
+    }
+
+    let asset = resolve_asset(client, spec, distro).await?;
+    if asset.version == version {
+        Ok(asset)
+    } else {
+        Err(Error::Resolve(format!("pinned version {version} unavailable: source currently resolves {}", asset.version)))
     }
 }
 
@@ -58,27 +293,30 @@ struct FlutterRelease {
     hash: String,
     version: String,
     archive: String,
+    release_date: Option<String>,
+    sha256: Option<String>,
 }
 
-fn resolve_flutter(client: &Client, channel: &str) -> Result<ResolvedAsset, String> {
-    let endpoint = "https://storage.googleapis.com/flutter_infra_release/releases/releases_linux.json";
-    let payload: FlutterReleases = client
-        .get(endpoint)
-        .send()
-        .map_err(|e| format!("failed to fetch flutter releases: {e}"))?
+async fn resolve_flutter(client: &Client, channel: &str) -> Result<ResolvedAsset> {
+    let endpoint = format!(
+        "https://storage.googleapis.com/flutter_infra_release/releases/releases_{}.json",
+        Platform::current().os_name()
+    );
+    let payload: FlutterReleases = send(client.get(&endpoint), "failed to fetch flutter releases").await?
         .json()
-        .map_err(|e| format!("failed to decode flutter releases json: {e}"))?;
+        .await
+        .map_err(|e| Error::Resolve(format!("failed to decode flutter releases json: {e}")))?;
 
     let hash = payload
         .current_release
         .get(channel)
-        .ok_or_else(|| format!("missing current release hash for channel '{channel}'"))?;
+        .ok_or_else(|| Error::Resolve(format!("missing current release hash for channel '{channel}'")))?;
 
     let release = payload
         .releases
         .iter()
         .find(|it| &it.hash == hash)
-        .ok_or_else(|| "failed to resolve flutter release by hash".to_string())?;
+        .ok_or_else(|| Error::Resolve("failed to resolve flutter release by hash".to_string()))?;
 
     Ok(ResolvedAsset {
         version: release.version.clone(),
@@ -92,16 +330,17 @@ fn resolve_flutter(client: &Client, channel: &str) -> Result<ResolvedAsset, Stri
             .next()
             .unwrap_or("flutter.tar.xz")
             .to_string(),
+        published_at: release.release_date.as_deref().and_then(crate::report::parse_date),
+        size: None,
+        sha256: release.sha256.clone(),
     })
 }
 
-fn resolve_android_studio(client: &Client) -> Result<ResolvedAsset, String> {
-    let html = client
-        .get("https://developer.android.com/studio")
-        .send()
-        .map_err(|e| format!("failed to fetch android studio page: {e}"))?
+async fn resolve_android_studio(client: &Client) -> Result<ResolvedAsset> {
+    let html = send(client.get("https://developer.android.com/studio"), "failed to fetch android studio page").await?
         .text()
-        .map_err(|e| format!("failed reading android studio html: {e}"))?;
+        .await
+        .map_err(|e| Error::Network(format!("failed reading android studio html: {e}")))?;
 
     let patterns = [
         r#"https://redirector\.gvt1\.com/edgedl/android/studio/ide-zips/[^"']+linux\.tar\.gz"#,
@@ -111,14 +350,14 @@ fn resolve_android_studio(client: &Client) -> Result<ResolvedAsset, String> {
     for pattern in &patterns {
         let re = match Regex::new(pattern) {
             Ok(r) => r,
-            Err(e) => return Err(format!("failed to compile android studio regex: {e}")),
+            Err(e) => return Err(Error::Resolve(format!("failed to compile android studio regex: {e}"))),
         };
         if let Some(url_match) = re.find(&html) {
             let url = url_match.as_str().to_string();
             let file_name = url
                 .rsplit('/')
                 .next()
-                .ok_or_else(|| "invalid android studio url".to_string())?
+                .ok_or_else(|| Error::Resolve("invalid android studio url".to_string()))?
                 .to_string();
 
             let version = file_name
@@ -130,27 +369,30 @@ fn resolve_android_studio(client: &Client) -> Result<ResolvedAsset, String> {
                 version,
                 url,
                 file_name,
+                published_at: None,
+                size: None,
+                sha256: None,
             });
         }
     }
 
-    Err("could not resolve android studio linux tarball link from developer.android.com".to_string())
+    Err(Error::Resolve("could not resolve android studio linux tarball link from developer.android.com".to_string()))
 }
 
-fn resolve_vscode(client: &Client, distro: &DistroInfo) -> Result<ResolvedAsset, String> {
-    let platform = match distro.pkg_manager {
-        PackageManager::Apt => "linux-deb-x64",
-        PackageManager::Dnf => "linux-rpm-x64",
-        _ => "linux-x64",
+async fn resolve_vscode(client: &Client, distro: &DistroInfo) -> Result<ResolvedAsset> {
+    let arch = if std::env::consts::ARCH == "aarch64" { "arm64" } else { "x64" };
+    let platform = match (Platform::current(), distro.pkg_manager.artifact_ext()) {
+        (Platform::Windows, _) => format!("win32-{arch}-archive"),
+        (_, Some(".deb")) => format!("linux-deb-{arch}"),
+        (_, Some(".rpm")) => format!("linux-rpm-{arch}"),
+        _ => format!("linux-{arch}"),
     };
 
     let base_url = format!("https://update.code.visualstudio.com/latest/{}/stable", platform);
-    let resp = client.get(&base_url)
-        .send()
-        .map_err(|e| format!("failed to fetch vscode redirect: {e}"))?;
+    let resp = send(client.get(&base_url), "failed to fetch vscode redirect").await?;
 
     let final_url = resp.url().as_str().to_string();
-    let file_name = final_url.split('/').last().unwrap_or("vscode_latest").to_string();
+    let file_name = final_url.split('/').next_back().unwrap_or("vscode_latest").to_string();
 
     let version_re = Regex::new(r"(\d+\.\d+\.\d+)").unwrap();
     let version = version_re.find(&file_name)
@@ -161,78 +403,328 @@ fn resolve_vscode(client: &Client, distro: &DistroInfo) -> Result<ResolvedAsset,
         version,
         url: final_url,
         file_name,
+        published_at: None,
+        size: None,
+        sha256: None,
     })
 }
 
-fn resolve_static(url: &str, file_name: &str) -> Result<ResolvedAsset, String> {
+fn resolve_static(url: &str, file_name: &str) -> Result<ResolvedAsset> {
     Ok(ResolvedAsset {
         version: "static".to_string(),
         url: url.to_string(),
         file_name: file_name.to_string(),
+        published_at: None,
+        size: None,
+        sha256: None,
     })
 }
 
-fn resolve_package_only(spec: &SoftwareSpec, distro: &DistroInfo) -> Result<ResolvedAsset, String> {
-    let package_name = spec.setup_steps.iter().find_map(|s| {
-        if let crate::catalog::SetupStep::Package { packages } = s {
-            packages.first()
-        } else {
-            None
+/// First package of a package-only entry, which stands in for its version.
+pub fn primary_package(spec: &SoftwareSpec) -> Option<&String> {
+    spec.setup_steps.iter().find_map(|s| s.packages_for("", "").first())
+}
+
+/// What configuration-only entries resolve to.
+fn steps_only() -> ResolvedAsset {
+    ResolvedAsset {
+        version: "steps only".to_string(),
+        url: "N/A".to_string(),
+        file_name: "N/A".to_string(),
+        published_at: None,
+        size: None,
+        sha256: None,
+    }
+}
+
+/// Resolves without the network, for offline mode: package-manager and
+/// steps-only entries as usual, downloads from a matching file already in
+/// `download_dir`. The newest match wins; its version is read from its name.
+pub fn resolve_offline(spec: &SoftwareSpec, distro: &DistroInfo, download_dir: &Path) -> Result<ResolvedAsset> {
+    let pattern = match &spec.source {
+        SourceSpec::PackageManager => return resolve_package_only(spec, distro),
+        SourceSpec::None => return Ok(steps_only()),
+        SourceSpec::Github { asset_pattern, .. } => expand_arch_vars(asset_pattern),
+        SourceSpec::Templated { url_template, .. } | SourceSpec::JsonApi { url_template: Some(url_template), .. } => {
+            let file_name = url_template.split(['?', '#']).next().and_then(|path| path.rsplit('/').next()).unwrap_or_default();
+            format!("^{}$", regex::escape(&expand_arch_vars(file_name)).replace(r"\{version\}", "(?P<version>[0-9A-Za-z._+-]+)"))
         }
-    }).map(|s| s.as_str()).unwrap_or("unknown");
+        _ => return Err(Error::Resolve("offline mode: this source needs the network".to_string())),
+    };
+    let re = Regex::new(&pattern).map_err(|e| Error::Catalog(format!("invalid pattern '{pattern}': {e}")))?;
+    let version_re = Regex::new(r"\d+(?:\.\d+)+").map_err(|e| Error::Resolve(e.to_string()))?;
+    let newest = fs::read_dir(download_dir)
+        .map_err(|e| Error::Io(format!("offline mode: no download cache at {}: {e}", download_dir.display())))?
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let captures = re.captures(&name)?;
+            let version = captures
+                .name("version")
+                .map(|m| m.as_str().to_string())
+                .or_else(|| version_re.find(&name).map(|m| m.as_str().to_string()))?;
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            Some((modified, version, name, entry.path()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0));
+    let Some((_, version, file_name, path)) = newest else {
+        return Err(Error::Resolve(format!("offline mode: no cached download in {} matches '{pattern}'", download_dir.display())));
+    };
+    Ok(ResolvedAsset {
+        version,
+        size: fs::metadata(&path).ok().map(|m| m.len()),
+        sha256: None,
+        url: path.display().to_string(),
+        file_name,
+        published_at: None,
+    })
+}
+
+fn resolve_package_only(spec: &SoftwareSpec, distro: &DistroInfo) -> Result<ResolvedAsset> {
+    let package_name = primary_package(spec).map(|s| s.as_str()).unwrap_or("unknown");
 
-    let version = distro.pkg_manager.get_package_version(package_name)
+    let version = distro.candidate_version(package_name)
+        .map(|v| distro.pkg_manager.upstream_version(&v))
         .unwrap_or_else(|| "package-manager".to_string());
 
     Ok(ResolvedAsset {
         version,
         url: "N/A".to_string(),
         file_name: "N/A".to_string(),
+        published_at: None,
+        size: None,
+        sha256: None,
     })
 }
 
-fn resolve_generic_scraper(
+/// Reads the latest version from a templated source's version endpoint,
+/// typically a plain-text file or a small JSON document.
+async fn fetch_templated_version(client: &Client, version_url: &str, version_regex: &str) -> Result<String> {
+    let body = send(client.get(version_url), &format!("failed to fetch {version_url}")).await?
+        .text()
+        .await
+        .map_err(|e| Error::Network(format!("failed reading {version_url}: {e}")))?;
+    let re = Regex::new(&expand_arch_vars(version_regex)).map_err(|e| Error::Catalog(format!("invalid version regex: {e}")))?;
+    re.captures(&body)
+        .and_then(|c| c.get(1).or_else(|| c.get(0)))
+        .map(|m| m.as_str().trim().to_string())
+        .ok_or_else(|| Error::Resolve(format!("could not find version on {version_url} using regex {version_regex}")))
+}
+
+/// The download of a templated source for one version.
+fn render_template(url_template: &str, version: &str) -> ResolvedAsset {
+    let url = expand_arch_vars(&url_template.replace("{version}", version));
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download")
+        .to_string();
+    ResolvedAsset {
+        version: version.to_string(),
+        url,
+        file_name,
+        published_at: None,
+        size: None,
+        sha256: None,
+    }
+}
+
+/// Looks `path` up in `root`: JSON pointer segments, where a `[key=value,...]`
+/// segment selects the first array element whose fields all match. Values
+/// accept the architecture placeholders.
+fn json_path<'a>(root: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    let mut node = root;
+    for segment in path.strip_prefix('/').unwrap_or(path).split('/').filter(|s| !s.is_empty()) {
+        node = match segment.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            Some(filter) => {
+                let conditions: Vec<(&str, String)> = filter
+                    .split(',')
+                    .filter_map(|c| c.split_once('='))
+                    .map(|(k, v)| (k.trim(), expand_arch_vars(v.trim())))
+                    .collect();
+                node.as_array()?.iter().find(|item| {
+                    conditions.iter().all(|(key, want)| match item.get(*key) {
+                        Some(serde_json::Value::String(s)) => s == want,
+                        Some(other) => serde_json::from_str::<serde_json::Value>(want).is_ok_and(|w| &w == other),
+                        None => false,
+                    })
+                })?
+            }
+            None => {
+                let key = segment.replace("~1", "/").replace("~0", "~");
+                match node {
+                    serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+                    _ => node.get(&key)?,
+                }
+            }
+        };
+    }
+    Some(node)
+}
+
+/// The value at `path` as text, for version, URL and date fields.
+fn json_text(root: &serde_json::Value, path: &str, url: &str) -> Result<String> {
+    match json_path(root, path) {
+        Some(serde_json::Value::String(s)) => Ok(s.clone()),
+        Some(serde_json::Value::Null) | None => Err(Error::Resolve(format!("nothing at {path} in {url}"))),
+        Some(other) => Ok(other.to_string()),
+    }
+}
+
+async fn resolve_json_api(
+    client: &Client,
+    url: &str,
+    version_path: &str,
+    version_regex: Option<&str>,
+    download_path: Option<&str>,
+    url_template: Option<&str>,
+    date_path: Option<&str>,
+) -> Result<ResolvedAsset> {
+    let root: serde_json::Value = send(client.get(url), &format!("failed to fetch {url}")).await?
+        .json()
+        .await
+        .map_err(|e| Error::Resolve(format!("failed to parse JSON from {url}: {e}")))?;
+
+    let raw_version = json_text(&root, version_path, url)?;
+    let version = match version_regex {
+        Some(pattern) => {
+            let re = Regex::new(&expand_arch_vars(pattern)).map_err(|e| Error::Catalog(format!("invalid version regex: {e}")))?;
+            re.captures(&raw_version)
+                .and_then(|c| c.get(1).or_else(|| c.get(0)))
+                .map(|m| m.as_str().to_string())
+                .ok_or_else(|| Error::Resolve(format!("version '{raw_version}' does not match regex {pattern}")))?
+        }
+        None => raw_version.trim_start_matches('v').to_string(),
+    };
+
+    let mut asset = match (url_template, download_path) {
+        (Some(template), _) => render_template(template, &version),
+        (None, Some(path)) => {
+            let link = json_text(&root, path, url)?;
+            let link = reqwest::Url::parse(url)
+                .and_then(|base| base.join(&link))
+                .map(|u| u.to_string())
+                .unwrap_or(link);
+            render_template(&link, &version)
+        }
+        (None, None) => return Err(Error::Catalog("json_api source needs download_path or url_template".to_string())),
+    };
+    asset.published_at = date_path
+        .and_then(|path| json_text(&root, path, url).ok())
+        .and_then(|date| crate::report::parse_date(&date));
+    Ok(asset)
+}
+
+/// Whether `repo` is a GitHub `owner/name` rather than a git URL.
+fn is_github_shorthand(repo: &str) -> bool {
+    !repo.contains("://") && !repo.contains('@') && repo.matches('/').count() == 1
+}
+
+fn clone_url(repo: &str) -> String {
+    if is_github_shorthand(repo) {
+        format!("https://github.com/{repo}.git")
+    } else {
+        repo.to_string()
+    }
+}
+
+/// Picks the tag to build: the pinned one, the latest GitHub release, or the
+/// highest version tag on any other remote.
+async fn resolve_source_checkout(client: &Client, repo: &str, tag: Option<&str>) -> Result<ResolvedAsset> {
+    let url = clone_url(repo);
+    let (tag, published_at) = match tag {
+        Some(tag) => (tag.to_string(), None),
+        None if is_github_shorthand(repo) => {
+            let api_url = format!("https://api.github.com/repos/{repo}/releases/latest");
+            let release: GitHubRelease = send(github_get(client, &api_url), &format!("failed to fetch release from {api_url}")).await?
+                .json()
+                .await
+                .map_err(|e| Error::Resolve(format!("failed to decode github release json: {e}")))?;
+            let published_at = release.published_at.as_deref().and_then(crate::report::parse_date);
+            (release.tag_name, published_at)
+        }
+        None => {
+            let remote = url.clone();
+            (blocking(move || latest_remote_tag(&remote)).await?, None)
+        }
+    };
+    let version = tag.strip_prefix('v').filter(|v| v.starts_with(|c: char| c.is_ascii_digit())).unwrap_or(&tag);
+    Ok(checkout_asset(&url, version, published_at))
+}
+
+/// Runs a package manager query or `git ls-remote` off the async workers.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work).await.map_err(|e| Error::Other(format!("resolver task failed: {e}")))?
+}
+
+/// The highest release-looking tag of a git remote, skipping pre-releases.
+fn latest_remote_tag(url: &str) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(["ls-remote", "--tags", "--refs", "--", url])
+        .output()
+        .map_err(|e| Error::ChildProcess(format!("failed to run git ls-remote: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::ChildProcess(format!("git ls-remote {url} failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    let numbers = Regex::new(r"[0-9]+").unwrap();
+    let version_key = |tag: &str| -> Vec<u64> { numbers.find_iter(tag).filter_map(|m| m.as_str().parse().ok()).collect() };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1)?.strip_prefix("refs/tags/"))
+        .filter(|tag| {
+            let lower = tag.to_lowercase();
+            !["rc", "alpha", "beta", "pre", "dev", "nightly"].iter().any(|w| lower.contains(w))
+        })
+        .filter(|tag| !version_key(tag).is_empty())
+        .max_by_key(|tag| version_key(tag))
+        .map(str::to_string)
+        .ok_or_else(|| Error::Resolve(format!("no release tags found in {url}")))
+}
+
+/// A source build of `version`: the clone URL, and a checkout folder name
+/// in place of the file name.
+fn checkout_asset(url: &str, version: &str, published_at: Option<u64>) -> ResolvedAsset {
+    let name = url.trim_end_matches('/').trim_end_matches(".git").rsplit(['/', ':']).next().unwrap_or("source");
+    ResolvedAsset {
+        version: version.to_string(),
+        url: url.to_string(),
+        file_name: format!("{name}-{version}"),
+        published_at,
+        size: None,
+        sha256: None,
+    }
+}
+
+async fn resolve_generic_scraper(
     client: &Client,
     url: &str,
     version_regex: &str,
     download_url_regex: &str,
-) -> Result<ResolvedAsset, String> {
-    let html = client
-        .get(url)
-        .send()
-        .map_err(|e| format!("failed to fetch {url}: {e}"))?
+    date_regex: Option<&str>,
+) -> Result<ResolvedAsset> {
+    let html = send(client.get(url), &format!("failed to fetch {url}")).await?
         .text()
-        .map_err(|e| format!("failed reading {url} html: {e}"))?;
-
-    let sys_arch = match std::env::consts::ARCH {
-        "x86_64" => "amd64",
-        "aarch64" => "arm64",
-        "x86" => "386",
-        other => other,
-    };
-    let dash_arch = std::env::consts::ARCH.replace('_', "-");
+        .await
+        .map_err(|e| Error::Network(format!("failed reading {url} html: {e}")))?;
 
-    let processed_v_re = version_regex
-        .replace("{arch}", sys_arch)
-        .replace("{xarch}", std::env::consts::ARCH)
-        .replace("{xarch_dash}", &dash_arch);
-    let processed_d_re = download_url_regex
-        .replace("{arch}", sys_arch)
-        .replace("{xarch}", std::env::consts::ARCH)
-        .replace("{xarch_dash}", &dash_arch);
+    let processed_v_re = expand_arch_vars(version_regex);
+    let processed_d_re = expand_arch_vars(download_url_regex);
 
-    let v_re = Regex::new(&processed_v_re).map_err(|e| format!("invalid version regex: {e}"))?;
-    let d_re = Regex::new(&processed_d_re).map_err(|e| format!("invalid download url regex: {e}"))?;
+    let v_re = Regex::new(&processed_v_re).map_err(|e| Error::Catalog(format!("invalid version regex: {e}")))?;
+    let d_re = Regex::new(&processed_d_re).map_err(|e| Error::Catalog(format!("invalid download url regex: {e}")))?;
 
     let version = v_re
         .captures(&html)
         .and_then(|c| c.get(1).map(|m| m.as_str().to_string()))
-        .ok_or_else(|| format!("could not find version on {} using regex {}", url, version_regex))?;
+        .ok_or_else(|| Error::Resolve(format!("could not find version on {} using regex {}", url, version_regex)))?;
 
     let download_url = d_re
         .find(&html)
         .map(|m| m.as_str().to_string())
-        .ok_or_else(|| format!("could not find download url on {} using regex {}", url, download_url_regex))?;
+        .ok_or_else(|| Error::Resolve(format!("could not find download url on {} using regex {}", url, download_url_regex)))?;
 
     let final_url = if let Ok(base) = reqwest::Url::parse(url) {
         base.join(&download_url).map(|u| u.to_string()).unwrap_or(download_url)
@@ -242,63 +734,124 @@ fn resolve_generic_scraper(
 
     let file_name = final_url
         .split('/')
-        .last()
+        .next_back()
         .unwrap_or("downloaded_file")
         .to_string();
 
+    let published_at = match date_regex {
+        Some(pattern) => {
+            let re = Regex::new(pattern).map_err(|e| Error::Catalog(format!("invalid date regex: {e}")))?;
+            re.captures(&html)
+                .and_then(|c| c.get(1))
+                .and_then(|m| crate::report::parse_date(m.as_str()))
+        }
+        None => None,
+    };
+
     Ok(ResolvedAsset {
         version,
         url: final_url,
         file_name,
+        published_at,
+        size: None,
+        sha256: None,
     })
 }
 
-fn resolve_github(client: &Client, repo_opt: &Option<String>, asset_pattern: &str, distro: &DistroInfo) -> Result<ResolvedAsset, String> {
+/// Whether an asset name (lowercased) mentions `arch`, under any of the
+/// spellings release pipelines use. Spellings only count as whole tokens,
+/// so `arm` does not match "swarm" or "charm".
+fn names_arch(name: &str, arch: &str) -> bool {
+    let any = |spellings: &[&str]| spellings.iter().any(|s| has_token(name, s));
+    match arch {
+        "x86_64" => any(&["x86_64", "x86-64", "amd64", "x64"]),
+        "aarch64" => any(&["aarch64", "arm64", "arm-64"]),
+        "arm" => any(&["armv7", "armhf", "arm"]) && !names_arch(name, "aarch64"),
+        "x86" => any(&["i386", "i686", "x86", "386"]) && !names_arch(name, "x86_64"),
+        _ => false,
+    }
+}
+
+/// Whether `token` appears in `name` between separators (`-`, `_`, `.`) or
+/// at either end.
+fn has_token(name: &str, token: &str) -> bool {
+    let separator = |c: Option<char>| c.is_none_or(|c| matches!(c, '-' | '_' | '.'));
+    name.match_indices(token)
+        .any(|(at, _)| separator(name[..at].chars().next_back()) && separator(name[at + token.len()..].chars().next()))
+}
+
+/// Whether an asset name is built for an architecture other than `arch`.
+fn foreign_arch(name: &str, arch: &str) -> bool {
+    !names_arch(name, arch) && ["x86_64", "aarch64", "arm", "x86"].iter().any(|other| *other != arch && names_arch(name, other))
+}
+
+/// Releases built with glibc older than this are rare; on older systems a
+/// `gnu` build likely fails with "GLIBC_2.xx not found", so static builds
+/// win.
+const OLD_GLIBC: (u32, u32) = (2, 31);
+
+/// Preference for `musl`/`static` over `gnu` builds: on musl systems glibc
+/// builds don't run at all, on old glibc they may not, elsewhere the native
+/// `gnu` build is preferred.
+fn libc_score(name_lower: &str, libc: Libc) -> i32 {
+    let is_static = name_lower.contains("musl") || name_lower.contains("static");
+    let is_gnu = name_lower.contains("gnu") || name_lower.contains("glibc");
+    match libc {
+        Libc::Musl if is_static => 40,
+        Libc::Musl if is_gnu => -60,
+        Libc::Glibc { .. } if libc.glibc_older_than(OLD_GLIBC.0, OLD_GLIBC.1) => {
+            if is_static { 40 } else if is_gnu { -10 } else { 0 }
+        }
+        Libc::Glibc { .. } if is_gnu => 10,
+        _ => 0,
+    }
+}
+
+async fn resolve_github(client: &Client, repo_opt: &Option<String>, asset_pattern: &str, distro: &DistroInfo) -> Result<ResolvedAsset> {
     let repo = repo_opt.as_ref()
-        .ok_or_else(|| "github repo not configured for this software".to_string())?;
+        .ok_or_else(|| Error::Catalog("github repo not configured for this software".to_string()))?;
 
     let api_url = format!("https://api.github.com/repos/{repo}/releases/latest");
-    let release: GitHubRelease = client
-        .get(&api_url)
-        .header("User-Agent", "rusty_rebase")
-        .send()
-        .map_err(|e| format!("failed to fetch latest release from {api_url}: {e}"))?
+    resolve_github_release(client, repo, &api_url, asset_pattern, distro).await
+}
+
+async fn resolve_github_release(client: &Client, repo: &str, api_url: &str, asset_pattern: &str, distro: &DistroInfo) -> Result<ResolvedAsset> {
+    let release: GitHubRelease = send(github_get(client, api_url), &format!("failed to fetch release from {api_url}")).await?
         .json()
-        .map_err(|e| format!("failed to decode github release json: {e}"))?;
+        .await
+        .map_err(|e| Error::Resolve(format!("failed to decode github release json: {e}")))?;
 
-    let re = Regex::new(asset_pattern).map_err(|e| format!("invalid asset pattern regex: {e}"))?;
+    let re = Regex::new(asset_pattern).map_err(|e| Error::Catalog(format!("invalid asset pattern regex: {e}")))?;
     let mut matched: Vec<&GitHubAsset> = release.assets.iter()
         .filter(|a| re.is_match(&a.name))
         .collect();
 
     if matched.is_empty() {
-        return Err(format!("no asset matching '{}' found in github:{}", asset_pattern, repo));
+        return Err(Error::Resolve(format!("no asset matching '{}' found in github:{}", asset_pattern, repo)));
     }
 
     let sys_arch = std::env::consts::ARCH;
-    let preferred_ext = match distro.pkg_manager {
-        crate::distro::PackageManager::Apt => ".deb",
-        crate::distro::PackageManager::Dnf => ".rpm",
-        _ => "___",
-    };
+    let preferred_ext = distro.pkg_manager.artifact_ext().unwrap_or("___");
+    let platform = Platform::current();
 
     let score = |name: &str| -> i32 {
         let mut s = 0;
         let name_lower = name.to_lowercase();
         
         // Arch match (higher priority)
-        let has_arch = match sys_arch {
-            "x86_64" => name_lower.contains("x86_64") || name_lower.contains("x86-64") || name_lower.contains("amd64") || name_lower.contains("x64"),
-            "aarch64" => name_lower.contains("aarch64") || name_lower.contains("arm64") || name_lower.contains("arm-64"),
-            "arm" => name_lower.contains("armv7") || name_lower.contains("armhf") || (name_lower.contains("arm") && !name_lower.contains("64")),
-            "x86" => name_lower.contains("i386") || name_lower.contains("x86") || name_lower.contains("386"),
-            _ => false,
-        };
-        if has_arch { s += 100; }
+        if names_arch(&name_lower, sys_arch) { s += 100; }
 
-        // Extension match
-        if name.ends_with(preferred_ext) { s += 50; }
-        else if name.ends_with(".deb") || name.ends_with(".rpm") { s += 20; }
+        // OS match: assets built for another OS are never usable
+        if platform.foreign_asset_keywords().iter().any(|k| name_lower.contains(k)) { s -= 100; }
+        else if platform.asset_keywords().iter().any(|k| name_lower.contains(k)) { s += 20; }
+
+        s += libc_score(&name_lower, distro.environment.libc);
+
+        // Extension match: native packages rank by whether the host's
+        // package manager installs them
+        let native = name.ends_with(".deb") || name.ends_with(".rpm");
+        if name.ends_with(preferred_ext) || (native && distro.pkg_manager.install_artifact(Path::new(name)).is_some()) { s += 50; }
+        else if native { s -= 50; }
         else if name.ends_with(".AppImage") { s += 10; }
         else if name.ends_with(".tar.gz") || name.ends_with(".tar.xz") || name.ends_with(".zip") { s += 5; }
 
@@ -307,10 +860,62 @@ fn resolve_github(client: &Client, repo_opt: &Option<String>, asset_pattern: &st
 
     matched.sort_by_key(|a| std::cmp::Reverse(score(&a.name)));
     let asset = matched[0];
+    // Assets without an architecture in their name (scripts, jars, universal
+    // builds) are fine; one built for another architecture never is.
+    if foreign_arch(&asset.name.to_lowercase(), sys_arch) {
+        let names: Vec<&str> = matched.iter().map(|a| a.name.as_str()).collect();
+        return Err(Error::Resolve(format!(
+            "no {sys_arch} asset in github:{repo} {} (matching '{asset_pattern}': {}); set supported_arch on the entry to hide it on {sys_arch}",
+            release.tag_name,
+            names.join(", ")
+        )));
+    }
 
     Ok(ResolvedAsset {
         version: release.tag_name.trim_start_matches('v').to_string(),
         url: asset.browser_download_url.clone(),
         file_name: asset.name.clone(),
+        published_at: release.published_at.as_deref().and_then(crate::report::parse_date),
+        size: asset.size,
+        sha256: asset.digest.as_deref().and_then(|d| d.strip_prefix("sha256:")).map(str::to_string),
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arch_names_match_whole_tokens() {
+        assert!(names_arch("tool-linux-amd64.tar.gz", "x86_64"));
+        assert!(names_arch("tool_x86_64-unknown-linux-musl.tar.gz", "x86_64"));
+        assert!(names_arch("tool-linux-armv7.tar.gz", "arm"));
+        assert!(names_arch("tool-linux-arm.tar.gz", "arm"));
+        assert!(!names_arch("tool-linux-arm64.tar.gz", "arm"));
+        assert!(!names_arch("tool-linux-x86_64.tar.gz", "x86"));
+        assert!(!names_arch("docker-swarm-linux.tar.gz", "arm"));
+        assert!(!names_arch("charm-1.0.tar.gz", "arm"));
+        assert!(!foreign_arch("charm-linux-amd64.tar.gz", "x86_64"));
+        assert!(foreign_arch("tool-linux-arm64.tar.gz", "x86_64"));
+    }
+
+    #[test]
+    fn json_paths_follow_keys_indexes_and_filters() {
+        let root = serde_json::json!({
+            "latest": { "version": "1.2.0", "a/b": true },
+            "downloads": [
+                { "os": "windows", "size": 10, "url": "https://example.com/win.zip" },
+                { "os": "linux", "size": 20, "url": "https://example.com/linux.tar.gz" }
+            ]
+        });
+        assert_eq!(json_path(&root, "/latest/version"), Some(&serde_json::json!("1.2.0")));
+        assert_eq!(json_path(&root, "latest/a~1b"), Some(&serde_json::json!(true)));
+        assert_eq!(json_path(&root, "/downloads/0/os"), Some(&serde_json::json!("windows")));
+        assert_eq!(json_path(&root, "/downloads/[os=linux]/url"), Some(&serde_json::json!("https://example.com/linux.tar.gz")));
+        assert_eq!(json_path(&root, "/downloads/[os=linux, size=20]/size"), Some(&serde_json::json!(20)));
+        assert_eq!(json_path(&root, "/downloads/[os=linux,size=10]"), None);
+        assert_eq!(json_path(&root, "/downloads/5"), None);
+        assert_eq!(json_path(&root, "/latest/missing"), None);
+        assert_eq!(json_path(&root, ""), Some(&root));
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// Bytes read from download responses since startup.
+static DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+
+pub fn add_downloaded(bytes: u64) {
+    DOWNLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// What one install or restore run cost, shown on the Completed screen and
+/// in the report.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunResources {
+    pub wall: Duration,
+    /// Fetched over the network by downloads; cached artifacts, git clones
+    /// and package-manager downloads are not counted.
+    pub downloaded: u64,
+    /// Highest resident memory of rusty_rebase and everything it spawned,
+    /// sampled while the run was going.
+    pub peak_ram: u64,
+    /// User plus system time of the commands that ran; `None` where the
+    /// platform doesn't report it.
+    pub child_cpu: Option<Duration>,
+}
+
+/// Accounts for a run from its start until [`Meter::finish`].
+#[derive(Debug)]
+pub struct Meter {
+    started: Instant,
+    downloaded: u64,
+    child_cpu: Option<Duration>,
+    peak_ram: u64,
+}
+
+impl Meter {
+    pub fn start() -> Self {
+        Self { started: Instant::now(), downloaded: DOWNLOADED.load(Ordering::Relaxed), child_cpu: children_cpu_time(), peak_ram: 0 }
+    }
+
+    /// Records the memory currently held by this process and its
+    /// descendants, keeping the highest value seen.
+    pub fn sample(&mut self, sys: &mut System) {
+        sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing().with_memory());
+        let own = Pid::from_u32(std::process::id());
+        let in_tree = |mut pid: Pid| {
+            // Bounded walk up the parents, in case of a cycle from pid reuse.
+            for _ in 0..64 {
+                if pid == own {
+                    return true;
+                }
+                match sys.process(pid).and_then(|p| p.parent()) {
+                    Some(parent) => pid = parent,
+                    None => return false,
+                }
+            }
+            false
+        };
+        let total: u64 = sys.processes().iter().filter(|(pid, _)| in_tree(**pid)).map(|(_, p)| p.memory()).sum();
+        self.peak_ram = self.peak_ram.max(total);
+    }
+
+    pub fn finish(&self) -> RunResources {
+        RunResources {
+            wall: self.started.elapsed(),
+            downloaded: DOWNLOADED.load(Ordering::Relaxed).saturating_sub(self.downloaded),
+            peak_ram: self.peak_ram,
+            child_cpu: children_cpu_time().zip(self.child_cpu).map(|(now, start)| now.saturating_sub(start)),
+        }
+    }
+}
+
+/// CPU time of every child process that has been waited for, from the
+/// `cutime` and `cstime` fields of `/proc/self/stat`. The kernel reports
+/// them in USER_HZ ticks, which is 100 per second on every Linux target.
+#[cfg(target_os = "linux")]
+fn children_cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name is in parentheses and may contain spaces.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(13)?.parse::<u64>().ok()? + fields.get(14)?.parse::<u64>().ok()?;
+    Some(Duration::from_millis(ticks * 10))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn children_cpu_time() -> Option<Duration> {
+    None
+}
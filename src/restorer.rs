@@ -1,139 +1,441 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use zip::read::ZipArchive;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct BackupIndexEntry {
     pub relative_path: String,
     pub original_size: u64,
+    /// Of the contents, or of the target for a symlink.
     pub sha256_hash: String,
     pub zip_file: Option<String>,
+    /// Permission bits, including setuid, setgid and sticky.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Modification time, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+    /// Where the entry points when it is a symlink.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    /// Extended attributes by name, with hex-encoded values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BackupInfo {
     pub source_path: String,
     pub backup_time: String,
+    /// The archives, zip or tar.zst despite the name.
     pub zip_files: Vec<String>,
     pub index: Option<Vec<BackupIndexEntry>>,
+    /// Format the archives were written in. Restoring tells them apart by
+    /// their contents; Rusty Sync only writes zip.
+    #[serde(default, skip_serializing_if = "BackupFormat::is_zip")]
+    pub format: BackupFormat,
+    /// Parts restored to their own location and picked separately, e.g.
+    /// the desktop settings. Backups from Rusty Sync have none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<BackupGroup>,
+}
+
+/// How the archives of a backup are compressed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFormat {
+    /// Deflate in zip files, which Rusty Sync and any unzip can read.
+    #[default]
+    Zip,
+    /// Tar streams compressed with Zstandard: faster, and smaller for
+    /// binary-heavy homes.
+    Zstd,
 }
 
-use crate::app::InstallMsg;
+impl BackupFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "zip" => Ok(BackupFormat::Zip),
+            "zstd" | "tar.zst" => Ok(BackupFormat::Zstd),
+            _ => Err(Error::Other(format!("unknown backup format '{name}' (known: zip, zstd)"))),
+        }
+    }
+
+    /// File name suffix of the archives, with the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            BackupFormat::Zip => ".zip",
+            BackupFormat::Zstd => ".tar.zst",
+        }
+    }
+
+    /// The format of the archive at `path`, from its first bytes.
+    fn detect(path: &Path) -> Option<Self> {
+        let mut magic = [0u8; 4];
+        File::open(path).and_then(|mut file| file.read_exact(&mut magic)).ok()?;
+        match magic {
+            [b'P', b'K', 0x03, 0x04] => Some(BackupFormat::Zip),
+            [0x28, 0xb5, 0x2f, 0xfd] => Some(BackupFormat::Zstd),
+            _ => None,
+        }
+    }
+
+    fn is_zip(&self) -> bool {
+        *self == BackupFormat::Zip
+    }
+}
+
+/// A part of a backup kept apart from the source folder's files.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupGroup {
+    /// Name used on the command line, e.g. `desktop`.
+    pub name: String,
+    /// What the restore picker shows, e.g. "Desktop settings (GNOME)".
+    pub label: String,
+    /// Folder the archives were taken from; `~` is the home directory.
+    /// Restoring always uses the group's own destination instead.
+    pub dest: String,
+    /// The archives, in the format of the backup.
+    #[serde(default)]
+    pub zip_files: Vec<String>,
+    #[serde(default)]
+    pub index: Vec<BackupIndexEntry>,
+    /// A `dconf dump /` next to the archives, loaded once they are
+    /// extracted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dconf: Option<String>,
+}
+
+use crate::events::{InstallMsg, LogLevel};
+use crate::file_attrs::{self, AttrRestore};
+use crate::error::{Error, Result};
 use std::sync::mpsc::Sender;
 
-pub fn restore_backup(backup_dir: &Path, tx: Option<&Sender<InstallMsg>>) -> Result<Vec<String>, String> {
-    let mut logs = Vec::new();
-    let info_path = backup_dir.join(".rusty_sync_info.json");
+/// Metadata file of a backup folder, written by Rusty Sync and `backup`.
+pub const INFO_FILE: &str = ".rusty_sync_info.json";
+
+/// Reads the `.rusty_sync_info.json` that Rusty Sync writes into a backup
+/// folder.
+pub fn load_backup_info(backup_dir: &Path) -> Result<BackupInfo> {
+    let info_path = backup_dir.join(INFO_FILE);
     if !info_path.exists() {
-        return Err(format!("Backup info file not found at: {}", info_path.display()));
+        return Err(Error::Io(format!("Backup info file not found at: {}", info_path.display())));
     }
 
-    let contents = fs::read_to_string(&info_path).map_err(|e| format!("Failed to read info file: {}", e))?;
-    let info: BackupInfo = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse info file: {}", e))?;
+    let contents = fs::read_to_string(&info_path).map_err(|e| Error::Io(format!("Failed to read info file: {}", e)))?;
+    serde_json::from_str(&contents).map_err(|e| Error::Io(format!("Failed to parse info file: {}", e)))
+}
 
-    let dest_dir = PathBuf::from(&info.source_path);
-    if let Some(s) = tx {
-        let _ = s.send(InstallMsg::Log(format!("Restoring backup from '{}' to '{}'", backup_dir.display(), dest_dir.display())));
+/// Which parts of a backup to restore: the files of its source folder
+/// and/or some of its groups, by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RestoreSelection {
+    pub files: bool,
+    pub groups: Vec<String>,
+    /// Give files back owners this machine does not have, and root's
+    /// setuid bits, as the index records them.
+    pub keep_owners: bool,
+}
+
+impl RestoreSelection {
+    /// Everything in the backup.
+    pub fn all(info: &BackupInfo) -> Self {
+        RestoreSelection { files: true, groups: info.groups.iter().map(|g| g.name.clone()).collect(), keep_owners: false }
+    }
+}
+
+/// Extracts every archive of the backup to its original location and checks
+/// the SHA-256 of each file, reporting progress on `tx` when given.
+pub fn restore_backup(backup_dir: &Path, tx: Option<&Sender<InstallMsg>>) -> Result<Vec<String>> {
+    let info = load_backup_info(backup_dir)?;
+    restore_selected(backup_dir, &RestoreSelection::all(&info), tx)
+}
+
+/// Like [`restore_backup`], for the parts in `selection` only. Groups are
+/// extracted to their own destination after the files.
+pub fn restore_selected(backup_dir: &Path, selection: &RestoreSelection, tx: Option<&Sender<InstallMsg>>) -> Result<Vec<String>> {
+    let mut logs = Vec::new();
+    let info = load_backup_info(backup_dir)?;
+    let groups: Vec<&BackupGroup> = info.groups.iter().filter(|g| selection.groups.contains(&g.name)).collect();
+    // Checked before anything is written, so a refusal leaves no half restore.
+    if let Some(browsers) = groups.iter().find(|g| g.name == crate::browser_profiles::GROUP) {
+        crate::browser_profiles::ensure_closed_for(&browsers.index)?;
     }
-    logs.push(format!("Restoring backup from '{}' to '{}'", backup_dir.display(), dest_dir.display()));
+    // Symlinks are made once every part is written, so that no file of the
+    // backup, a group's included, is written through one of them.
+    let mut links = Vec::new();
+    let mut attrs = AttrRestore::new(selection.keep_owners);
 
-    if !dest_dir.exists() {
-        fs::create_dir_all(&dest_dir).map_err(|e| format!("Failed to create destination dir: {}", e))?;
+    if selection.files {
+        let dest_dir = PathBuf::from(&info.source_path);
+        report(tx, &mut logs, format!("Restoring backup from '{}' to '{}'", backup_dir.display(), dest_dir.display()));
+        if !dest_dir.exists() {
+            fs::create_dir_all(&dest_dir).map_err(|e| Error::Io(format!("Failed to create destination dir: {}", e)))?;
+        }
+        if info.zip_files.is_empty() {
+            logs.push("No zip files found in metadata.".to_string());
+        } else {
+            extract_archives(backup_dir, &info.zip_files, info.format, info.index.as_deref(), &dest_dir, &mut links, &mut attrs, tx, &mut logs)?;
+        }
     }
 
-    if info.zip_files.is_empty() {
-        logs.push("No zip files found in metadata.".to_string());
-        return Ok(logs);
+    for group in groups {
+        // Every group restores into the home directory; the `dest` in the
+        // file is not trusted, as a crafted backup could point it anywhere.
+        if !crate::backup::GROUPS.contains(&group.name.as_str()) {
+            report(tx, &mut logs, format!("[warn] Skipping the unknown group '{}'", group.name));
+            continue;
+        }
+        let dest_dir = crate::installer::home_dir().map_err(Error::Io)?;
+        report(tx, &mut logs, format!("Restoring {} to '{}'", group.label, dest_dir.display()));
+        extract_archives(backup_dir, &group.zip_files, info.format, Some(&group.index), &dest_dir, &mut links, &mut attrs, tx, &mut logs)?;
+        if group.name == crate::desktop_settings::GROUP {
+            crate::desktop_settings::apply(backup_dir, group, tx, &mut logs);
+        }
     }
+    create_links(links, &mut attrs, tx, &mut logs)?;
+    attrs.report(tx, &mut logs);
 
-    // Pre-calculate total files for progress bar
+    if let Some(s) = tx {
+        let _ = s.send(InstallMsg::SubProgress(1.0));
+        let _ = s.send(InstallMsg::Log(LogLevel::Info, "✓ Restore completed successfully!".to_string()));
+    }
+    logs.push("Restore completed successfully!".to_string());
+    Ok(logs)
+}
+
+/// Sends `msg` to the log on `tx` and keeps it for the returned logs.
+pub(crate) fn report(tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>, msg: String) {
+    if let Some(s) = tx {
+        let _ = s.send(InstallMsg::Log(LogLevel::classify(&msg), msg.clone()));
+    }
+    logs.push(msg);
+}
+
+/// A symlink of the backup, made by [`create_links`] once every file is
+/// restored.
+struct PendingLink {
+    outpath: PathBuf,
+    target: String,
+    rel_path: String,
+    entry: Option<BackupIndexEntry>,
+}
+
+/// Extracts `zip_files` from the backup folder into `dest_dir`, checking
+/// each file against `index` when the backup has one. Each archive's format
+/// is read from its contents, falling back to `format`. Symlinks are added
+/// to `links` rather than made, and attributes are put back through `attrs`.
+#[allow(clippy::too_many_arguments)]
+fn extract_archives(
+    backup_dir: &Path,
+    zip_files: &[String],
+    format: BackupFormat,
+    index: Option<&[BackupIndexEntry]>,
+    dest_dir: &Path,
+    links: &mut Vec<PendingLink>,
+    attrs: &mut AttrRestore,
+    tx: Option<&Sender<InstallMsg>>,
+    logs: &mut Vec<String>,
+) -> Result<()> {
+    // Pre-calculate total files for progress bar. Tar streams have no
+    // directory to count, so theirs come from the index.
     let mut total_files = 0;
-    for zip_name in &info.zip_files {
+    for zip_name in zip_files {
         let zip_path = backup_dir.join(zip_name);
-        if let Ok(file) = File::open(&zip_path) {
-            if let Ok(archive) = ZipArchive::new(file) {
-                total_files += archive.len();
+        match BackupFormat::detect(&zip_path).unwrap_or(format) {
+            BackupFormat::Zip => {
+                if let Ok(file) = File::open(&zip_path)
+                    && let Ok(archive) = ZipArchive::new(file) {
+                    total_files += archive.len();
+                }
+            }
+            BackupFormat::Zstd => {
+                total_files += index.unwrap_or_default().iter().filter(|e| e.zip_file.as_ref() == Some(zip_name)).count();
             }
         }
     }
-    let total_archives = info.zip_files.len();
+    let total_archives = zip_files.len();
     if let Some(s) = tx {
-        let _ = s.send(InstallMsg::Log(format!("[info] Found {} files across {} archives.", total_files, total_archives)));
+        let _ = s.send(InstallMsg::Log(LogLevel::Info, format!("[info] Found {} files across {} archives.", total_files, total_archives)));
     }
 
+    let entries: HashMap<&str, &BackupIndexEntry> = index.unwrap_or_default().iter().map(|e| (e.relative_path.as_str(), e)).collect();
+    let expected = |rel_path: &str| entries.get(rel_path).map(|e| e.sha256_hash.as_str());
+    let mut buffer = vec![0u8; 64 * 1024];
     let mut restored_count = 0;
-    for (archive_idx, zip_name) in info.zip_files.iter().enumerate() {
+    let mut defer = |outpath: PathBuf, target: String, rel_path: String| {
+        let entry = entries.get(rel_path.as_str()).map(|e| (*e).clone());
+        links.push(PendingLink { outpath, target, rel_path, entry });
+    };
+    for (archive_idx, zip_name) in zip_files.iter().enumerate() {
         let zip_path = backup_dir.join(zip_name);
         if !zip_path.exists() {
-            let msg = format!("[error] Zip archive missing: {}", zip_name);
-            if let Some(s) = tx { let _ = s.send(InstallMsg::Log(msg.clone())); }
-            logs.push(msg);
+            report(tx, logs, format!("[error] Zip archive missing: {}", zip_name));
             continue;
         }
 
         if let Some(s) = tx {
-            let _ = s.send(InstallMsg::Progress("Restoring Files".to_string(), format!("Extracting {} ({}/{})", zip_name, archive_idx + 1, total_archives), Some("BUSY".to_string())));
+            let _ = s.send(InstallMsg::Progress("Restoring Files".to_string(), format!("Extracting {} ({}/{})", zip_name, archive_idx + 1, total_archives), None));
             let _ = s.send(InstallMsg::SubProgress((archive_idx as f64) / (total_archives as f64)));
         }
+        let mut restored = |rel_path: &str, intact: bool, logs: &mut Vec<String>| {
+            if !intact {
+                report(tx, logs, format!("[WARNING] Integrity check FAILED for {}", rel_path));
+            }
+            restored_count += 1;
+            if let Some(s) = tx {
+                let _ = s.send(InstallMsg::Progress("Restoring Files".to_string(), format!("{} ({})", zip_name, rel_path), None));
+                let _ = s.send(InstallMsg::SubProgress(((restored_count as f64) / (total_files as f64)).min(1.0)));
+            }
+        };
 
-        let file = File::open(&zip_path).map_err(|e| format!("Failed to open zip: {}", e))?;
-        let mut archive = ZipArchive::new(file).map_err(|e| format!("Failed to read zip: {}", e))?;
-
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i).map_err(|e| format!("Failed to read file from zip: {}", e))?;
-            let rel_path = file.name().to_string();
-            let outpath = match file.enclosed_name() {
-                Some(path) => dest_dir.join(path),
-                None => continue,
-            };
+        let file = File::open(&zip_path).map_err(|e| Error::Io(format!("Failed to open archive: {}", e)))?;
+        match BackupFormat::detect(&zip_path).unwrap_or(format) {
+            BackupFormat::Zip => {
+                let mut archive = ZipArchive::new(file).map_err(|e| Error::Io(format!("Failed to read zip: {}", e)))?;
+                for i in 0..archive.len() {
+                    let mut file = archive.by_index(i).map_err(|e| Error::Io(format!("Failed to read file from zip: {}", e)))?;
+                    let rel_path = file.name().to_string();
+                    let outpath = match file.enclosed_name() {
+                        Some(path) => dest_dir.join(path),
+                        None => continue,
+                    };
 
-            if rel_path.ends_with('/') {
-                fs::create_dir_all(&outpath).map_err(|e| format!("Failed to create dir: {}", e))?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(p).map_err(|e| format!("Failed to create parent dir: {}", e))?;
+                    if rel_path.ends_with('/') {
+                        fs::create_dir_all(&outpath).map_err(|e| Error::Io(format!("Failed to create dir: {}", e)))?;
+                    } else if file.is_symlink() {
+                        let mut target = String::new();
+                        file.read_to_string(&mut target).map_err(|e| Error::Io(format!("Failed to read symlink {}: {}", rel_path, e)))?;
+                        restored(&rel_path, intact_link(&target, expected(&rel_path)), logs);
+                        defer(outpath, target, rel_path);
+                    } else {
+                        let intact = restore_file(&mut file, &outpath, expected(&rel_path), &mut buffer)?;
+                        restored(&rel_path, intact, logs);
+                        if let Some(entry) = entries.get(rel_path.as_str()) {
+                            file_attrs::apply(&outpath, entry, attrs);
+                        }
                     }
                 }
-                
-                let mut buffer = Vec::new();
-                file.read_to_end(&mut buffer).map_err(|e| format!("Failed to read zip file contents: {}", e))?;
-                
-                let mut outfile = File::create(&outpath).map_err(|e| format!("Failed to create outfile: {}", e))?;
-                outfile.write_all(&buffer).map_err(|e| format!("Failed to write outfile: {}", e))?;
-                
-                // Integrity check
-                if let Some(ref index) = info.index {
-                    if let Some(entry) = index.iter().find(|e| e.relative_path == rel_path) {
-                        let mut hasher = Sha256::new();
-                        hasher.update(&buffer);
-                        let current_hash = format!("{:x}", hasher.finalize());
-                        if current_hash != entry.sha256_hash {
-                            let msg = format!("[WARNING] Integrity check FAILED for {}", rel_path);
-                            if let Some(s) = tx { let _ = s.send(InstallMsg::Log(msg.clone())); }
-                            logs.push(msg);
+            }
+            BackupFormat::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(file).map_err(|e| Error::Io(format!("Failed to read {}: {}", zip_name, e)))?;
+                let mut archive = tar::Archive::new(decoder);
+                let members = archive.entries().map_err(|e| Error::Io(format!("Failed to read {}: {}", zip_name, e)))?;
+                for entry in members {
+                    let mut entry = entry.map_err(|e| Error::Io(format!("Failed to read file from {}: {}", zip_name, e)))?;
+                    let name = entry.path().map_err(|e| Error::Io(format!("Invalid path in {}: {}", zip_name, e)))?.into_owned();
+                    // Like zip's `enclosed_name`: nothing outside `dest_dir`.
+                    if !name.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                        continue;
+                    }
+                    let rel_path = name.to_string_lossy().into_owned();
+                    let outpath = dest_dir.join(&name);
+                    let kind = entry.header().entry_type();
+                    if kind.is_dir() {
+                        fs::create_dir_all(&outpath).map_err(|e| Error::Io(format!("Failed to create dir: {}", e)))?;
+                    } else if kind.is_symlink() {
+                        let target = entry.link_name().ok().flatten().map(|t| t.to_string_lossy().into_owned()).unwrap_or_default();
+                        restored(&rel_path, intact_link(&target, expected(&rel_path)), logs);
+                        defer(outpath, target, rel_path);
+                    } else if kind.is_file() {
+                        let intact = restore_file(&mut entry, &outpath, expected(&rel_path), &mut buffer)?;
+                        restored(&rel_path, intact, logs);
+                        if let Some(entry) = entries.get(rel_path.as_str()) {
+                            file_attrs::apply(&outpath, entry, attrs);
                         }
                     }
                 }
-                restored_count += 1;
-                if let Some(s) = tx {
-                    let _ = s.send(InstallMsg::Progress("Restoring Files".to_string(), format!("{} ({})", zip_name, rel_path), None));
-                    let _ = s.send(InstallMsg::SubProgress((restored_count as f64) / (total_files as f64)));
+            }
+        }
+        report(tx, logs, format!("[done] Restored archive: {}", zip_name));
+    }
+
+    Ok(())
+}
+
+/// Makes the symlinks [`extract_archives`] set aside, with their attributes.
+fn create_links(links: Vec<PendingLink>, attrs: &mut AttrRestore, tx: Option<&Sender<InstallMsg>>, logs: &mut Vec<String>) -> Result<()> {
+    for link in links {
+        if let Some(p) = link.outpath.parent() {
+            fs::create_dir_all(p).map_err(|e| Error::Io(format!("Failed to create parent dir: {}", e)))?;
+        }
+        match file_attrs::symlink(&link.target, &link.outpath) {
+            Ok(()) => {
+                if let Some(entry) = &link.entry {
+                    file_attrs::apply(&link.outpath, entry, attrs);
                 }
             }
+            Err(e) => report(tx, logs, format!("[warn] Could not create the symlink {} -> {}: {}", link.rel_path, link.target, e)),
         }
-        let msg = format!("[done] Restored archive: {}", zip_name);
-        if let Some(s) = tx { let _ = s.send(InstallMsg::Log(msg.clone())); }
-        logs.push(msg);
     }
+    Ok(())
+}
 
-    if let Some(s) = tx {
-        let _ = s.send(InstallMsg::SubProgress(1.0));
-        let _ = s.send(InstallMsg::Log("✓ Restore completed successfully!".to_string()));
+/// Whether a symlink's target matches the hash the backup recorded for it.
+fn intact_link(target: &str, expected: Option<&str>) -> bool {
+    expected.is_none_or(|expected| format!("{:x}", Sha256::digest(target.as_bytes())) == expected)
+}
+
+/// Writes one file of an archive to `outpath`, hashed chunk by chunk as it
+/// is written so multi-gigabyte files such as browser databases are never
+/// held in memory. False when it does not match `expected`.
+fn restore_file(reader: &mut dyn Read, outpath: &Path, expected: Option<&str>, buffer: &mut [u8]) -> Result<bool> {
+    if let Some(p) = outpath.parent()
+        && !p.exists() {
+        fs::create_dir_all(p).map_err(|e| Error::Io(format!("Failed to create parent dir: {}", e)))?;
+    }
+    // Replaced rather than overwritten: an earlier restore may have left it
+    // read-only, or a symlink that would be written through.
+    if fs::symlink_metadata(outpath).is_ok_and(|m| !m.is_dir()) {
+        let _ = fs::remove_file(outpath);
+    }
+    let mut hasher = expected.map(|_| Sha256::new());
+    let mut outfile = File::create(outpath).map_err(|e| Error::Io(format!("Failed to create outfile: {}", e)))?;
+    loop {
+        let n = reader.read(buffer).map_err(|e| Error::Io(format!("Failed to read archive contents: {}", e)))?;
+        if n == 0 {
+            break;
+        }
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..n]);
+        }
+        outfile.write_all(&buffer[..n]).map_err(|e| Error::Io(format!("Failed to write outfile: {}", e)))?;
+    }
+
+    // Integrity check
+    Ok(match (expected, hasher) {
+        (Some(expected), Some(hasher)) => format!("{:x}", hasher.finalize()) == expected,
+        _ => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_detected_from_magic_bytes() {
+        let dir = std::env::temp_dir().join(format!("rusty_rebase-{}-detect", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cases: [(&str, &[u8], Option<BackupFormat>); 4] = [
+            ("a.zip", b"PK\x03\x04rest", Some(BackupFormat::Zip)),
+            ("a.tar.zst", &[0x28, 0xb5, 0x2f, 0xfd, 0x00], Some(BackupFormat::Zstd)),
+            ("a.txt", b"plain text", None),
+            ("short", b"PK", None),
+        ];
+        for (name, content, expected) in cases {
+            fs::write(dir.join(name), content).unwrap();
+            assert_eq!(BackupFormat::detect(&dir.join(name)), expected, "{name}");
+        }
+        assert_eq!(BackupFormat::detect(&dir.join("missing")), None);
+        fs::remove_dir_all(&dir).unwrap();
     }
-    logs.push("Restore completed successfully!".to_string());
-    Ok(logs)
 }
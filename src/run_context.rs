@@ -0,0 +1,121 @@
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::platform::Platform;
+
+/// Set by `--safe-mode` to use the most conservative terminal handling.
+static FORCED_SAFE: AtomicBool = AtomicBool::new(false);
+
+static CONTEXT: OnceLock<RunContext> = OnceLock::new();
+
+pub fn force_safe_mode() {
+    FORCED_SAFE.store(true, Ordering::Relaxed);
+}
+
+/// Where the TUI is running, read from the environment once at startup.
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    pub ssh: bool,
+    /// `tmux` or `screen` when running inside a multiplexer.
+    pub multiplexer: Option<&'static str>,
+    pub utf8: bool,
+    pub truecolor: bool,
+    /// Safe mode: forced, or picked automatically for terminals without
+    /// UTF-8 or with a bare `TERM` such as the Linux console.
+    pub safe: bool,
+}
+
+pub fn current() -> &'static RunContext {
+    CONTEXT.get_or_init(detect)
+}
+
+fn detect() -> RunContext {
+    let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+    let term = var("TERM").unwrap_or_default();
+
+    let ssh = var("SSH_CONNECTION").is_some() || var("SSH_TTY").is_some();
+    let multiplexer = if var("TMUX").is_some() || term.starts_with("tmux") {
+        Some("tmux")
+    } else if var("STY").is_some() || term.starts_with("screen") {
+        Some("screen")
+    } else {
+        None
+    };
+    let utf8 = match Platform::current() {
+        Platform::Windows => true,
+        Platform::Linux => ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| var(name))
+            .is_some_and(|locale| {
+                let locale = locale.to_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            }),
+    };
+    let truecolor = var("COLORTERM").is_some_and(|v| v == "truecolor" || v == "24bit")
+        || term.contains("truecolor")
+        || term.contains("24bit")
+        || term.ends_with("-direct")
+        || var("WT_SESSION").is_some();
+    let basic_term = Platform::current() == Platform::Linux
+        && matches!(term.as_str(), "" | "dumb" | "linux" | "vt100" | "vt102" | "vt220");
+
+    RunContext {
+        ssh,
+        multiplexer,
+        utf8,
+        truecolor,
+        safe: FORCED_SAFE.load(Ordering::Relaxed) || !utf8 || basic_term,
+    }
+}
+
+impl RunContext {
+    /// Draw with ASCII only: borders, symbols and the banner.
+    pub fn ascii(&self) -> bool {
+        self.safe || !self.utf8
+    }
+
+    /// Whether RGB colours can be sent as-is; otherwise they are mapped to
+    /// the 256-colour palette.
+    pub fn rgb(&self) -> bool {
+        self.truecolor && !self.safe
+    }
+
+    /// Safe mode draws on the main screen: some consoles and serial
+    /// terminals handle the alternate screen badly.
+    pub fn alternate_screen(&self) -> bool {
+        !self.safe
+    }
+
+    /// Shortest time between redraws for progress from background work;
+    /// keys redraw straight away. Longer over SSH and in safe mode, where
+    /// every frame costs bandwidth.
+    pub fn refresh_interval(&self) -> Duration {
+        if self.ssh || self.safe {
+            Duration::from_millis(500)
+        } else {
+            Duration::from_millis(200)
+        }
+    }
+
+    /// Short tags for the header, e.g. `SSH`, `tmux`, `ASCII`.
+    pub fn labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        if self.ssh {
+            labels.push("SSH");
+        }
+        if let Some(name) = self.multiplexer {
+            labels.push(name);
+        }
+        if self.safe {
+            labels.push("SAFE MODE");
+        } else if !self.truecolor {
+            labels.push("256 COLORS");
+        }
+        if self.ascii() {
+            labels.push("ASCII");
+        }
+        labels
+    }
+}
@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+/// Runs the network side of the engine: resolving, downloading and link
+/// checks. Started on first use and kept for the rest of the process.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+pub fn get() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .thread_name("rusty_rebase-net")
+            .enable_all()
+            .build()
+            .expect("failed to start the async runtime")
+    })
+}
+
+/// Waits for `future` on the calling thread; the bridge for the synchronous
+/// install threads and CLI commands. Must not be called from async code.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    get().block_on(future)
+}
+
+/// Runs `future` in the background; results come back over a channel the
+/// future was given, as with the threads elsewhere.
+pub fn spawn<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    get().spawn(future)
+}
+
+/// How often a throttled [`bounded`] checks whether the load has dropped.
+const THROTTLE_RECHECK: Duration = Duration::from_millis(500);
+
+/// Runs `work` for every item, at most `parallel` at a time, and fewer while
+/// [`crate::throttle`] is active. Returns early, aborting what is still
+/// running, once `cancel` fires or a task returns `false` because the
+/// receiver of its result is gone.
+pub async fn bounded<T, F, Fut>(items: impl IntoIterator<Item = T>, parallel: usize, cancel: &CancellationToken, mut work: F)
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    let limit = Arc::new(Semaphore::new(parallel.max(1)));
+    let mut tasks = JoinSet::new();
+    for item in items {
+        let permit = tokio::select! {
+            _ = cancel.cancelled() => return,
+            permit = limit.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+        };
+        // Under sustained CPU load, wait for running tasks to finish until
+        // the lower limit is met; the full one is back once the load drops.
+        while tasks.len() >= crate::throttle::limit(parallel) {
+            let done = tokio::select! {
+                _ = cancel.cancelled() => return,
+                done = tasks.join_next() => done,
+                _ = tokio::time::sleep(THROTTLE_RECHECK) => continue,
+            };
+            if !matches!(done, Some(Ok(true))) {
+                return;
+            }
+        }
+        let task = work(item);
+        tasks.spawn(async move {
+            let delivered = task.await;
+            drop(permit);
+            delivered
+        });
+        while let Some(done) = tasks.try_join_next() {
+            if !matches!(done, Ok(true)) {
+                return;
+            }
+        }
+    }
+    loop {
+        let done = tokio::select! {
+            _ = cancel.cancelled() => return,
+            done = tasks.join_next() => done,
+        };
+        if !matches!(done, Some(Ok(true))) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::command::CommandLine;
+use crate::platform::Platform;
+
+/// Set by `--sandbox` to enable sandboxing regardless of the config.
+static FORCED: AtomicBool = AtomicBool::new(false);
+
+pub fn force_enable() {
+    FORCED.store(true, Ordering::Relaxed);
+}
+
+/// Whether untrusted catalog shell steps run sandboxed.
+pub fn enabled() -> bool {
+    FORCED.load(Ordering::Relaxed) || crate::config::current().sandbox.unwrap_or(false)
+}
+
+const RESTRICTED_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Wraps `inner` so it runs without network access, with `home` as a scratch
+/// HOME and a minimal environment. Prefers bubblewrap (read-only root
+/// filesystem), then firejail, then `unshare -rn`; returns the wrapped command
+/// and the isolation that was used.
+pub fn wrap(inner: CommandLine, home: &Path) -> Result<(CommandLine, &'static str), String> {
+    let platform = Platform::current();
+    if platform != Platform::Linux {
+        return Err("sandboxed shell steps are only supported on Linux".to_string());
+    }
+    let home_str = home.display().to_string();
+    let run = [vec![inner.program.clone()], inner.args.clone()].concat();
+
+    let (wrapped, kind) = if platform.has_command("bwrap") {
+        let cmd = CommandLine::new("bwrap")
+            .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"])
+            .args(["--bind", home_str.as_str(), home_str.as_str()])
+            .args(["--unshare-net", "--unshare-pid", "--die-with-parent", "--"])
+            .args(run);
+        (cmd, "bubblewrap")
+    } else if platform.has_command("firejail") {
+        let cmd = CommandLine::new("firejail")
+            .args(["--quiet", "--noprofile", "--net=none"])
+            .arg(format!("--private={home_str}"))
+            .arg("--")
+            .args(run);
+        (cmd, "firejail")
+    } else if platform.has_command("unshare") {
+        (CommandLine::new("unshare").args(["-rn", "--"]).args(run), "unshare (no network)")
+    } else {
+        (inner, "restricted environment only")
+    };
+
+    let mut wrapped = wrapped;
+    wrapped.clear_env = true;
+    wrapped.env.extend([
+        ("HOME".to_string(), home_str),
+        ("PATH".to_string(), RESTRICTED_PATH.to_string()),
+        ("LANG".to_string(), "C.UTF-8".to_string()),
+    ]);
+    Ok((wrapped, kind))
+}
@@ -0,0 +1,16 @@
+use keyring::Entry;
+
+/// Keyring service name every catalog secret is filed under.
+const SERVICE: &str = "rusty_rebase";
+
+/// Reads a previously stored secret from the Secret Service / Windows
+/// Credential Manager.
+pub fn lookup(name: &str) -> Option<String> {
+    Entry::new(SERVICE, name).ok()?.get_password().ok()
+}
+
+pub fn store(name: &str, value: &str) -> Result<(), String> {
+    Entry::new(SERVICE, name)
+        .and_then(|entry| entry.set_password(value))
+        .map_err(|e| e.to_string())
+}
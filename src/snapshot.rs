@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+
+use crate::catalog::{load_catalog, CatalogFile, SetupStep};
+use crate::manifest::{DotfileGroup, Manifest, ToolPin};
+
+pub const DEFAULT_BUNDLE: &str = "rusty_rebase_snapshot.zip";
+
+// Layout of a bundle. The catalog folder also holds the files its
+// `template` and `copy_file` steps read, at the same relative paths.
+const MANIFEST: &str = "manifest.toml";
+const STATE: &str = "state.json";
+const CONFIG: &str = "config.toml";
+const CATALOG_DIR: &str = "catalog";
+const DOTFILES_DIR: &str = "dotfiles";
+/// Dotfile group the bundled files are restored through.
+const DOTFILE_GROUP: &str = "snapshot";
+
+/// Writes a bundle with everything needed to set this machine up again: a
+/// manifest pinning every tool in the state database to its installed
+/// version, the state database itself, the catalog with the files it
+/// refers to, the user config, and the `dotfiles` (paths relative to the
+/// home directory) when given.
+pub fn snapshot(out: &Path, dotfiles: &[String]) -> Result<(), String> {
+    let state = crate::state::StateDb::load();
+    if state.tools.is_empty() && dotfiles.is_empty() {
+        return Err("no installed tools recorded yet, nothing to snapshot".to_string());
+    }
+    let catalog_path = crate::config::require_catalog()?;
+    let catalog = load_catalog(&catalog_path)?;
+    let home = crate::installer::home_dir()?;
+
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let read = |path: &Path| fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()));
+
+    for (relative, path) in catalog_files(&catalog_path, &catalog) {
+        entries.push((format!("{CATALOG_DIR}/{relative}"), read(&path)?));
+    }
+
+    let tools: BTreeMap<String, ToolPin> = state
+        .tools
+        .iter()
+        .filter(|(key, _)| catalog.software.contains_key(*key))
+        .map(|(key, tool)| (key.clone(), ToolPin { version: Some(tool.version.clone()) }))
+        .collect();
+    let skipped: Vec<&String> = state.tools.keys().filter(|key| !catalog.software.contains_key(*key)).collect();
+
+    let mut files = Vec::new();
+    for dotfile in dotfiles {
+        let relative = dotfile.trim_start_matches("~/");
+        if Path::new(relative).is_absolute() || relative.contains("..") {
+            return Err(format!("dotfile '{dotfile}' must be a path inside the home directory"));
+        }
+        entries.push((format!("{DOTFILES_DIR}/{relative}"), read(&home.join(relative))?));
+        files.push(relative.to_string());
+    }
+    let mut groups = BTreeMap::new();
+    if !files.is_empty() {
+        // The source is filled in with the extracted folder on apply.
+        groups.insert(DOTFILE_GROUP.to_string(), DotfileGroup { source: DOTFILES_DIR.to_string(), target: "~".to_string(), files });
+    }
+    let manifest = Manifest {
+        catalog: Some(format!("{CATALOG_DIR}/{}", crate::config::CATALOG_FILE_NAME)),
+        tools,
+        dotfiles: groups,
+        restore: None,
+    };
+    let manifest = toml::to_string_pretty(&manifest).map_err(|e| format!("failed to serialize manifest: {e}"))?;
+    entries.push((MANIFEST.to_string(), manifest.into_bytes()));
+    entries.push((STATE.to_string(), serde_json::to_vec_pretty(&state).map_err(|e| e.to_string())?));
+    let config_path = crate::config::config_path()?;
+    if config_path.is_file() {
+        entries.push((CONFIG.to_string(), read(&config_path)?));
+    }
+
+    let file = File::create(out).map_err(|e| format!("failed to create {}: {e}", out.display()))?;
+    let mut zip = zip::ZipWriter::new(file);
+    for (name, data) in &entries {
+        zip.start_file(name.as_str(), SimpleFileOptions::default()).map_err(|e| format!("failed to write {name}: {e}"))?;
+        zip.write_all(data).map_err(|e| format!("failed to write {name}: {e}"))?;
+    }
+    zip.finish().map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+
+    println!("Wrote {} with {} tool(s) and {} dotfile(s).", out.display(), state.tools.len() - skipped.len(), dotfiles.len());
+    for key in skipped {
+        println!("  [warn] {key} is not in the current catalog and was left out");
+    }
+    Ok(())
+}
+
+/// The catalog file and the files its `template` and `copy_file` steps
+/// read, each with its path relative to the catalog folder.
+pub fn catalog_files(catalog_path: &Path, catalog: &CatalogFile) -> Vec<(String, PathBuf)> {
+    let catalog_dir = catalog_path.parent().unwrap_or(Path::new("."));
+    let mut referenced: Vec<&str> = catalog
+        .software
+        .values()
+        .flat_map(|spec| &spec.setup_steps)
+        .filter_map(|step| match step {
+            SetupStep::Template { source, .. } => Some(source.as_str()),
+            SetupStep::CopyFile { from, .. } => Some(from.as_str()),
+            _ => None,
+        })
+        .filter(|path| Path::new(path).is_relative() && !path.starts_with('~') && !path.contains(".."))
+        .collect();
+    referenced.sort();
+    referenced.dedup();
+    let mut files = vec![(crate::config::CATALOG_FILE_NAME.to_string(), catalog_path.to_path_buf())];
+    files.extend(
+        referenced
+            .into_iter()
+            .map(|relative| (relative.replace('\\', "/"), catalog_dir.join(relative)))
+            .filter(|(_, path)| path.is_file()),
+    );
+    files
+}
+
+/// Sets a machine up from a bundle written by [`snapshot`]: the bundle is
+/// unpacked under the data directory, its catalog and config are installed
+/// when this machine has none yet, and its manifest is applied. With
+/// `latest`, tools get their newest version instead of the recorded one.
+pub fn apply_snapshot(bundle: &Path, dry_run: bool, assume_yes: bool, latest: bool) -> Result<(), String> {
+    let name = bundle.file_stem().and_then(|s| s.to_str()).unwrap_or("snapshot");
+    let dir = dirs::data_dir()
+        .ok_or("data directory not found")?
+        .join("rusty_rebase")
+        .join("snapshots")
+        .join(name);
+    unpack(bundle, &dir)?;
+    println!("Unpacked {} to {}", bundle.display(), dir.display());
+
+    let manifest_path = dir.join(MANIFEST);
+    let mut manifest = crate::manifest::load_manifest(&manifest_path)?;
+    if let Some(group) = manifest.dotfiles.get_mut(DOTFILE_GROUP) {
+        group.source = dir.join(DOTFILES_DIR).display().to_string();
+    }
+    if latest {
+        manifest.tools.values_mut().for_each(|pin| pin.version = None);
+    }
+    let content = toml::to_string_pretty(&manifest).map_err(|e| format!("failed to serialize manifest: {e}"))?;
+    fs::write(&manifest_path, content).map_err(|e| format!("failed to write {}: {e}", manifest_path.display()))?;
+
+    install_catalog_and_config(&dir, dry_run)?;
+    crate::manifest::apply_manifest(&manifest_path, dry_run, assume_yes, false, None)
+}
+
+/// Copies the bundled catalog folder into the config directory and writes
+/// the bundled config pointing at it, unless a config already exists.
+fn install_catalog_and_config(dir: &Path, dry_run: bool) -> Result<(), String> {
+    let config_path = crate::config::config_path()?;
+    if config_path.exists() {
+        println!("Keeping the existing config at {}", config_path.display());
+        return Ok(());
+    }
+    let config_dir = crate::config::config_dir()?;
+    let catalog_path = config_dir.join(crate::config::CATALOG_FILE_NAME);
+    if dry_run {
+        println!("[dry-run] install the bundled catalog and config into {}", config_dir.display());
+        return Ok(());
+    }
+    copy_tree(&dir.join(CATALOG_DIR), &config_dir)?;
+    let mut config: crate::config::Config = match fs::read_to_string(dir.join(CONFIG)) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("failed to parse the bundled config: {e}"))?,
+        Err(_) => crate::config::Config::default(),
+    };
+    config.catalog_path = Some(catalog_path.display().to_string());
+    let written = crate::config::save_config(&config)?;
+    println!("Installed the catalog to {} and the config to {}", catalog_path.display(), written.display());
+    Ok(())
+}
+
+/// Extracts `bundle` into a fresh `dir`, refusing entries that would land
+/// outside it.
+fn unpack(bundle: &Path, dir: &Path) -> Result<(), String> {
+    let file = File::open(bundle).map_err(|e| format!("failed to open {}: {e}", bundle.display()))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("failed to read zip: {e}"))?;
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| format!("failed to clear {}: {e}", dir.display()))?;
+    }
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("failed to read zip entry: {e}"))?;
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(format!("refusing unsafe bundle entry '{}'", entry.name()));
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        let dest = dir.join(relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(|e| format!("failed to read {}: {e}", entry.name()))?;
+        fs::write(&dest, data).map_err(|e| format!("failed to write {}: {e}", dest.display()))?;
+    }
+    if !dir.join(MANIFEST).is_file() {
+        return Err(format!("{} is not a snapshot bundle (no {MANIFEST})", bundle.display()));
+    }
+    Ok(())
+}
+
+/// Copies the files under `from` into `to`, keeping existing ones.
+fn copy_tree(from: &Path, to: &Path) -> Result<(), String> {
+    let entries = fs::read_dir(from).map_err(|e| format!("failed to read {}: {e}", from.display()))?;
+    for entry in entries.filter_map(Result::ok) {
+        let (source, dest) = (entry.path(), to.join(entry.file_name()));
+        if source.is_dir() {
+            copy_tree(&source, &dest)?;
+        } else if !dest.exists() {
+            fs::create_dir_all(to).map_err(|e| format!("failed to create {}: {e}", to.display()))?;
+            fs::copy(&source, &dest).map_err(|e| format!("failed to copy to {}: {e}", dest.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// `--dotfiles=.bashrc,.gitconfig` as a list.
+pub fn parse_dotfiles(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|list| list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::now_secs;
+use crate::report::format_timestamp;
+
+/// Runs kept in the history; older ones are dropped.
+const MAX_RUNS: usize = 200;
+
+/// Sessions in a row an entry fails in before it needs attention, when
+/// `attention_after_sessions` is not configured.
+pub const DEFAULT_ATTENTION_SESSIONS: u32 = 3;
+
+/// This process: a TUI session or one `apply`. Failures count once per
+/// session however often the entry is retried in it.
+static SESSION: LazyLock<String> = LazyLock::new(|| format!("{}-{}", now_secs(), std::process::id()));
+
+/// A tool as it was last installed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstalledTool {
+    pub version: String,
+    pub installed_at: u64,
+    pub run_id: String,
+    /// Folders and files the install created or put in place.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+/// What a run did with one tool. `error` is `None` when it succeeded, and
+/// `version` is empty when the tool never resolved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunTool {
+    pub key: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// How a catalog entry has fared across sessions, to notice entries whose
+/// upstream moved or went away.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct EntryHealth {
+    /// Last time the entry resolved from the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_resolved: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_installed: Option<u64>,
+    /// Sessions in a row in which it failed; a success resets it.
+    #[serde(default)]
+    pub failing_sessions: u32,
+    /// When the current run of failures started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failing_since: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Whether an install failed during the run of failures, which a
+    /// successful resolution alone does not end.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub install_failed: bool,
+    /// The session that last counted a failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// What happened to an entry, for [`record_health`].
+pub enum HealthEvent {
+    Resolved,
+    Installed,
+    ResolveFailed(String),
+    InstallFailed(String),
+}
+
+impl EntryHealth {
+    /// Whether the entry failed in `threshold` sessions in a row, usually
+    /// [`attention_after_sessions`] read once by the caller.
+    pub fn needs_attention(&self, threshold: u32) -> bool {
+        self.failing_sessions >= threshold
+    }
+
+    fn note(&mut self, event: HealthEvent) {
+        let now = now_secs();
+        let error = match event {
+            HealthEvent::Resolved => {
+                self.last_resolved = Some(now);
+                if self.install_failed {
+                    return;
+                }
+                None
+            }
+            HealthEvent::Installed => {
+                self.last_installed = Some(now);
+                self.last_resolved = Some(now);
+                None
+            }
+            HealthEvent::ResolveFailed(error) => Some(error),
+            HealthEvent::InstallFailed(error) => {
+                self.install_failed = true;
+                Some(error)
+            }
+        };
+        match error {
+            Some(error) => {
+                if self.session.as_deref() != Some(SESSION.as_str()) {
+                    self.session = Some(SESSION.clone());
+                    self.failing_sessions += 1;
+                }
+                self.failing_since.get_or_insert(now);
+                self.last_error = Some(error);
+            }
+            None => {
+                self.failing_sessions = 0;
+                self.failing_since = None;
+                self.last_error = None;
+                self.install_failed = false;
+                self.session = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunEntry {
+    pub id: String,
+    pub started_at: u64,
+    /// What started the run, e.g. `tui` or `apply machine.toml`.
+    pub source: String,
+    #[serde(default)]
+    pub tools: Vec<RunTool>,
+}
+
+/// Every tool installed on this machine and the runs that did it, shared by
+/// the TUI, `apply`, `uninstall` and `history`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StateDb {
+    #[serde(default)]
+    pub tools: BTreeMap<String, InstalledTool>,
+    #[serde(default)]
+    pub runs: Vec<RunEntry>,
+    /// Keyed by catalog key, including entries never installed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub health: BTreeMap<String, EntryHealth>,
+}
+
+/// `~/.local/share/rusty_rebase/state` on Linux.
+fn state_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("rusty_rebase").join("state"))
+}
+
+fn state_file() -> Option<PathBuf> {
+    Some(state_dir()?.join("state.json"))
+}
+
+impl StateDb {
+    /// The recorded state; empty when nothing was installed yet or the file
+    /// cannot be read.
+    pub fn load() -> Self {
+        state_file()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes through a temporary file, so an interrupted write never leaves
+    /// a truncated database behind.
+    fn save(&self) -> Result<(), String> {
+        let path = state_file().ok_or("no data directory for the state database")?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let partial = path.with_extension("json.part");
+        fs::write(&partial, json).map_err(|e| format!("failed to write {}: {e}", partial.display()))?;
+        fs::rename(&partial, &path).map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    pub fn installed_version(&self, key: &str) -> Option<&str> {
+        self.tools.get(key).map(|t| t.version.as_str())
+    }
+}
+
+/// Records one run into the state database as its tools finish, so a run
+/// that is interrupted still leaves what it installed behind. Dry runs are
+/// not recorded.
+pub struct RunRecorder {
+    id: String,
+    started_at: u64,
+    source: String,
+}
+
+impl RunRecorder {
+    pub fn start(source: impl Into<String>) -> Self {
+        let started_at = now_secs();
+        RunRecorder { id: format!("{started_at}-{}", std::process::id()), started_at, source: source.into() }
+    }
+
+    /// Adds the outcome for `key`. On success the tool's entry is replaced
+    /// with `version` and the folders and files the install left behind.
+    pub fn record(&self, key: &str, version: &str, result: Result<&[PathBuf], &str>) -> Result<(), String> {
+        let mut db = StateDb::load();
+        if let Ok(files) = result {
+            db.tools.insert(key.to_string(), InstalledTool {
+                version: version.to_string(),
+                installed_at: now_secs(),
+                run_id: self.id.clone(),
+                files: files.iter().map(|f| f.display().to_string()).collect(),
+            });
+        }
+        if db.runs.last().is_none_or(|run| run.id != self.id) {
+            db.runs.push(RunEntry { id: self.id.clone(), started_at: self.started_at, source: self.source.clone(), tools: Vec::new() });
+            let excess = db.runs.len().saturating_sub(MAX_RUNS);
+            db.runs.drain(..excess);
+        }
+        if let Some(run) = db.runs.last_mut() {
+            run.tools.push(RunTool { key: key.to_string(), version: version.to_string(), error: result.err().map(str::to_string) });
+        }
+        db.save()
+    }
+}
+
+/// `attention_after_sessions` from the config, at least 1.
+pub fn attention_after_sessions() -> u32 {
+    crate::config::current().attention_after_sessions.unwrap_or(DEFAULT_ATTENTION_SESSIONS).max(1)
+}
+
+/// Notes resolutions and installs, and their failures, in the entries'
+/// health. Dry runs and offline sessions should not report failures, since
+/// they say nothing about the entry.
+pub fn record_health(events: impl IntoIterator<Item = (String, HealthEvent)>) -> Result<(), String> {
+    let mut events = events.into_iter().peekable();
+    if events.peek().is_none() {
+        return Ok(());
+    }
+    let mut db = StateDb::load();
+    for (key, event) in events {
+        db.health.entry(key).or_default().note(event);
+    }
+    db.save()
+}
+
+/// Drops uninstalled tools from the database.
+pub fn forget(keys: &[String]) -> Result<(), String> {
+    let mut db = StateDb::load();
+    let before = db.tools.len();
+    db.tools.retain(|key, _| !keys.contains(key));
+    if db.tools.len() == before {
+        return Ok(());
+    }
+    db.save()
+}
+
+/// Prints the most recent `limit` runs, newest first, for `rusty_rebase history`.
+pub fn print_history(limit: usize) {
+    let db = StateDb::load();
+    if db.runs.is_empty() {
+        println!("No recorded runs yet.");
+        return;
+    }
+    for run in db.runs.iter().rev().take(limit) {
+        let failed = run.tools.iter().filter(|t| t.error.is_some()).count();
+        println!(
+            "{}  {}  {} ({} ok, {failed} failed)",
+            format_timestamp(run.started_at),
+            run.id,
+            run.source,
+            run.tools.len() - failed
+        );
+        for tool in &run.tools {
+            // Tools that failed to resolve have no version.
+            let label = if tool.version.is_empty() { tool.key.clone() } else { format!("{} {}", tool.key, tool.version) };
+            match &tool.error {
+                None => println!("    + {label}"),
+                Some(error) => println!("    x {label}: {error}"),
+            }
+        }
+    }
+}
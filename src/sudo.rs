@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// How often the cached credential is refreshed during a run, well inside
+/// sudo's default 5 to 15 minute timeout.
+const REFRESH: Duration = Duration::from_secs(60);
+
+/// Stops the refresher when dropped.
+static KEEPALIVE: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+fn quiet(args: &[&str]) -> bool {
+    Command::new("sudo")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Whether sudo runs without asking for a password right now.
+pub fn cached() -> bool {
+    quiet(&["-n", "true"])
+}
+
+/// Validates `password` through `sudo -S`, caching the credential. Nothing
+/// reaches the terminal: the prompt is empty and sudo's output is captured.
+pub fn authenticate(password: &str) -> Result<(), String> {
+    let mut child = Command::new("sudo")
+        .args(["-S", "-v", "-p", ""])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start sudo: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // sudo closes stdin early when it needs no password.
+        let _ = stdin.write_all(password.as_bytes()).and_then(|()| stdin.write_all(b"\n"));
+    }
+    let output = child.wait_with_output().map_err(|e| format!("sudo failed: {e}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // The last line says why; earlier ones are retries and the lecture.
+    match stderr.lines().map(str::trim).rfind(|l| !l.is_empty()) {
+        Some(line) if line.contains("incorrect password") || line.contains("no password was provided") => {
+            Err("Sorry, try again.".to_string())
+        }
+        Some(line) => Err(line.trim_start_matches("sudo: ").to_string()),
+        None => Err(format!("sudo exited with {}", output.status)),
+    }
+}
+
+/// Refreshes the cached credential in the background until [`forget`], so a
+/// long install does not stop at a password prompt halfway through. Calling
+/// it again while it runs does nothing.
+pub fn keep_alive() {
+    let mut keepalive = KEEPALIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if keepalive.is_some() {
+        return;
+    }
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    *keepalive = Some(stop_tx);
+    thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(REFRESH) {
+            if !quiet(&["-n", "-v"]) {
+                break;
+            }
+        }
+    });
+}
+
+/// Stops the refresher and drops the cached credential.
+pub fn forget() {
+    KEEPALIVE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    quiet(&["-k"]);
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use crate::catalog::SoftwareSpec;
+
+/// Variables every template can use: `username`, `email`, `hostname`,
+/// `home`, `install_root` and `download_dir`. Values that cannot be
+/// determined are left out so rendering reports them as missing.
+pub fn builtin_vars(spec: &SoftwareSpec) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    if let Ok(user) = std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+        vars.insert("username".to_string(), user);
+    }
+    if let Some(email) = git_global("user.email") {
+        vars.insert("email".to_string(), email);
+    }
+    if let Some(host) = sysinfo::System::host_name() {
+        vars.insert("hostname".to_string(), host);
+    }
+    if let Ok(home) = crate::installer::home_dir() {
+        vars.insert("home".to_string(), home.display().to_string());
+    }
+    if let Ok(root) = crate::installer::install_root(spec) {
+        vars.insert("install_root".to_string(), root.display().to_string());
+    }
+    if let Ok(dir) = crate::installer::download_dir() {
+        vars.insert("download_dir".to_string(), dir.display().to_string());
+    }
+    vars
+}
+
+fn git_global(key: &str) -> Option<String> {
+    let output = std::process::Command::new("git").args(["config", "--global", "--get", key]).output().ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+/// Replaces every `{{ name }}` in `text`. Fails listing the names that have
+/// no value instead of writing a half-rendered file.
+pub fn render(text: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut missing = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => {
+                if !missing.contains(&name) {
+                    missing.push(name);
+                }
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+
+    if missing.is_empty() {
+        Ok(out)
+    } else {
+        Err(format!("template variables without a value: {}", missing.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn fills_in_variables() {
+        let vars = vars(&[("username", "ada"), ("home", "/home/ada")]);
+        assert_eq!(render("user={{username}} dir={{ home }}/bin", &vars).unwrap(), "user=ada dir=/home/ada/bin");
+        assert_eq!(render("no placeholders", &vars).unwrap(), "no placeholders");
+        assert_eq!(render("left {{ open", &vars).unwrap(), "left {{ open");
+    }
+
+    #[test]
+    fn lists_missing_variables_once() {
+        let err = render("{{ email }} {{ hostname }} {{email}}", &vars(&[])).unwrap_err();
+        assert_eq!(err, "template variables without a value: email, hostname");
+    }
+}
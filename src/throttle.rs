@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// CPU usage, in percent, above which a sustained load throttles extraction.
+pub const DEFAULT_CPU_THRESHOLD: f32 = 85.0;
+
+/// How long usage has to stay above (or back below) the threshold before the
+/// throttle switches, so short spikes are ignored.
+const SUSTAIN: Duration = Duration::from_secs(10);
+
+/// Usage has to fall this far under the threshold to lift the throttle.
+const HYSTERESIS: f32 = 20.0;
+
+/// Share of each period extraction keeps working while throttled.
+const WORK_SLICE: Duration = Duration::from_millis(50);
+
+static THROTTLED: AtomicBool = AtomicBool::new(false);
+
+pub fn active() -> bool {
+    THROTTLED.load(Ordering::Relaxed)
+}
+
+/// How many of `parallel` tasks may run at once right now: half of them,
+/// at least one, while throttled.
+pub fn limit(parallel: usize) -> usize {
+    if active() { (parallel / 2).max(1) } else { parallel.max(1) }
+}
+
+/// Turns the throttle on and off from periodic CPU usage samples.
+#[derive(Debug)]
+pub struct LoadMonitor {
+    threshold: f32,
+    /// When usage last crossed to the other side of the threshold.
+    crossed_at: Option<Instant>,
+}
+
+impl LoadMonitor {
+    pub fn new(threshold: f32) -> Self {
+        Self { threshold, crossed_at: None }
+    }
+
+    pub fn sample(&mut self, cpu_usage: f32) {
+        let throttled = active();
+        let crossed = if throttled {
+            cpu_usage < self.threshold - HYSTERESIS
+        } else {
+            cpu_usage > self.threshold
+        };
+        if !crossed {
+            self.crossed_at = None;
+            return;
+        }
+        let since = *self.crossed_at.get_or_insert_with(Instant::now);
+        if since.elapsed() >= SUSTAIN {
+            THROTTLED.store(!throttled, Ordering::Relaxed);
+            self.crossed_at = None;
+        }
+    }
+}
+
+/// Paces a CPU-bound loop: while throttled, every `WORK_SLICE` of work is
+/// followed by an equal pause, halving the extractor's CPU share.
+pub struct Pacer {
+    slice_start: Instant,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self { slice_start: Instant::now() }
+    }
+
+    pub fn tick(&mut self) {
+        if self.slice_start.elapsed() < WORK_SLICE {
+            return;
+        }
+        if active() {
+            std::thread::sleep(WORK_SLICE);
+        }
+        self.slice_start = Instant::now();
+    }
+}
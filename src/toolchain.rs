@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::catalog::{CatalogFile, SetupStep, VersionManager};
+use crate::command::{CommandLine, Script};
+use crate::platform::Platform;
+
+/// A language package manager that installs tools for the current user
+/// rather than system-wide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Toolchain {
+    Cargo,
+    Npm,
+    Pipx,
+}
+
+impl Toolchain {
+    pub fn program(self) -> &'static str {
+        match self {
+            Toolchain::Cargo => "cargo",
+            Toolchain::Npm => "npm",
+            Toolchain::Pipx => "pipx",
+        }
+    }
+
+    /// Catalog keys that usually install the program.
+    fn providers(self) -> &'static [&'static str] {
+        match self {
+            Toolchain::Cargo => &["rust", "rustup", "cargo"],
+            Toolchain::Npm => &["npm", "node", "nodejs"],
+            Toolchain::Pipx => &["pipx"],
+        }
+    }
+
+    /// Finds the program on PATH, or in the per-user folder its installer
+    /// uses, which is not on PATH yet when it was installed earlier in the
+    /// same run.
+    pub fn locate(self) -> Option<PathBuf> {
+        let platform = Platform::current();
+        if platform.has_command(self.program()) {
+            return Some(PathBuf::from(self.program()));
+        }
+        let home = dirs::home_dir()?;
+        let dir = match self {
+            Toolchain::Cargo => home.join(".cargo").join("bin"),
+            Toolchain::Pipx => home.join(".local").join("bin"),
+            Toolchain::Npm => return None,
+        };
+        let file = match platform {
+            Platform::Windows => format!("{}.exe", self.program()),
+            Platform::Linux => self.program().to_string(),
+        };
+        Some(dir.join(file)).filter(|path| path.exists())
+    }
+
+    /// Commands installing `packages` with `program`. Global npm packages go
+    /// under `~/.local` instead of the system prefix; local ones into
+    /// `prefix`.
+    pub fn install(self, program: &Path, packages: &[String], global: bool, prefix: &Path) -> Script {
+        let program = program.display().to_string();
+        match self {
+            Toolchain::Cargo => vec![CommandLine::new(program).args(["install", "--"]).args(packages)],
+            Toolchain::Npm => vec![CommandLine::new(program)
+                .args(["install", "--prefix", &npm_prefix(global, prefix).display().to_string()])
+                .args(global.then_some("--global"))
+                .arg("--")
+                .args(packages)],
+            Toolchain::Pipx => packages
+                .iter()
+                .map(|package| CommandLine::new(program.clone()).args(["install", "--", package.as_str()]))
+                .collect(),
+        }
+    }
+
+    /// Names of the packages `program` has installed, so re-runs skip them.
+    pub fn installed(self, program: &Path, global: bool, prefix: &Path) -> Vec<String> {
+        let output = match self {
+            Toolchain::Cargo => Command::new(program).args(["install", "--list"]).output(),
+            Toolchain::Npm => Command::new(program)
+                .args(["ls", "--parseable", "--depth=0", "--prefix"])
+                .arg(npm_prefix(global, prefix))
+                .args(global.then_some("--global"))
+                .output(),
+            Toolchain::Pipx => Command::new(program).args(["list", "--short"]).output(),
+        };
+        let Ok(output) = output else { return Vec::new() };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match self {
+            // `ripgrep v14.1.0:` followed by indented binary names.
+            Toolchain::Cargo => stdout
+                .lines()
+                .filter(|l| !l.starts_with(char::is_whitespace))
+                .filter_map(|l| l.split_whitespace().next())
+                .map(str::to_string)
+                .collect(),
+            // One folder per package; scoped ones keep their `@scope/`.
+            Toolchain::Npm => stdout
+                .lines()
+                .filter_map(|l| l.rsplit_once("node_modules").map(|(_, name)| name.trim_start_matches(['/', '\\'])))
+                .map(|name| name.replace('\\', "/"))
+                .filter(|name| !name.is_empty())
+                .collect(),
+            // `black 24.1.0`
+            Toolchain::Pipx => stdout.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string).collect(),
+        }
+    }
+}
+
+fn npm_prefix(global: bool, install_root: &Path) -> PathBuf {
+    if global {
+        dirs::home_dir().unwrap_or_default().join(".local")
+    } else {
+        install_root.to_path_buf()
+    }
+}
+
+/// The toolchain, packages and npm `global` flag of a language package step.
+pub fn step_toolchain(step: &SetupStep) -> Option<(Toolchain, &[String], bool)> {
+    match step {
+        SetupStep::Cargo { crates } => Some((Toolchain::Cargo, crates, false)),
+        SetupStep::Npm { packages, global } => Some((Toolchain::Npm, packages, *global)),
+        SetupStep::Pipx { packages } => Some((Toolchain::Pipx, packages, false)),
+        _ => None,
+    }
+}
+
+/// The catalog entry that installs `toolchain`: one named after it, or one
+/// whose package step installs its program.
+pub fn provider(catalog: &CatalogFile, toolchain: Toolchain) -> Option<String> {
+    let by_key = toolchain.providers().iter().find(|key| catalog.software.contains_key(**key));
+    if let Some(key) = by_key {
+        return Some(key.to_string());
+    }
+    catalog.software.iter().find_map(|(key, spec)| {
+        let provides = spec.setup_steps.iter().any(|step| step.packages_for("", "").iter().any(|p| p == toolchain.program()));
+        provides.then(|| key.clone())
+    })
+}
+
+/// nvm release installed when it is missing.
+const NVM_RELEASE: &str = "v0.40.1";
+
+impl VersionManager {
+    /// Folder under the home directory holding the manager's binary; nvm
+    /// has none, being a shell function.
+    pub fn bin_dir(self) -> Option<&'static str> {
+        match self {
+            VersionManager::Rustup => Some(".cargo/bin"),
+            VersionManager::Pyenv => Some(".pyenv/bin"),
+            VersionManager::Nvm => None,
+        }
+    }
+
+    /// Where the manager lives once installed: the `rustup`/`pyenv` binary,
+    /// or nvm's `nvm.sh`.
+    fn home_path(self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        Some(match self.bin_dir() {
+            Some(dir) => home.join(dir).join(self.label()),
+            None => std::env::var_os("NVM_DIR").map(PathBuf::from).unwrap_or(home.join(".nvm")).join("nvm.sh"),
+        })
+    }
+
+    /// Whether the manager is installed, on PATH or in its home folder.
+    pub fn installed(self) -> bool {
+        let on_path = self != VersionManager::Nvm && Platform::current().has_command(self.label());
+        on_path || self.home_path().is_some_and(|path| path.exists())
+    }
+
+    /// The official installer, piped from its download into a shell.
+    pub fn bootstrap(self) -> CommandLine {
+        let script = match self {
+            VersionManager::Rustup => "curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y".to_string(),
+            VersionManager::Nvm => format!("curl -fsSL https://raw.githubusercontent.com/nvm-sh/nvm/{NVM_RELEASE}/install.sh | bash"),
+            VersionManager::Pyenv => "curl -fsSL https://pyenv.run | bash".to_string(),
+        };
+        CommandLine::new("sh").args(["-c", script.as_str()])
+    }
+
+    /// A manager subcommand. nvm is a shell function, so it runs through
+    /// bash after sourcing `nvm.sh`.
+    fn command(self, args: &[&str]) -> CommandLine {
+        let binary = || {
+            if Platform::current().has_command(self.label()) {
+                self.label().to_string()
+            } else {
+                self.home_path().map(|p| p.display().to_string()).unwrap_or_else(|| self.label().to_string())
+            }
+        };
+        match self {
+            VersionManager::Nvm => {
+                let nvm_sh = self.home_path().map(|p| p.display().to_string()).unwrap_or_default();
+                CommandLine::new("bash").args(["-c", r#". "$0" && nvm "$@""#, nvm_sh.as_str()]).args(args.iter().copied())
+            }
+            VersionManager::Rustup | VersionManager::Pyenv => CommandLine::new(binary()).args(args.iter().copied()),
+        }
+    }
+
+    fn output(self, args: &[&str]) -> String {
+        self.command(args)
+            .to_command()
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Whether `version` (a channel, alias or version prefix) is installed.
+    pub fn has_version(self, version: &str) -> bool {
+        match self {
+            // `stable-x86_64-unknown-linux-gnu (default)`
+            VersionManager::Rustup => self.output(&["toolchain", "list"]).lines().any(|l| l.starts_with(&format!("{version}-"))),
+            VersionManager::Nvm => self.output(&["version", version]).trim().starts_with('v'),
+            VersionManager::Pyenv => self
+                .output(&["versions", "--bare"])
+                .lines()
+                .any(|l| l == version || l.starts_with(&format!("{version}."))),
+        }
+    }
+
+    /// Whether `version` is already the default.
+    pub fn is_default(self, version: &str) -> bool {
+        match self {
+            VersionManager::Rustup => self.output(&["default"]).starts_with(&format!("{version}-")),
+            VersionManager::Nvm => {
+                let current = self.output(&["version", "default"]);
+                current.trim().starts_with('v') && current == self.output(&["version", version])
+            }
+            VersionManager::Pyenv => self.output(&["global"]).lines().next().is_some_and(|l| l == version || l.starts_with(&format!("{version}."))),
+        }
+    }
+
+    pub fn install_version(self, version: &str) -> CommandLine {
+        match self {
+            VersionManager::Rustup => self.command(&["toolchain", "install", version]),
+            VersionManager::Nvm => self.command(&["install", version]),
+            VersionManager::Pyenv => self.command(&["install", "--skip-existing", version]),
+        }
+    }
+
+    pub fn set_default(self, version: &str) -> CommandLine {
+        match self {
+            VersionManager::Rustup => self.command(&["default", version]),
+            VersionManager::Nvm => self.command(&["alias", "default", version]),
+            VersionManager::Pyenv => self.command(&["global", version]),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::catalog::SwapMethod;
+use crate::command::{CommandLine, Script};
+
+pub const ZRAM_CONFIG: &str = "/etc/systemd/zram-generator.conf";
+pub const SYSCTL_FILE: &str = "/etc/sysctl.d/99-rusty-rebase.conf";
+pub const SWAPFILE: &str = "/swapfile";
+
+/// Installed memory in MiB, as reported by sysinfo.
+pub fn total_memory_mb() -> u64 {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_memory();
+    sys.total_memory() / (1024 * 1024)
+}
+
+/// Swap size for a machine with `ram_mb` of memory. zram only costs memory
+/// for pages actually swapped, so it can be sized generously; a swapfile
+/// costs disk and mostly serves as headroom.
+pub fn swap_size_mb(method: SwapMethod, ram_mb: u64) -> u64 {
+    match method {
+        SwapMethod::Zram if ram_mb <= 8192 => ram_mb,
+        SwapMethod::Zram => (ram_mb / 2).min(16384),
+        SwapMethod::Swapfile if ram_mb <= 2048 => ram_mb * 2,
+        SwapMethod::Swapfile if ram_mb <= 8192 => ram_mb,
+        SwapMethod::Swapfile => 4096,
+    }
+}
+
+/// Devices and files listed in /proc/swaps.
+pub fn active_swaps() -> Vec<String> {
+    std::fs::read_to_string("/proc/swaps")
+        .unwrap_or_default()
+        .lines()
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().next().map(str::to_string))
+        .collect()
+}
+
+pub fn zram_active() -> bool {
+    active_swaps().iter().any(|s| s.starts_with("/dev/zram"))
+}
+
+/// Whether zram swap is in use or configured to come up on the next boot.
+pub fn zram_configured() -> bool {
+    zram_active() || Path::new(ZRAM_CONFIG).exists()
+}
+
+/// Package shipping systemd's zram-generator for the given manager.
+pub fn zram_generator_package(manager: &str) -> &'static str {
+    match manager {
+        "apt" => "systemd-zram-generator",
+        _ => "zram-generator",
+    }
+}
+
+pub fn zram_config(size_mb: u64) -> String {
+    format!("# Added by rusty_rebase\n[zram0]\nzram-size = {size_mb}\ncompression-algorithm = zstd\n")
+}
+
+/// Loads the zram-generator config without a reboot.
+pub fn zram_start_script() -> Script {
+    vec![
+        CommandLine::new("systemctl").arg("daemon-reload").elevated(),
+        CommandLine::new("systemctl").args(["start", "systemd-zram-setup@zram0.service"]).elevated(),
+    ]
+}
+
+/// Creates, formats and enables [`SWAPFILE`]. The fstab entry is written
+/// separately so an existing file is only appended to.
+pub fn swapfile_script(size_mb: u64) -> Script {
+    vec![
+        CommandLine::new("fallocate").args(["-l", &format!("{size_mb}M"), SWAPFILE]).elevated(),
+        CommandLine::new("chmod").args(["600", SWAPFILE]).elevated(),
+        CommandLine::new("mkswap").arg(SWAPFILE).elevated(),
+        CommandLine::new("swapon").arg(SWAPFILE).elevated(),
+    ]
+}
+
+pub fn fstab_swap_line() -> String {
+    format!("{SWAPFILE} none swap defaults 0 0")
+}
+
+/// sysctl values for a development machine with `ram_mb` of memory. With
+/// zram, swapping is cheap and the kernel is told to prefer it over
+/// dropping the page cache; otherwise swapping is kept for emergencies on
+/// machines with enough memory.
+pub fn recommended_sysctl(ram_mb: u64, zram: bool) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let mut set = |key: &str, value: &str| {
+        values.insert(key.to_string(), value.to_string());
+    };
+    if zram {
+        set("vm.swappiness", "180");
+        set("vm.page-cluster", "0");
+        set("vm.watermark_boost_factor", "0");
+        set("vm.watermark_scale_factor", "125");
+    } else if ram_mb <= 4096 {
+        set("vm.swappiness", "60");
+    } else {
+        set("vm.swappiness", "10");
+    }
+    if ram_mb <= 4096 {
+        // Start writeback early so a big extraction does not fill memory
+        // with dirty pages.
+        set("vm.dirty_background_ratio", "5");
+        set("vm.dirty_ratio", "10");
+    }
+    set("vm.vfs_cache_pressure", "50");
+    // Editors, language servers and dev servers watch whole source trees.
+    set("fs.inotify.max_user_watches", "524288");
+    values
+}
+
+pub fn sysctl_conf(values: &BTreeMap<String, String>) -> String {
+    let mut out = String::from("# Added by rusty_rebase\n");
+    for (key, value) in values {
+        out.push_str(&format!("{key} = {value}\n"));
+    }
+    out
+}
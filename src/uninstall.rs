@@ -0,0 +1,85 @@
+use crate::catalog::{load_catalog, SourceSpec};
+use crate::distro::detect_distro;
+use crate::command::display_script;
+
+/// Removes the packages behind package-based catalog entries. Entries
+/// installed from downloads are listed with their install root, or the files
+/// the state database recorded for them, so they can be cleaned up by hand.
+/// Removed entries are dropped from the state database.
+pub fn uninstall(keys: &[String], dry_run: bool, assume_yes: bool) -> Result<(), String> {
+    let catalog = load_catalog(&crate::config::require_catalog()?)?;
+    let distro = detect_distro()?;
+    let state = crate::state::StateDb::load();
+    let recorded_files = |key: &str| state.tools.get(key).map(|t| t.files.clone()).unwrap_or_default();
+
+    let mut packages: Vec<String> = Vec::new();
+    let mut removed_keys: Vec<String> = Vec::new();
+    for key in keys {
+        let spec = catalog
+            .software
+            .get(key)
+            .ok_or_else(|| format!("'{key}' is not in the catalog"))?;
+        if matches!(spec.source, SourceSpec::None) {
+            println!("  {key}: setup steps only, nothing to remove");
+            continue;
+        }
+        if let SourceSpec::BuildFromSource { .. } = spec.source {
+            let root = crate::installer::install_root(spec)?;
+            println!("  {key}: built from source, remove what its build steps installed into {} manually", root.display());
+            continue;
+        }
+        if spec.source.has_artifact() {
+            let files = recorded_files(key);
+            if files.is_empty() {
+                let root = crate::installer::install_root(spec)?;
+                println!("  {key}: installed from a download, remove it from {} manually", root.display());
+            } else {
+                println!("  {key}: installed from a download, remove these manually: {}", files.join(", "));
+            }
+            continue;
+        }
+        removed_keys.push(key.clone());
+        for step in &spec.setup_steps {
+            for name in step.packages_for(&distro.id, distro.pkg_manager.name()) {
+                if distro.pkg_manager.is_installed(name) && !packages.contains(name) {
+                    packages.push(name.clone());
+                }
+            }
+        }
+    }
+
+    if packages.is_empty() {
+        println!("No installed packages to remove.");
+        return if dry_run { Ok(()) } else { crate::state::forget(&removed_keys) };
+    }
+    crate::package_manager::check_package_names(&packages)?;
+    let script = distro
+        .pkg_manager
+        .remove_command(&packages)
+        .ok_or_else(|| format!("package manager '{}' cannot remove packages", distro.pkg_manager))?;
+
+    println!("Packages to remove: {}", packages.join(" "));
+    let dependents = distro.pkg_manager.reverse_dependencies(&packages);
+    if !dependents.is_empty() {
+        println!("Installed packages that depend on them: {}", dependents.join(" "));
+    }
+    println!("Command: {}", display_script(&script));
+
+    if dry_run {
+        println!("[dry-run] nothing removed");
+        return Ok(());
+    }
+
+    if !assume_yes && !crate::answers::confirm("uninstall", &format!("Remove {} package(s)?", packages.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    for (i, cmd) in script.iter().enumerate() {
+        let status = cmd.to_command().status().map_err(|e| format!("failed to run {cmd}: {e}"))?;
+        if !status.success() && (!cmd.keep_going || i + 1 == script.len()) {
+            return Err(format!("package removal failed with {status}"));
+        }
+    }
+    crate::state::forget(&removed_keys)
+}
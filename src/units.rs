@@ -0,0 +1,151 @@
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+/// How sizes are scaled and labelled.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, GiB.
+    #[default]
+    Binary,
+    /// Powers of 1000: kB, MB, GB, as disk and network vendors count.
+    Decimal,
+}
+
+impl SizeUnits {
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeUnits::Binary => "binary",
+            SizeUnits::Decimal => "decimal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NumberFormat {
+    units: SizeUnits,
+    decimal_mark: char,
+    /// Between groups of three digits; `None` for the C locale.
+    group_separator: Option<char>,
+}
+
+/// Read from the config and the locale on first use.
+static FORMAT: RwLock<Option<NumberFormat>> = RwLock::new(None);
+
+fn format() -> NumberFormat {
+    if let Some(format) = *FORMAT.read().unwrap_or_else(|e| e.into_inner()) {
+        return format;
+    }
+    let config = crate::config::current();
+    let locale = config.number_locale.filter(|l| !l.trim().is_empty()).unwrap_or_else(env_locale);
+    let (decimal_mark, group_separator) = separators(&locale);
+    let format = NumberFormat { units: config.size_units.unwrap_or_default(), decimal_mark, group_separator };
+    *FORMAT.write().unwrap_or_else(|e| e.into_inner()) = Some(format);
+    format
+}
+
+/// Picks up a changed `size_units` or `number_locale` from the config.
+pub fn reload() {
+    *FORMAT.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// The locale numbers are formatted for, from `LC_ALL`, `LC_NUMERIC` or
+/// `LANG` as the C library would pick it.
+fn env_locale() -> String {
+    ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "C".to_string())
+}
+
+/// Decimal mark and digit grouping for a locale name such as `de_DE.UTF-8`.
+/// Only the language and region matter; unknown locales format like
+/// English.
+fn separators(locale: &str) -> (char, Option<char>) {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = name.split_once(['_', '-']).unwrap_or((name, ""));
+    match (language, region) {
+        ("C" | "POSIX", _) => ('.', None),
+        ("de" | "it" | "fr", "CH") | ("de", "LI") => ('.', Some('\'')),
+        ("de" | "es" | "it" | "pt" | "nl" | "da" | "id" | "tr" | "el" | "ro" | "sl" | "hr" | "sr", _) => (',', Some('.')),
+        ("fr" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "no" | "hu" | "bg" | "lt" | "lv" | "et", _) => (',', Some(' ')),
+        _ => ('.', Some(',')),
+    }
+}
+
+fn group(digits: &str, separator: Option<char>) -> String {
+    let Some(separator) = separator else { return digits.to_string() };
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A whole number with digit grouping, e.g. `12,345` or `12.345`.
+pub fn format_number(n: u64) -> String {
+    group(&n.to_string(), format().group_separator)
+}
+
+/// `value` with `precision` decimals, e.g. `1,234.5` or `1.234,5`.
+pub fn format_decimal(value: f64, precision: usize) -> String {
+    let format = format();
+    let text = format!("{:.*}", precision, value.abs());
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let mut out = if value.is_sign_negative() && value != 0.0 { "-".to_string() } else { String::new() };
+    out.push_str(&group(whole, format.group_separator));
+    if !fraction.is_empty() {
+        out.push(format.decimal_mark);
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// A size in the configured units, e.g. `1.5 GiB` or `1,6 GB`.
+pub fn format_size(bytes: u64) -> String {
+    let (base, units) = match format().units {
+        SizeUnits::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+        SizeUnits::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+    };
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit + 1 < units.len() {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", format_number(bytes)) } else { format!("{} {}", format_decimal(value, 1), units[unit]) }
+}
+
+pub fn format_speed(bytes_per_sec: f64) -> String {
+    format!("{}/s", format_size(bytes_per_sec as u64))
+}
+
+/// Time left such as `42s`, `3m 5s` or `1h 20m`.
+pub fn format_eta(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h {}m", format_number(secs / 3600), (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_are_grouped_in_threes() {
+        assert_eq!(group("0", Some(',')), "0");
+        assert_eq!(group("999", Some(',')), "999");
+        assert_eq!(group("1000", Some(',')), "1,000");
+        assert_eq!(group("1234567", Some('.')), "1.234.567");
+        assert_eq!(group("123456", Some(' ')), "123 456");
+        assert_eq!(group("1234567", None), "1234567");
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::catalog::{CatalogFile, SetupStep, SoftwareSpec};
+use crate::installer::{download_dir, install_root};
+use crate::units::format_size;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UsageKind {
+    Install,
+    Download,
+}
+
+impl std::fmt::Display for UsageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsageKind::Install => write!(f, "install"),
+            UsageKind::Download => write!(f, "download"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageEntry {
+    pub label: String,
+    pub kind: UsageKind,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Recursively sums file sizes without following symlinks.
+pub fn dir_size(path: &Path) -> u64 {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(_) => return 0,
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|iter| iter.flatten().map(|e| dir_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Directories a catalog entry owns: the top-level folder each path hint
+/// points into, or the install_dir itself when it is not the home directory.
+fn managed_dirs(spec: &SoftwareSpec) -> Vec<PathBuf> {
+    let root = match install_root(spec) {
+        Ok(r) => r,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs: Vec<PathBuf> = spec.setup_steps.iter().filter_map(|s| match s {
+        SetupStep::PathHint { value } => value
+            .strip_prefix("<install_root>/")
+            .and_then(|rest| rest.split('/').next())
+            .filter(|top| !top.is_empty())
+            .map(|top| root.join(top)),
+        _ => None,
+    }).collect();
+
+    if dirs.is_empty() && spec.install_dir.is_some() && Some(&root) != dirs::home_dir().as_ref() {
+        dirs.push(root);
+    }
+    dirs.dedup();
+    dirs
+}
+
+pub fn collect_usage(catalog: &CatalogFile) -> Vec<UsageEntry> {
+    let mut entries = Vec::new();
+
+    for (key, spec) in &catalog.software {
+        for dir in managed_dirs(spec) {
+            if dir.exists() {
+                entries.push(UsageEntry {
+                    label: key.clone(),
+                    kind: UsageKind::Install,
+                    bytes: dir_size(&dir),
+                    path: dir,
+                });
+            }
+        }
+    }
+
+    if let Ok(iter) = download_dir().and_then(|d| fs::read_dir(d).map_err(|e| e.to_string())) {
+        for entry in iter.flatten() {
+            let path = entry.path();
+            entries.push(UsageEntry {
+                label: entry.file_name().to_string_lossy().to_string(),
+                kind: UsageKind::Download,
+                bytes: dir_size(&path),
+                path,
+            });
+        }
+    }
+
+    sort_usage(&mut entries, true);
+    entries
+}
+
+pub fn sort_usage(entries: &mut [UsageEntry], by_size: bool) {
+    if by_size {
+        entries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.label.cmp(&b.label)));
+    } else {
+        entries.sort_by(|a, b| a.label.cmp(&b.label));
+    }
+}
+
+pub fn print_usage(catalog: &CatalogFile, by_size: bool) {
+    let mut entries = collect_usage(catalog);
+    sort_usage(&mut entries, by_size);
+    let total: u64 = entries.iter().map(|e| e.bytes).sum();
+
+    for entry in &entries {
+        println!("{:>10}  {:<8}  {:<20}  {}", format_size(entry.bytes), entry.kind, entry.label, entry.path.display());
+    }
+    println!("{:>10}  total", format_size(total));
+}
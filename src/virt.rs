@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+
+use crate::platform::Platform;
+
+/// What the CPU and kernel offer for hardware-accelerated VMs.
+#[derive(Debug)]
+struct KvmStatus {
+    /// `vmx` (Intel) or `svm` (AMD) is among the CPU flags.
+    cpu_flag: Option<&'static str>,
+    /// The CPU reports a hypervisor, i.e. this machine is itself a VM.
+    guest: bool,
+    module_loaded: bool,
+    device: bool,
+    /// `/dev/kvm` can be opened by the current user.
+    accessible: bool,
+    /// The KVM module's `nested` parameter, when it has one.
+    nested: Option<bool>,
+}
+
+impl KvmStatus {
+    fn detect() -> Self {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+        let flags: Vec<&str> = cpuinfo
+            .lines()
+            .find(|l| l.starts_with("flags") || l.starts_with("Features"))
+            .and_then(|l| l.split_once(':'))
+            .map(|(_, flags)| flags.split_whitespace().collect())
+            .unwrap_or_default();
+        let cpu_flag = ["vmx", "svm"].into_iter().find(|flag| flags.contains(flag));
+        let parameters = Path::new("/sys/module").join(kvm_module(cpu_flag)).join("parameters");
+        let nested = fs::read_to_string(parameters.join("nested"))
+            .ok()
+            .map(|v| matches!(v.trim(), "Y" | "y" | "1"));
+        let device = Path::new("/dev/kvm").exists();
+        KvmStatus {
+            cpu_flag,
+            guest: flags.contains(&"hypervisor"),
+            module_loaded: parameters.parent().is_some_and(Path::exists),
+            device,
+            accessible: device && fs::OpenOptions::new().read(true).write(true).open("/dev/kvm").is_ok(),
+            nested,
+        }
+    }
+
+    fn module(&self) -> &'static str {
+        kvm_module(self.cpu_flag)
+    }
+
+    /// Why VMs would not get hardware acceleration, most fundamental cause
+    /// first, followed by the nested virtualization state where it matters.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.cpu_flag.is_none() && !self.device {
+            problems.push(if self.guest {
+                "this machine is a VM without nested virtualization; enable it for this VM on the host".to_string()
+            } else {
+                "hardware virtualization (VT-x/AMD-V) is missing or disabled in the firmware; enable it in the BIOS/UEFI setup".to_string()
+            });
+        } else if !self.device {
+            problems.push(if self.module_loaded {
+                "/dev/kvm is missing although KVM is loaded; virtualization may be disabled in the firmware".to_string()
+            } else {
+                format!("the {} module is not loaded; run `sudo modprobe {}` or check that virtualization is enabled in the firmware", self.module(), self.module())
+            });
+        } else if !self.accessible {
+            problems.push("/dev/kvm is not accessible to your user; add yourself to the kvm group".to_string());
+        }
+        if !problems.is_empty() {
+            problems.push("QEMU will fall back to slow software emulation".to_string());
+        } else if !self.guest && self.nested == Some(false) {
+            problems.push(format!("nested virtualization is off, so VMs cannot run VMs themselves; set `options {} nested=1` in /etc/modprobe.d to allow it", self.module()));
+        }
+        problems
+    }
+}
+
+fn kvm_module(cpu_flag: Option<&str>) -> &'static str {
+    match cpu_flag {
+        Some("svm") => "kvm_amd",
+        _ => "kvm_intel",
+    }
+}
+
+/// Warnings for entries with `requires_kvm`, empty when KVM is ready. Only
+/// Linux is checked.
+pub fn kvm_problems() -> Vec<String> {
+    if Platform::current() != Platform::Linux {
+        return Vec::new();
+    }
+    KvmStatus::detect().problems()
+}